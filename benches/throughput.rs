@@ -0,0 +1,92 @@
+//! Ray throughput of the acceleration structures. Run with
+//! `cargo bench --bench throughput`, add `--features simd` and
+//! `RUSTFLAGS="-C target-cpu=native"` for the explicit SIMD paths.
+
+use lumo::tracer::*;
+use lumo::*;
+use std::time::Instant;
+
+const GRID: usize = 512;
+const NUM_SPHERES: usize = 20_000;
+const NUM_RAYS: usize = 1_000_000;
+
+/// Rays from a sphere around the unit cube towards points inside it.
+/// Seeded so that every structure gets the same rays.
+fn rays() -> Vec<Ray> {
+    let mut rng = Xorshift::new(0x2545F4914F6CDD1D);
+    (0..NUM_RAYS).map(|_| {
+        let xo = 2.0 * (rng.gen_vec3() - 0.5).normalize() + Vec3::splat(0.5);
+        Ray::new(xo, rng.gen_vec3() - xo)
+    }).collect()
+}
+
+/// Wavy height field over the unit square, `2 * GRID^2` triangles
+fn terrain() -> (Vec<Vec3>, Vec<Face>) {
+    let n = GRID + 1;
+    let mut vertices = Vec::with_capacity(n * n);
+    for i in 0..n {
+        for j in 0..n {
+            let (x, z) = (i as Float / GRID as Float, j as Float / GRID as Float);
+            let y = 0.5 + 0.2 * (12.0 * x).sin() * (9.0 * z).cos();
+            vertices.push(Vec3::new(x, y, z));
+        }
+    }
+
+    let mut faces = Vec::with_capacity(2 * GRID * GRID);
+    for i in 0..GRID {
+        for j in 0..GRID {
+            let v = i * n + j;
+            faces.push(Face::new(vec!(v, v + 1, v + n + 1), vec!(), vec!()));
+            faces.push(Face::new(vec!(v, v + n + 1, v + n), vec!(), vec!()));
+        }
+    }
+
+    (vertices, faces)
+}
+
+fn measure(name: &str, object: &dyn Object, rays: &[Ray]) {
+    let start = Instant::now();
+    let hits = rays.iter()
+        .filter(|r| object.hit(r, 0.0, Float::INFINITY).is_some())
+        .count();
+    let closest = NUM_RAYS as Float / start.elapsed().as_secs_f64() / 1e6;
+
+    let start = Instant::now();
+    let occluded = rays.iter()
+        .filter(|r| object.hit_t(r, 0.0, Float::INFINITY) < Float::INFINITY)
+        .count();
+    let any = NUM_RAYS as Float / start.elapsed().as_secs_f64() / 1e6;
+
+    assert!(hits == occluded);
+    println!(
+        "{:<16} {:>8.2} Mrays/s closest, {:>8.2} Mrays/s any, {:.1} % hit",
+        name, closest, any, 100.0 * hits as Float / NUM_RAYS as Float,
+    );
+}
+
+fn main() {
+    let rays = rays();
+    let mesh = || {
+        let (vertices, faces) = terrain();
+        (vertices, faces, vec!(), vec!(), Material::Blank)
+    };
+
+    println!("{} triangles, single thread", 2 * GRID * GRID);
+    let (v, f, n, t, m) = mesh();
+    measure("kD-tree", &TriangleMesh::new(v, f, n, t, m), &rays);
+    for build in [BVHBuild::Hlbvh, BVHBuild::BinnedSah] {
+        let (v, f, n, t, m) = mesh();
+        let bvh = TriangleMesh::new_bvh(v, f, n, t, m, build);
+        measure(&format!("BVH {:?}", build), &bvh, &rays);
+    }
+
+    println!("{} spheres, single thread", NUM_SPHERES);
+    let mut rng = Xorshift::new(0x9E3779B97F4A7C15);
+    let mut spheres: BVH<Box<dyn Object>> = BVH::default();
+    for _ in 0..NUM_SPHERES {
+        let c = rng.gen_vec3();
+        spheres.add(Sphere::new(0.01, Material::Blank).translate(c.x, c.y, c.z));
+    }
+    spheres.build();
+    measure("BVH Hlbvh", &spheres, &rays);
+}
@@ -0,0 +1,16 @@
+use lumo::tracer::*;
+use lumo::*;
+
+const DRAGON_URL: &str = "https://casual-effects.com/g3d/data10/research/model/dragon/dragon.zip";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // same mesh with each acceleration structure
+    let kdtree = parser::mesh_from_url(DRAGON_URL, Material::Blank)?;
+    let hlbvh = parser::mesh_bvh_from_url(DRAGON_URL, Material::Blank, BVHBuild::Hlbvh)?;
+    let binned = parser::mesh_bvh_from_url(DRAGON_URL, Material::Blank, BVHBuild::BinnedSah)?;
+
+    println!("kD-tree:    {}", kdtree.stats());
+    println!("HLBVH:      {}", hlbvh.stats());
+    println!("binned SAH: {}", binned.stats());
+    Ok(())
+}
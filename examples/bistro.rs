@@ -25,6 +25,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             false,
             Some("exterior-night.mtl"),
             Some(("cobblestone_street_night_4k.hdr", 0.001)),
+            MeshAccel::BVH(BVHBuild::BinnedSah),
         )?
     } else {
         parser::scene_from_url(
@@ -33,6 +34,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             false,
             None,
             Some(("san_giuseppe_bridge_4k.hdr", 0.05)),
+            MeshAccel::BVH(BVHBuild::BinnedSah),
         )?
     };
 
@@ -10,7 +10,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .towards(500.0, 0.0, 250.0)
         .build();
 
-    let mut scene = parser::scene_from_url(SCENE_URL, SCENE_NAME, true, None, None)?;
+    let mut scene = parser::scene_from_url(
+        SCENE_URL, SCENE_NAME, true, None, None, MeshAccel::default()
+    )?;
 
     scene.add_light(
         Sphere::new(10.0, Material::light(Texture::from(Spectrum::WHITE)))
@@ -0,0 +1,63 @@
+use lumo::tracer::*;
+use lumo::*;
+use std::sync::Arc;
+
+const BLADES: usize = 200_000;
+
+/// Hash of `i` to `[0,1)`
+fn rand(i: usize) -> Float {
+    let mut x = (i as u64).wrapping_mul(0x9e3779b97f4a7c15);
+    x ^= x >> 31;
+    x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+    x ^= x >> 29;
+    (x >> 11) as Float / (1u64 << 53) as Float
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let camera = Camera::builder()
+        .origin(0.0, 0.6, 2.0)
+        .towards(0.0, 0.1, 0.0)
+        .resolution((1024, 576))
+        .build();
+
+    let mut scene = Scene::default();
+    scene.add(Rectangle::unit_xz(Material::diffuse(Texture::from(Spectrum::from_srgb(60, 40, 20))))
+              .scale_uniform(100.0)
+    );
+    scene.set_environment_map(Texture::from(Spectrum::WHITE), 1.0);
+
+    // every blade is a bent ribbon, all of them share the material
+    let material = Arc::new(Material::diffuse(Texture::from(Spectrum::from_srgb(70, 140, 40))));
+    let mut blades = Vec::with_capacity(BLADES);
+    for i in 0..BLADES {
+        let (x, z) = (4.0 * rand(4 * i) - 2.0, 4.0 * rand(4 * i + 1) - 3.0);
+        let height = 0.1 + 0.15 * rand(4 * i + 2);
+        let phi = 2.0 * PI * rand(4 * i + 3);
+        let bend = Vec3::new(phi.cos(), 0.0, phi.sin()) * 0.4 * height;
+
+        let root = Vec3::new(x, 0.0, z);
+        let cps = [
+            root,
+            root + Vec3::new(0.0, height / 2.0, 0.0),
+            root + Vec3::new(0.0, height, 0.0) + bend / 2.0,
+            root + Vec3::new(0.0, height, 0.0) + bend,
+        ];
+        let normal = Vec3::new(-phi.sin(), 0.0, phi.cos());
+
+        blades.extend(Curve::strand(
+            &cps,
+            CurveBasis::Bezier,
+            0.01,
+            0.002,
+            CurveType::Ribbon(normal, normal),
+            Arc::clone(&material),
+        ));
+    }
+    scene.add(Box::new(CurveBVH::new(blades, BVHBuild::BinnedSah)));
+
+    Renderer::new(scene, camera)
+        .samples(64)
+        .render()
+        .save("grass.png")?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use lumo::tracer::*;
+use lumo::*;
+
+const TEAPOT_URL: &str = "https://casual-effects.com/g3d/data10/common/model/teapot/teapot.zip";
+const GRID: usize = 64;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let camera = Camera::builder()
+        .origin(0.0, 4.0, 6.0)
+        .towards(0.0, 0.0, -4.0)
+        .resolution((1024, 512))
+        .build();
+
+    let mut scene = Scene::default();
+    scene.add(Rectangle::unit_xz(Material::diffuse(Texture::from(0.5 * Spectrum::WHITE)))
+              .scale_uniform(100.0)
+    );
+    scene.set_environment_map(Texture::from(Spectrum::WHITE), 1.0);
+
+    // the mesh and its kD-tree get stored once, all of the teapots share them
+    let teapot = parser::mesh_from_url(TEAPOT_URL, Material::Blank)?
+        .to_unit_size()
+        .to_origin()
+        .set_y(0.0)
+        .with_material(Material::metal(
+            Texture::from(Spectrum::from_srgb(200, 160, 120)), 0.2, 2.5, 0.0
+        ));
+
+    for i in 0..GRID * GRID {
+        let (x, z) = ((i % GRID) as Float, (i / GRID) as Float);
+        let x = 1.5 * (x - GRID as Float / 2.0);
+        let z = -1.5 * z;
+
+        scene.add(teapot.clone(None)
+                  .rotate_y(x * z)
+                  .translate(x, 0.0, z)
+        );
+    }
+
+    Renderer::new(scene, camera)
+        .samples(64)
+        .render()
+        .save("instances.png")?;
+    Ok(())
+}
@@ -62,7 +62,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     scene.add(
         Cube::new(
             Material::microfacet(
-                0.1, 0.6, 2.8, false, false,
+                Vec2::splat(0.1), 0.6, 2.8, false, false,
                 Texture::from(Spectrum::from_srgb(218, 138, 103)),
                 Texture::from(Spectrum::from_srgb(218, 138, 103)),
                 Texture::from(Spectrum::BLACK),
@@ -88,7 +88,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 	        parser::mesh_from_url(
                 NEFE_URL,
 		        Material::microfacet(
-                    0.8,
+                    Vec2::splat(0.8),
                     1.5,
                     0.0,
                     false,
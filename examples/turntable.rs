@@ -0,0 +1,55 @@
+use lumo::tracer::*;
+use lumo::*;
+
+const FRAMES: u64 = 48;
+const FPS: Float = 24.0;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let duration = FRAMES as Float / FPS;
+    let builder = Camera::builder()
+        .towards(0.0, 0.0, 0.0)
+        .resolution((512, 512));
+
+    // circle around the scene while zooming in
+    let mut origin = Keyframes::new(0.0, Vec3::new(0.0, 1.0, 3.0));
+    for i in 1..=4 {
+        let theta = i as Float * PI / 2.0;
+        let t = i as Float * duration / 4.0;
+        origin = origin.key(t, Vec3::new(3.0 * theta.sin(), 1.0, 3.0 * theta.cos()));
+    }
+    let path = CameraPath::new(builder)
+        .origin(origin)
+        .vfov(Keyframes::new(0.0, 60.0).key(duration, 40.0))
+        .shutter(0.0, 0.5 / FPS);
+
+    let mut scene = Scene::default();
+    scene.add(Rectangle::unit_xz(Material::diffuse(Texture::from(0.8 * Spectrum::WHITE)))
+              .scale_uniform(10.0)
+              .translate(0.0, -0.5, 0.0)
+    );
+
+    // bouncing ball
+    let mut ball = Sphere::new(0.25, Material::metal(
+        Texture::from(Spectrum::from_srgb(230, 180, 90)), 0.2, 2.5, 0.0
+    )).translate(0.0, -0.25, 0.0);
+    for i in 0..4 {
+        let t = i as Float * duration / 4.0;
+        ball = ball.animate(t, t + duration / 8.0).translate(0.0, 1.0, 0.0)
+            .animate(t + duration / 8.0, t + duration / 4.0).translate(0.0, -1.0, 0.0);
+    }
+    scene.add(ball);
+
+    // light fades in
+    scene.add_light(Rectangle::unit_xz(
+        Material::light(Texture::from(Spectrum::WHITE))
+            .with_intensity(Keyframes::new(0.0, 0.5).key(duration / 2.0, 5.0))
+    )
+                    .rotate_z(PI)
+                    .translate(0.0, 3.0, 0.0)
+    );
+
+    Renderer::new(scene, path.camera_at(0.0))
+        .samples(64)
+        .render_frames(&path, 0..FRAMES, FPS, "turntable_")?;
+    Ok(())
+}
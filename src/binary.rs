@@ -0,0 +1,154 @@
+use crate::{ Axis, Float, Vec2, Vec3 };
+use std::io::{ self, Read, Result, Write };
+
+/// Capacity reserved up front when reading vectors, guards against
+/// allocating garbage lengths of corrupt files and images
+pub const MAX_RESERVE: usize = 1 << 20;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Values written to and read from little endian bytes
+pub trait Binary: Sized {
+    /// Writes `self` to `w`
+    fn write(&self, w: &mut impl Write) -> Result<()>;
+    /// Reads a value written by `write` from `r`
+    fn read(r: &mut impl Read) -> Result<Self>;
+}
+
+impl Binary for u64 {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(&self.to_le_bytes())
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let mut bytes = [0; 8];
+        r.read_exact(&mut bytes)?;
+        Ok(Self::from_le_bytes(bytes))
+    }
+}
+
+impl Binary for usize {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        (*self as u64).write(w)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        Self::try_from(u64::read(r)?)
+            .map_err(|_| invalid("Index does not fit in usize"))
+    }
+}
+
+impl Binary for bool {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(&[*self as u8])
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let mut byte = [0; 1];
+        r.read_exact(&mut byte)?;
+        match byte[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("Bad boolean")),
+        }
+    }
+}
+
+impl Binary for Float {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(&self.to_le_bytes())
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let mut bytes = [0; std::mem::size_of::<Float>()];
+        r.read_exact(&mut bytes)?;
+        Ok(Self::from_le_bytes(bytes))
+    }
+}
+
+impl Binary for Axis {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        (*self as usize).write(w)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        match usize::read(r)? {
+            0 => Ok(Axis::X),
+            1 => Ok(Axis::Y),
+            2 => Ok(Axis::Z),
+            _ => Err(invalid("Bad axis")),
+        }
+    }
+}
+
+impl Binary for Vec2 {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.x.write(w)?;
+        self.y.write(w)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        Ok(Self::new(Float::read(r)?, Float::read(r)?))
+    }
+}
+
+impl Binary for Vec3 {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.x.write(w)?;
+        self.y.write(w)?;
+        self.z.write(w)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        Ok(Self::new(Float::read(r)?, Float::read(r)?, Float::read(r)?))
+    }
+}
+
+impl<T: Binary> Binary for (T, T, T) {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.0.write(w)?;
+        self.1.write(w)?;
+        self.2.write(w)
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        Ok((T::read(r)?, T::read(r)?, T::read(r)?))
+    }
+}
+
+impl<T: Binary> Binary for Option<T> {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.is_some().write(w)?;
+        match self {
+            Some(v) => v.write(w),
+            None => Ok(()),
+        }
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        if bool::read(r)? { Ok(Some(T::read(r)?)) } else { Ok(None) }
+    }
+}
+
+impl<T: Binary> Binary for Vec<T> {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.len().write(w)?;
+        self.iter().try_for_each(|v| v.write(w))
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let len = usize::read(r)?;
+        let mut vec = Vec::with_capacity(len.min(MAX_RESERVE));
+        for _ in 0..len {
+            vec.push(T::read(r)?);
+        }
+        Ok(vec)
+    }
+}
+
+/// Checks that the index `idx` read from a file is below `len`
+pub fn check_index(idx: usize, len: usize) -> Result<usize> {
+    if idx < len { Ok(idx) } else { Err(invalid("Index out of bounds")) }
+}
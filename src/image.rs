@@ -1,7 +1,25 @@
-use png::{BitDepth, ColorType, Decoder, DecodingError};
-use std::{ io::{BufRead, BufReader, Read}, fs::{self, File} };
+use std::{ io::{self, BufRead, Read}, fs::{self, File} };
 use crate::{Float, Normal, Vec2, Vec3};
-use crate::tracer::{Color, ColorWavelength, Spectrum, RGB};
+use crate::tracer::{Color, ColorWavelength, Spectrum, Uplift, RGB};
+
+pub(crate) use decode::Pixels;
+
+/// PNG, JPEG and TGA decoders
+mod decode;
+/// Radiance HDR decoder
+mod hdr;
+
+#[cfg(test)]
+mod decode_tests;
+
+/// How the pixel values of an image file map to linear values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorEncoding {
+    /// Gamma encoded with the sRGB transfer function, e.g. color maps
+    sRGB,
+    /// Stored as is, e.g. normal, roughness or other data maps
+    Linear,
+}
 
 /// Loaded texture images stored in a Rust vector
 #[derive(Clone)]
@@ -13,83 +31,35 @@ pub struct Image<T> {
     /// Height of rendered image.
     pub height: u32,
     mean: T,
+    /// Successively halved copies of the image for filtered lookups,
+    /// coarsest last. Empty if the image is not mipmapped.
+    levels: Vec<Image<T>>,
 }
 
 impl<T> Image<T> {
-    fn decode_png<R: Read>(read: R) -> Result<(Vec<[u8; 3]>, png::OutputInfo), DecodingError> {
-        let decoder = Decoder::new(read);
-        let mut reader = decoder.read_info()?;
-        let mut bytes = vec![0; reader.output_buffer_size()];
-        let info = reader.next_frame(&mut bytes)?;
-
-        let buffer = match info.color_type {
-            ColorType::Indexed => {
-                let palette = reader.info().palette.as_ref().unwrap();
-
-                (0..info.height * info.width)
-                    .map(|idx| {
-                        let (bidx, rss, msk) = match info.bit_depth {
-                            BitDepth::One => {
-                                (idx / 8, idx % 8, (1 << 1) - 1)
-                            }
-                            BitDepth::Two => {
-                                (idx / 4, 2 * (idx % 4), (1 << 2) - 1)
-                            }
-                            BitDepth::Four => {
-                                (idx / 2, 4 * (idx % 2), (1 << 4) - 1)
-                            }
-                            BitDepth::Eight => {
-                                (idx, 0, 0xFF)
-                            }
-                            BitDepth::Sixteen => unreachable!(),
-                        };
-                        let byte = bytes[bidx as usize];
-                        let pidx = (byte >> rss) & msk;
-                        let pidx = pidx as usize;
-                        [palette[3*pidx + 0], palette[3*pidx + 1], palette[3*pidx + 2]]
-                    })
-                    .collect()
-            }
-            ColorType::Grayscale | ColorType::GrayscaleAlpha => {
-                let chunk_size = if info.color_type == ColorType::GrayscaleAlpha
-                { 2 } else { 1 };
-
-                bytes[..info.buffer_size()]
-                    .chunks(chunk_size)
-                    .map(|gs| [gs[0], gs[0], gs[0]])
-                    .collect()
-            }
-            ColorType::Rgb | ColorType::Rgba => {
-                let chunk_size = {
-                    let bytes = 1;
-                    // only support rgb/rgba
-                    let channels = if info.color_type == ColorType::Rgb { 3 } else { 4 };
-                    bytes * channels
-                };
-
-                bytes[..info.buffer_size()]
-                    .chunks(chunk_size)
-                    .map(|rgb| [rgb[0], rgb[1], rgb[2]])
-                    .collect()
-            }
-        };
+    /// Image of `width` x `height` pixels stored row by row in `buffer`,
+    /// starting from the top row. `mean` is the average pixel value.
+    pub fn new(buffer: Vec<T>, width: u32, height: u32, mean: T) -> Self {
+        assert!(buffer.len() == (width * height) as usize);
+        Self { buffer, width, height, mean, levels: Vec::new() }
+    }
 
-        Ok((buffer, info))
+    /// Decode a PNG, JPEG or TGA image from `read`
+    pub(crate) fn decode<R: Read>(read: R) -> io::Result<Pixels> {
+        decode::decode(read)
     }
 
     /// Get the mean Vec3 of rgb channels from the image
-    pub fn mean_vec3_from_file<R: Read>(read: R) -> Result<Vec3, DecodingError> {
-        let (pixels, info) = Self::decode_png(read)?;
-        let scale = 1.0 / (info.width * info.height) as Float;
+    pub fn mean_vec3_from_file<R: Read>(read: R) -> io::Result<Vec3> {
+        let pixels = Self::decode(read)?;
+        let scale = 1.0 / pixels.rgba.len() as Float;
         Ok(
-            pixels.iter()
-                .fold(Vec3::ZERO, |acc, rgb| {
-                    let map_byte = |c: u8| { scale * c as Float / 256.0};
-
-                    acc + Vec3::new(
-                        map_byte(rgb[0]),
-                        map_byte(rgb[1]),
-                        map_byte(rgb[2]),
+            pixels.rgba.iter()
+                .fold(Vec3::ZERO, |acc, px| {
+                    acc + scale * Vec3::new(
+                        Pixels::unorm(px, 0),
+                        Pixels::unorm(px, 1),
+                        Pixels::unorm(px, 2),
                     )
                 })
         )
@@ -139,34 +109,111 @@ impl Image<Normal> {
         lerp(&y0, &y1, v)
     }
 
-    /// Parse a bump map from file
-    pub fn bump_from_file<R: Read>(read: R) -> Result<Self, DecodingError> {
-        let (pixels, info) = Self::decode_png(read)?;
+    /// Parse a tangent space normal map from file
+    pub fn bump_from_file<R: Read>(read: R) -> io::Result<Self> {
+        let pixels = Self::decode(read)?;
+        Ok(Self::normals_from_pixels(&pixels))
+    }
 
-        let buffer = pixels.iter()
-            .map(|rgb| {
-                let map_byte = |c: u8| {
-                    c as Float / 128.0 - 1.0
+    /// Normals from decoded pixels, channels map from `\[0,1\]` to `\[-1,1\]`
+    pub(crate) fn normals_from_pixels(pixels: &Pixels) -> Self {
+        let buffer = pixels.rgba.iter()
+            .map(|px| {
+                let map_channel = |c: usize| {
+                    2.0 * Pixels::unorm(px, c) - 1.0
                 };
 
-                Normal::new(
-                    map_byte(rgb[0]),
-                    map_byte(rgb[1]),
-                    map_byte(rgb[2]),
-                ).normalize()
+                let n = Normal::new(
+                    map_channel(0),
+                    map_channel(1),
+                    map_channel(2),
+                );
+                if n.length_squared() == 0.0 { Normal::Z } else { n.normalize() }
             })
             .collect();
 
-        let width = info.width;
-        let height = info.height;
+        let width = pixels.width;
+        let height = pixels.height;
 
-        Ok(Self {
+        Self {
             buffer,
             width,
             height,
             mean: Normal::Z,
+            levels: Vec::new(),
+        }
+    }
+}
+
+impl Image<Float> {
+    /// Bilinearly interpolated value at `uv`
+    #[inline]
+    pub fn value_at(&self, uv: Vec2) -> Float {
+        let lerp = |a0: &Float, a1: &Float, v: Float| -> Float {
+            a0 * v + a1 * (1.0 - v)
+        };
+        let (y0, y1, v) = self.bilin_interp(uv, lerp);
+        lerp(&y0, &y1, v)
+    }
+
+    /// Average over all of the pixels
+    pub fn mean(&self) -> Float {
+        self.mean
+    }
+
+    /// Parse a single channel of an image from file, e.g. the green
+    /// channel of a packed occlusion-roughness-metalness texture.
+    /// Channels `0..4` are red, green, blue and alpha.
+    pub fn channel_from_file<R: Read>(read: R, channel: usize) -> io::Result<Self> {
+        assert!(channel < 4);
+        let pixels = Self::decode(read)?;
+        Ok(Self::from_pixels(&pixels, |px| Pixels::unorm(px, channel)))
+    }
+
+    /// Parse an opacity mask from file. Uses the alpha channel if the image
+    /// has one, otherwise the mean of the color channels.
+    pub fn alpha_from_file<R: Read>(read: R) -> io::Result<Self> {
+        let pixels = Self::decode(read)?;
+        let has_alpha = pixels.rgba.iter().any(|px| px[3] != u16::MAX);
+
+        if has_alpha {
+            Ok(Self::from_pixels(&pixels, |px| Pixels::unorm(px, 3)))
+        } else {
+            Ok(Self::heights_from_pixels(&pixels))
+        }
+    }
+
+    /// Parse a height field in `\[0,1\]` from file, e.g. for bump or displacement
+    pub fn height_from_file<R: Read>(read: R) -> io::Result<Self> {
+        let pixels = Self::decode(read)?;
+        Ok(Self::heights_from_pixels(&pixels))
+    }
+
+    /// Heights from decoded pixels as the mean of the color channels
+    pub(crate) fn heights_from_pixels(pixels: &Pixels) -> Self {
+        Self::from_pixels(pixels, |px| {
+            (Pixels::unorm(px, 0) + Pixels::unorm(px, 1) + Pixels::unorm(px, 2)) / 3.0
         })
     }
+
+    fn from_pixels<F>(pixels: &Pixels, map_pixel: F) -> Self
+    where
+        F: Fn(&[u16; 4]) -> Float
+    {
+        let buffer: Vec<Float> = pixels.rgba.iter().map(map_pixel).collect();
+        let mean = buffer.iter().sum::<Float>() / buffer.len() as Float;
+
+        let width = pixels.width;
+        let height = pixels.height;
+
+        Self {
+            buffer,
+            width,
+            height,
+            mean,
+            levels: Vec::new(),
+        }
+    }
 }
 
 impl Image<Spectrum> {
@@ -183,95 +230,148 @@ impl Image<Spectrum> {
         c
     }
 
+    /// Trilinearly filter the texture at `uv` for `lambda`. The mipmap level
+    /// is chosen such that a texel covers the footprint spanned by the
+    /// texture coordinate differentials `duvdx` and `duvdy`.
+    pub fn filtered_value_at(
+        &self,
+        uv: Vec2,
+        duvdx: Vec2,
+        duvdy: Vec2,
+        lambda: &ColorWavelength
+    ) -> Color {
+        let (w, h) = (self.width as Float, self.height as Float);
+        let width = (duvdx.x.abs() * w).max(duvdy.x.abs() * w)
+            .max((duvdx.y.abs() * h).max(duvdy.y.abs() * h));
+
+        let level = width.max(1.0).log2();
+        if level == 0.0 || self.levels.is_empty() {
+            return self.value_at(uv, lambda);
+        }
+
+        let level = level.min(self.levels.len() as Float);
+        let lo = level.floor() as usize;
+        let t = level - lo as Float;
+        let level_at = |l: usize| -> &Self {
+            if l == 0 { self } else { &self.levels[l - 1] }
+        };
+
+        let c = level_at(lo).value_at(uv, lambda);
+        if t == 0.0 {
+            c
+        } else {
+            c * (1.0 - t) + level_at(lo + 1).value_at(uv, lambda) * t
+        }
+    }
+
+    /// Number of mipmap levels in addition to the full resolution image
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
     /// Get the power of the texture, or average over all of the pixels
     pub fn power(&self, lambda: &ColorWavelength) -> Color {
         self.mean.sample(lambda)
     }
 
     /// Creates an `image` struct from a file at `path`
-    pub fn from_path(path: &str) -> Result<Self, DecodingError> {
+    pub fn from_path(path: &str) -> io::Result<Self> {
         println!("Decoding \"{}\"", path);
         Self::from_file(File::open(path)?)
     }
 
     /// Create image file from a Radiance HDR image file
-    pub fn from_hdri(path: &str) -> Result<Self, std::io::Error> {
+    pub fn from_hdri(path: &str) -> io::Result<Self> {
         println!("Decoding HDR image \"{}\"", path);
         let bytes = fs::read(path)?;
         Self::from_hdri_bytes(bytes.as_slice())
     }
 
-    /// Radiance HDR image file byte content
-    pub fn from_hdri_bytes<R: BufRead>(bytes: R) -> Result<Self, std::io::Error> {
-        let mut reader = BufReader::new(bytes);
-        let mut buffer = Vec::new();
-
-        let ascii_lf = 10;
-        let ascii_plus = 43;
-        let ascii_minus = 45;
-        reader.read_until(ascii_lf, &mut buffer)?;
-        let header = std::str::from_utf8(&buffer).unwrap();
-        assert!(header.trim() == "#?RADIANCE");
-
-        let width;
-        let height;
-        loop {
-            buffer.clear();
-            reader.read_until(ascii_lf, &mut buffer)?;
-            if buffer[0] == ascii_plus || buffer[0] == ascii_minus {
-                let fields: Vec<&str> = std::str::from_utf8(&buffer).unwrap()
-                    .split_whitespace().collect();
-                assert!(fields[0].chars().nth(0) == Some('-'));
-                assert!(fields[2].chars().nth(0) == Some('+'));
-                height = fields[1].parse::<u32>().unwrap();
-                width = fields[3].parse::<u32>().unwrap();
-                break;
-            }
-        }
+    /// Radiance HDR image file byte content, uplifted as an illuminant
+    pub fn from_hdri_bytes<R: BufRead>(read: R) -> io::Result<Self> {
+        Self::from_file_encoded(read, ColorEncoding::Linear, Uplift::Illuminant)
+    }
 
+    /// Creates an `image` from `file`. Supports Radiance HDR, PNG, JPEG
+    /// and TGA images, all but HDR are assumed to be sRGB encoded.
+    pub fn from_file<R: Read>(mut read: R) -> io::Result<Self> {
         let mut bytes = Vec::new();
-        while let Ok(len) = reader.read_until(0x00, &mut bytes) {
-            if len == 0 { break; }
-        }
-
-        let chunk_size = 4;
-        assert!(bytes.len() as u32 == width * height * chunk_size as u32);
+        read.read_to_end(&mut bytes)?;
+        let uplift = if hdr::is_hdr(&bytes) { Uplift::Illuminant } else { Uplift::default() };
 
-        let sum_rgb = bytes.chunks(chunk_size).fold(RGB::BLACK, |acc, chk| {
-            acc + RGB::from_rgbe(chk[0], chk[1], chk[2], chk[3])
-        });
-        let mean = Spectrum::from_rgb(sum_rgb / (width * height) as Float);
+        Self::from_file_encoded(bytes.as_slice(), ColorEncoding::sRGB, uplift)
+    }
 
-        let buffer: Vec<Spectrum> = bytes.chunks(chunk_size)
-            .map(|chk| Spectrum::from_rgb(RGB::from_rgbe(chk[0], chk[1], chk[2], chk[3])))
-            .collect();
+    /// Creates an `image` from `file` with pixels encoded as `encoding`,
+    /// uplifted to spectra with `uplift`. Radiance HDR images are always
+    /// linear.
+    pub fn from_file_encoded<R: Read>(
+        mut read: R,
+        encoding: ColorEncoding,
+        uplift: Uplift,
+    ) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        read.read_to_end(&mut bytes)?;
+
+        let (rgb, width, height) = if hdr::is_hdr(&bytes) {
+            hdr::decode(&bytes)?
+        } else {
+            let pixels = Self::decode(bytes.as_slice())?;
+            let rgb = pixels.rgba.iter()
+                .map(|px| match encoding {
+                    ColorEncoding::sRGB => RGB::from_srgb16(px[0], px[1], px[2]),
+                    ColorEncoding::Linear => RGB::new(
+                        Pixels::unorm(px, 0),
+                        Pixels::unorm(px, 1),
+                        Pixels::unorm(px, 2),
+                    ),
+                })
+                .collect();
+            (rgb, pixels.width, pixels.height)
+        };
 
-        assert!(buffer.len() == (width * height) as usize);
         println!("Decoded succesfully");
-        Ok(Self { width, height, buffer, mean })
+        Ok(Self::from_rgb(rgb, width, height, uplift))
     }
 
-    /// Creates an `image` from `file`
-    pub fn from_file<R: Read>(read: R) -> Result<Self, DecodingError> {
-        let (pixels, info) = Self::decode_png(read)?;
-
-        let sum_rgb = pixels.iter()
-            .fold(RGB::BLACK, |acc, rgb| acc + RGB::from_srgb(rgb[0], rgb[1], rgb[2]));
-        let mean = Spectrum::from_rgb(sum_rgb / pixels.len() as Float);
-
-        let buffer = pixels.iter()
-            .map(|rgb| Spectrum::from_srgb(rgb[0], rgb[1], rgb[2]))
-            .collect();
-
-        let width = info.width;
-        let height = info.height;
+    /// Mipmapped image of `width` x `height` linear RGB pixels stored
+    /// row by row in `rgb`, starting from the top row. Pixels are uplifted
+    /// to spectra with `uplift`.
+    pub fn from_rgb(rgb: Vec<RGB>, width: u32, height: u32, uplift: Uplift) -> Self {
+        assert!(rgb.len() == (width * height) as usize);
+
+        let sum_rgb = rgb.iter().fold(RGB::BLACK, |mut acc, c| { acc += c; acc });
+        let mean = Spectrum::uplift(sum_rgb / rgb.len() as Float, uplift);
+
+        let mut levels = Vec::new();
+        let (mut level, mut w, mut h) = (rgb, width, height);
+        while w > 1 || h > 1 {
+            let (w_half, h_half) = ((w / 2).max(1), (h / 2).max(1));
+            let rgb_half = (0..w_half * h_half)
+                .map(|idx| {
+                    let (x, y) = (2 * (idx % w_half), 2 * (idx / w_half));
+                    let (xi, yi) = ((x + 1).min(w - 1), (y + 1).min(h - 1));
+                    let px = |x: u32, y: u32| level[(x + y * w) as usize].clone();
+                    (px(x, y) + px(xi, y) + px(x, yi) + px(xi, yi)) / 4.0
+                })
+                .collect();
 
-        println!("Decoded succesfully");
-        Ok(Self {
-            buffer,
-            width,
-            height,
-            mean,
-        })
+            levels.push((level, w, h));
+            (level, w, h) = (rgb_half, w_half, h_half);
+        }
+        levels.push((level, w, h));
+
+        let mut levels = levels.into_iter()
+            .map(|(rgb, width, height)| Self {
+                buffer: rgb.into_iter().map(|c| Spectrum::uplift(c, uplift)).collect(),
+                width,
+                height,
+                mean: mean.clone(),
+                levels: Vec::new(),
+            });
+
+        let mut image = levels.next().unwrap();
+        image.levels = levels.collect();
+        image
     }
 }
@@ -0,0 +1,263 @@
+use crate::binary::MAX_RESERVE;
+use jpeg_decoder::PixelFormat;
+use png::{BitDepth, Decoder, Transformations};
+use std::io::{self, Read};
+
+/// Largest value of a channel
+pub const CHANNEL_MAX: u16 = u16::MAX;
+
+/// Decoded low dynamic range image. Channels are widened to 16 bits
+/// regardless of the bit depth of the source.
+pub struct Pixels {
+    /// RGBA channels of the pixels stored row by row, starting from the top row
+    pub rgba: Vec<[u16; 4]>,
+    /// Width of the image
+    pub width: u32,
+    /// Height of the image
+    pub height: u32,
+}
+
+impl Pixels {
+    /// Channel `c` of the pixel `px` mapped to `\[0,1\]`
+    #[inline]
+    pub fn unorm(px: &[u16; 4], c: usize) -> crate::Float {
+        px[c] as crate::Float / CHANNEL_MAX as crate::Float
+    }
+}
+
+/// Function to create io::Error for malformed images
+pub fn decode_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Widen an 8-bit channel to 16 bits
+#[inline]
+fn widen(c: u8) -> u16 {
+    c as u16 * 257
+}
+
+/// Decodes a PNG, JPEG or TGA image. The format is detected from the
+/// content, as TGA files do not have a signature it is tried last.
+pub fn decode<R: Read>(mut read: R) -> io::Result<Pixels> {
+    let mut bytes = Vec::new();
+    read.read_to_end(&mut bytes)?;
+
+    let pixels = if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        decode_png(&bytes)?
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        decode_jpeg(&bytes)?
+    } else {
+        decode_tga(&bytes)?
+    };
+
+    if pixels.rgba.len() != pixels.width as usize * pixels.height as usize {
+        return Err(decode_error("Image data does not match its dimensions"));
+    }
+    Ok(pixels)
+}
+
+/// Decodes PNG images of any color type and bit depth
+fn decode_png(bytes: &[u8]) -> io::Result<Pixels> {
+    let mut decoder = Decoder::new(bytes);
+    // palettes and low bit depths to 8 bits and transparency to alpha
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let sample_size = if info.bit_depth == BitDepth::Sixteen { 2 } else { 1 };
+    let channels = info.color_type.samples();
+    let sample = |px: &[u8], c: usize| -> u16 {
+        if sample_size == 2 {
+            u16::from_be_bytes([px[2 * c], px[2 * c + 1]])
+        } else {
+            widen(px[c])
+        }
+    };
+
+    let rgba = buffer[..info.buffer_size()]
+        .chunks_exact(channels * sample_size)
+        .map(|px| match channels {
+            1 => [sample(px, 0), sample(px, 0), sample(px, 0), CHANNEL_MAX],
+            2 => [sample(px, 0), sample(px, 0), sample(px, 0), sample(px, 1)],
+            3 => [sample(px, 0), sample(px, 1), sample(px, 2), CHANNEL_MAX],
+            _ => [sample(px, 0), sample(px, 1), sample(px, 2), sample(px, 3)],
+        })
+        .collect();
+
+    Ok(Pixels { rgba, width: info.width, height: info.height })
+}
+
+/// Decodes baseline, progressive and lossless JPEG images
+fn decode_jpeg(bytes: &[u8]) -> io::Result<Pixels> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let buffer = decoder.decode()
+        .map_err(|e| decode_error(&e.to_string()))?;
+    let info = decoder.info()
+        .ok_or_else(|| decode_error("Missing JPEG metadata"))?;
+
+    let rgba = match info.pixel_format {
+        PixelFormat::L8 => buffer.iter()
+            .map(|&l| [widen(l), widen(l), widen(l), CHANNEL_MAX])
+            .collect(),
+        PixelFormat::L16 => buffer.chunks_exact(2)
+            .map(|l| u16::from_ne_bytes([l[0], l[1]]))
+            .map(|l| [l, l, l, CHANNEL_MAX])
+            .collect(),
+        PixelFormat::RGB24 => buffer.chunks_exact(3)
+            .map(|rgb| [widen(rgb[0]), widen(rgb[1]), widen(rgb[2]), CHANNEL_MAX])
+            .collect(),
+        PixelFormat::CMYK32 => buffer.chunks_exact(4)
+            .map(|cmyk| {
+                let k = 255 - cmyk[3] as u16;
+                let c = |c: u8| (255 - c as u16) * k / 255;
+                [widen(c(cmyk[0]) as u8), widen(c(cmyk[1]) as u8),
+                 widen(c(cmyk[2]) as u8), CHANNEL_MAX]
+            })
+            .collect(),
+    };
+
+    Ok(Pixels { rgba, width: info.width as u32, height: info.height as u32 })
+}
+
+/// Kind of pixel data stored in a TGA file
+#[derive(PartialEq)]
+enum TgaKind {
+    ColorMapped,
+    TrueColor,
+    Grayscale,
+}
+
+/// Bytes `start..start + len` of TGA data
+fn tga_slice(bytes: &[u8], start: usize, len: usize) -> io::Result<&[u8]> {
+    bytes.get(start..start + len)
+        .ok_or_else(|| decode_error("Truncated TGA data"))
+}
+
+/// 15, 16, 24 or 32-bit TGA color stored as little endian BGR(A)
+fn tga_color(px: &[u8], depth: u8, has_alpha: bool) -> [u16; 4] {
+    match depth {
+        15 | 16 => {
+            let v = u16::from_le_bytes([px[0], px[1]]);
+            let c = |shift: u16| (((v >> shift) & 0x1F) as u32 * CHANNEL_MAX as u32 / 0x1F) as u16;
+            [c(10), c(5), c(0), CHANNEL_MAX]
+        }
+        24 => [widen(px[2]), widen(px[1]), widen(px[0]), CHANNEL_MAX],
+        _ => {
+            let alpha = if has_alpha { widen(px[3]) } else { CHANNEL_MAX };
+            [widen(px[2]), widen(px[1]), widen(px[0]), alpha]
+        }
+    }
+}
+
+/// Decodes uncompressed and run-length encoded TGA images that are
+/// color mapped, true color or grayscale
+fn decode_tga(bytes: &[u8]) -> io::Result<Pixels> {
+    let header = tga_slice(bytes, 0, 18)
+        .map_err(|_| decode_error("Unknown image format"))?;
+    let u16_at = |idx: usize| u16::from_le_bytes([header[idx], header[idx + 1]]);
+
+    let id_length = header[0] as usize;
+    let color_map_type = header[1];
+    let (kind, rle) = match header[2] {
+        1 => (TgaKind::ColorMapped, false),
+        2 => (TgaKind::TrueColor, false),
+        3 => (TgaKind::Grayscale, false),
+        9 => (TgaKind::ColorMapped, true),
+        10 => (TgaKind::TrueColor, true),
+        11 => (TgaKind::Grayscale, true),
+        _ => return Err(decode_error("Unknown image format")),
+    };
+    let map_first = u16_at(3) as usize;
+    let map_length = u16_at(5) as usize;
+    let map_depth = header[7];
+    let width = u16_at(12) as u32;
+    let height = u16_at(14) as u32;
+    let depth = header[16];
+    let descriptor = header[17];
+    let has_alpha = descriptor & 0x0F != 0;
+
+    let depth_ok = match kind {
+        TgaKind::ColorMapped => color_map_type == 1 && matches!(depth, 8 | 16),
+        TgaKind::TrueColor => matches!(depth, 15 | 16 | 24 | 32),
+        TgaKind::Grayscale => matches!(depth, 8 | 16),
+    };
+    if color_map_type > 1 || !depth_ok || width == 0 || height == 0 {
+        return Err(decode_error("Unknown image format"));
+    }
+
+    let mut pos = 18 + id_length;
+    let color_map = if color_map_type == 1 {
+        if !matches!(map_depth, 15 | 16 | 24 | 32) {
+            return Err(decode_error("Unsupported TGA color map depth"));
+        }
+        let entry_size = (map_depth as usize).div_ceil(8);
+        let data = tga_slice(bytes, pos, map_length * entry_size)?;
+        pos += map_length * entry_size;
+        data.chunks_exact(entry_size)
+            .map(|px| tga_color(px, map_depth, has_alpha))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let pixel_size = (depth as usize).div_ceil(8);
+    let to_rgba = |px: &[u8]| -> io::Result<[u16; 4]> {
+        match kind {
+            TgaKind::ColorMapped => {
+                let idx = if pixel_size == 2 {
+                    u16::from_le_bytes([px[0], px[1]]) as usize
+                } else {
+                    px[0] as usize
+                };
+                idx.checked_sub(map_first)
+                    .and_then(|idx| color_map.get(idx).copied())
+                    .ok_or_else(|| decode_error("TGA color map index out of bounds"))
+            }
+            TgaKind::Grayscale => {
+                let l = widen(px[0]);
+                let alpha = if pixel_size == 2 { widen(px[1]) } else { CHANNEL_MAX };
+                Ok([l, l, l, alpha])
+            }
+            TgaKind::TrueColor => Ok(tga_color(px, depth, has_alpha)),
+        }
+    };
+
+    let num_pixels = width as usize * height as usize;
+    // the header may claim more than the data has
+    let mut rgba = Vec::with_capacity(num_pixels.min(MAX_RESERVE));
+    if rle {
+        while rgba.len() < num_pixels {
+            let packet = tga_slice(bytes, pos, 1)?[0];
+            pos += 1;
+            let count = (packet & 0x7F) as usize + 1;
+            if packet & 0x80 != 0 {
+                let px = to_rgba(tga_slice(bytes, pos, pixel_size)?)?;
+                pos += pixel_size;
+                rgba.extend(std::iter::repeat_n(px, count));
+            } else {
+                for _ in 0..count {
+                    rgba.push(to_rgba(tga_slice(bytes, pos, pixel_size)?)?);
+                    pos += pixel_size;
+                }
+            }
+        }
+        rgba.truncate(num_pixels);
+    } else {
+        let data = tga_slice(bytes, pos, num_pixels * pixel_size)?;
+        for px in data.chunks_exact(pixel_size) {
+            rgba.push(to_rgba(px)?);
+        }
+    }
+
+    // origin in the bottom left unless the descriptor says otherwise
+    let row_length = width as usize;
+    if descriptor & 0x10 != 0 {
+        rgba.chunks_exact_mut(row_length).for_each(|row| row.reverse());
+    }
+    if descriptor & 0x20 == 0 {
+        rgba = rgba.chunks_exact(row_length).rev().flatten().copied().collect();
+    }
+
+    Ok(Pixels { rgba, width, height })
+}
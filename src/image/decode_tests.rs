@@ -0,0 +1,164 @@
+use super::*;
+use png::{BitDepth, ColorType, Encoder};
+
+/// TGA header for a `width` x `height` image
+fn tga_header(image_type: u8, width: u16, height: u16, depth: u8, descriptor: u8) -> Vec<u8> {
+    let mut header = vec![0; 18];
+    header[2] = image_type;
+    header[12..14].copy_from_slice(&width.to_le_bytes());
+    header[14..16].copy_from_slice(&height.to_le_bytes());
+    header[16] = depth;
+    header[17] = descriptor;
+    header
+}
+
+fn encode_png(width: u32, height: u32, color: ColorType, depth: BitDepth, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut encoder = Encoder::new(&mut bytes, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    if color == ColorType::Indexed {
+        encoder.set_palette(vec![255, 0, 0, 0, 0, 255]);
+    }
+    encoder.write_header().unwrap().write_image_data(data).unwrap();
+    bytes
+}
+
+/// Radiance HDR file with the given scanline data
+fn hdr_file(width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let mut bytes = format!(
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width
+    ).into_bytes();
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn tga_bottom_left_origin() {
+    // rows stored bottom up, pixels as BGR
+    let mut bytes = tga_header(2, 2, 2, 24, 0);
+    bytes.extend_from_slice(&[0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255]);
+    let pixels = Image::<Float>::decode(bytes.as_slice()).unwrap();
+
+    let (r, g, b, w) = ([u16::MAX, 0, 0], [0, u16::MAX, 0], [0, 0, u16::MAX], [u16::MAX; 3]);
+    let expected = [b, w, r, g];
+    for (px, rgb) in pixels.rgba.iter().zip(expected) {
+        assert!(px[..3] == rgb);
+    }
+}
+
+#[test]
+fn tga_rle_matches_raw() {
+    let raw = [10, 20, 30, 10, 20, 30, 10, 20, 30, 40, 50, 60];
+    let mut bytes = tga_header(2, 4, 1, 24, 0x20);
+    bytes.extend_from_slice(&raw);
+    let raw = Image::<Float>::decode(bytes.as_slice()).unwrap();
+
+    // run of three followed by a single raw pixel
+    let mut bytes = tga_header(10, 4, 1, 24, 0x20);
+    bytes.extend_from_slice(&[0x82, 10, 20, 30, 0x00, 40, 50, 60]);
+    let rle = Image::<Float>::decode(bytes.as_slice()).unwrap();
+
+    assert!(raw.rgba == rle.rgba);
+}
+
+#[test]
+fn truncated_tga_is_error() {
+    let mut bytes = tga_header(2, 4, 4, 24, 0);
+    bytes.extend_from_slice(&[0; 10]);
+    assert!(Image::<Float>::decode(bytes.as_slice()).is_err());
+}
+
+#[test]
+fn huge_claimed_size_is_error() {
+    let mut bytes = tga_header(10, u16::MAX, u16::MAX, 24, 0);
+    bytes.extend_from_slice(&[0xFF, 10, 20, 30]);
+    assert!(Image::<Float>::decode(bytes.as_slice()).is_err());
+
+    let bytes = hdr_file(u32::MAX, u32::MAX, &[10, 20, 30, 130]);
+    assert!(hdr::decode(&bytes).is_err());
+}
+
+#[test]
+fn unknown_format_is_error() {
+    assert!(Image::<Float>::height_from_file(&b"not an image"[..]).is_err());
+    assert!(Image::<Spectrum>::from_file(&b""[..]).is_err());
+}
+
+#[test]
+fn png_sixteen_bit() {
+    let data = [0x12, 0x34, 0x00, 0x00, 0xFF, 0xFF];
+    let bytes = encode_png(1, 1, ColorType::Rgb, BitDepth::Sixteen, &data);
+    let pixels = Image::<Float>::decode(bytes.as_slice()).unwrap();
+
+    assert!(pixels.rgba[0] == [0x1234, 0, u16::MAX, u16::MAX]);
+}
+
+#[test]
+fn png_low_bit_depth_indexed() {
+    // 1-bit indices 0, 1, 1, 0 into a red and blue palette
+    let bytes = encode_png(4, 1, ColorType::Indexed, BitDepth::One, &[0b0110_0000]);
+    let pixels = Image::<Float>::decode(bytes.as_slice()).unwrap();
+
+    let (red, blue) = ([u16::MAX, 0, 0, u16::MAX], [0, 0, u16::MAX, u16::MAX]);
+    assert!(pixels.rgba == vec![red, blue, blue, red]);
+}
+
+#[test]
+fn hdr_rle_matches_flat() {
+    let width = 8;
+    let rgbe: Vec<[u8; 4]> = (0..width)
+        .map(|x| [x as u8, 7, if x < 4 { 1 } else { 2 }, 130])
+        .collect();
+    let flat = hdr_file(width, 1, rgbe.as_flattened());
+
+    let mut rle = vec![2, 2, 0, width as u8];
+    // literal run of red values
+    rle.push(width as u8);
+    rle.extend(rgbe.iter().map(|px| px[0]));
+    // green as a single run, blue and exponent in two runs
+    rle.extend_from_slice(&[128 + 8, 7]);
+    rle.extend_from_slice(&[128 + 4, 1, 128 + 4, 2]);
+    rle.extend_from_slice(&[128 + 4, 130, 128 + 4, 130]);
+    let rle = hdr_file(width, 1, &rle);
+
+    let (flat, w, h) = hdr::decode(&flat).unwrap();
+    let (rle, _, _) = hdr::decode(&rle).unwrap();
+    assert!(w == width && h == 1);
+    for (a, b) in flat.iter().zip(rle.iter()) {
+        assert!(a.r() == b.r() && a.g() == b.g() && a.b() == b.b());
+    }
+}
+
+#[test]
+fn hdr_old_rle_repeats() {
+    let data = [10, 20, 30, 128, 1, 1, 1, 2];
+    let (rgb, _, _) = hdr::decode(&hdr_file(3, 1, &data)).unwrap();
+    assert!(rgb.iter().all(|c| c.r() == rgb[0].r() && c.b() == rgb[0].b()));
+    // (10 + 0.5) / 256
+    assert!((rgb[0].r() - 10.5 / 256.0).abs() < 1e-10);
+}
+
+#[test]
+fn truncated_hdr_is_error() {
+    let data = [2, 2, 0, 8, 8, 1, 2, 3];
+    assert!(hdr::decode(&hdr_file(8, 1, &data)).is_err());
+    assert!(hdr::decode(&hdr_file(2, 2, &[0; 12])).is_err());
+    assert!(Image::<Spectrum>::from_hdri_bytes(&b"#?RADIANCE\n"[..]).is_err());
+}
+
+#[test]
+fn linear_encoding_skips_gamma() {
+    let bytes = encode_png(1, 1, ColorType::Grayscale, BitDepth::Eight, &[128]);
+    let lambda = ColorWavelength::sample(0.5);
+    // illuminant uplift keeps the brightness in the scale of the spectrum
+    let value = |encoding: ColorEncoding| -> Float {
+        let img = Image::<Spectrum>::from_file_encoded(
+            bytes.as_slice(), encoding, Uplift::Illuminant
+        ).unwrap();
+        img.power(&lambda).max()
+    };
+
+    assert!((value(ColorEncoding::Linear) - 128.0 / 255.0).abs() < 1e-3);
+    assert!((value(ColorEncoding::sRGB) - RGB::srgb_decode(128)).abs() < 1e-3);
+}
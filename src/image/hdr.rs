@@ -0,0 +1,141 @@
+use super::decode::decode_error;
+use crate::binary::MAX_RESERVE;
+use crate::tracer::RGB;
+use std::io;
+
+/// Does `bytes` start with the signature of a Radiance HDR file?
+pub fn is_hdr(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"#?")
+}
+
+/// Decodes a Radiance HDR file with flat, run-length encoded or old
+/// style run-length encoded scanlines. Returns the linear RGB pixels
+/// stored row by row from the top, the width and the height.
+pub fn decode(bytes: &[u8]) -> io::Result<(Vec<RGB>, u32, u32)> {
+    if !is_hdr(bytes) {
+        return Err(decode_error("Missing Radiance HDR signature"));
+    }
+
+    let mut pos = 0;
+    let mut next_line = || -> io::Result<&str> {
+        let len = bytes[pos..].iter().position(|&b| b == b'\n')
+            .ok_or_else(|| decode_error("Truncated Radiance HDR header"))?;
+        let line = std::str::from_utf8(&bytes[pos..pos + len])
+            .map_err(|_| decode_error("Malformed Radiance HDR header"))?;
+        pos += len + 1;
+        Ok(line.trim())
+    };
+
+    let (width, height) = loop {
+        let line = next_line()?;
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(decode_error("Only RGBE Radiance HDR files are supported"));
+            }
+        } else if line.starts_with('-') || line.starts_with('+') {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 || fields[0] != "-Y" || fields[2] != "+X" {
+                return Err(decode_error("Unsupported Radiance HDR orientation"));
+            }
+            let parse = |s: &str| s.parse::<u32>()
+                .map_err(|_| decode_error("Malformed Radiance HDR resolution"));
+            break (parse(fields[3])?, parse(fields[1])?);
+        }
+    };
+
+    // the header may claim more than the data has
+    let num_pixels = width as usize * height as usize;
+    let mut rgbe = Vec::with_capacity(num_pixels.min(MAX_RESERVE));
+    for _ in 0..height {
+        read_scanline(bytes, &mut pos, width as usize, &mut rgbe)?;
+    }
+
+    let rgb = rgbe.iter()
+        .map(|px| RGB::from_rgbe(px[0], px[1], px[2], px[3]))
+        .collect();
+
+    Ok((rgb, width, height))
+}
+
+/// Next byte of scanline data
+fn next_byte(bytes: &[u8], pos: &mut usize) -> io::Result<u8> {
+    let b = bytes.get(*pos)
+        .ok_or_else(|| decode_error("Truncated Radiance HDR data"))?;
+    *pos += 1;
+    Ok(*b)
+}
+
+/// Decodes one scanline of `width` RGBE pixels starting at `pos` to `out`
+fn read_scanline(
+    bytes: &[u8],
+    pos: &mut usize,
+    width: usize,
+    out: &mut Vec<[u8; 4]>,
+) -> io::Result<()> {
+    let start = bytes.get(*pos..*pos + 4)
+        .ok_or_else(|| decode_error("Truncated Radiance HDR data"))?;
+
+    let is_rle = (8..0x8000).contains(&width)
+        && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0;
+
+    if is_rle {
+        if ((start[2] as usize) << 8 | start[3] as usize) != width {
+            return Err(decode_error("Radiance HDR scanline width mismatch"));
+        }
+        *pos += 4;
+
+        // each channel is encoded separately
+        let mut line = vec![[0; 4]; width];
+        for c in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = next_byte(bytes, pos)? as usize;
+                let (count, run) = if count > 128 {
+                    (count - 128, true)
+                } else {
+                    (count, false)
+                };
+                if count == 0 || x + count > width {
+                    return Err(decode_error("Bad Radiance HDR run length"));
+                }
+
+                if run {
+                    let value = next_byte(bytes, pos)?;
+                    line[x..x + count].iter_mut().for_each(|px| px[c] = value);
+                } else {
+                    for px in &mut line[x..x + count] {
+                        px[c] = next_byte(bytes, pos)?;
+                    }
+                }
+                x += count;
+            }
+        }
+        out.extend(line);
+    } else {
+        // flat pixels, possibly with old style runs of the previous pixel
+        let line_start = out.len();
+        let mut shift = 0;
+        while out.len() - line_start < width {
+            let px = [
+                next_byte(bytes, pos)?, next_byte(bytes, pos)?,
+                next_byte(bytes, pos)?, next_byte(bytes, pos)?,
+            ];
+
+            if px[0] == 1 && px[1] == 1 && px[2] == 1 {
+                let count = (px[3] as usize).checked_shl(shift).unwrap_or(usize::MAX);
+                let prev = *out[line_start..].last()
+                    .ok_or_else(|| decode_error("Bad Radiance HDR run length"))?;
+                if count > width - (out.len() - line_start) {
+                    return Err(decode_error("Bad Radiance HDR run length"));
+                }
+                out.extend(std::iter::repeat_n(prev, count));
+                shift += 8;
+            } else {
+                out.push(px);
+                shift = 0;
+            }
+        }
+    }
+
+    Ok(())
+}
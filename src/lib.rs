@@ -10,17 +10,20 @@
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
 
-pub use image::Image;
+pub use image::{ColorEncoding, Image};
 pub use perlin::Perlin;
+pub use rng::Xorshift;
 pub use renderer::Renderer;
 pub use samplers::SamplerType;
 pub use tone_mapping::ToneMap;
 
-/// Wavefront .mtl and .obj parser
+/// Wavefront .mtl and .obj parser, and a curve loader
 pub mod parser;
 /// The heart.
 pub mod tracer;
 
+/// Little endian encoding of acceleration structures to cache them on disk
+mod binary;
 /// `Float` with built in tracking of floating point error
 mod efloat;
 /// Utility functions to format output
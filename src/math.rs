@@ -7,6 +7,8 @@ pub mod vec3;
 pub mod mat3;
 pub mod mat4;
 pub mod transform;
+/// Four wide `f64` vectors to intersect four boxes or triangles at once
+pub mod simd;
 
 #[cfg(test)]
 /// Chi2 CDF
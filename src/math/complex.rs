@@ -42,6 +42,16 @@ impl Complex {
         }
     }
 
+    /// exp(x + iy) = e^x (cos(y) + i sin(y))
+    #[inline]
+    pub fn exp(&self) -> Complex {
+        let r = self.Re.exp();
+        Complex {
+            Re: r * self.Im.cos(),
+            Im: r * self.Im.sin(),
+        }
+    }
+
     #[inline]
     fn norm(&self) -> Float {
         self.norm_sqr().sqrt()
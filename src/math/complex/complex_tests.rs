@@ -37,3 +37,11 @@ fn complex_sub_id() {
     let a = Complex::new(1.23, 4.56);
     assert!((a - a).norm() == 0.0);
 }
+
+#[test]
+fn complex_exp_unit_circle() {
+    let y: Float = 1.23;
+    let a = Complex::new(0.0, y).exp();
+    assert!((a.norm() - 1.0).abs() < crate::EPSILON);
+    assert!((a.Re - y.cos()).abs() < crate::EPSILON);
+}
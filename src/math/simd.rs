@@ -0,0 +1,303 @@
+// Explicit SSE2 or AVX code with the `simd` feature on x86-64, AVX needs to
+// be enabled at compile time, e.g. `-C target-cpu=native`. Otherwise a
+// plain array the compiler is free to vectorize. All of them round
+// identically to scalar `f64` code.
+
+#[cfg(test)]
+mod simd_tests;
+
+pub use imp::F64x4;
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+mod imp {
+    use std::ops::{ Add, Div, Mul, Sub };
+
+    /// Four `f64` lanes
+    #[derive(Clone, Copy, Debug)]
+    pub struct F64x4([f64; 4]);
+
+    impl F64x4 {
+        /// Lanes from an array
+        #[inline(always)]
+        pub fn new(lanes: [f64; 4]) -> Self { Self(lanes) }
+
+        /// `v` in every lane
+        #[inline(always)]
+        pub fn splat(v: f64) -> Self { Self([v; 4]) }
+
+        /// Lanes as an array
+        #[inline(always)]
+        pub fn to_array(self) -> [f64; 4] { self.0 }
+
+        #[inline(always)]
+        fn map2(self, o: Self, f: impl Fn(f64, f64) -> f64) -> Self {
+            Self([f(self.0[0], o.0[0]), f(self.0[1], o.0[1]),
+                  f(self.0[2], o.0[2]), f(self.0[3], o.0[3])])
+        }
+
+        #[inline(always)]
+        fn mask(self, o: Self, f: impl Fn(f64, f64) -> bool) -> u32 {
+            (f(self.0[0], o.0[0]) as u32)
+                | (f(self.0[1], o.0[1]) as u32) << 1
+                | (f(self.0[2], o.0[2]) as u32) << 2
+                | (f(self.0[3], o.0[3]) as u32) << 3
+        }
+
+        /// Lane-wise minimum, ignores NaN like `f64::min`
+        #[inline(always)]
+        pub fn min(self, o: Self) -> Self { self.map2(o, f64::min) }
+
+        /// Lane-wise maximum, ignores NaN like `f64::max`
+        #[inline(always)]
+        pub fn max(self, o: Self) -> Self { self.map2(o, f64::max) }
+
+        /// Bit mask of lanes where `self < o`
+        #[inline(always)]
+        pub fn lt_mask(self, o: Self) -> u32 { self.mask(o, |a, b| a < b) }
+
+        /// Bit mask of lanes where `self <= o`
+        #[inline(always)]
+        pub fn le_mask(self, o: Self) -> u32 { self.mask(o, |a, b| a <= b) }
+
+        /// Bit mask of lanes where `self > o`
+        #[inline(always)]
+        pub fn gt_mask(self, o: Self) -> u32 { o.lt_mask(self) }
+
+        /// Bit mask of lanes where `self == o`
+        #[inline(always)]
+        pub fn eq_mask(self, o: Self) -> u32 { self.mask(o, |a, b| a == b) }
+    }
+
+    impl Add for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, o: Self) -> Self { self.map2(o, |a, b| a + b) }
+    }
+
+    impl Sub for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn sub(self, o: Self) -> Self { self.map2(o, |a, b| a - b) }
+    }
+
+    impl Mul for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, o: Self) -> Self { self.map2(o, |a, b| a * b) }
+    }
+
+    impl Div for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn div(self, o: Self) -> Self { self.map2(o, |a, b| a / b) }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "avx"))]
+#[allow(unused_unsafe)]
+mod imp {
+    use std::arch::x86_64::*;
+    use std::ops::{ Add, Div, Mul, Sub };
+
+    /// Four `f64` lanes in an AVX register
+    #[derive(Clone, Copy, Debug)]
+    pub struct F64x4(__m256d);
+
+    impl F64x4 {
+        /// Lanes from an array
+        #[inline(always)]
+        pub fn new(lanes: [f64; 4]) -> Self {
+            Self(unsafe { _mm256_set_pd(lanes[3], lanes[2], lanes[1], lanes[0]) })
+        }
+
+        /// `v` in every lane
+        #[inline(always)]
+        pub fn splat(v: f64) -> Self { Self(unsafe { _mm256_set1_pd(v) }) }
+
+        /// Lanes as an array
+        #[inline(always)]
+        pub fn to_array(self) -> [f64; 4] {
+            let mut lanes = [0.0; 4];
+            unsafe { _mm256_storeu_pd(lanes.as_mut_ptr(), self.0) };
+            lanes
+        }
+
+        /// Lane-wise minimum, ignores NaN like `f64::min`
+        #[inline(always)]
+        pub fn min(self, o: Self) -> Self {
+            // `_mm256_min_pd` returns `o` if either is NaN
+            unsafe {
+                let nan = _mm256_cmp_pd::<_CMP_UNORD_Q>(o.0, o.0);
+                Self(_mm256_blendv_pd(_mm256_min_pd(self.0, o.0), self.0, nan))
+            }
+        }
+
+        /// Lane-wise maximum, ignores NaN like `f64::max`
+        #[inline(always)]
+        pub fn max(self, o: Self) -> Self {
+            unsafe {
+                let nan = _mm256_cmp_pd::<_CMP_UNORD_Q>(o.0, o.0);
+                Self(_mm256_blendv_pd(_mm256_max_pd(self.0, o.0), self.0, nan))
+            }
+        }
+
+        /// Bit mask of lanes where `self < o`
+        #[inline(always)]
+        pub fn lt_mask(self, o: Self) -> u32 {
+            unsafe { _mm256_movemask_pd(_mm256_cmp_pd::<_CMP_LT_OQ>(self.0, o.0)) as u32 }
+        }
+
+        /// Bit mask of lanes where `self <= o`
+        #[inline(always)]
+        pub fn le_mask(self, o: Self) -> u32 {
+            unsafe { _mm256_movemask_pd(_mm256_cmp_pd::<_CMP_LE_OQ>(self.0, o.0)) as u32 }
+        }
+
+        /// Bit mask of lanes where `self > o`
+        #[inline(always)]
+        pub fn gt_mask(self, o: Self) -> u32 { o.lt_mask(self) }
+
+        /// Bit mask of lanes where `self == o`
+        #[inline(always)]
+        pub fn eq_mask(self, o: Self) -> u32 {
+            unsafe { _mm256_movemask_pd(_mm256_cmp_pd::<_CMP_EQ_OQ>(self.0, o.0)) as u32 }
+        }
+    }
+
+    impl Add for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, o: Self) -> Self { Self(unsafe { _mm256_add_pd(self.0, o.0) }) }
+    }
+
+    impl Sub for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn sub(self, o: Self) -> Self { Self(unsafe { _mm256_sub_pd(self.0, o.0) }) }
+    }
+
+    impl Mul for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, o: Self) -> Self { Self(unsafe { _mm256_mul_pd(self.0, o.0) }) }
+    }
+
+    impl Div for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn div(self, o: Self) -> Self { Self(unsafe { _mm256_div_pd(self.0, o.0) }) }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(target_feature = "avx")))]
+#[allow(unused_unsafe)]
+mod imp {
+    use std::arch::x86_64::*;
+    use std::ops::{ Add, Div, Mul, Sub };
+
+    /// Four `f64` lanes in two SSE2 registers
+    #[derive(Clone, Copy, Debug)]
+    pub struct F64x4(__m128d, __m128d);
+
+    impl F64x4 {
+        /// Lanes from an array
+        #[inline(always)]
+        pub fn new(lanes: [f64; 4]) -> Self {
+            unsafe { Self(_mm_set_pd(lanes[1], lanes[0]), _mm_set_pd(lanes[3], lanes[2])) }
+        }
+
+        /// `v` in every lane
+        #[inline(always)]
+        pub fn splat(v: f64) -> Self {
+            unsafe { Self(_mm_set1_pd(v), _mm_set1_pd(v)) }
+        }
+
+        /// Lanes as an array
+        #[inline(always)]
+        pub fn to_array(self) -> [f64; 4] {
+            let mut lanes = [0.0; 4];
+            unsafe {
+                _mm_storeu_pd(lanes.as_mut_ptr(), self.0);
+                _mm_storeu_pd(lanes.as_mut_ptr().add(2), self.1);
+            }
+            lanes
+        }
+
+        #[inline(always)]
+        fn map2(self, o: Self, f: impl Fn(__m128d, __m128d) -> __m128d) -> Self {
+            Self(f(self.0, o.0), f(self.1, o.1))
+        }
+
+        #[inline(always)]
+        fn mask(self, o: Self, f: impl Fn(__m128d, __m128d) -> __m128d) -> u32 {
+            unsafe {
+                (_mm_movemask_pd(f(self.0, o.0)) | _mm_movemask_pd(f(self.1, o.1)) << 2) as u32
+            }
+        }
+
+        /// Lane-wise minimum, ignores NaN like `f64::min`
+        #[inline(always)]
+        pub fn min(self, o: Self) -> Self {
+            // `_mm_min_pd` returns `b` if either is NaN
+            self.map2(o, |a, b| unsafe {
+                let nan = _mm_cmpunord_pd(b, b);
+                _mm_or_pd(_mm_and_pd(nan, a), _mm_andnot_pd(nan, _mm_min_pd(a, b)))
+            })
+        }
+
+        /// Lane-wise maximum, ignores NaN like `f64::max`
+        #[inline(always)]
+        pub fn max(self, o: Self) -> Self {
+            self.map2(o, |a, b| unsafe {
+                let nan = _mm_cmpunord_pd(b, b);
+                _mm_or_pd(_mm_and_pd(nan, a), _mm_andnot_pd(nan, _mm_max_pd(a, b)))
+            })
+        }
+
+        /// Bit mask of lanes where `self < o`
+        #[inline(always)]
+        pub fn lt_mask(self, o: Self) -> u32 {
+            self.mask(o, |a, b| unsafe { _mm_cmplt_pd(a, b) })
+        }
+
+        /// Bit mask of lanes where `self <= o`
+        #[inline(always)]
+        pub fn le_mask(self, o: Self) -> u32 {
+            self.mask(o, |a, b| unsafe { _mm_cmple_pd(a, b) })
+        }
+
+        /// Bit mask of lanes where `self > o`
+        #[inline(always)]
+        pub fn gt_mask(self, o: Self) -> u32 { o.lt_mask(self) }
+
+        /// Bit mask of lanes where `self == o`
+        #[inline(always)]
+        pub fn eq_mask(self, o: Self) -> u32 {
+            self.mask(o, |a, b| unsafe { _mm_cmpeq_pd(a, b) })
+        }
+    }
+
+    impl Add for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, o: Self) -> Self { self.map2(o, |a, b| unsafe { _mm_add_pd(a, b) }) }
+    }
+
+    impl Sub for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn sub(self, o: Self) -> Self { self.map2(o, |a, b| unsafe { _mm_sub_pd(a, b) }) }
+    }
+
+    impl Mul for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, o: Self) -> Self { self.map2(o, |a, b| unsafe { _mm_mul_pd(a, b) }) }
+    }
+
+    impl Div for F64x4 {
+        type Output = Self;
+        #[inline(always)]
+        fn div(self, o: Self) -> Self { self.map2(o, |a, b| unsafe { _mm_div_pd(a, b) }) }
+    }
+}
@@ -0,0 +1,41 @@
+use super::*;
+
+const A: [f64; 4] = [1.0, -2.0, f64::NAN, f64::INFINITY];
+const B: [f64; 4] = [f64::NAN, 3.0, 0.5, -0.0];
+
+#[test]
+fn min_max_ignore_nan() {
+    let (a, b) = (F64x4::new(A), F64x4::new(B));
+    let (min, max) = (a.min(b).to_array(), a.max(b).to_array());
+
+    for i in 0..4 {
+        assert!(min[i] == A[i].min(B[i]));
+        assert!(max[i] == A[i].max(B[i]));
+    }
+}
+
+#[test]
+fn masks_match_scalar() {
+    let (a, b) = (F64x4::new(A), F64x4::new(B));
+    let mask = |f: fn(f64, f64) -> bool| {
+        (0..4).fold(0, |m, i| m | (f(A[i], B[i]) as u32) << i)
+    };
+
+    assert!(a.lt_mask(b) == mask(|x, y| x < y));
+    assert!(a.le_mask(b) == mask(|x, y| x <= y));
+    assert!(a.gt_mask(b) == mask(|x, y| x > y));
+    assert!(a.eq_mask(a) == mask(|x, _| x == x));
+}
+
+#[test]
+fn arithmetic_matches_scalar() {
+    let (a, b) = (F64x4::new([0.1, 7.0, -3.3, 1e-300]), F64x4::splat(0.3));
+    let lanes = a.to_array();
+
+    for (i, (s, d)) in (a + b).to_array().iter().zip((a - b).to_array()).enumerate() {
+        assert!(*s == lanes[i] + 0.3 && d == lanes[i] - 0.3);
+    }
+    for (i, (m, d)) in (a * b).to_array().iter().zip((a / b).to_array()).enumerate() {
+        assert!(*m == lanes[i] * 0.3 && d == lanes[i] / 0.3);
+    }
+}
@@ -5,6 +5,9 @@ use std::fmt;
 #[cfg(test)]
 mod transform_tests;
 
+mod animated;
+pub use animated::AnimatedTransform;
+
 // TODO: separate projection from affine
 #[derive(Clone)]
 pub struct Transform {
@@ -0,0 +1,240 @@
+use super::*;
+
+/// Maximum number of iterations in the polar decomposition
+const POLAR_MAX_ITER: usize = 100;
+/// Polar decomposition converged when the rows change less than this
+const POLAR_EPSILON: Float = 1e-12;
+/// Quaternions closer than this get linearly interpolated in slerp
+const SLERP_LINEAR: Float = 0.9995;
+
+/// Unit quaternion representing a rotation
+#[derive(Clone, Copy)]
+struct Quaternion {
+    v: Vec3,
+    w: Float,
+}
+
+impl Quaternion {
+    /// Quaternion of the rotation matrix `m`
+    fn from_mat3(m: &Mat3) -> Self {
+        let trace = m.y0.x + m.y1.y + m.y2.z;
+
+        if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            let v = Vec3::new(m.y2.y - m.y1.z, m.y0.z - m.y2.x, m.y1.x - m.y0.y);
+            Self { v: v / s, w: 0.25 * s }
+        } else if m.y0.x > m.y1.y && m.y0.x > m.y2.z {
+            let s = 2.0 * (1.0 + m.y0.x - m.y1.y - m.y2.z).sqrt();
+            let v = Vec3::new(0.25 * s, (m.y0.y + m.y1.x) / s, (m.y0.z + m.y2.x) / s);
+            Self { v, w: (m.y2.y - m.y1.z) / s }
+        } else if m.y1.y > m.y2.z {
+            let s = 2.0 * (1.0 + m.y1.y - m.y0.x - m.y2.z).sqrt();
+            let v = Vec3::new((m.y0.y + m.y1.x) / s, 0.25 * s, (m.y1.z + m.y2.y) / s);
+            Self { v, w: (m.y0.z - m.y2.x) / s }
+        } else {
+            let s = 2.0 * (1.0 + m.y2.z - m.y0.x - m.y1.y).sqrt();
+            let v = Vec3::new((m.y0.z + m.y2.x) / s, (m.y1.z + m.y2.y) / s, 0.25 * s);
+            Self { v, w: (m.y1.x - m.y0.y) / s }
+        }
+    }
+
+    /// Rotation matrix of `self`
+    fn to_mat3(self) -> Mat3 {
+        let Vec3 { x, y, z } = self.v;
+        let w = self.w;
+
+        Mat3::new(
+            Vec3::new(
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+            ),
+            Vec3::new(
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+            ),
+            Vec3::new(
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ),
+        )
+    }
+
+    fn dot(&self, other: &Self) -> Float {
+        self.v.dot(other.v) + self.w * other.w
+    }
+
+    fn scale(self, s: Float) -> Self {
+        Self { v: s * self.v, w: s * self.w }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self { v: self.v + other.v, w: self.w + other.w }
+    }
+
+    fn normalize(self) -> Self {
+        self.scale(1.0 / self.dot(&self).sqrt())
+    }
+
+    /// Spherical linear interpolation along the shorter arc
+    fn slerp(self, other: Self, t: Float) -> Self {
+        let cos_theta = self.dot(&other);
+        let (other, cos_theta) = if cos_theta < 0.0 {
+            (other.scale(-1.0), -cos_theta)
+        } else {
+            (other, cos_theta)
+        };
+
+        if cos_theta > SLERP_LINEAR {
+            return self.scale(1.0 - t).add(other.scale(t)).normalize();
+        }
+
+        let theta = t * cos_theta.clamp(-1.0, 1.0).acos();
+        let perp = other.add(self.scale(-cos_theta)).normalize();
+        self.scale(theta.cos()).add(perp.scale(theta.sin()))
+    }
+
+    /// Angle of the rotation from `self` to `other`
+    fn angle_to(&self, other: &Self) -> Float {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+}
+
+/// Decomposition `T * R * S` of an affine transformation
+#[derive(Clone, Copy)]
+struct Decomposed {
+    translation: Vec3,
+    rotation: Quaternion,
+    stretch: [Vec3; 3],
+}
+
+impl Decomposed {
+    fn new(transform: &Transform) -> Self {
+        let translation = transform.to_translation();
+        let m = transform.to_mat3();
+        // polar decomposition needs a proper rotation, push mirroring to stretch
+        let flip = if m.det() < 0.0 { -1.0 } else { 1.0 };
+        let m = flip * m;
+
+        let mut r = m.clone();
+        for _ in 0..POLAR_MAX_ITER {
+            let r_it = r.inv().transpose();
+            let next = Mat3::new(
+                0.5 * (r.y0 + r_it.y0),
+                0.5 * (r.y1 + r_it.y1),
+                0.5 * (r.y2 + r_it.y2),
+            );
+            let delta = (next.y0 - r.y0).abs().max_element()
+                .max((next.y1 - r.y1).abs().max_element())
+                .max((next.y2 - r.y2).abs().max_element());
+            r = next;
+            if delta < POLAR_EPSILON {
+                break;
+            }
+        }
+
+        let s = flip * (r.transpose() * m);
+        Self {
+            translation,
+            rotation: Quaternion::from_mat3(&r),
+            stretch: [s.y0, s.y1, s.y2],
+        }
+    }
+
+    fn stretch(&self) -> Mat3 {
+        Mat3::new(self.stretch[0], self.stretch[1], self.stretch[2])
+    }
+}
+
+/// Transformation keyframed at the start and the end of a time interval.
+/// Translation and stretch get interpolated linearly and rotation
+/// spherically in between.
+#[derive(Clone)]
+pub struct AnimatedTransform {
+    start: Transform,
+    end: Transform,
+    time_start: Float,
+    time_end: Float,
+    keyframes: [Decomposed; 2],
+}
+
+impl AnimatedTransform {
+    /// Animate from `start` at `time_start` to `end` at `time_end`
+    pub fn new(start: Transform, end: Transform, time_start: Float, time_end: Float) -> Self {
+        assert!(time_start <= time_end);
+        let keyframes = [Decomposed::new(&start), Decomposed::new(&end)];
+
+        Self { start, end, time_start, time_end, keyframes }
+    }
+
+    /// Keyframe at the start of the interval
+    pub fn start(&self) -> &Transform {
+        &self.start
+    }
+
+    /// Keyframe at the end of the interval
+    pub fn end(&self) -> &Transform {
+        &self.end
+    }
+
+    /// Start and end times of the keyframes
+    pub fn time_range(&self) -> (Float, Float) {
+        (self.time_start, self.time_end)
+    }
+
+    /// Position of `time` in the interval, clamped to `\[0,1\]`
+    fn interval_fraction(&self, time: Float) -> Float {
+        if self.time_end == self.time_start {
+            0.0
+        } else {
+            ((time - self.time_start) / (self.time_end - self.time_start)).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Interpolated transformation at `time`. Times outside of the interval
+    /// get the closest keyframe.
+    pub fn at(&self, time: Float) -> Transform {
+        let t = self.interval_fraction(time);
+        if t == 0.0 {
+            return self.start.clone();
+        } else if t == 1.0 {
+            return self.end.clone();
+        }
+
+        let [k0, k1] = &self.keyframes;
+        let translation = (1.0 - t) * k0.translation + t * k1.translation;
+        let rotation = k0.rotation.slerp(k1.rotation, t).to_mat3();
+        let stretch = Mat3::new(
+            (1.0 - t) * k0.stretch[0] + t * k1.stretch[0],
+            (1.0 - t) * k0.stretch[1] + t * k1.stretch[1],
+            (1.0 - t) * k0.stretch[2] + t * k1.stretch[2],
+        );
+
+        Transform::translation(translation.x, translation.y, translation.z)
+            * Transform::mat3(rotation * stretch)
+    }
+
+    /// Upper bound for how far the path of local point `p` strays from the
+    /// straight lines between its positions at `steps + 1` evenly spaced
+    /// times over the interval
+    pub fn path_deviation(&self, p: Point, steps: usize) -> Float {
+        let [k0, k1] = &self.keyframes;
+        let angle = k0.rotation.angle_to(&k1.rotation);
+        if angle == 0.0 {
+            // translation and stretch move the point along a line
+            return 0.0;
+        }
+
+        let sp0 = k0.stretch().mul_vec3(p);
+        let sp1 = k1.stretch().mul_vec3(p);
+        let radius = sp0.length().max(sp1.length());
+        // p(t) = T(t) + R(t) S(t) p, the linear terms vanish from p''(t) and
+        // the chord of a curve strays at most |p''| / 8 times the step squared
+        let max_curvature = angle * angle * radius + 2.0 * angle * (sp1 - sp0).length();
+        let step = 1.0 / steps as Float;
+
+        max_curvature * step * step / 8.0
+    }
+}
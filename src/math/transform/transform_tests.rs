@@ -55,3 +55,67 @@ fn inv() {
         }
     }
 }
+
+fn rand_affine(rng: &mut Xorshift) -> Transform {
+    let mut rand_signed = || 2.0 * rng.gen_float() - 1.0;
+    Transform::translation(rand_signed(), rand_signed(), rand_signed())
+        * Transform::rotate_x(rand_signed())
+        * Transform::rotate_y(rand_signed())
+        * Transform::rotate_z(rand_signed())
+        * Transform::scale(rand_signed(), rand_signed(), rand_signed())
+        * Transform::rotate_y(rand_signed())
+}
+
+#[test]
+fn animated_reconstructs_keyframes() {
+    let mut rng = Xorshift::default();
+
+    for _ in 0..NUM_SAMPLES / 10 {
+        let a = rand_affine(&mut rng);
+        let at = AnimatedTransform::new(a.clone(), a.clone(), 0.0, 1.0).at(0.5);
+
+        let p = rng.gen_vec3();
+        assert!(a.transform_pt(p).distance_squared(at.transform_pt(p)) < crate::EPSILON);
+        assert!(a.transform_pt_inv(p).distance_squared(at.transform_pt_inv(p)) < crate::EPSILON);
+    }
+}
+
+#[test]
+fn animated_interpolates_rotation() {
+    let start = Transform::translation(1.0, 0.0, 0.0);
+    let end = Transform::translation(1.0, 2.0, 0.0) * Transform::rotate_z(crate::PI / 2.0);
+    let animated = AnimatedTransform::new(start, end, 1.0, 3.0);
+
+    let expected = Vec3::new(1.0 + (crate::PI / 4.0).cos(), 1.0 + (crate::PI / 4.0).sin(), 0.0);
+    let p = animated.at(2.0).transform_pt(Vec3::X);
+    assert!(p.distance_squared(expected) < crate::EPSILON);
+    // clamped outside of the keyframes
+    assert!(animated.at(0.0).transform_pt(Vec3::X).distance_squared(2.0 * Vec3::X) < crate::EPSILON);
+    assert!(animated.at(4.0).transform_pt(Vec3::X).distance_squared(Vec3::new(1.0, 3.0, 0.0)) < crate::EPSILON);
+}
+
+#[test]
+fn animated_path_within_deviation() {
+    let mut rng = Xorshift::default();
+    let steps = 4;
+
+    for _ in 0..NUM_SAMPLES / 100 {
+        let animated = AnimatedTransform::new(rand_affine(&mut rng), rand_affine(&mut rng), 0.0, 1.0);
+        let p = rng.gen_vec3();
+        let deviation = animated.path_deviation(p, steps);
+
+        for i in 0..steps {
+            let a = animated.at(i as Float / steps as Float).transform_pt(p);
+            let b = animated.at((i + 1) as Float / steps as Float).transform_pt(p);
+            for j in 1..10 {
+                let t = (i as Float + j as Float / 10.0) / steps as Float;
+                let c = animated.at(t).transform_pt(p);
+                // distance from the chord between the samples
+                let ac = c - a;
+                let ab = b - a;
+                let along = (ac.dot(ab) / ab.length_squared().max(crate::EPSILON)).clamp(0.0, 1.0);
+                assert!((ac - along * ab).length() <= deviation + crate::EPSILON.sqrt());
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use crate::{Vec2, Vec3, Image, Float, Normal, Point};
+use crate::{Vec2, Vec3, Mat3, Transform, ColorEncoding, Image, Float, Normal, Point};
 use crate::tracer::{
-    Scene, Material, Texture,
-    TriangleMesh, Face, Mesh, Spectrum
+    Scene, Material, Texture, Object, Instance, Triangle,
+    TriangleMesh, Face, Mesh, Spectrum, Uplift, Displacement, Subdivision,
+    BVHBuild, MeshAccel, MeshBVH, KdTree,
+    BVH, Curve, CurveBVH, CurveBasis, CurveType
 };
 use std::fs::{ self, File };
 use std::sync::Arc;
@@ -11,11 +13,18 @@ use std::io::{
 };
 use rustc_hash::FxHashMap;
 use zip::ZipArchive;
+use cache::{ CacheKey, CacheWriter, CachedScene };
+
+pub use cache::CACHE_DIR_VAR;
 
 /// .obj parser
 mod obj;
 /// .mtl parser
 mod mtl;
+/// Cache of built kD-trees
+mod cache;
+/// Curve file parser
+mod curves;
 
 const SCENE_DIR: &str = "./scenes/";
 
@@ -113,23 +122,123 @@ fn _extract_zip(bytes: &[u8], end_match: &str) -> Result<Vec<u8>> {
     }
 }
 
-/// Loads `tex_name` from `zip` to an `Image`
-fn _img_from_zip(zip: &[u8], tex_name: &str) -> Result<Image<Spectrum>> {
+/// Loads `tex_name` from `zip` to an `Image` with pixels encoded as
+/// `encoding` and uplifted to spectra with `uplift`
+fn _img_from_zip(
+    zip: &[u8],
+    tex_name: &str,
+    encoding: ColorEncoding,
+    uplift: Uplift,
+) -> Result<Image<Spectrum>> {
     let file_bytes = _extract_zip(zip, tex_name)?;
     println!("Decoding texture");
-    Image::from_file(file_bytes.as_slice())
-        .map_err(|decode_error| obj_error(&decode_error.to_string()))
+    Image::from_file_encoded(file_bytes.as_slice(), encoding, uplift)
 }
 
-/// Loads a .OBJ file at the given path
+/// Loads a .OBJ file at the given path. The kD-tree gets cached to the
+/// directory in `CACHE_DIR_VAR`, if set, and loaded from there if the file
+/// has not changed.
 pub fn mesh_from_path(path: &str, material: Material) -> Result<Mesh> {
     println!("Loading .OBJ file \"{}\"", path);
-    obj::load_file(File::open(path)?, material)
+    _mesh_cached(&fs::read(path)?, material, None)
+}
+
+/// Loads a .OBJ file at the given path and subdivides it with
+/// `subdivision`. Cached like in `mesh_from_path`.
+pub fn mesh_subdivided_from_path(
+    path: &str,
+    material: Material,
+    subdivision: &Subdivision,
+) -> Result<Mesh> {
+    println!("Loading .OBJ file \"{}\"", path);
+    _mesh_cached(&fs::read(path)?, material, Some(subdivision))
+}
+
+/// Loads a .OBJ file at the given path to a BVH split with `build`
+pub fn mesh_bvh_from_path(path: &str, material: Material, build: BVHBuild) -> Result<MeshBVH> {
+    println!("Loading .OBJ file \"{}\"", path);
+    obj::load_file(File::open(path)?, |vertices, faces, normals, uvs| {
+        TriangleMesh::new_bvh(vertices, faces, normals, uvs, material, build)
+    })
+}
+
+/// Loads strands of curves from the file at the given path to a BVH split
+/// with `build`. See `curves::load_file` for the format.
+pub fn curves_from_path(path: &str, material: Material, build: BVHBuild) -> Result<CurveBVH> {
+    println!("Loading curves \"{}\"", path);
+    let curves = curves::load_file(File::open(path)?, material)?;
+    println!("Loaded {} curves", curves.len());
+    Ok(BVH::new(curves, build))
 }
 
 /// Loads .OBJ file from resource at an URL. Supports direct .OBJ files and
-/// .OBJ files within a zip archive.
+/// .OBJ files within a zip archive. The kD-tree is cached like in
+/// `mesh_from_path`.
 pub fn mesh_from_url(url: &str, material: Material) -> Result<Mesh> {
+    _mesh_cached(&_obj_from_url(url)?, material, None)
+}
+
+/// Loads .OBJ file from resource at an URL like `mesh_from_url` and
+/// subdivides it with `subdivision`.
+pub fn mesh_subdivided_from_url(
+    url: &str,
+    material: Material,
+    subdivision: &Subdivision,
+) -> Result<Mesh> {
+    _mesh_cached(&_obj_from_url(url)?, material, Some(subdivision))
+}
+
+/// Loads .OBJ file from resource at an URL to a BVH split with `build`.
+/// Supports direct .OBJ files and .OBJ files within a zip archive.
+pub fn mesh_bvh_from_url(url: &str, material: Material, build: BVHBuild) -> Result<MeshBVH> {
+    let bytes = _obj_from_url(url)?;
+    obj::load_file(bytes.as_slice(), |vertices, faces, normals, uvs| {
+        TriangleMesh::new_bvh(vertices, faces, normals, uvs, material, build)
+    })
+}
+
+/// Mesh of the .OBJ file in `bytes` from the cache, or built and cached.
+/// Subdivided with `subdivision` if given.
+fn _mesh_cached(
+    bytes: &[u8],
+    material: Material,
+    subdivision: Option<&Subdivision>,
+) -> Result<Mesh> {
+    let mut key = CacheKey::new("mesh");
+    if let Some(key) = &mut key {
+        key.update(bytes);
+        if let Some(subdivision) = subdivision {
+            let mut params = Vec::new();
+            subdivision.write_cache(&mut params)?;
+            key.update(&params);
+        }
+    }
+
+    let cached = key.as_ref()
+        .and_then(|key| CachedScene::load(key, 1))
+        .and_then(CachedScene::into_tree);
+    if let Some((mut mesh, tree)) = cached {
+        mesh.materials = vec![material];
+        return Ok(tree.build(&Arc::new(mesh)));
+    }
+
+    let mesh = obj::load_file(bytes, |vertices, faces, normals, uvs| match subdivision {
+        Some(subdivision) => TriangleMesh::new_subdivided(
+            vertices, faces, normals, uvs, material, subdivision,
+        ),
+        None => TriangleMesh::new(vertices, faces, normals, uvs, material),
+    })?;
+
+    if let (Some(key), Some(triangle_mesh)) = (&key, mesh.mesh()) {
+        let mut cache = CacheWriter::create(key, triangle_mesh, 1);
+        cache.tree(&mesh);
+        cache.finish();
+    }
+
+    Ok(mesh)
+}
+
+fn _obj_from_url(url: &str) -> Result<Vec<u8>> {
     let path = _check_cached(url)?;
     println!("Loading .OBJ from \"{}\"", &path);
     let mut bytes = fs::read(path)?;
@@ -143,7 +252,7 @@ pub fn mesh_from_url(url: &str, material: Material) -> Result<Mesh> {
         ));
     }
 
-    obj::load_file(bytes.as_slice(), material)
+    Ok(bytes)
 }
 
 fn _check_cached(url: &str) -> Result<String> {
@@ -166,8 +275,9 @@ fn _check_cached(url: &str) -> Result<String> {
 
 /// Loads `tex_name` from .zip at `url`. zip cached to `SCENE_DIR`
 pub fn texture_from_url(url: &str, tex_name: &str) -> Result<Image<Spectrum>> {
-    if !tex_name.ends_with(".png") {
-        return Err(obj_error("Can only load .png files"));
+    let supported = [".png", ".jpg", ".jpeg", ".tga", ".hdr"];
+    if !supported.iter().any(|ext| tex_name.to_lowercase().ends_with(ext)) {
+        return Err(obj_error("Can only load .png, .jpg, .tga and .hdr files"));
     }
     if !url.ends_with(".zip") {
         return Err(obj_error("Can only extract textures from zip archives"));
@@ -176,17 +286,24 @@ pub fn texture_from_url(url: &str, tex_name: &str) -> Result<Image<Spectrum>> {
     let path = _check_cached(url)?;
     println!("Loading texture \"{}\" from \"{}\"", tex_name, path);
 
-    _img_from_zip(&fs::read(path)?, tex_name)
+    let uplift = if tex_name.to_lowercase().ends_with(".hdr") {
+        Uplift::Illuminant
+    } else {
+        Uplift::default()
+    };
+    _img_from_zip(&fs::read(path)?, tex_name, ColorEncoding::sRGB, uplift)
 }
 
 /// Parses a whole scene from a .obj file specified by `name`
-/// in a .zip archive at `url`. Cache `url` to `SCENE_DIR`
+/// in a .zip archive at `url`. Cache `url` to `SCENE_DIR`. Meshes of the
+/// scene get accelerated with `accel` and are not subdivided.
 pub fn scene_from_url(
     url: &str,
     obj_name: &str,
     map_ks: bool,
     mtllib: Option<&str>,
-    env_map: Option<(&str, Float)>
+    env_map: Option<(&str, Float)>,
+    accel: MeshAccel,
 ) -> Result<Scene> {
     if !url.ends_with(".zip") {
         return Err(obj_error("Can only load scenes from .zip"));
@@ -197,16 +314,19 @@ pub fn scene_from_url(
 
     let path = _check_cached(url)?;
 
-    scene_from_file(&path, obj_name, map_ks, mtllib, env_map)
+    scene_from_file(&path, obj_name, map_ks, mtllib, env_map, accel)
 }
 
-/// Load a scene from zip file at `pth`
+/// Load a scene from zip file at `pth`. Meshes of the scene get
+/// accelerated with `accel`. kD-trees get cached like in `mesh_from_path`
+/// and loaded from there if the archive has not changed.
 pub fn scene_from_file(
     path: &str,
     obj_name: &str,
     map_ks: bool,
     mtllib: Option<&str>,
-    env_map: Option<(&str, Float)>
+    env_map: Option<(&str, Float)>,
+    accel: MeshAccel,
 ) -> Result<Scene> {
     println!("Loading scene \"{}\" from \"{}\"", obj_name, path);
     let zip_file = Arc::new(fs::read(path)?);
@@ -215,6 +335,7 @@ pub fn scene_from_file(
     // parse materials first
     let mut materials = Vec::new();
     let mut material_indices = FxHashMap::<String, usize>::default();
+    let mut displacements = FxHashMap::<usize, Displacement>::default();
 
     if let Some(mtllib_name) = mtllib {
         let mtl_bytes = _extract_zip(&zip_file, mtllib_name)?;
@@ -224,6 +345,7 @@ pub fn scene_from_file(
             Arc::clone(&zip_file),
             &mut materials,
             &mut material_indices,
+            &mut displacements,
         )?;
     }
 
@@ -244,15 +366,35 @@ pub fn scene_from_file(
                 Arc::clone(&zip_file),
                 &mut materials,
                 &mut material_indices,
+                &mut displacements,
             )?;
         }
     }
 
-    let mut scene = obj::load_scene(
-        obj_bytes.as_slice(),
-        materials,
-        material_indices,
-    )?;
+    // the archive has the .mtl files and height fields that change the
+    // geometry too, so key the cache by all of it
+    let cache_key = CacheKey::new("scene")
+        .filter(|_| accel == MeshAccel::KdTree)
+        .map(|mut key| {
+            key.update(&zip_file);
+            key.update(obj_name.as_bytes());
+            key.update(mtllib.unwrap_or_default().as_bytes());
+            key
+        });
+    let cached = cache_key.as_ref()
+        .and_then(|key| CachedScene::load(key, materials.len()));
+
+    let mut scene = match cached {
+        Some(cached) => cached.into_scene(materials),
+        None => obj::load_scene(
+            obj_bytes.as_slice(),
+            materials,
+            material_indices,
+            displacements,
+            accel,
+            cache_key,
+        )?,
+    };
 
     if let Some((map_file, scale)) = env_map {
         let map_bytes = _extract_zip(&zip_file, map_file)?;
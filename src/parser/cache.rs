@@ -0,0 +1,225 @@
+use super::*;
+use crate::binary::Binary;
+use crate::formatting;
+use crate::tracer::{ CachedKdTree, KdTree, Triangle, TriangleIndices };
+use std::env;
+use std::io::BufWriter;
+use std::path::{ Path, PathBuf };
+use std::time::Instant;
+
+/// Start of every cache file
+const MAGIC: &[u8; 8] = b"LUMOKDT\0";
+/// Bump when the layout of the cache changes
+const VERSION: u64 = 1;
+
+/// Environment variable naming the directory of the caches. Nothing gets
+/// cached without it.
+pub const CACHE_DIR_VAR: &str = "LUMO_CACHE_DIR";
+
+/// FNV-1a hash of the sources of a mesh, names the cache file
+pub struct CacheKey {
+    hash: u64,
+    dir: PathBuf,
+}
+
+impl CacheKey {
+    /// Key for sources of `kind`, separates meshes and scenes. `None` if
+    /// caching is not enabled with `CACHE_DIR_VAR`.
+    pub fn new(kind: &str) -> Option<Self> {
+        let dir = env::var_os(CACHE_DIR_VAR).filter(|dir| !dir.is_empty())?;
+        let mut key = Self { hash: 0xcbf29ce484222325, dir: PathBuf::from(dir) };
+        key.update(kind.as_bytes());
+        Some(key)
+    }
+
+    /// Hashes `bytes` to the key
+    pub fn update(&mut self, bytes: &[u8]) {
+        for b in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.hash ^= *b as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(format!("{:016x}.kdtree", self.hash))
+    }
+}
+
+/// One mesh of a cached scene
+pub enum CachedMesh {
+    /// Triangles in a kD-tree
+    Tree(CachedKdTree),
+    /// Emissive triangles added to the scene one by one
+    Lights(Vec<TriangleIndices>),
+}
+
+/// Meshes read from a cache, get their materials in `into_scene`
+pub struct CachedScene {
+    mesh: TriangleMesh,
+    meshes: Vec<CachedMesh>,
+}
+
+impl CachedScene {
+    /// Reads the cache of `key` if there is one. The meshes may use
+    /// `num_materials` materials. Bad caches get ignored.
+    pub fn load(key: &CacheKey, num_materials: usize) -> Option<Self> {
+        let path = key.path();
+        if !fs::exists(&path).unwrap_or(false) {
+            return None;
+        }
+
+        println!("Loading cached kD-trees from \"{}\"", path.display());
+        match Self::read(&path, num_materials) {
+            Ok(cached) => Some(cached),
+            Err(e) => {
+                println!("Ignoring bad cache \"{}\": {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn read(path: &Path, num_materials: usize) -> Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC || u64::read(&mut r)? != VERSION {
+            return Err(obj_error("Not a cache of this version"));
+        }
+
+        let mesh = TriangleMesh::read_cache(&mut r)?;
+        let num_meshes = usize::read(&mut r)?;
+        let mut meshes = Vec::new();
+        for _ in 0..num_meshes {
+            let mesh = if bool::read(&mut r)? {
+                let num_lights = usize::read(&mut r)?;
+                let lights = (0..num_lights)
+                    .map(|_| TriangleIndices::read(&mut r, &mesh, num_materials))
+                    .collect::<Result<Vec<_>>>()?;
+                CachedMesh::Lights(lights)
+            } else {
+                CachedMesh::Tree(CachedKdTree::read(&mut r, &mesh, num_materials)?)
+            };
+            meshes.push(mesh);
+        }
+
+        if r.read(&mut [0])? != 0 {
+            return Err(obj_error("Trailing bytes in cache"));
+        }
+
+        Ok(Self { mesh, meshes })
+    }
+
+    /// The mesh and its kD-tree if the cache is of a single mesh
+    pub fn into_tree(mut self) -> Option<(TriangleMesh, CachedKdTree)> {
+        match self.meshes.pop() {
+            Some(CachedMesh::Tree(tree)) if self.meshes.is_empty() => Some((self.mesh, tree)),
+            _ => None,
+        }
+    }
+
+    /// Scene of the cached meshes with `materials`
+    pub fn into_scene(mut self, materials: Vec<Material>) -> Scene {
+        let start = Instant::now();
+        let mut scene = Scene::default();
+        let num_meshes = self.meshes.len();
+
+        self.mesh.materials = materials;
+        let mesh = Arc::new(self.mesh);
+        for cached in self.meshes {
+            match cached {
+                CachedMesh::Tree(tree) => scene.add(Box::new(tree.build(&mesh))),
+                CachedMesh::Lights(lights) => for indices in &lights {
+                    let triangle = Triangle::from_cache(Arc::clone(&mesh), indices);
+                    scene.add_light(Box::new(triangle));
+                }
+            }
+        }
+        println!(
+            "Loaded {} cached meshes in {}",
+            num_meshes, formatting::fmt_elapsed(start.elapsed()),
+        );
+
+        scene
+    }
+}
+
+/// Writes built meshes to the cache of a key. Goes to a temporary file
+/// first so that interrupted runs do not leave partial caches behind.
+/// Caching gets skipped if the directory can not be written to and
+/// failing later only prints the error, the meshes are usable anyway.
+pub struct CacheWriter {
+    w: Option<BufWriter<File>>,
+    path: PathBuf,
+    tmp_path: PathBuf,
+}
+
+impl CacheWriter {
+    /// Starts the cache of `key` with `num_meshes` meshes of `mesh`
+    pub fn create(key: &CacheKey, mesh: &TriangleMesh, num_meshes: usize) -> Self {
+        let path = key.path();
+        let tmp_path = path.with_extension("kdtree.tmp");
+        let w = fs::create_dir_all(&key.dir)
+            .and_then(|_| File::create(&tmp_path))
+            .ok()
+            .map(BufWriter::new);
+        let mut writer = Self { w, path, tmp_path };
+
+        writer.write(|w| {
+            w.write_all(MAGIC)?;
+            VERSION.write(w)?;
+            mesh.write_cache(w)?;
+            num_meshes.write(w)
+        });
+
+        writer
+    }
+
+    /// Writes the next mesh as a kD-tree
+    pub fn tree(&mut self, tree: &KdTree<Triangle>) {
+        self.write(|w| {
+            false.write(w)?;
+            tree.write_cache(w)
+        });
+    }
+
+    /// Writes the next mesh as emissive triangles
+    pub fn lights(&mut self, lights: &[Triangle]) {
+        self.write(|w| {
+            true.write(w)?;
+            lights.len().write(w)?;
+            lights.iter().try_for_each(|triangle| triangle.write_cache(w))
+        });
+    }
+
+    /// Moves the finished cache in place
+    pub fn finish(mut self) {
+        let Some(mut w) = self.w.take() else { return; };
+        match w.flush().and_then(|_| fs::rename(&self.tmp_path, &self.path)) {
+            Ok(()) => println!("Cached kD-trees to \"{}\"", self.path.display()),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn write(&mut self, f: impl FnOnce(&mut BufWriter<File>) -> Result<()>) {
+        if let Some(w) = &mut self.w {
+            if let Err(e) = f(w) {
+                self.fail(e);
+            }
+        }
+    }
+
+    fn fail(&mut self, e: io::Error) {
+        println!("Could not write cache \"{}\": {}", self.path.display(), e);
+        self.w = None;
+    }
+}
+
+impl Drop for CacheWriter {
+    fn drop(&mut self) {
+        // unfinished or failed, the temporary file is useless
+        self.w = None;
+        let _ = fs::remove_file(&self.tmp_path);
+    }
+}
+
@@ -0,0 +1,188 @@
+use super::*;
+
+/// Parses strands of curves. The format is line based, `#` starts a comment:
+///
+/// * `basis bezier|bspline` - basis of the following strands, Bézier by default
+/// * `type flat|cylinder` - cross section of the following strands, flat by default
+/// * `type ribbon x0 y0 z0 x1 y1 z1` - following strands are ribbons
+///   oriented by the normals at the start and end of the strand
+/// * `width w0 [w1]` - width at the start and end of the following strands,
+///   one of the ends can be zero
+/// * `c x y z x y z ...` - strand with the given control points, `3n + 1`
+///   points for Bézier and at least four for B-splines
+///
+/// All curves share `material`.
+pub fn load_file<T: Read + Sized>(file: T, material: Material) -> Result<Vec<Curve>> {
+    let material = Arc::new(material);
+    let mut curves = Vec::new();
+    let mut basis = CurveBasis::default();
+    let mut curve_type = CurveType::default();
+    let mut width = None;
+
+    let reader = BufReader::new(file);
+    for line in reader.lines() {
+        let line = line?.trim().to_string();
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_ascii_whitespace().collect();
+
+        match tokens[0] {
+            "basis" => {
+                basis = match (tokens.get(1), tokens.len()) {
+                    (Some(&"bezier"), 2) => CurveBasis::Bezier,
+                    (Some(&"bspline"), 2) => CurveBasis::BSpline,
+                    _ => return Err(obj_error("Unknown curve basis")),
+                };
+            }
+            "type" => {
+                curve_type = match (tokens.get(1), tokens.len()) {
+                    (Some(&"flat"), 2) => CurveType::Flat,
+                    (Some(&"cylinder"), 2) => CurveType::Cylinder,
+                    (Some(&"ribbon"), 8) => {
+                        let n0 = parse_vec3(&tokens[1..])?;
+                        let n1 = parse_vec3(&tokens[4..])?;
+                        if !is_normal(n0) || !is_normal(n1) {
+                            return Err(obj_error("Degenerate ribbon normal"));
+                        }
+                        CurveType::Ribbon(n0, n1)
+                    }
+                    _ => return Err(obj_error("Bad curve type")),
+                };
+            }
+            "width" => {
+                if tokens.len() > 3 {
+                    return Err(obj_error("Too many curve widths"));
+                }
+                let w0 = parse_double(tokens.get(1).ok_or(obj_error("Missing width"))?)?;
+                let w1 = tokens.get(2).map_or(Ok(w0), |w| parse_double(w))?;
+                if !(w0 >= 0.0 && w1 >= 0.0 && w0.max(w1) > 0.0
+                     && w0.is_finite() && w1.is_finite()) {
+                    return Err(obj_error("Curve width has to be positive at one end"));
+                }
+                width = Some((w0, w1));
+            }
+            "c" => {
+                let (w0, w1) = width.ok_or(obj_error("Curve width not set"))?;
+                let cps = parse_points(&tokens[1..])?;
+                let valid = match basis {
+                    CurveBasis::Bezier => cps.len() >= 4 && (cps.len() - 1) % 3 == 0,
+                    CurveBasis::BSpline => cps.len() >= 4,
+                };
+                if !valid {
+                    return Err(obj_error("Bad number of control points in curve"));
+                }
+
+                curves.extend(Curve::strand(
+                    &cps, basis, w0, w1, curve_type, Arc::clone(&material),
+                ));
+            }
+            _ => return Err(obj_error(&format!("Unknown curve keyword {}", tokens[0]))),
+        }
+    }
+
+    Ok(curves)
+}
+
+/// Points from consecutive triplets of coordinates
+fn parse_points(tokens: &[&str]) -> Result<Vec<Point>> {
+    if tokens.len() % 3 != 0 {
+        return Err(obj_error("Control points need three coordinates"));
+    }
+
+    tokens.chunks(3)
+        .map(|xyz| {
+            let p = Point::new(
+                parse_double(xyz[0])?,
+                parse_double(xyz[1])?,
+                parse_double(xyz[2])?,
+            );
+            if !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite()) {
+                return Err(obj_error("Control points have to be finite"));
+            }
+            Ok(p)
+        })
+        .collect()
+}
+
+/// Finite and non-zero ribbon normal
+fn is_normal(n: Normal) -> bool {
+    n.x.is_finite() && n.y.is_finite() && n.z.is_finite() && n.length_squared() > 0.0
+}
+
+#[cfg(test)]
+mod curves_tests {
+    use super::*;
+
+    const BEZIER: &str = "0 0 0  1 0 0  2 1 0  3 1 0";
+
+    fn load(curves: &str) -> Result<Vec<Curve>> {
+        load_file(curves.as_bytes(), Material::Blank)
+    }
+
+    #[test]
+    fn loads_strands() {
+        let curves = load(&format!(
+            "# hair\n\nwidth 0.1\nc {}\nc {}  4 1 0  5 2 0  6 2 0\n\
+             basis bspline\ntype cylinder\nwidth 0.2 0\nc {}  4 2 0\n\
+             basis bezier\ntype ribbon 0 0 1  0 1 1\nc {}\ntype flat\nc {}\n",
+            BEZIER, BEZIER, BEZIER, BEZIER, BEZIER,
+        )).unwrap();
+
+        // 1 + 2 Bézier segments, 2 B-spline segments, ribbon and flat
+        assert!(curves.len() == 7);
+    }
+
+    #[test]
+    fn empty_file_has_no_curves() {
+        assert!(load("").unwrap().is_empty());
+        assert!(load("# nothing here\n\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_lines_are_errors() {
+        let bad = [
+            // curves without a width
+            &format!("c {}", BEZIER),
+            // control points
+            "width 1\nc",
+            "width 1\nc 0 0 0  1 0 0  2 0 0",
+            "width 1\nc 0 0 0  1 0 0  2 0 0  3 0 0  4 0 0",
+            "width 1\nc 0 0 0  1 0 0  2 0 0  3 0",
+            "width 1\nc 0 0 0  1 0 0  2 0 0  3 x 0",
+            "width 1\nc 0 0 0  1 0 0  2 0 0  3 nan 0",
+            "width 1\nc 0 0 0  1 0 0  2 0 0  3 inf 0",
+            "basis bspline\nwidth 1\nc 0 0 0  1 0 0  2 0 0",
+            // widths
+            "width",
+            "width x",
+            "width 0",
+            "width 0 0",
+            "width 1 -1",
+            "width nan",
+            "width inf",
+            "width 1 1 1",
+            // bases and types
+            "basis",
+            "basis nurbs",
+            "basis bezier bspline",
+            "type",
+            "type tube",
+            "type flat 1",
+            "type ribbon",
+            "type ribbon 0 0 1  0 1",
+            "type ribbon 0 0 1  0 1 1  0",
+            "type ribbon 0 0 1  0 x 1",
+            "type ribbon 0 0 0  0 1 1",
+            "type ribbon 0 0 1  0 nan 1",
+            // keywords
+            "curve 0 0 0",
+            "C 0 0 0",
+        ];
+
+        for curves in bad {
+            let Err(err) = load(curves) else { panic!("{}", curves) };
+            assert!(err.kind() == io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 use crate::pool::{Executor, ThreadPool};
-use crate::tracer::{RGB, Spectrum};
+use crate::tracer::{AlphaMask, DenseSpectrum, Displacement, NormalMap, Principled, RGB, Spectrum, materials};
 
 use task::{MtlTask, MtlTaskExecutor};
 
@@ -20,20 +20,46 @@ pub struct MtlConfig {
     pub Ke: Spectrum,
     /// Emission map
     pub map_Ke: Option<Image<Spectrum>>,
-    /// Bump map for the material
-    pub map_Bump: Option<Image<Normal>>,
+    /// Normal or bump map for the material
+    pub map_Bump: Option<NormalMap>,
+    /// Displacement map applied to meshes using the material
+    pub disp: Option<Displacement>,
+    /// Opacity of the material, `1.0` is fully opaque
+    pub d: Float,
+    /// Opacity mask
+    pub map_d: Option<Image<Float>>,
     /// How much each light channel passes on transmission
     pub Tf: Spectrum,
     /// Refraction index of the material
     pub eta: Float,
     /// Roughness of the material
     pub roughness: Float,
+    /// Roughness map of a principled material, replaces `roughness`
+    pub map_Pr: Option<Image<Float>>,
     /// Absorption coefficient
     pub k: Float,
+    /// Measured refraction index, from a named material in `Ni`
+    pub eta_spectrum: Option<DenseSpectrum>,
+    /// Measured absorption coefficient, from a named conductor in `Ni`
+    pub k_spectrum: Option<DenseSpectrum>,
     /// Is fresnel enabled for the material a.k.a. is it a conductor/dielectric
     pub fresnel_enabled: bool,
     /// Is the material dielectric?
     pub is_transparent: bool,
+    /// Metalness of a principled material (`Pm`)
+    pub metallic: Float,
+    /// Metalness map of a principled material, replaces `metallic`
+    pub map_Pm: Option<Image<Float>>,
+    /// Sheen of a principled material (`Ps`)
+    pub sheen: Float,
+    /// Clearcoat thickness of a principled material (`Pc`)
+    pub clearcoat: Float,
+    /// Clearcoat roughness of a principled material (`Pcr`)
+    pub clearcoat_roughness: Float,
+    /// Anisotropy of a principled material (`aniso`)
+    pub anisotropy: Float,
+    /// Did the material use the PBR extension keys?
+    pub is_principled: bool,
 }
 
 impl Default for MtlConfig {
@@ -45,19 +71,65 @@ impl Default for MtlConfig {
             Tf: Spectrum::BLACK,
             eta: 1.5,
             k: 0.0,
+            eta_spectrum: None,
+            k_spectrum: None,
             roughness: 1.0,
+            map_Pr: None,
             fresnel_enabled: false,
             is_transparent: false,
+            metallic: 0.0,
+            map_Pm: None,
+            sheen: 0.0,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.0,
+            anisotropy: 0.0,
+            is_principled: false,
             map_Kd: None,
             map_Ks: None,
             map_Ke: None,
             map_Bump: None,
+            disp: None,
+            d: 1.0,
+            map_d: None,
         }
     }
 }
 
 impl MtlConfig {
-    pub fn build_material(self) -> Material {
+    /// Roughness along the tangent and bitangent. Stretches `roughness`
+    /// with `anisotropy` as in the Disney BRDF.
+    fn anisotropic_roughness(&self) -> Vec2 {
+        let aspect = (1.0 - 0.9 * self.anisotropy).sqrt();
+        Vec2::new(
+            (self.roughness / aspect).min(1.0),
+            self.roughness * aspect,
+        )
+    }
+
+    /// Opacity mask from `d` and `map_d`, if the material is not opaque.
+    /// Transparent materials often set `d` to describe their transmission,
+    /// so only their `map_d` is used.
+    fn alpha_mask(&mut self) -> Option<AlphaMask> {
+        let d = if self.is_transparent { 1.0 } else { self.d.clamp(0.0, 1.0) };
+        match self.map_d.take() {
+            Some(img) => Some(AlphaMask::from_image(img).with_scale(d)),
+            None if d < 1.0 => Some(AlphaMask::constant(d)),
+            None => None,
+        }
+    }
+
+    pub fn build_material(mut self) -> Material {
+        let alpha = self.alpha_mask();
+        let material = self.build_opaque();
+
+        match alpha {
+            Some(alpha) if !material.is_light() => material.with_alpha(alpha),
+            _ => material,
+        }
+    }
+
+    fn build_opaque(self) -> Material {
+        let roughness = self.anisotropic_roughness();
         if !self.Ke.is_black() || self.map_Ke.is_some() {
             if let Some(img) = self.map_Ke {
                 Material::light(Texture::Image(img))
@@ -70,6 +142,28 @@ impl MtlConfig {
             } else {
                 Texture::from(self.Kd)
             };
+
+            if self.is_principled {
+                let transmission = if self.is_transparent { 1.0 } else { 0.0 };
+                let mut principled = Principled::new(kd)
+                    .metallic(self.metallic)
+                    .roughness(self.roughness)
+                    .anisotropic(self.anisotropy)
+                    .eta(self.eta.max(1.0))
+                    .sheen(self.sheen, 0.5)
+                    .clearcoat(self.clearcoat, 1.0 - self.clearcoat_roughness)
+                    .transmission(transmission);
+                if let Some(img) = self.map_Pr {
+                    principled = principled.roughness(1.0)
+                        .roughness_map(Texture::Grayscale(img));
+                }
+                if let Some(img) = self.map_Pm {
+                    principled = principled.metallic(1.0)
+                        .metallic_map(Texture::Grayscale(img));
+                }
+                return Material::principled(principled, self.map_Bump);
+            }
+
             let ks = if let Some(img) = self.map_Ks {
                 Texture::Image(img)
             } else {
@@ -77,8 +171,22 @@ impl MtlConfig {
             };
             let tf = Texture::from(self.Tf);
 
+            if let Some(eta) = self.eta_spectrum {
+                let k = self.k_spectrum
+                    .unwrap_or(DenseSpectrum::from_constant(self.k));
+                return Material::microfacet_spectral(
+                    roughness,
+                    eta,
+                    k,
+                    self.is_transparent,
+                    self.fresnel_enabled,
+                    kd, ks, tf,
+                    self.map_Bump,
+                );
+            }
+
             Material::microfacet(
-                self.roughness,
+                roughness,
                 self.eta,
                 self.k,
                 self.is_transparent,
@@ -96,6 +204,7 @@ pub fn load_file<T: Read + Sized>(
     zip_file: Arc<Vec<u8>>,
     materials: &mut Vec<Material>,
     material_indices: &mut FxHashMap<String, usize>,
+    displacements: &mut FxHashMap<usize, Displacement>,
 ) -> Result<()> {
     let reader = BufReader::new(bytes);
     let mut block = Vec::new();
@@ -128,19 +237,71 @@ pub fn load_file<T: Read + Sized>(
 
     pool.all_published();
 
+    // drain the pool even after an error so that every worker finishes
+    let mut error = None;
     let mut finished = 0;
     while finished < threads {
         let result = pool.pop_result();
         if let Some(result) = result {
+            let mut result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    error.get_or_insert(e);
+                    continue;
+                }
+            };
             if !material_indices.contains_key(&result.mtl_name) {
                 println!("{}", result.mtl_name);
+                let disp = result.mtl_cfg.disp.take();
                 materials.push(result.mtl_cfg.build_material());
                 material_indices.insert(result.mtl_name, materials.len() - 1);
+                if let Some(disp) = disp {
+                    displacements.insert(materials.len() - 1, disp);
+                }
             }
         } else {
             finished += 1;
         }
     }
 
-    Ok(())
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod mtl_tests {
+    use super::*;
+
+    /// Number of materials parsed from `mtl`
+    fn load(mtl: &str) -> Result<usize> {
+        let mut materials = Vec::new();
+        load_file(
+            mtl.as_bytes(),
+            false,
+            Arc::new(Vec::new()),
+            &mut materials,
+            &mut FxHashMap::default(),
+            &mut FxHashMap::default(),
+        )?;
+        Ok(materials.len())
+    }
+
+    #[test]
+    fn named_material() {
+        assert!(load("newmtl gold\nKs 1 1 1\nNi Au\nillum 5\n").unwrap() == 1);
+    }
+
+    #[test]
+    fn unknown_named_material_is_error() {
+        let err = load("newmtl a\nNi unobtainium\n\nnewmtl b\nKd 1 1 1\n").unwrap_err();
+        assert!(err.kind() == io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_number_is_error() {
+        let err = load("newmtl a\nKd 1 x 1\n").unwrap_err();
+        assert!(err.kind() == io::ErrorKind::InvalidData);
+    }
 }
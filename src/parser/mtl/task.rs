@@ -1,5 +1,8 @@
 use super::*;
 
+/// Wavelength (nm) where named materials are sampled for a scalar refraction index
+const NAMED_ETA_LAMBDA: Float = 550.0;
+
 #[derive(Clone)]
 pub struct MtlTaskExecutor {
     zip_bytes: Arc<Vec<u8>>,
@@ -12,8 +15,15 @@ impl MtlTaskExecutor {
     }
 }
 
-impl Executor<MtlTask, MtlTaskResult> for MtlTaskExecutor {
-    fn exec(&mut self, task: MtlTask) -> MtlTaskResult {
+impl Executor<MtlTask, Result<MtlTaskResult>> for MtlTaskExecutor {
+    fn exec(&mut self, task: MtlTask) -> Result<MtlTaskResult> {
+        self.parse(task)
+    }
+}
+
+impl MtlTaskExecutor {
+    /// Parses the block of a single material
+    fn parse(&self, task: MtlTask) -> Result<MtlTaskResult> {
         let mut mtl = MtlConfig::default();
         let mut mtl_name = String::default();
         for line in task.lines {
@@ -22,83 +32,139 @@ impl Executor<MtlTask, MtlTaskResult> for MtlTaskExecutor {
                 "newmtl" => mtl_name = tokens[1].to_string(),
                 /* diffuse color */
                 "Kd" => {
-                    let kd = parse_vec3(&tokens)
-                        .expect("Couldn't parse vec3");
+                    let kd = parse_vec3(&tokens)?;
                     mtl.Kd = Spectrum::from_rgb(RGB::from(kd));
                 }
                 /* texture map */
                 "map_Kd" => {
-                    let tex_name = tokens[1..].join(" ").replace('\\', "/");
-                    let img = super::_img_from_zip(&self.zip_bytes, &tex_name)
-                        .expect("Couldn't extract image");
+                    let img = self.color_map(&tokens[1..], Uplift::Reflectance)?;
                     mtl.map_Kd = Some(img);
                 }
                 /* emission color */
                 "Ke" => {
-                    let ke = parse_vec3(&tokens)
-                        .expect("Couldn't parse vec3");
+                    let ke = parse_vec3(&tokens)?;
                     mtl.Ke = Spectrum::from_rgb(RGB::from(ke));
                 }
                 "map_Ke" => {
-                    let tex_name = tokens[1..].join(" ").replace('\\', "/");
-                    let img = super::_img_from_zip(&self.zip_bytes, &tex_name)
-                        .expect("Couldn't extract image");
+                    let img = self.color_map(&tokens[1..], Uplift::Illuminant)?;
                     mtl.map_Ke = Some(img);
                 }
                 /* specular color */
                 "Ks" => {
-                    let ks = parse_vec3(&tokens)
-                        .expect("Couldn't parse vec3");
+                    let ks = parse_vec3(&tokens)?;
                     mtl.Ks = Spectrum::from_rgb(RGB::from(ks));
                 }
                 "map_Ks" => {
                     let tex_name = tokens[1..].join(" ").replace('\\', "/");
                     if self.map_ks {
-                        let img = super::_img_from_zip(&self.zip_bytes, &tex_name)
-                            .expect("Couldn't extract image");
+                        let img = self.color_map(&tokens[1..], Uplift::Reflectance)?;
                         mtl.map_Ks = Some(img);
                     } else {
-                        let bytes = super::_extract_zip(&self.zip_bytes, &tex_name)
-                            .expect("Couldn't extract image");
+                        let bytes = super::_extract_zip(&self.zip_bytes, &tex_name)?;
                         // occlusion, roughness, metalness
-                        let orm = Image::<Vec3>::mean_vec3_from_file(bytes.as_slice())
-                            .expect("Couldn't decode image");
+                        let orm = Image::<Vec3>::mean_vec3_from_file(bytes.as_slice())?;
                         mtl.roughness = orm.y;
                         mtl.k = orm.z;
                         mtl.Ks = Spectrum::WHITE;
                     }
                 }
-                /* bump map */
-                "map_Bump" => {
+                /* bump map, height field or tangent space normals */
+                "map_Bump" | "bump" | "norm" => {
+                    let opts = parse_map_options(&tokens[1..])?;
+                    let bytes = super::_extract_zip(&self.zip_bytes, &opts.name)?;
+                    mtl.map_Bump = Some(NormalMap::from_file(bytes.as_slice(), opts.scale)?);
+                }
+                /* displacement map */
+                "disp" => {
+                    let opts = parse_map_options(&tokens[1..])?;
+                    let bytes = super::_extract_zip(&self.zip_bytes, &opts.name)?;
+                    let map = Image::height_from_file(bytes.as_slice())?;
+                    mtl.disp = Some(Displacement::new(map, opts.scale));
+                }
+                /* opacity */
+                "d" => {
+                    mtl.d = parse_double(tokens[1])?;
+                }
+                /* transparency, inverse of opacity */
+                "Tr" => {
+                    let tr = parse_double(tokens[1])?;
+                    mtl.d = 1.0 - tr;
+                }
+                /* opacity mask */
+                "map_d" => {
                     let map_name = tokens[1..].join(" ").replace('\\', "/");
-                    let bytes = super::_extract_zip(&self.zip_bytes, &map_name)
-                        .expect("Couldn't extract image");
-                    mtl.map_Bump = Some(Image::bump_from_file(bytes.as_slice())
-                                        .expect("Couldn't decode image"));
+                    let bytes = super::_extract_zip(&self.zip_bytes, &map_name)?;
+                    mtl.map_d = Some(Image::alpha_from_file(bytes.as_slice())?);
                 }
                 /* transmission filter */
                 "Tf" => {
-                    let tf = parse_vec3(&tokens)
-                        .expect("Couldn't parse vec3");
+                    let tf = parse_vec3(&tokens)?;
                     mtl.Tf = Spectrum::from_rgb(RGB::from(tf));
                 }
-                /* refraction index */
+                /* refraction index, or the name of a measured material */
                 "Ni" => {
-                    let ni = parse_double(tokens[1])
-                        .expect("Couldn't parse double");
-                    mtl.eta = ni;
+                    if let Ok(ni) = parse_double(tokens[1]) {
+                        mtl.eta = ni;
+                    } else if let Some((eta, k)) = materials::conductor(tokens[1]) {
+                        mtl.eta = eta.sample_one(NAMED_ETA_LAMBDA);
+                        mtl.eta_spectrum = Some(eta);
+                        mtl.k_spectrum = Some(k);
+                        mtl.fresnel_enabled = true;
+                    } else if let Some(eta) = materials::dielectric(tokens[1]) {
+                        mtl.eta = eta.sample_one(NAMED_ETA_LAMBDA);
+                        mtl.eta_spectrum = Some(eta);
+                    } else {
+                        return Err(obj_error(&format!("Unknown material {}", tokens[1])));
+                    }
                 }
                 /* roughness */
                 "Ns" => {
-                    let ns = parse_double(tokens[1])
-                        .expect("Couldn't parse double");
+                    let ns = parse_double(tokens[1])?;
                     // blender uses this mapping
                     mtl.roughness = 1.0 - ns.min(900.0).sqrt() / 30.0;
                 }
+                /* PBR extension: roughness */
+                "Pr" => {
+                    mtl.roughness = parse_double(tokens[1])?;
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: roughness map */
+                "map_Pr" => {
+                    mtl.map_Pr = Some(self.scalar_map(&tokens[1..])?);
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: metallic */
+                "Pm" => {
+                    mtl.metallic = parse_double(tokens[1])?;
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: metallic map */
+                "map_Pm" => {
+                    mtl.map_Pm = Some(self.scalar_map(&tokens[1..])?);
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: sheen */
+                "Ps" => {
+                    mtl.sheen = parse_double(tokens[1])?;
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: clearcoat thickness */
+                "Pc" => {
+                    mtl.clearcoat = parse_double(tokens[1])?;
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: clearcoat roughness */
+                "Pcr" => {
+                    mtl.clearcoat_roughness = parse_double(tokens[1])?;
+                    mtl.is_principled = true;
+                }
+                /* PBR extension: anisotropy */
+                "aniso" => {
+                    mtl.anisotropy = parse_double(tokens[1])?;
+                }
                 /* illumination model */
                 "illum" => {
-                    let illum = parse_double(tokens[1])
-                        .expect("Couldn't parse double")as usize;
+                    let illum = parse_double(tokens[1])? as usize;
                     match illum {
                         5 => mtl.fresnel_enabled = true,
                         6 => mtl.is_transparent = true,
@@ -111,10 +177,90 @@ impl Executor<MtlTask, MtlTaskResult> for MtlTaskExecutor {
             }
         }
 
-        MtlTaskResult::new(mtl, mtl_name)
+        Ok(MtlTaskResult::new(mtl, mtl_name))
+    }
+
+    /// Color map, e.g. albedo or emission. sRGB encoded unless
+    /// `-colorspace linear` is given.
+    fn color_map(&self, tokens: &[&str], uplift: Uplift) -> Result<Image<Spectrum>> {
+        let opts = parse_map_options(tokens)?;
+        super::_img_from_zip(&self.zip_bytes, &opts.name, opts.encoding, uplift)
+    }
+
+    /// Single channel map, e.g. roughness. Uses the channel given by
+    /// `-imfchan` or the mean of the color channels.
+    fn scalar_map(&self, tokens: &[&str]) -> Result<Image<Float>> {
+        let opts = parse_map_options(tokens)?;
+        let bytes = super::_extract_zip(&self.zip_bytes, &opts.name)?;
+        match opts.channel {
+            Some(channel) => Image::channel_from_file(bytes.as_slice(), channel),
+            None => Image::height_from_file(bytes.as_slice()),
+        }
     }
 }
 
+/// Options of a map statement
+struct MapOptions {
+    /// Name of the texture
+    name: String,
+    /// Given by the bump multiplier (`-bm`) or the gain of
+    /// the value range (`-mm base gain`)
+    scale: Float,
+    /// Channel of single channel maps (`-imfchan`), `0..4` for `rgba`.
+    /// `None` for the luminance.
+    channel: Option<usize>,
+    /// Encoding of color maps (`-colorspace srgb|linear`)
+    encoding: ColorEncoding,
+}
+
+fn parse_map_options(tokens: &[&str]) -> Result<MapOptions> {
+    let mut scale = 1.0;
+    let mut channel = None;
+    let mut encoding = ColorEncoding::sRGB;
+    let mut name = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            "-bm" if i + 1 < tokens.len() => {
+                scale = parse_double(tokens[i + 1])?;
+                i += 2;
+            }
+            "-mm" if i + 2 < tokens.len() => {
+                scale = parse_double(tokens[i + 2])?;
+                i += 3;
+            }
+            "-imfchan" if i + 1 < tokens.len() => {
+                channel = match tokens[i + 1] {
+                    "r" => Some(0),
+                    "g" => Some(1),
+                    "b" => Some(2),
+                    "m" => Some(3),
+                    _ => None,
+                };
+                i += 2;
+            }
+            "-colorspace" if i + 1 < tokens.len() => {
+                encoding = match tokens[i + 1].to_lowercase().as_str() {
+                    "linear" | "raw" => ColorEncoding::Linear,
+                    _ => ColorEncoding::sRGB,
+                };
+                i += 2;
+            }
+            _ => {
+                name.push(tokens[i]);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(MapOptions {
+        name: name.join(" ").replace('\\', "/"),
+        scale,
+        channel,
+        encoding,
+    })
+}
+
 pub struct MtlTask {
     pub lines: Vec<String>,
 }
@@ -1,9 +1,13 @@
 use super::*;
-use crate::tracer::KdTree;
+use crate::formatting;
+use std::time::Instant;
 
 /// https://github.com/ekzhang/rpt/blob/master/src/io.rs
 /// https://www.cs.cmu.edu/~mbz/personal/graphics/obj.html
-pub fn load_file<T: Read + Sized>(file: T, material: Material) -> Result<Mesh> {
+pub fn load_file<T: Read + Sized, M>(
+    file: T,
+    build: impl FnOnce(Vec<Point>, Vec<Face>, Vec<Normal>, Vec<Vec2>) -> M,
+) -> Result<M> {
     let mut vertices: Vec<Point> = Vec::new();
     let mut normals: Vec<Normal> = Vec::new();
     let mut uvs: Vec<Vec2> = Vec::new();
@@ -20,14 +24,24 @@ pub fn load_file<T: Read + Sized>(file: T, material: Material) -> Result<Mesh> {
         parse_tokens(tokens, &mut vertices, &mut normals, &mut uvs, &mut faces)?;
     }
 
-    Ok(TriangleMesh::new(vertices, faces, normals, uvs, material))
+    Ok(build(vertices, faces, normals, uvs))
 }
 
-
+/// Loads the meshes of a scene. Written to the cache of `cache_key` if
+/// given, then the meshes are always kD-trees.
+///
+/// Objects named with `o` or `g` can be instanced with
+/// `inst <name> <12 numbers>`, where the numbers are the rows of the
+/// 3x4 affine transformation of the instance. Instanced objects get built
+/// once to a mesh of `accel` shared by all of their instances and are not
+/// placed in the scene by themselves. Scenes with instances are not cached.
 pub fn load_scene<T: Read + Sized>(
     file: T,
     materials: Vec<Material>,
     material_indices: FxHashMap<String, usize>,
+    displacements: FxHashMap<usize, Displacement>,
+    accel: MeshAccel,
+    cache_key: Option<CacheKey>,
 ) -> Result<Scene> {
     let mut scene = Scene::default();
     let mut vertices: Vec<Point> = Vec::new();
@@ -35,8 +49,10 @@ pub fn load_scene<T: Read + Sized>(
     let mut uvs: Vec<Vec2> = Vec::new();
 
     let mut faces: Vec<Face> = Vec::new();
-    let mut meshes: Vec<(Vec<Face>, usize)> = Vec::new();
+    let mut meshes: Vec<(Vec<Face>, usize, Option<String>)> = Vec::new();
     let mut midx = usize::MAX;
+    let mut name: Option<String> = None;
+    let mut instances: Vec<(String, Transform)> = Vec::new();
 
     let reader = BufReader::new(file);
     for line in reader.lines() {
@@ -49,14 +65,15 @@ pub fn load_scene<T: Read + Sized>(
         match tokens[0] {
             "g" | "o" => {
                 if !faces.is_empty() {
-                    meshes.push((faces, midx));
+                    meshes.push((faces, midx, name));
                     faces = Vec::new();
                     midx = usize::MAX;
                 }
+                name = tokens.get(1).map(|name| name.to_string());
             }
             "usemtl" => {
                 if !faces.is_empty() {
-                    meshes.push((faces, midx));
+                    meshes.push((faces, midx, name.clone()));
                     faces = Vec::new();
                 }
                 match material_indices.get(tokens[1]) {
@@ -68,6 +85,10 @@ pub fn load_scene<T: Read + Sized>(
                     }
                 }
             }
+            "inst" => {
+                let transform = parse_instance(&tokens)?;
+                instances.push((tokens[1].to_string(), transform));
+            }
             _ => {
                 parse_tokens(
                     tokens,
@@ -80,16 +101,43 @@ pub fn load_scene<T: Read + Sized>(
         }
     }
 
-    meshes.push((faces, midx));
+    meshes.push((faces, midx, name));
 
-    let mesh = Arc::new(TriangleMesh {
+    let mut mesh = TriangleMesh {
         vertices,
         normals,
         uvs,
+        tangents: vec![],
         materials,
-    });
+    };
+
+    let meshes: Vec<(Vec<Face>, usize, Option<String>)> = meshes.into_iter()
+        .map(|(faces, midx, name)| match displacements.get(&midx) {
+            Some(disp) => (mesh.displace(faces, disp), midx, name),
+            None => (faces, midx, name),
+        })
+        .collect();
+
+    let all_faces = meshes.iter().flat_map(|(faces, _, _)| faces);
+    mesh.tangents = TriangleMesh::vertex_tangents(
+        &mesh.vertices,
+        &mesh.normals,
+        &mesh.uvs,
+        all_faces,
+    );
+    let mesh = Arc::new(mesh);
 
-    for (faces, midx) in meshes {
+    let start = Instant::now();
+    let num_meshes = meshes.len();
+    // the cache only knows how to place the meshes once
+    let cache_key = cache_key.filter(|_| instances.is_empty());
+    let mut cache = cache_key.map(|key| CacheWriter::create(&key, &mesh, num_meshes));
+    let mut prototypes = FxHashMap::<String, Vec<Triangle>>::default();
+    for (name, _) in &instances {
+        prototypes.insert(name.clone(), Vec::new());
+    }
+
+    for (faces, midx, name) in meshes {
         let is_light = mesh.materials[midx].is_light();
         let triangles = TriangleMesh::triangles_from_faces(
             Arc::clone(&mesh),
@@ -97,18 +145,71 @@ pub fn load_scene<T: Read + Sized>(
             midx,
         );
 
-        if is_light {
+        if let Some(prototype) = name.and_then(|name| prototypes.get_mut(&name)) {
+            if is_light {
+                return Err(obj_error("Instanced objects can not emit light"));
+            }
+            prototype.extend(triangles);
+        } else if is_light {
+            if let Some(cache) = &mut cache {
+                cache.lights(&triangles);
+            }
             for triangle in triangles {
                 scene.add_light(Box::new(triangle));
             }
+        } else if let Some(cache) = &mut cache {
+            // only kD-trees get cached
+            let tree = KdTree::new(triangles);
+            cache.tree(&tree);
+            scene.add(Box::new(tree));
         } else {
-            scene.add(Box::new(KdTree::new(triangles)));
+            scene.add(accel.build(triangles));
         }
     }
 
+    let mut blases = FxHashMap::<String, Arc<dyn Object>>::default();
+    for (name, triangles) in prototypes {
+        if triangles.is_empty() {
+            return Err(obj_error(&format!("Could not find object {} to instance", name)));
+        }
+        blases.insert(name, Arc::from(accel.build(triangles)));
+    }
+    let num_instances = instances.len();
+    for (name, transform) in instances {
+        scene.add(Instance::from_arc(Arc::clone(&blases[&name])).apply(transform));
+    }
+
+    println!(
+        "Built {} meshes with {:?} in {}",
+        num_meshes, accel, formatting::fmt_elapsed(start.elapsed()),
+    );
+    if num_instances > 0 {
+        println!("Placed {} instances of {} objects", num_instances, blases.len());
+    }
+    if let Some(cache) = cache {
+        cache.finish();
+    }
+
     Ok(scene)
 }
 
+/// Parses the transformation of `inst <name> <12 numbers>`
+fn parse_instance(tokens: &[&str]) -> Result<Transform> {
+    if tokens.len() != 14 {
+        return Err(obj_error("Instance needs an object name and 12 numbers"));
+    }
+    let row = |i: usize| -> Result<Vec3> { parse_vec3(&tokens[4 * i + 1..]) };
+    let (r0, r1, r2) = (row(0)?, row(1)?, row(2)?);
+    let translation = Vec3::new(
+        parse_double(tokens[5])?,
+        parse_double(tokens[9])?,
+        parse_double(tokens[13])?,
+    );
+
+    Ok(Transform::translation(translation.x, translation.y, translation.z)
+       * Transform::mat3(Mat3::new(r0, r1, r2)))
+}
+
 fn parse_tokens(
     tokens: Vec<&str>,
     vertices: &mut Vec<Point>,
@@ -145,13 +246,14 @@ fn parse_tokens(
     Ok(())
 }
 
-/// Parses a face from a .obj file
+/// Parses a face from a .obj file. Polygons are kept whole, they get fanned
+/// to triangles when building the mesh or subdivided before that.
 fn parse_face(
     tokens: &[&str],
     vertices: &[Point],
     normals: &[Normal],
     uvs: &[Vec2],
-) -> Result<Vec<Face>> {
+) -> Result<Option<Face>> {
     let mut vidxs: Vec<usize> = Vec::new();
     let mut nidxs: Vec<usize> = Vec::new();
     let mut tidxs: Vec<usize> = Vec::new();
@@ -173,26 +275,91 @@ fn parse_face(
         }
     }
 
-    let mut faces: Vec<Face> = Vec::new();
+    if vidxs.len() < 3 {
+        return Ok(None);
+    }
+    if (!nidxs.is_empty() && nidxs.len() != vidxs.len())
+        || (!tidxs.is_empty() && tidxs.len() != vidxs.len()) {
+        return Err(obj_error("Face has indices missing from some vertices"));
+    }
 
-    for i in 1..vidxs.len() - 1 {
-        let (a, b, c) = (0, i, i + 1);
-        let vidx = vec![vidxs[a], vidxs[b], vidxs[c]];
+    Ok(Some(Face::new(vidxs, nidxs, tidxs)))
+}
 
-        let nidx = if nidxs.is_empty() {
-            Vec::new()
-        } else {
-            vec![nidxs[a], nidxs[b], nidxs[c]]
-        };
+#[cfg(test)]
+mod obj_tests {
+    use super::*;
+    use crate::tracer::Ray;
+    use crate::Direction;
 
-        let tidx = if tidxs.is_empty() {
-            Vec::new()
-        } else {
-            vec![tidxs[a], tidxs[b], tidxs[c]]
-        };
+    const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\n";
+
+    fn load(obj: &str) -> Result<Scene> {
+        let materials = vec![Material::Blank, Material::light(Texture::from(Spectrum::WHITE))];
+        let mut material_indices = FxHashMap::default();
+        material_indices.insert("blank".to_string(), 0);
+        material_indices.insert("light".to_string(), 1);
+
+        let mut scene = load_scene(
+            obj.as_bytes(),
+            materials,
+            material_indices,
+            FxHashMap::default(),
+            MeshAccel::KdTree,
+            None,
+        )?;
+        scene.set_environment_map(Texture::from(Spectrum::WHITE), 1.0);
+        scene.build();
+        Ok(scene)
+    }
 
-        faces.push(Face::new(vidx, nidx, tidx));
+    fn hits(scene: &Scene, x: Float, z: Float) -> bool {
+        let r = Ray::new(Point::new(x, 1.0, z), -Direction::Y);
+        scene.objects.hit(&r, 0.0, crate::INF).is_some()
     }
 
-    Ok(faces)
+    #[test]
+    fn instances_share_object() {
+        let scene = load(&format!(
+            "{}o quad\nusemtl blank\nf 1 2 3 4\n\
+             inst quad 1 0 0 5  0 1 0 0  0 0 1 0\n\
+             inst quad 2 0 0 0  0 2 0 0  0 0 2 -5\n\
+             o floor\nusemtl blank\nf -4 -3 -2 -1\n\
+             inst floor 1 0 0 -5  0 1 0 -1  0 0 1 0\n",
+            QUAD,
+        )).unwrap();
+
+        assert!(!hits(&scene, 0.5, 0.5));
+        assert!(hits(&scene, 5.5, 0.5));
+        assert!(hits(&scene, 1.5, -3.5));
+        assert!(!hits(&scene, 2.5, -3.5));
+        assert!(hits(&scene, -4.5, 0.5));
+    }
+
+    #[test]
+    fn objects_without_instances_are_placed() {
+        let scene = load(&format!(
+            "{}o quad\nusemtl blank\nf 1 2 3 4\ninst quad 1 0 0 5  0 1 0 0  0 0 1 0\n\
+             o floor\nusemtl blank\nf 1 2 3 4\n",
+            QUAD,
+        )).unwrap();
+
+        assert!(hits(&scene, 0.5, 0.5));
+        assert!(hits(&scene, 5.5, 0.5));
+    }
+
+    #[test]
+    fn bad_instances_are_errors() {
+        let bad = [
+            "o quad\nusemtl blank\nf 1 2 3 4\ninst tree 1 0 0 0  0 1 0 0  0 0 1 0\n",
+            "o quad\nusemtl blank\nf 1 2 3 4\ninst quad 1 0 0 0  0 1 0 0\n",
+            "o quad\nusemtl blank\nf 1 2 3 4\ninst quad 1 0 0 0  0 1 0 0  0 0 x 0\n",
+            "o quad\nusemtl blank\nf 1 2 3 4\ninst\n",
+            "o quad\nusemtl light\nf 1 2 3 4\ninst quad 1 0 0 0  0 1 0 0  0 0 1 0\n",
+        ];
+        for obj in bad {
+            let Err(err) = load(&format!("{}{}", QUAD, obj)) else { panic!() };
+            assert!(err.kind() == io::ErrorKind::InvalidData);
+        }
+    }
 }
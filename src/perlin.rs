@@ -45,20 +45,20 @@ impl Perlin {
         }
     }
 
-    /// Computes Perlin noise at point `p`
-    // consider using uv instead of world position
+    /// Computes Perlin noise at point `p`. Continuous over the whole space,
+    /// including negative coordinates.
     pub fn noise_at(&self, p: Vec3) -> Float {
-        let weight = p.fract();
         let floor = p.floor();
+        let weight = p - floor;
 
         let normals = (0..2)
             .cartesian_product(0..2)
             .cartesian_product(0..2)
             .map(|((i, j), k)| {
                 self.lattice[self._hash(
-                    floor.x as usize + i,
-                    floor.y as usize + j,
-                    floor.z as usize + k,
+                    floor.x as i64 + i,
+                    floor.y as i64 + j,
+                    floor.z as i64 + k,
                 )]
             })
             .collect();
@@ -67,10 +67,9 @@ impl Perlin {
     }
 
     /// Hash utility function to get normals in the lattice
-    fn _hash(&self, x: usize, y: usize, z: usize) -> usize {
-        self.perm.x[x % PERLIN_POINTS]
-            ^ self.perm.y[y % PERLIN_POINTS]
-            ^ self.perm.z[z % PERLIN_POINTS]
+    fn _hash(&self, x: i64, y: i64, z: i64) -> usize {
+        let wrap = |c: i64| c.rem_euclid(PERLIN_POINTS as i64) as usize;
+        self.perm.x[wrap(x)] ^ self.perm.y[wrap(y)] ^ self.perm.z[wrap(z)]
     }
 
     /// Smoothing for weights
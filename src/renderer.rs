@@ -2,14 +2,15 @@ use crate::{
     formatting, rng::Xorshift, Vec2, Float, ToneMap, SamplerType
 };
 use crate::tracer::{
-    Camera, Film, FilmSample,
+    Camera, CameraPath, Film, FilmSample,
     Integrator, Scene, FilmTile
 };
 use crate::pool::{Executor, ThreadPool};
 use crate::math::vec2::UVec2;
 use std::{
-    sync::Arc, io::Write, time::{Duration, Instant}
+    sync::Arc, io::Write, ops::Range, time::{Duration, Instant}
 };
+use png::EncodingError;
 use itertools::Itertools;
 
 const TILE_SIZE: u64 = 16;
@@ -242,4 +243,26 @@ impl Renderer {
 
         film
     }
+
+    /// Renders `frames` of a camera moving along `path` at `fps` frames per
+    /// second and saves them to `{prefix}{frame:04}.png`. The scene and its
+    /// acceleration structures are built once and shared by all frames.
+    pub fn render_frames(
+        &mut self,
+        path: &CameraPath,
+        frames: Range<u64>,
+        fps: Float,
+        prefix: &str,
+    ) -> Result<(), EncodingError> {
+        assert!(fps > 0.0);
+
+        for frame in frames {
+            println!("Frame {}", frame);
+            self.camera = Arc::new(path.camera_at(frame as Float / fps));
+            self.resolution = self.camera.get_resolution();
+            self.render().save(&format!("{}{:04}.png", prefix, frame))?;
+        }
+
+        Ok(())
+    }
 }
@@ -21,6 +21,7 @@ pub fn gen_seed() -> u64 {
     seed.max(1)
 }
 
+/// Xorshiftr128+ pseudorandom number generator
 pub struct Xorshift {
     hi: u64,
     lo: u64,
@@ -35,7 +36,7 @@ impl Default for Xorshift {
 }
 
 impl Xorshift {
-
+    /// Generator seeded with `seed`, gives the same numbers for the same seed
     pub fn new(seed: u64) -> Self {
         let mut rng = Self {
             lo: seed.max(1),
@@ -63,6 +64,7 @@ impl Xorshift {
         hi
     }
 
+    /// Random 64 bit integer
     pub fn gen_u64(&mut self) -> u64 {
         self.step()
     }
@@ -91,6 +93,7 @@ impl Xorshift {
         )
     }
 
+    /// Random vector in `[0,1)^4`
     pub fn gen_vec4(&mut self) -> Vec4 {
         Vec4::new(
             self.gen_float(),
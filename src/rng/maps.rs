@@ -36,7 +36,6 @@ pub fn square_to_cos_hemisphere(rand_sq: Vec2) -> Vec3 {
 }
 
 /// Uniform random point in hemisphere with Z up
-#[cfg(test)]
 pub fn square_to_hemisphere(rand_sq: Vec2) -> Vec3 {
     let z = rand_sq.x;
     let r = (1.0 - z * z).max(0.0).sqrt();
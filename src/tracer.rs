@@ -1,18 +1,28 @@
-pub use camera::{ Camera, CameraBuilder, CameraType };
-pub use color::{Color, ColorWavelength, DenseSpectrum, Spectrum, RGB, ColorSpace};
+pub use alpha::AlphaMask;
+pub use animation::{Keyframes, Lerp};
+pub use camera::{ Camera, CameraBuilder, CameraPath, CameraType };
+pub use color::{Color, ColorWavelength, DenseSpectrum, Spectrum, Uplift, RGB, ColorSpace, materials};
 pub use film::{Film, FilmTile, FilmSample};
 pub use integrator::Integrator;
 pub use material::Material;
+pub use bxdf::{Hair, Layered, Principled, Sheen};
 pub use medium::Medium;
+pub use normal_map::NormalMap;
 pub use object::{
-    Disk, Instance, Instanceable, KdTree, Object, BVH,
-    Cone, Cube, Cylinder, Rectangle, Sphere, Triangle,
-    Sampleable, TriangleMesh, Face, Mesh
+    Disk, Instance, Instanceable, KdTree, Object, BVH, BVHBuild, BVHPrimitive, CurveBVH, MeshBVH,
+    Cone, Cube, Curve, CurveBasis, CurveType, Cylinder, Displacement, Rectangle, Sphere, Triangle,
+    Sampleable, Subdivision, SubdivisionScheme, TriangleMesh, Face, Mesh, MeshAccel, AccelStats
 };
+pub(crate) use object::{CachedKdTree, TriangleIndices};
+pub use ray::Ray;
 pub use scene::Scene;
-pub use texture::Texture;
+pub use texture::{Texture, TexCoord, NoisePattern, SolidNoise, TextureSpace};
 pub use filter::PixelFilter;
 
+/// Opacity masks for cutouts
+mod alpha;
+/// Keyframed values for animations
+mod animation;
 mod bxdf;
 mod bsdf;
 /// Abstraction for a camera
@@ -31,6 +41,8 @@ mod material;
 mod medium;
 /// MFDistribution
 mod microfacet;
+/// Normal and bump maps
+mod normal_map;
 /// Abstractions for objects in the 3D world
 mod object;
 /// Utility struct for orthonormal basis.
@@ -0,0 +1,114 @@
+use crate::{ Direction, Float, Image, Point, Vec2 };
+
+/// Opacity mask of a material. Rays pass through the surface
+/// at the parts where the mask is not opaque.
+pub struct AlphaMask {
+    /// Opacity image, constant opacity if `None`
+    image: Option<Image<Float>>,
+    /// Opacity multiplier in `\[0,1\]`, e.g. the `d` key of MTL files
+    scale: Float,
+    /// Surfaces with opacity below the threshold are cut out.
+    /// Rays pass through stochastically proportional to opacity if `None`.
+    threshold: Option<Float>,
+}
+
+impl AlphaMask {
+    /// Mask with constant opacity `alpha`
+    pub fn constant(alpha: Float) -> Self {
+        assert!((0.0..=1.0).contains(&alpha));
+        Self { image: None, scale: alpha, threshold: None }
+    }
+
+    /// Mask with opacity read from `image`
+    pub fn from_image(image: Image<Float>) -> Self {
+        Self { image: Some(image), scale: 1.0, threshold: None }
+    }
+
+    /// Multiply the opacity by `scale`
+    pub fn with_scale(mut self, scale: Float) -> Self {
+        assert!((0.0..=1.0).contains(&scale));
+        self.scale = scale;
+        self
+    }
+
+    /// Cut out everything with opacity below `threshold`
+    /// instead of letting rays pass through stochastically
+    pub fn with_threshold(mut self, threshold: Float) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Opacity of the mask at `uv`
+    pub fn opacity_at(&self, uv: Vec2) -> Float {
+        match &self.image {
+            None => self.scale,
+            Some(img) => self.scale * img.value_at(uv),
+        }
+    }
+
+    /// Is the surface opaque at `uv` for a ray arriving at `xi` from
+    /// direction `wo`? Stochastic decisions hash the point and the direction,
+    /// so that the same ray gets the same answer in every intersection test.
+    pub fn is_opaque(&self, uv: Vec2, xi: Point, wo: Direction) -> bool {
+        let alpha = self.opacity_at(uv);
+        match self.threshold {
+            Some(threshold) => alpha >= threshold,
+            None if alpha >= 1.0 => true,
+            None if alpha <= 0.0 => false,
+            None => hash_float(xi, wo) < alpha,
+        }
+    }
+}
+
+/// Hash `xi` and `wo` to a float in `\[0,1)`
+fn hash_float(xi: Point, wo: Direction) -> Float {
+    let h = [xi.x, xi.y, xi.z, wo.x, wo.y, wo.z].iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, v| {
+            // fmix64 of MurmurHash3 on each component
+            let mut h = (h ^ v.to_bits()).wrapping_mul(0x0000_0100_0000_01b3);
+            h ^= h >> 33;
+            h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+            h ^= h >> 33;
+            h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+            h ^ (h >> 33)
+        });
+
+    (h >> 11) as Float / (1_u64 << 53) as Float
+}
+
+#[cfg(test)]
+mod alpha_tests {
+    use super::*;
+
+    #[test]
+    fn threshold_cuts_out() {
+        let mask = AlphaMask::constant(0.4).with_threshold(0.5);
+        assert!(!mask.is_opaque(Vec2::ZERO, Point::ONE, Direction::Z));
+
+        let mask = AlphaMask::constant(0.6).with_threshold(0.5);
+        assert!(mask.is_opaque(Vec2::ZERO, Point::ONE, Direction::Z));
+    }
+
+    #[test]
+    fn stochastic_is_deterministic() {
+        let mask = AlphaMask::constant(0.5);
+        let xi = Point::new(0.1, 0.2, 0.3);
+        let wo = Direction::new(0.0, -1.0, 0.0);
+        assert!(mask.is_opaque(Vec2::ZERO, xi, wo) == mask.is_opaque(Vec2::ZERO, xi, wo));
+    }
+
+    #[test]
+    fn stochastic_matches_opacity() {
+        let mask = AlphaMask::constant(0.3);
+        let samples = 10_000;
+        let opaque = (0..samples)
+            .filter(|i| {
+                let xi = Point::new(*i as Float * 1e-3, 0.5, -0.25);
+                mask.is_opaque(Vec2::ZERO, xi, Direction::Z)
+            })
+            .count();
+
+        let ratio = opaque as Float / samples as Float;
+        assert!((ratio - 0.3).abs() < 0.02);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{ Float, Vec3 };
+
+#[cfg(test)]
+mod animation_tests;
+
+/// Values that can be interpolated between keyframes
+pub trait Lerp {
+    /// Value `t` of the way from `self` to `other`
+    fn lerp(&self, other: &Self, t: Float) -> Self;
+}
+
+impl Lerp for Float {
+    fn lerp(&self, other: &Self, t: Float) -> Self {
+        (1.0 - t) * self + t * other
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Self, t: Float) -> Self {
+        (1.0 - t) * *self + t * *other
+    }
+}
+
+/// Values at increasing times, linearly interpolated in between. Times
+/// outside of the keyframes get the closest keyframe.
+#[derive(Clone)]
+pub struct Keyframes<T> {
+    keys: Vec<(Float, T)>,
+}
+
+impl<T: Lerp + Clone> From<T> for Keyframes<T> {
+    fn from(value: T) -> Self {
+        Self::new(0.0, value)
+    }
+}
+
+impl<T: Lerp + Clone> Keyframes<T> {
+    /// Keyframes starting with `value` at `time`
+    pub fn new(time: Float, value: T) -> Self {
+        Self { keys: vec![(time, value)] }
+    }
+
+    /// Add a keyframe with `value` at `time`, after the previous keyframe
+    pub fn key(mut self, time: Float, value: T) -> Self {
+        assert!(self.keys.last().is_none_or(|(t, _)| *t <= time));
+        self.keys.push((time, value));
+        self
+    }
+
+    /// Value at `time`
+    pub fn at(&self, time: Float) -> T {
+        let idx = self.keys.partition_point(|(t, _)| *t <= time);
+        if idx == 0 {
+            return self.keys[0].1.clone();
+        } else if idx == self.keys.len() {
+            return self.keys[idx - 1].1.clone();
+        }
+
+        let (t0, v0) = &self.keys[idx - 1];
+        let (t1, v1) = &self.keys[idx];
+        v0.lerp(v1, (time - t0) / (t1 - t0))
+    }
+
+    /// Values of the keyframes
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.keys.iter().map(|(_, v)| v)
+    }
+}
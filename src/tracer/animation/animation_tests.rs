@@ -0,0 +1,62 @@
+use super::*;
+
+#[test]
+fn keyframes_interpolate_linearly() {
+    let keys = Keyframes::new(1.0, 2.0)
+        .key(3.0, 6.0)
+        .key(4.0, 0.0);
+
+    assert!(keys.at(0.0) == 2.0);
+    assert!((keys.at(2.0) - 4.0).abs() < crate::EPSILON);
+    assert!((keys.at(3.5) - 3.0).abs() < crate::EPSILON);
+    assert!(keys.at(5.0) == 0.0);
+}
+
+#[test]
+fn keyframes_hold_at_equal_times() {
+    let keys = Keyframes::new(0.0, Vec3::ZERO)
+        .key(1.0, Vec3::X)
+        .key(1.0, Vec3::Y);
+
+    assert!(keys.at(1.0) == Vec3::Y);
+    assert!(keys.at(0.5).distance(0.5 * Vec3::X) < crate::EPSILON);
+}
+
+#[test]
+#[should_panic]
+fn keyframes_reject_decreasing_time() {
+    let _ = Keyframes::new(1.0, 0.0).key(0.0, 1.0);
+}
+
+#[test]
+fn camera_follows_path() {
+    use crate::{ Vec2, tracer::{ Camera, CameraPath } };
+
+    let path = CameraPath::new(Camera::builder().resolution((64, 64)))
+        .origin(Keyframes::new(0.0, Vec3::ZERO).key(2.0, 2.0 * Vec3::X))
+        .towards(Keyframes::new(0.0, -Vec3::Z).key(2.0, 2.0 * Vec3::X - Vec3::Z))
+        .shutter(0.0, 0.5);
+
+    let camera = path.camera_at(1.0);
+    let r = camera.generate_ray(Vec2::splat(32.0), Vec2::splat(0.5));
+    assert!(r.origin.distance(Vec3::X) < crate::EPSILON);
+    assert!(r.dir.distance(-Vec3::Z) < crate::EPSILON);
+    assert!(camera.sample_time(0.0) == 1.0 && camera.sample_time(1.0) == 1.5);
+}
+
+#[test]
+fn light_intensity_follows_time() {
+    use crate::{ Direction, Point, tracer::{ ColorWavelength, Material, Texture, Object, Disk, ray::Ray } };
+
+    let light = Material::light(Texture::from(1.0))
+        .with_intensity(Keyframes::new(0.0, 0.0).key(1.0, 4.0));
+    let disk = Disk::new(Point::ZERO, Direction::Z, 1.0, light);
+    let mut h = disk.hit(&Ray::new(Point::Z, -Direction::Z), 0.0, crate::INF).unwrap();
+    let lambda = ColorWavelength::sample(0.5);
+
+    h.time = 0.25;
+    let quarter = h.material.emit(&lambda, &h);
+    h.time = 1.0;
+    let full = h.material.emit(&lambda, &h);
+    assert!(full.max() > 0.0 && (full - 4.0 * quarter).max().abs() < crate::EPSILON);
+}
@@ -1,5 +1,5 @@
 use crate::{ Direction, Transport, Float, Vec2, Normal };
-use crate::tracer::{ Color, ColorWavelength, bxdf::BxDF, onb::Onb };
+use crate::tracer::{ Color, ColorWavelength, TexCoord, bxdf::BxDF, onb::Onb };
 
 pub struct BSDF {
     BxDF: BxDF
@@ -33,35 +33,33 @@ impl BSDF {
         backface: bool,
         t: Float,
         ng: Normal,
-        ns: Normal,
-        uv: Vec2,
+        uvw: &Onb,
+        tc: &TexCoord,
         mode: Transport
     ) -> Color {
         let reflection = Self::is_reflection(wo, wi, ng);
-        let uvw = Onb::new(ns);
-
         let wo_local = uvw.to_local(wo);
         let wi_local = uvw.to_local(wi);
 
-        self.BxDF.f(wo_local, wi_local, lambda, reflection, backface, t, uv, mode)
+        self.BxDF.f(wo_local, wi_local, lambda, reflection, backface, t, tc, mode)
     }
 
     /// Sample direction from a random BxDF
+    #[allow(clippy::too_many_arguments)]
     #[inline]
     pub fn sample(
         &self,
         wo: Direction,
-        ns: Normal,
+        uvw: &Onb,
+        tc: &TexCoord,
         backface: bool,
         lambda: &mut ColorWavelength,
         rand_u: Float,
         rand_sq: Vec2,
     ) -> Option<Direction> {
-        let uvw = Onb::new(ns);
-
         let wo_local = uvw.to_local(wo);
 
-        self.BxDF.sample(wo_local, backface, lambda, rand_u, rand_sq)
+        self.BxDF.sample(wo_local, backface, lambda, tc, rand_u, rand_sq)
             .map(|wi| uvw.to_world(wi))
     }
 
@@ -72,16 +70,15 @@ impl BSDF {
         wo: Direction,
         wi: Direction,
         ng: Normal,
-        ns: Normal,
+        uvw: &Onb,
+        tc: &TexCoord,
         lambda: &ColorWavelength,
     ) -> Float {
         let reflection = Self::is_reflection(wo, wi, ng);
-        let uvw = Onb::new(ns);
-
         let wo_local = uvw.to_local(wo);
         let wi_local = uvw.to_local(wi);
 
-        self.BxDF.pdf(wo_local, wi_local, reflection, lambda)
+        self.BxDF.pdf(wo_local, wi_local, reflection, lambda, tc)
     }
 
     #[inline(always)]
@@ -4,11 +4,21 @@ use crate::{
 };
 use crate::tracer::{
     Color, ColorWavelength, Spectrum,
-    microfacet::MfDistribution, onb::Onb
+    microfacet::{MfDistribution, MfPoint}, onb::Onb, texture::TexCoord
 };
 
+pub use hair::Hair;
+pub use layered::Layered;
+pub use principled::Principled;
+pub use sheen::Sheen;
+
+mod compensation;
+mod hair;
+mod layered;
 mod microfacet;
+mod principled;
 mod scatter;
+mod sheen;
 mod volumetric;
 
 #[cfg(test)]
@@ -25,6 +35,16 @@ pub enum BxDF {
     MfConductor(MfDistribution),
     /// Microfacet glass
     MfDielectric(MfDistribution),
+    /// Thin glass sheet without refraction, roughness is ignored
+    ThinDielectric(MfDistribution),
+    /// Principled BSDF with diffuse, specular, sheen, clearcoat and transmission
+    Principled(Principled),
+    /// Dielectric coat on top of an opaque BxDF
+    Layered(Layered),
+    /// Microfiber sheen on a diffuse base for cloth
+    Sheen(Sheen),
+    /// Scattering from hair fibers
+    Hair(Hair),
     /// Volumetric medium[scattering_parameter, t_scale, sigma_t, sigma_s]
     Volumetric(Float, Float, Spectrum, Spectrum),
     None,
@@ -34,7 +54,7 @@ impl BxDF {
     #[inline]
     pub fn is_specular(&self) -> bool {
         match self {
-            Self::Volumetric(..) | Self::MfDielectric(_) => true,
+            Self::Volumetric(..) | Self::MfDielectric(_) | Self::ThinDielectric(_) => true,
             Self::MfConductor(mfd) => mfd.is_specular(),
             _ => false,
         }
@@ -47,7 +67,9 @@ impl BxDF {
     #[inline]
     pub fn is_transmission(&self) -> bool {
         match self {
-            Self::MfDielectric(_) | Self::Volumetric(..) => true,
+            Self::MfDielectric(_) | Self::ThinDielectric(_) | Self::Volumetric(..)
+                | Self::Hair(_) => true,
+            Self::Principled(p) => p.is_transmission(),
             _ => false
         }
     }
@@ -60,8 +82,9 @@ impl BxDF {
         match self {
             Self::MfConductor(mfd) => mfd.is_delta(),
             Self::MfDielectric(mfd) => {
-                mfd.is_delta() || mfd.eta_at(lambda.leading_sample()) == 1.0
+                mfd.is_delta() || mfd.is_index_matched(lambda.leading_sample())
             }
+            Self::ThinDielectric(_) => true,
             _ => false,
         }
     }
@@ -76,7 +99,7 @@ impl BxDF {
         reflection: bool,
         backface: bool,
         t: Float,
-        uv: Vec2,
+        tc: &TexCoord,
         mode: Transport
     ) -> Color {
         if (!reflection || backface) && self.is_reflection() {
@@ -85,14 +108,24 @@ impl BxDF {
         match self {
             Self::Lambertian(spec) => scatter::lambertian::f(spec, lambda),
             Self::MfDiffuse(mfd) => {
-                microfacet::diffuse::f(wo, wi, lambda, uv, mfd)
+                microfacet::diffuse::f(wo, wi, lambda, tc, &mfd.at(lambda, tc))
             }
             Self::MfConductor(mfd) => {
-                microfacet::conductor::f(wo, wi, lambda, uv, mfd)
+                compensation::conductor::f(wo, wi, lambda, tc, &mfd.at(lambda, tc))
             }
             Self::MfDielectric(mfd) => {
-                microfacet::dielectric::f(wo, wi, lambda, reflection, uv, mfd, mode)
+                let mfd = mfd.at(lambda, tc);
+                compensation::dielectric::f(wo, wi, lambda, reflection, tc, &mfd, mode)
+            }
+            Self::ThinDielectric(mfd) => {
+                microfacet::thin_dielectric::f(wo, wi, lambda, reflection, tc, &mfd.at(lambda, tc))
+            }
+            Self::Principled(p) => {
+                principled::f(wo, wi, lambda, reflection, tc, p, mode)
             }
+            Self::Layered(l) => layered::f(wo, wi, lambda, reflection, tc, l, mode),
+            Self::Sheen(s) => sheen::f(wo, wi, lambda, tc, s),
+            Self::Hair(h) => hair::f(wo, wi, lambda, tc, h),
             Self::Volumetric(_, t_scale, sigma_t, sigma_s) => {
                 volumetric::f(lambda, *t_scale * t, sigma_t, sigma_s)
             }
@@ -106,6 +139,7 @@ impl BxDF {
         wo: Direction,
         backface: bool,
         lambda: &mut ColorWavelength,
+        tc: &TexCoord,
         rand_u: Float,
         rand_sq: Vec2,
     ) -> Option<Direction> {
@@ -115,11 +149,23 @@ impl BxDF {
         match self {
             Self::Lambertian(_) => scatter::lambertian::sample(rand_sq),
             //Self::MfDiffuse(_) => scatter::lambertian::pdf(wo, wi),
-            Self::MfDiffuse(mfd) => microfacet::diffuse::sample(wo, mfd, rand_u, rand_sq),
-            Self::MfConductor(mfd) => microfacet::conductor::sample(wo, mfd, rand_sq),
+            Self::MfDiffuse(mfd) => {
+                microfacet::diffuse::sample(wo, &mfd.at(lambda, tc), rand_u, rand_sq)
+            }
+            Self::MfConductor(mfd) => {
+                compensation::conductor::sample(wo, &mfd.at(lambda, tc), rand_u, rand_sq)
+            }
             Self::MfDielectric(mfd) => {
-                microfacet::dielectric::sample(wo, mfd, lambda, rand_u, rand_sq)
+                let mfd = mfd.at(lambda, tc);
+                microfacet::dielectric::sample(wo, &mfd, lambda, rand_u, rand_sq)
+            }
+            Self::ThinDielectric(mfd) => {
+                microfacet::thin_dielectric::sample(wo, &mfd.at(lambda, tc), lambda, rand_u)
             }
+            Self::Principled(p) => principled::sample(wo, p, lambda, tc, rand_u, rand_sq),
+            Self::Layered(l) => layered::sample(wo, l, lambda, tc, rand_u, rand_sq),
+            Self::Sheen(_) => sheen::sample(rand_u, rand_sq),
+            Self::Hair(h) => hair::sample(wo, lambda, tc, h, rand_u, rand_sq),
             Self::Volumetric(g, ..) => volumetric::sample(wo, *g, rand_sq),
             Self::None => None,
         }
@@ -131,7 +177,8 @@ impl BxDF {
         wo: Direction,
         wi: Direction,
         reflection: bool,
-        lambda: &ColorWavelength
+        lambda: &ColorWavelength,
+        tc: &TexCoord,
     ) -> Float {
         if !reflection && self.is_reflection() {
             // backfaces too? or explicitly in pdfs?
@@ -140,11 +187,20 @@ impl BxDF {
         match self {
             Self::Lambertian(_) => scatter::lambertian::pdf(wo, wi),
             //Self::MfDiffuse(_) => scatter::lambertian::pdf(wo, wi),
-            Self::MfDiffuse(mfd) => microfacet::diffuse::pdf(wo, wi, mfd),
-            Self::MfConductor(mfd) => microfacet::conductor::pdf(wo, wi, mfd),
+            Self::MfDiffuse(mfd) => microfacet::diffuse::pdf(wo, wi, &mfd.at(lambda, tc)),
+            Self::MfConductor(mfd) => {
+                compensation::conductor::pdf(wo, wi, &mfd.at(lambda, tc))
+            }
             Self::MfDielectric(mfd) => {
-                microfacet::dielectric::pdf(wo, wi, reflection, lambda, mfd)
+                microfacet::dielectric::pdf(wo, wi, reflection, lambda, &mfd.at(lambda, tc))
+            }
+            Self::ThinDielectric(mfd) => {
+                microfacet::thin_dielectric::pdf(wo, wi, reflection, lambda, &mfd.at(lambda, tc))
             }
+            Self::Principled(p) => principled::pdf(wo, wi, reflection, lambda, tc, p),
+            Self::Layered(l) => layered::pdf(wo, wi, reflection, lambda, tc, l),
+            Self::Sheen(_) => sheen::pdf(wo, wi),
+            Self::Hair(h) => hair::pdf(wo, wi, lambda, tc, h),
             Self::Volumetric(g, ..) => volumetric::pdf(wo, wi, *g),
             Self::None => 0.0,
         }
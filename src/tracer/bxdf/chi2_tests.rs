@@ -1,6 +1,6 @@
 use super::*;
 use crate::{ math::simpson_integration, rng::Xorshift };
-use crate::tracer::{ DenseSpectrum, Spectrum, Texture };
+use crate::tracer::{ DenseSpectrum, Spectrum, Texture, microfacet::MicrofacetConfig };
 use std::io::Write;
 use std::fs::{self, File};
 use std::path::Path;
@@ -13,30 +13,37 @@ const CHI2_RUNS: usize = 20;
 const CHI2_SLEVEL: Float = 0.01;
 const CHI2_TOLERANCE: Float = (NUM_SAMPLES as Float) * 1e-5;
 const CHI2_MIN_FREQ: Float = 5.0;
+// texture coordinates of the sampled point, `v` gives the offset on hair
+const TC: TexCoord = TexCoord::from_uv(Vec2::new(0.5, 0.3));
 
+// seeds the cases that fail too often with random seeds
 macro_rules! test_bxdf {
+    ( seed $seed:expr; $( $name:ident, $bxdf:expr ),* ) => {
+        $( test_bxdf!(@case $name, $bxdf, Xorshift::new($seed)); )*
+    };
     ( $( $name:ident, $bxdf:expr ),* ) => {
-        $(
-            mod $name {
-                use super::*;
-
-                #[test]
-                fn chi2() {
-                    let mut rng = Xorshift::default();
-
-                    for _ in 0..CHI2_RUNS {
-                        let wo = rng::maps::square_to_hemisphere(rng.gen_vec2());
-                        assert!(chi2_pass(wo, &mut rng, $bxdf));
-                    }
+        $( test_bxdf!(@case $name, $bxdf, Xorshift::default()); )*
+    };
+    ( @case $name:ident, $bxdf:expr, $rng:expr ) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn chi2() {
+                let mut rng = $rng;
+
+                for _ in 0..CHI2_RUNS {
+                    let wo = rng::maps::square_to_hemisphere(rng.gen_vec2());
+                    assert!(chi2_pass(wo, &mut rng, $bxdf));
                 }
             }
-        )*
-    }
+        }
+    };
 }
 
 fn mfd(roughness: Float, eta: Float) -> MfDistribution {
     MfDistribution::new(
-        roughness,
+        Vec2::splat(roughness),
         DenseSpectrum::from_constant(eta),
         DenseSpectrum::from_constant(0.0), /* k has no effect on sampling */
         Texture::from(Spectrum::WHITE),
@@ -45,6 +52,30 @@ fn mfd(roughness: Float, eta: Float) -> MfDistribution {
     )
 }
 
+fn mfd_aniso(roughness: Vec2, beckmann: bool) -> MfDistribution {
+    let cfg = MicrofacetConfig::new(
+        roughness,
+        DenseSpectrum::from_constant(1.5),
+        DenseSpectrum::from_constant(0.0),
+        Texture::from(Spectrum::WHITE),
+        Texture::from(Spectrum::WHITE),
+        Texture::from(Spectrum::WHITE),
+    );
+    if beckmann { MfDistribution::Beckmann(cfg) } else { MfDistribution::Ggx(cfg) }
+}
+
+fn principled() -> Principled {
+    Principled::new(Texture::from(Spectrum::WHITE))
+}
+
+fn sheen(roughness: Float) -> Sheen {
+    Sheen::new(Texture::from(Spectrum::WHITE), Texture::from(Spectrum::WHITE), roughness)
+}
+
+fn coated_diffuse() -> Layered {
+    Layered::coated_diffuse(Texture::from(Spectrum::WHITE))
+}
+
 test_bxdf!{
     lambertian, BxDF::Lambertian(Spectrum::WHITE),
 
@@ -58,6 +89,11 @@ test_bxdf!{
     conductor25, BxDF::MfConductor(mfd(0.25, 1.5)),
     conductor10, BxDF::MfConductor(mfd(0.10, 1.5)),
 
+    conductor_aniso, BxDF::MfConductor(mfd_aniso(Vec2::new(0.1, 0.5), false)),
+    conductor_beckmann, BxDF::MfConductor(mfd_aniso(Vec2::splat(0.3), true)),
+    conductor_beckmann_aniso, BxDF::MfConductor(mfd_aniso(Vec2::new(0.5, 0.1), true)),
+    dielectric_aniso, BxDF::MfDielectric(mfd_aniso(Vec2::new(0.1, 0.5), false)),
+
     dielectric75_eta15, BxDF::MfDielectric(mfd(0.75, 1.5)),
     dielectric50_eta15, BxDF::MfDielectric(mfd(0.50, 1.5)),
     dielectric25_eta15, BxDF::MfDielectric(mfd(0.25, 1.5)),
@@ -66,7 +102,54 @@ test_bxdf!{
     dielectric75_eta25, BxDF::MfDielectric(mfd(0.75, 2.5)),
     dielectric50_eta25, BxDF::MfDielectric(mfd(0.50, 2.5)),
     dielectric25_eta25, BxDF::MfDielectric(mfd(0.25, 2.5)),
-    dielectric10_eta25, BxDF::MfDielectric(mfd(0.10, 2.5))
+    dielectric10_eta25, BxDF::MfDielectric(mfd(0.10, 2.5)),
+
+    // texture coordinates of the test point give the maps 0.5
+    conductor_roughness_map, BxDF::MfConductor(
+        mfd(1.0, 1.5).with_roughness_map(Texture::Gradient(Vec2::X))
+    ),
+    dielectric_eta_map, BxDF::MfDielectric(
+        mfd(0.3, 1.5).with_eta_map(
+            Texture::Gradient(Vec2::X).remap(Vec2::Y, Vec2::new(1.3, 1.8))
+        )
+    ),
+
+    principled_plastic,   BxDF::Principled(principled().roughness(0.5)),
+    principled_metal,     BxDF::Principled(principled().metallic(1.0).roughness(0.4)),
+    principled_aniso,     BxDF::Principled(principled().metallic(0.5).anisotropic(0.8)),
+    principled_coated,    BxDF::Principled(principled().clearcoat(1.0, 0.5)),
+    principled_glass,     BxDF::Principled(principled().transmission(1.0).roughness(0.5)),
+    principled_all,       BxDF::Principled(
+        principled()
+            .metallic(0.3)
+            .roughness(0.6)
+            .sheen(1.0, 0.5)
+            .clearcoat(0.5, 0.3)
+            .transmission(0.5)
+    ),
+    principled_maps,      BxDF::Principled(
+        principled()
+            .roughness_map(Texture::Gradient(Vec2::X))
+            .metallic(1.0)
+            .metallic_map(Texture::Gradient(Vec2::X))
+            .transmission(0.5)
+    ),
+
+    layered_diffuse,   BxDF::Layered(coated_diffuse()),
+    layered_rough,     BxDF::Layered(coated_diffuse().coat(0.5, 1.5)),
+    layered_conductor, BxDF::Layered(
+        Layered::coated_conductor(Texture::from(Spectrum::WHITE), 0.3, 1.5, 3.0)
+    ),
+
+    sheen,       BxDF::Sheen(sheen(0.5)),
+    sheen_rough, BxDF::Sheen(sheen(1.0))
+}
+
+test_bxdf!{
+    seed 0x5eed_4a12;
+    hair,        BxDF::Hair(Hair::melanin(1.3, 0.0)),
+    hair_rough,  BxDF::Hair(Hair::melanin(0.3, 0.5).roughness(0.6, 0.8)),
+    hair_smooth, BxDF::Hair(Hair::melanin(8.0, 0.0).roughness(0.2, 0.3).alpha(5.0))
 }
 
 fn write_tables(
@@ -181,7 +264,7 @@ fn sample_frequencies(
     let phi_factor = PHI_BINS as Float / (2.0 * crate::PI);
 
     for _ in 0..NUM_SAMPLES {
-        match bxdf.sample(wo, false, lambda, rng.gen_float(), rng.gen_vec2()) {
+        match bxdf.sample(wo, false, lambda, &TC, rng.gen_float(), rng.gen_vec2()) {
             None => (),
             Some(wi) => {
                 let theta = spherical_utils::theta(wi);
@@ -223,7 +306,7 @@ fn compute_frequencies(
                 let reflection = spherical_utils::cos_theta(wo)
                     * spherical_utils::cos_theta(wi) >= 0.0;
                 // pdf in solid angle, change to spherical coordinates
-                bxdf.pdf(wo, wi, reflection, lambda) * theta.sin()
+                bxdf.pdf(wo, wi, reflection, lambda, &TC) * theta.sin()
             };
             let integral = simpson_integration::simpson2d(f, theta0, theta1, phi0, phi1);
             ig += integral;
@@ -0,0 +1,106 @@
+use super::*;
+
+/*
+ * ENERGY COMPENSATION
+ * Microfacet models only account for light that scatters once from the
+ * microsurface. The rest of the energy is lost, which darkens rough
+ * surfaces. The lost energy is added back using precomputed albedos of
+ * single scattering.
+ */
+
+/// Rough conductors with the multiple scattering lobe of Kulla & Conty 2017
+pub mod conductor {
+    use super::*;
+
+    pub fn f(
+        wo: Direction,
+        wi: Direction,
+        lambda: &ColorWavelength,
+        tc: &TexCoord,
+        mfd: &MfPoint,
+    ) -> Color {
+        let f_ss = microfacet::conductor::f(wo, wi, lambda, tc, mfd);
+        let e_avg = mfd.albedo_avg();
+        if mfd.is_delta() || 1.0 - e_avg < crate::EPSILON
+            || !spherical_utils::same_hemisphere(wo, wi) {
+            return f_ss;
+        }
+
+        // average Fresnel, Schlick with the reflectance at normal incidence
+        let f0 = mfd.f(Direction::Z, Normal::Z, lambda);
+        let f_avg = (20.0 * f0 + Color::WHITE) / 21.0;
+        // energy that survives the bounces between the microfacets, the lobe
+        // integrates to `1 - E(wo)` so clamping keeps `E + E_ms <= 1`
+        let f_ms = (f_avg * f_avg * e_avg / (Color::WHITE - f_avg * (1.0 - e_avg)))
+            .clamp(0.0, 1.0);
+
+        let e_wo = mfd.albedo(spherical_utils::cos_theta(wo));
+        let e_wi = mfd.albedo(spherical_utils::cos_theta(wi));
+        let lobe = (1.0 - e_wo) * (1.0 - e_wi) / (crate::PI * (1.0 - e_avg));
+
+        f_ss + mfd.ks(lambda, tc) * f_ms * lobe
+    }
+
+    /// Probability to sample the cosine weighted multiple scattering lobe
+    fn ms_probability(wo: Direction, mfd: &MfPoint) -> Float {
+        if mfd.is_delta() {
+            0.0
+        } else {
+            1.0 - mfd.albedo(spherical_utils::cos_theta(wo))
+        }
+    }
+
+    pub fn sample(
+        wo: Direction,
+        mfd: &MfPoint,
+        rand_u: Float,
+        rand_sq: Vec2,
+    ) -> Option<Direction> {
+        if rand_u < ms_probability(wo, mfd) {
+            scatter::lambertian::sample(rand_sq)
+        } else {
+            microfacet::conductor::sample(wo, mfd, rand_sq)
+        }
+    }
+
+    pub fn pdf(
+        wo: Direction,
+        wi: Direction,
+        mfd: &MfPoint,
+    ) -> Float {
+        let p_ms = ms_probability(wo, mfd);
+        let p_ss = microfacet::conductor::pdf(wo, wi, mfd);
+        if p_ms == 0.0 {
+            p_ss
+        } else {
+            (1.0 - p_ms) * p_ss + p_ms * scatter::lambertian::pdf(wo, wi)
+        }
+    }
+}
+
+/// Rough dielectrics scaled by their albedo of single scattering, as the
+/// energy lost from a non-absorbing dielectric should scatter eventually.
+/// Turquin 2019.
+pub mod dielectric {
+    use super::*;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn f(
+        wo: Direction,
+        wi: Direction,
+        lambda: &ColorWavelength,
+        reflection: bool,
+        tc: &TexCoord,
+        mfd: &MfPoint,
+        mode: Transport,
+    ) -> Color {
+        let f_ss = microfacet::dielectric::f(wo, wi, lambda, reflection, tc, mfd, mode);
+        let wl = lambda.leading_sample();
+        if mfd.is_delta() || mfd.eta_at(wl) == 1.0 {
+            return f_ss;
+        }
+
+        let albedo = mfd.dielectric_albedo(spherical_utils::cos_theta(wo), wl);
+        f_ss / albedo.max(crate::EPSILON)
+    }
+}
@@ -0,0 +1,428 @@
+use super::*;
+use crate::tracer::DenseSpectrum;
+
+/// Number of explicitly modeled lobes, the rest are summed to one
+const P_MAX: usize = 3;
+
+/// Hair fiber scattering of Chiang et al. 2016, as described in PBR 3rd ed.
+/// The shading frame has `x` along the fiber and `z` towards the viewer.
+/// The offset `h` across the fiber is given by `v` of the texture coordinates.
+///
+/// Scattering is split into lobes `p` by the number of transmissions through
+/// the fiber: R (`p = 0`), TT (`p = 1`), TRT (`p = 2`) and the rest.
+/// Each lobe is a product of a longitudinal (`Mp`), attenuation (`Ap`)
+/// and an azimuthal (`Np`) term.
+pub struct Hair {
+    /// Absorption coefficient of the fiber interior
+    sigma_a: DenseSpectrum,
+    /// Refraction index of the fiber
+    eta: Float,
+    /// Longitudinal roughness [0,1]
+    beta_m: Float,
+    /// Azimuthal roughness [0,1]
+    beta_n: Float,
+    /// Tilt of the cuticle scales in degrees
+    alpha: Float,
+}
+
+impl Hair {
+    /// Hair with the absorption coefficient `sigma_a` in the fiber
+    pub fn new(sigma_a: DenseSpectrum) -> Self {
+        Self {
+            sigma_a,
+            eta: 1.55,
+            beta_m: 0.3,
+            beta_n: 0.3,
+            alpha: 2.0,
+        }
+    }
+
+    /// Hair colored by the concentrations of eumelanin (brown-black) and
+    /// pheomelanin (red-yellow). Blond hair has `eumelanin` around 0.3,
+    /// brown around 1.3 and black around 8.
+    pub fn melanin(eumelanin: Float, pheomelanin: Float) -> Self {
+        assert!(eumelanin >= 0.0 && pheomelanin >= 0.0);
+        // exponential fits to the RGB absorption coefficients of d'Eon et al. 2011
+        let sigma_a = DenseSpectrum::from_fn(|lambda| {
+            eumelanin * 1.37 * (-0.00817 * (lambda - 465.0)).exp()
+                + pheomelanin * 1.05 * (-0.0119 * (lambda - 465.0)).exp()
+        });
+        Self::new(sigma_a)
+    }
+
+    /// Set the refraction index of the fiber
+    pub fn eta(mut self, eta: Float) -> Self {
+        assert!(eta > 0.0);
+        self.eta = eta;
+        self
+    }
+
+    /// Set the longitudinal and azimuthal roughness
+    pub fn roughness(mut self, beta_m: Float, beta_n: Float) -> Self {
+        assert!((0.0..=1.0).contains(&beta_m));
+        assert!((0.0..=1.0).contains(&beta_n));
+        self.beta_m = beta_m;
+        self.beta_n = beta_n;
+        self
+    }
+
+    /// Set the tilt of the cuticle scales in degrees
+    pub fn alpha(mut self, alpha: Float) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Variance of the longitudinal lobes
+    fn v(&self) -> [Float; P_MAX + 1] {
+        let beta_m = self.beta_m.max(1e-2);
+        let v0 = (0.726 * beta_m + 0.812 * beta_m * beta_m + 3.7 * beta_m.powi(20)).powi(2);
+        [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0]
+    }
+
+    /// Logistic scale of the azimuthal lobes
+    fn s(&self) -> Float {
+        let beta_n = self.beta_n.max(1e-2);
+        (crate::PI / 8.0).sqrt()
+            * (0.265 * beta_n + 1.194 * beta_n * beta_n + 5.372 * beta_n.powi(22))
+    }
+
+    /// Rotate `sin(θo)` and `cos(θo)` by the scale tilt of lobe `p`
+    fn tilt(&self, p: usize, sin_theta_o: Float, cos_theta_o: Float) -> (Float, Float) {
+        let sin_alpha = self.alpha.to_radians().sin();
+        let cos_alpha = (1.0 - sin_alpha * sin_alpha).max(0.0).sqrt();
+        // sin(2^k α) and cos(2^k α) for k = 0, 1, 2
+        let mut sin_2k = [sin_alpha, 0.0, 0.0];
+        let mut cos_2k = [cos_alpha, 0.0, 0.0];
+        for k in 1..3 {
+            sin_2k[k] = 2.0 * cos_2k[k - 1] * sin_2k[k - 1];
+            cos_2k[k] = cos_2k[k - 1].powi(2) - sin_2k[k - 1].powi(2);
+        }
+
+        let (sin_op, cos_op) = match p {
+            0 => (sin_theta_o * cos_2k[1] - cos_theta_o * sin_2k[1],
+                  cos_theta_o * cos_2k[1] + sin_theta_o * sin_2k[1]),
+            1 => (sin_theta_o * cos_2k[0] + cos_theta_o * sin_2k[0],
+                  cos_theta_o * cos_2k[0] - sin_theta_o * sin_2k[0]),
+            2 => (sin_theta_o * cos_2k[2] + cos_theta_o * sin_2k[2],
+                  cos_theta_o * cos_2k[2] - sin_theta_o * sin_2k[2]),
+            _ => (sin_theta_o, cos_theta_o),
+        };
+
+        (sin_op, cos_op.abs())
+    }
+
+    /// Attenuation of each lobe
+    fn ap(&self, cos_theta_o: Float, h: Float, lambda: &ColorWavelength) -> [Color; P_MAX + 1] {
+        let sin_theta_o = (1.0 - cos_theta_o * cos_theta_o).max(0.0).sqrt();
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = (1.0 - sin_theta_t * sin_theta_t).max(0.0).sqrt();
+
+        let cos_gamma_t = (1.0 - (h / util::eta_p(self.eta, sin_theta_o, cos_theta_o)).powi(2))
+            .max(0.0).sqrt();
+        // transmittance of a single path through the fiber
+        let t = (-self.sigma_a.sample(lambda) * (2.0 * cos_gamma_t / cos_theta_t)).exp();
+
+        let cos_gamma_o = (1.0 - h * h).max(0.0).sqrt();
+        let f = util::fr_dielectric(cos_theta_o * cos_gamma_o, self.eta);
+
+        let ap0 = Color::WHITE * f;
+        let ap1 = (1.0 - f) * (1.0 - f) * t;
+        let ap2 = ap1 * t * f;
+        let ap3 = ap2 * t * f / (Color::WHITE - t * f);
+
+        [ap0, ap1, ap2, ap3]
+    }
+
+    /// Probabilities of sampling each lobe
+    fn ap_pdf(&self, cos_theta_o: Float, h: Float, lambda: &ColorWavelength) -> [Float; P_MAX + 1] {
+        let ap = self.ap(cos_theta_o, h, lambda).map(|ap| ap.mean());
+        let sum: Float = ap.iter().sum();
+
+        if sum <= 0.0 {
+            [1.0, 0.0, 0.0, 0.0]
+        } else {
+            ap.map(|ap| ap / sum)
+        }
+    }
+}
+
+mod util {
+    use super::*;
+
+    /// Offset across the fiber from the texture coordinates
+    pub fn h(tc: &TexCoord) -> Float {
+        (2.0 * tc.uv.y - 1.0).clamp(-1.0 + crate::EPSILON, 1.0 - crate::EPSILON)
+    }
+
+    /// Modified refraction index in the plane perpendicular to the fiber
+    pub fn eta_p(eta: Float, sin_theta_o: Float, cos_theta_o: Float) -> Float {
+        (eta * eta - sin_theta_o * sin_theta_o).sqrt() / cos_theta_o
+    }
+
+    /// Fresnel reflectance of a dielectric from outside
+    pub fn fr_dielectric(cos_theta: Float, eta: Float) -> Float {
+        let cos_o = cos_theta.clamp(0.0, 1.0);
+        let sin2_i = (1.0 - cos_o * cos_o) / (eta * eta);
+        if sin2_i >= 1.0 {
+            return 1.0;
+        }
+        let cos_i = (1.0 - sin2_i).max(0.0).sqrt();
+
+        let r_par = (eta * cos_o - cos_i) / (eta * cos_o + cos_i);
+        let r_per = (cos_o - eta * cos_i) / (cos_o + eta * cos_i);
+
+        (r_par * r_par + r_per * r_per) / 2.0
+    }
+
+    /// Modified Bessel function of the first kind
+    fn i0(x: Float) -> Float {
+        let mut val = 0.0;
+        let mut x2i = 1.0;
+        let mut ifact = 1.0;
+        let mut i4 = 1.0;
+        for i in 0..10 {
+            if i > 1 {
+                ifact *= i as Float;
+            }
+            val += x2i / (i4 * ifact * ifact);
+            x2i *= x * x;
+            i4 *= 4.0;
+        }
+        val
+    }
+
+    fn log_i0(x: Float) -> Float {
+        if x > 12.0 {
+            x + 0.5 * (-(2.0 * crate::PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+        } else {
+            i0(x).ln()
+        }
+    }
+
+    /// Longitudinal scattering
+    pub fn mp(
+        cos_theta_i: Float,
+        cos_theta_o: Float,
+        sin_theta_i: Float,
+        sin_theta_o: Float,
+        v: Float,
+    ) -> Float {
+        let a = cos_theta_i * cos_theta_o / v;
+        let b = sin_theta_i * sin_theta_o / v;
+
+        if v <= 0.1 {
+            (log_i0(a) - b - 1.0 / v + std::f64::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+        } else {
+            (-b).exp() * i0(a) / ((1.0 / v).sinh() * 2.0 * v)
+        }
+    }
+
+    /// Azimuthal angle of exit for lobe `p`
+    fn phi(p: usize, gamma_o: Float, gamma_t: Float) -> Float {
+        2.0 * p as Float * gamma_t - 2.0 * gamma_o + p as Float * crate::PI
+    }
+
+    fn logistic(x: Float, s: Float) -> Float {
+        let x = x.abs();
+        (-x / s).exp() / (s * (1.0 + (-x / s).exp()).powi(2))
+    }
+
+    fn logistic_cdf(x: Float, s: Float) -> Float {
+        1.0 / (1.0 + (-x / s).exp())
+    }
+
+    fn trimmed_logistic(x: Float, s: Float, a: Float, b: Float) -> Float {
+        logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+    }
+
+    /// Azimuthal scattering
+    pub fn np(phi_d: Float, p: usize, s: Float, gamma_o: Float, gamma_t: Float) -> Float {
+        let mut dphi = phi_d - phi(p, gamma_o, gamma_t);
+        while dphi > crate::PI { dphi -= 2.0 * crate::PI; }
+        while dphi < -crate::PI { dphi += 2.0 * crate::PI; }
+
+        trimmed_logistic(dphi, s, -crate::PI, crate::PI)
+    }
+
+    /// Sample an azimuthal offset for lobe `p`
+    pub fn sample_np(
+        rand_u: Float,
+        p: usize,
+        s: Float,
+        gamma_o: Float,
+        gamma_t: Float
+    ) -> Float {
+        let (a, b) = (-crate::PI, crate::PI);
+        let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+        let x = -s * (1.0 / (rand_u * k + logistic_cdf(a, s)) - 1.0).ln();
+
+        phi(p, gamma_o, gamma_t) + x.clamp(a, b)
+    }
+
+    /// Sample `sin(θi)` of a longitudinal lobe
+    pub fn sample_mp(rand_sq: Vec2, sin_theta_op: Float, cos_theta_op: Float, v: Float) -> Float {
+        let u = rand_sq.x.max(1e-5);
+        let cos_theta = 1.0 + v * (u + (1.0 - u) * (-2.0 / v).exp()).ln();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let cos_phi = (2.0 * crate::PI * rand_sq.y).cos();
+
+        (-cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op).clamp(-1.0, 1.0)
+    }
+
+    /// Spherical angles of `w` with respect to the fiber
+    pub fn angles(w: Direction) -> (Float, Float, Float) {
+        let sin_theta = w.x.clamp(-1.0, 1.0);
+        let cos_theta = (1.0 - sin_theta * sin_theta).max(0.0).sqrt();
+        let phi = w.z.atan2(w.y);
+
+        (sin_theta, cos_theta, phi)
+    }
+
+    /// Azimuthal angle of the refracted ray inside the fiber
+    pub fn gamma_t(eta: Float, h: Float, sin_theta_o: Float, cos_theta_o: Float) -> Float {
+        let sin_gamma_t = h / eta_p(eta, sin_theta_o, cos_theta_o);
+        sin_gamma_t.clamp(-1.0, 1.0).asin()
+    }
+}
+
+pub fn f(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    hair: &Hair,
+) -> Color {
+    let h = util::h(tc);
+    let (sin_theta_o, cos_theta_o, phi_o) = util::angles(wo);
+    let (sin_theta_i, cos_theta_i, phi_i) = util::angles(wi);
+
+    let gamma_o = h.asin();
+    let gamma_t = util::gamma_t(hair.eta, h, sin_theta_o, cos_theta_o);
+
+    let ap = hair.ap(cos_theta_o, h, lambda);
+    let v = hair.v();
+    let s = hair.s();
+    let phi = phi_i - phi_o;
+
+    let mut f = Color::BLACK;
+    for p in 0..P_MAX {
+        let (sin_theta_op, cos_theta_op) = hair.tilt(p, sin_theta_o, cos_theta_o);
+        f += util::mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, v[p])
+            * ap[p] * util::np(phi, p, s, gamma_o, gamma_t);
+    }
+    f += util::mp(cos_theta_i, cos_theta_o, sin_theta_i, sin_theta_o, v[P_MAX])
+        * ap[P_MAX] / (2.0 * crate::PI);
+
+    // cancel the shading cosine of the integrator
+    let cos_theta_wi = spherical_utils::cos_theta(wi).abs();
+    if cos_theta_wi > 0.0 { f / cos_theta_wi } else { f }
+}
+
+pub fn sample(
+    wo: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    hair: &Hair,
+    rand_u: Float,
+    rand_sq: Vec2,
+) -> Option<Direction> {
+    let h = util::h(tc);
+    let (sin_theta_o, cos_theta_o, phi_o) = util::angles(wo);
+    let ap_pdf = hair.ap_pdf(cos_theta_o, h, lambda);
+
+    // choose the lobe and reuse `rand_u` for the azimuth
+    let mut p = 0;
+    let mut rand_u = rand_u;
+    while p < P_MAX && rand_u >= ap_pdf[p] {
+        rand_u -= ap_pdf[p];
+        p += 1;
+    }
+    let rand_u = (rand_u / ap_pdf[p]).clamp(0.0, 1.0 - crate::EPSILON);
+
+    let (sin_theta_op, cos_theta_op) = hair.tilt(p, sin_theta_o, cos_theta_o);
+    let sin_theta_i = util::sample_mp(rand_sq, sin_theta_op, cos_theta_op, hair.v()[p]);
+    let cos_theta_i = (1.0 - sin_theta_i * sin_theta_i).max(0.0).sqrt();
+
+    let gamma_o = h.asin();
+    let gamma_t = util::gamma_t(hair.eta, h, sin_theta_o, cos_theta_o);
+    let dphi = if p < P_MAX {
+        util::sample_np(rand_u, p, hair.s(), gamma_o, gamma_t)
+    } else {
+        2.0 * crate::PI * rand_u
+    };
+    let phi_i = phi_o + dphi;
+
+    Some( Direction::new(
+        sin_theta_i,
+        cos_theta_i * phi_i.cos(),
+        cos_theta_i * phi_i.sin(),
+    ) )
+}
+
+pub fn pdf(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    hair: &Hair,
+) -> Float {
+    let h = util::h(tc);
+    let (sin_theta_o, cos_theta_o, phi_o) = util::angles(wo);
+    let (sin_theta_i, cos_theta_i, phi_i) = util::angles(wi);
+
+    let gamma_o = h.asin();
+    let gamma_t = util::gamma_t(hair.eta, h, sin_theta_o, cos_theta_o);
+
+    let ap_pdf = hair.ap_pdf(cos_theta_o, h, lambda);
+    let v = hair.v();
+    let s = hair.s();
+    let phi = phi_i - phi_o;
+
+    let mut pdf = 0.0;
+    for p in 0..P_MAX {
+        let (sin_theta_op, cos_theta_op) = hair.tilt(p, sin_theta_o, cos_theta_o);
+        pdf += util::mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, v[p])
+            * ap_pdf[p] * util::np(phi, p, s, gamma_o, gamma_t);
+    }
+    pdf += util::mp(cos_theta_i, cos_theta_o, sin_theta_i, sin_theta_o, v[P_MAX])
+        * ap_pdf[P_MAX] / (2.0 * crate::PI);
+
+    pdf
+}
+
+#[cfg(test)]
+mod hair_tests {
+    use super::*;
+    use crate::rng::Xorshift;
+
+    const NUM_SAMPLES: usize = 100_000;
+
+    #[test]
+    fn white_furnace() {
+        // fixed seed, the test is about energy and not about the random numbers
+        let mut rng = Xorshift::new(0x5eed_f00d);
+        let lambda = ColorWavelength::sample(rng.gen_float());
+        let wo = rng::maps::square_to_sphere(rng.gen_vec2());
+
+        for beta in [0.2, 0.5, 0.8] {
+            let hair = Hair::new(DenseSpectrum::from_constant(0.0))
+                .roughness(beta, beta);
+            let mut sum = 0.0;
+            for _ in 0..NUM_SAMPLES {
+                let tc = TexCoord::from_uv(Vec2::new(0.0, rng.gen_float()));
+                let Some(wi) = sample(wo, &lambda, &tc, &hair, rng.gen_float(), rng.gen_vec2())
+                else { continue; };
+                let pdf = pdf(wo, wi, &lambda, &tc, &hair);
+                if pdf == 0.0 {
+                    continue;
+                }
+                let f = f(wo, wi, &lambda, &tc, &hair);
+                sum += f.mean() * spherical_utils::cos_theta(wi).abs() / pdf;
+            }
+            // importance sampled with the lobes of the hair
+            let energy = sum / NUM_SAMPLES as Float;
+            println!("beta: {}, energy: {}", beta, energy);
+            assert!((0.95..1.05).contains(&energy));
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use super::*;
+use crate::rng::Xorshift;
+use crate::tracer::{ DenseSpectrum, Texture, microfacet::MicrofacetConfig };
+
+/// Smallest roughness (α) of the coat. Keeps every interface evaluable
+/// for arbitrary direction pairs, which the random walk requires.
+const MIN_ROUGHNESS: Float = 0.01;
+/// Probability to sample the cosine weighted hemisphere after
+/// the coat did not reflect. Guards against a too narrow base lobe.
+const DEFENSIVE_PROBABILITY: Float = 0.1;
+/// Russian roulette starts after this many bounces inside the layer
+const RR_DEPTH: usize = 3;
+
+/// Dielectric coat on top of an opaque base BxDF with an optional
+/// medium in between. `f` is evaluated stochastically with the position-free
+/// random walk of Guo et al. 2018 as done in PBR 4th ed. Chapter 14.3.
+/// Sampling uses a mixture of the coat and base lobes with closed form PDF,
+/// such that `f / pdf` stays unbiased for the integrators.
+pub struct Layered {
+    /// Dielectric interface on top
+    coat: MfDistribution,
+    /// Opaque interface at the bottom
+    base: Box<BxDF>,
+    /// Thickness of the medium between the interfaces in mean free paths
+    thickness: Float,
+    /// Single scattering albedo of the medium. `None` if it only absorbs.
+    albedo: Option<Spectrum>,
+    /// Henyey-Greenstein asymmetry of the medium
+    g: Float,
+    /// Maximum number of bounces inside the layer
+    max_depth: usize,
+    /// Number of random walks per evaluation
+    samples: usize,
+}
+
+impl Layered {
+    fn new(base: BxDF) -> Self {
+        Self {
+            coat: Self::coat_distribution(0.0, 1.5),
+            base: Box::new(base),
+            thickness: 0.01,
+            albedo: None,
+            g: 0.0,
+            max_depth: 10,
+            samples: 1,
+        }
+    }
+
+    /// Smooth dielectric coat on top of a diffuse base with albedo `kd`
+    pub fn coated_diffuse(kd: Texture) -> Self {
+        let mfd = MfDistribution::new(
+            Vec2::ONE,
+            DenseSpectrum::from_constant(1.5),
+            DenseSpectrum::from_constant(0.0),
+            kd,
+            Texture::from(Spectrum::BLACK),
+            Texture::from(Spectrum::BLACK),
+        );
+        Self::new(BxDF::MfDiffuse(mfd))
+    }
+
+    /// Smooth dielectric coat on top of a conductor with roughness
+    /// `roughness`, refraction index `eta` and absorption `k`.
+    pub fn coated_conductor(ks: Texture, roughness: Float, eta: Float, k: Float) -> Self {
+        // delta base under a delta coat cannot be evaluated with the random walk
+        let roughness = roughness.max(MIN_ROUGHNESS);
+        let mfd = MfDistribution::new(
+            Vec2::splat(roughness),
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(k),
+            Texture::from(Spectrum::WHITE),
+            ks,
+            Texture::from(Spectrum::BLACK),
+        );
+        Self::new(BxDF::MfConductor(mfd))
+    }
+
+    /// Set roughness and refraction index of the coat
+    pub fn coat(mut self, roughness: Float, eta: Float) -> Self {
+        assert!(eta > 1.0);
+        self.coat = Self::coat_distribution(roughness, eta);
+        self
+    }
+
+    /// Set thickness of the medium between the coat and the base
+    pub fn thickness(mut self, thickness: Float) -> Self {
+        assert!(thickness > 0.0);
+        self.thickness = thickness;
+        self
+    }
+
+    /// Make the medium between the coat and the base scatter light with
+    /// single scattering `albedo` and Henyey-Greenstein asymmetry `g`
+    pub fn medium(mut self, albedo: Spectrum, g: Float) -> Self {
+        assert!((-1.0..=1.0).contains(&g));
+        self.albedo = Some(albedo);
+        self.g = g;
+        self
+    }
+
+    /// Set the number of random walks per evaluation
+    pub fn samples(mut self, samples: usize) -> Self {
+        assert!(samples > 0);
+        self.samples = samples;
+        self
+    }
+
+    /// Set the maximum number of bounces inside the layer
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    fn coat_distribution(roughness: Float, eta: Float) -> MfDistribution {
+        let roughness = roughness.max(MIN_ROUGHNESS);
+        MfDistribution::Ggx(MicrofacetConfig::new(
+            Vec2::splat(roughness),
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(0.0),
+            Texture::from(Spectrum::BLACK),
+            Texture::from(Spectrum::WHITE),
+            Texture::from(Spectrum::WHITE),
+        ))
+    }
+
+    /// Probability to sample reflection from the coat
+    #[inline]
+    fn coat_probability(&self, wo: Direction, lambda: &ColorWavelength, tc: &TexCoord) -> Float {
+        self.coat.at(lambda, tc).f_at(wo, Normal::Z, lambda.leading_sample())
+    }
+}
+
+mod util {
+    use super::*;
+
+    /// Sampled direction with the BxDF value and PDF
+    pub struct LobeSample {
+        pub wi: Direction,
+        pub f: Color,
+        pub pdf: Float,
+    }
+
+    impl LobeSample {
+        /// Is `wi` in the same hemisphere as `wo`?
+        #[inline]
+        pub fn is_reflection(&self, wo: Direction) -> bool {
+            spherical_utils::same_hemisphere(wo, self.wi)
+        }
+    }
+
+    #[inline]
+    pub fn reverse(mode: Transport) -> Transport {
+        match mode {
+            Transport::Radiance => Transport::Importance,
+            Transport::Importance => Transport::Radiance,
+        }
+    }
+
+    /// Seed the random walk deterministically from the directions
+    pub fn seed(wo: Direction, wi: Direction) -> u64 {
+        // FNV-1a
+        [wo.x, wo.y, wo.z, wi.x, wi.y, wi.z].iter()
+            .fold(0xcbf29ce484222325, |hash: u64, v| {
+                (hash ^ v.to_bits() as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// Transmittance through `dz` of the medium along `w`
+    #[inline]
+    pub fn tr(dz: Float, w: Direction) -> Float {
+        if dz.abs() <= Float::MIN_POSITIVE {
+            1.0
+        } else {
+            (-(dz / spherical_utils::cos_theta(w)).abs()).exp()
+        }
+    }
+
+    #[inline]
+    pub fn power_heuristic(pdf_f: Float, pdf_g: Float) -> Float {
+        let f2 = pdf_f * pdf_f;
+        let g2 = pdf_g * pdf_g;
+        if f2 + g2 == 0.0 { 0.0 } else { f2 / (f2 + g2) }
+    }
+
+    pub fn sample_coat(
+        wo: Direction,
+        l: &Layered,
+        lambda: &ColorWavelength,
+        tc: &TexCoord,
+        mode: Transport,
+        rng: &mut Xorshift,
+    ) -> Option<LobeSample> {
+        // coat has constant eta, wavelengths do not get terminated
+        let mut lambda_coat = lambda.clone();
+        let wi = microfacet::dielectric::sample(
+            wo, &l.coat.at(lambda, tc), &mut lambda_coat, rng.gen_float(), rng.gen_vec2()
+        )?;
+        let reflection = spherical_utils::same_hemisphere(wo, wi);
+        let f = microfacet::dielectric::f(wo, wi, lambda, reflection, tc, &l.coat.at(lambda, tc), mode);
+        let pdf = microfacet::dielectric::pdf(wo, wi, reflection, lambda, &l.coat.at(lambda, tc));
+
+        if pdf <= 0.0 || f.is_black() { None } else { Some(LobeSample { wi, f, pdf }) }
+    }
+
+    pub fn sample_base(
+        wo: Direction,
+        l: &Layered,
+        lambda: &ColorWavelength,
+        tc: &TexCoord,
+        mode: Transport,
+        rng: &mut Xorshift,
+    ) -> Option<LobeSample> {
+        let mut lambda_base = lambda.clone();
+        let wi = l.base.sample(
+            wo, false, &mut lambda_base, tc, rng.gen_float(), rng.gen_vec2()
+        )?;
+        let f = l.base.f(wo, wi, lambda, true, false, 0.0, tc, mode);
+        let pdf = l.base.pdf(wo, wi, true, lambda, tc);
+
+        if pdf <= 0.0 || f.is_black() { None } else { Some(LobeSample { wi, f, pdf }) }
+    }
+}
+
+/// One position-free random walk inside the layer from `wo` to `wi`,
+/// both in the upper hemisphere.
+#[allow(clippy::too_many_arguments)]
+fn random_walk(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    l: &Layered,
+    mode: Transport,
+    rng: &mut Xorshift,
+) -> Color {
+    let mut f = Color::BLACK;
+    let thickness = l.thickness;
+
+    // enter the layer from wo
+    let Some(wos) = util::sample_coat(wo, l, lambda, tc, mode, rng) else {
+        return f;
+    };
+    if wos.is_reflection(wo) {
+        return f;
+    }
+    // and exit towards wi, sampled from the adjoint direction
+    let Some(wis) = util::sample_coat(wi, l, lambda, tc, util::reverse(mode), rng) else {
+        return f;
+    };
+    if wis.is_reflection(wi) {
+        return f;
+    }
+
+    let mut beta = wos.f * spherical_utils::cos_theta(wos.wi).abs() / wos.pdf;
+    let mut z = thickness;
+    let mut w = wos.wi;
+
+    for depth in 0..l.max_depth {
+        // russian roulette
+        if depth > RR_DEPTH && beta.max() < 0.25 {
+            let q = (1.0 - beta.max()).max(0.0);
+            if rng.gen_float() < q {
+                break;
+            }
+            beta /= 1.0 - q;
+        }
+
+        match &l.albedo {
+            None => {
+                z = if z == thickness { 0.0 } else { thickness };
+                beta *= util::tr(thickness, w);
+            }
+            Some(albedo) => {
+                // sample distance to the next scattering event, σ_t = 1
+                let dz = -(1.0 - rng.gen_float()).ln()
+                    * spherical_utils::cos_theta(w).abs();
+                let zp = if w.z > 0.0 { z + dz } else { z - dz };
+                if zp == z {
+                    continue;
+                }
+
+                if 0.0 < zp && zp < thickness {
+                    let albedo = albedo.sample(lambda);
+                    // next event estimation through the coat
+                    let phase = volumetric::pdf(-w, -wis.wi, l.g);
+                    let wt = util::power_heuristic(wis.pdf, phase);
+                    f += beta * albedo * phase * wt * util::tr(zp - thickness, wis.wi)
+                        * wis.f / wis.pdf;
+
+                    // scatter in the medium. phase function is sampled perfectly
+                    let Some(wp) = volumetric::sample(w, l.g, rng.gen_vec2()) else {
+                        break;
+                    };
+                    let pdf_phase = volumetric::pdf(-w, wp, l.g);
+                    if pdf_phase == 0.0 || wp.z == 0.0 {
+                        break;
+                    }
+                    beta *= albedo;
+                    w = wp;
+                    z = zp;
+
+                    // phase sampled direction leaves through the coat
+                    if w.z > 0.0 {
+                        let f_exit = microfacet::dielectric::f(
+                            -w, wi, lambda, false, tc, &l.coat.at(lambda, tc), mode
+                        );
+                        if !f_exit.is_black() {
+                            let pdf_exit = microfacet::dielectric::pdf(
+                                -w, wi, false, lambda, &l.coat.at(lambda, tc)
+                            );
+                            let wt = util::power_heuristic(pdf_phase, pdf_exit);
+                            f += beta * util::tr(zp - thickness, w) * f_exit * wt;
+                        }
+                    }
+                    continue;
+                }
+                z = zp.clamp(0.0, thickness);
+            }
+        }
+
+        if z == thickness {
+            // reflect back in from the coat, exiting is handled by NEE
+            let Some(bs) = util::sample_coat(-w, l, lambda, tc, mode, rng) else {
+                break;
+            };
+            if !bs.is_reflection(-w) {
+                break;
+            }
+            beta *= bs.f * spherical_utils::cos_theta(bs.wi).abs() / bs.pdf;
+            w = bs.wi;
+        } else {
+            // next event estimation at the base through the coat
+            let f_base = l.base.f(-w, -wis.wi, lambda, true, false, 0.0, tc, mode);
+            if !f_base.is_black() {
+                let pdf_base = l.base.pdf(-w, -wis.wi, true, lambda, tc);
+                let wt = util::power_heuristic(wis.pdf, pdf_base);
+                f += beta * f_base * spherical_utils::cos_theta(wis.wi).abs() * wt
+                    * util::tr(thickness, wis.wi) * wis.f / wis.pdf;
+            }
+
+            let Some(bs) = util::sample_base(-w, l, lambda, tc, mode, rng) else {
+                break;
+            };
+            beta *= bs.f * spherical_utils::cos_theta(bs.wi).abs() / bs.pdf;
+            w = bs.wi;
+
+            // base sampled direction leaves through the coat
+            let f_exit = microfacet::dielectric::f(-w, wi, lambda, false, tc, &l.coat.at(lambda, tc), mode);
+            if !f_exit.is_black() {
+                let pdf_exit = microfacet::dielectric::pdf(-w, wi, false, lambda, &l.coat.at(lambda, tc));
+                let wt = util::power_heuristic(bs.pdf, pdf_exit);
+                f += beta * util::tr(thickness, bs.wi) * f_exit * wt;
+            }
+        }
+    }
+
+    f
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn f(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    reflection: bool,
+    tc: &TexCoord,
+    l: &Layered,
+    mode: Transport,
+) -> Color {
+    // two sided
+    let (wo, wi) = if wo.z < 0.0 { (-wo, -wi) } else { (wo, wi) };
+    if !reflection || !spherical_utils::same_hemisphere(wo, wi) {
+        return Color::BLACK;
+    }
+
+    let f_coat = microfacet::dielectric::f(wo, wi, lambda, true, tc, &l.coat.at(lambda, tc), mode);
+
+    let mut rng = Xorshift::new(util::seed(wo, wi));
+    let f_walk = (0..l.samples)
+        .map(|_| random_walk(wo, wi, lambda, tc, l, mode, &mut rng))
+        .fold(Color::BLACK, |acc, f| acc + f);
+
+    f_coat + f_walk / l.samples as Float
+}
+
+pub fn sample(
+    wo: Direction,
+    l: &Layered,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    rand_u: Float,
+    rand_sq: Vec2,
+) -> Option<Direction> {
+    let (wo, flip) = if wo.z < 0.0 { (-wo, true) } else { (wo, false) };
+    let pr = l.coat_probability(wo, lambda, tc);
+
+    let wi = if rand_u < pr {
+        microfacet::conductor::sample(wo, &l.coat.at(lambda, tc), rand_sq)
+    } else {
+        let rand_u = (rand_u - pr) / (1.0 - pr);
+        if rand_u < DEFENSIVE_PROBABILITY {
+            scatter::lambertian::sample(rand_sq)
+        } else {
+            let rand_u = (rand_u - DEFENSIVE_PROBABILITY) / (1.0 - DEFENSIVE_PROBABILITY);
+            let mut lambda = lambda.clone();
+            l.base.sample(wo, false, &mut lambda, tc, rand_u, rand_sq)
+        }
+    };
+
+    wi.map(|wi| if flip { -wi } else { wi })
+}
+
+pub fn pdf(
+    wo: Direction,
+    wi: Direction,
+    reflection: bool,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    l: &Layered,
+) -> Float {
+    let (wo, wi) = if wo.z < 0.0 { (-wo, -wi) } else { (wo, wi) };
+    if !reflection || !spherical_utils::same_hemisphere(wo, wi) {
+        return 0.0;
+    }
+    let pr = l.coat_probability(wo, lambda, tc);
+
+    let pdf_coat = microfacet::conductor::pdf(wo, wi, &l.coat.at(lambda, tc));
+    let pdf_base = DEFENSIVE_PROBABILITY * scatter::lambertian::pdf(wo, wi)
+        + (1.0 - DEFENSIVE_PROBABILITY) * l.base.pdf(wo, wi, true, lambda, tc);
+
+    pr * pdf_coat + (1.0 - pr) * pdf_base
+}
@@ -45,11 +45,13 @@ mod util {
         wo: Direction,
         wi: Direction,
         lambda: &ColorWavelength,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
     ) -> Color {
         let cos_theta_wo = spherical_utils::cos_theta(wo);
         let cos_theta_wi = spherical_utils::cos_theta(wi);
         let wh = (wi + wo).normalize();
+        // orient MS normal to same side as geometric normal for fresnel
+        let wh = if spherical_utils::cos_theta(wh) < 0.0 { -wh } else { wh };
 
         let d = mfd.d(wh);
         let f = mfd.f(wo, wh, lambda);
@@ -69,10 +71,10 @@ pub mod conductor {
         wo: Direction,
         wi: Direction,
         lambda: &ColorWavelength,
-        uv: Vec2,
-        mfd: &MfDistribution,
+        tc: &TexCoord,
+        mfd: &MfPoint,
     ) -> Color {
-        let ks = mfd.ks(lambda, uv);
+        let ks = mfd.ks(lambda, tc);
         if mfd.is_delta() {
             let f = mfd.f(wo, Normal::Z, lambda);
             ks * f / spherical_utils::cos_theta(wi).abs()
@@ -83,7 +85,7 @@ pub mod conductor {
 
     pub fn sample(
         wo: Direction,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
         rand_sq: Vec2
     ) -> Option<Direction> {
         if mfd.is_delta() {
@@ -98,7 +100,7 @@ pub mod conductor {
     pub fn pdf(
         wo: Direction,
         wi: Direction,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
     ) -> Float {
         // check if in same hemisphere or perpendicular to normal
         if !spherical_utils::same_hemisphere(wi, wo) {
@@ -129,8 +131,8 @@ pub mod diffuse {
         wo: Direction,
         wi: Direction,
         lambda: &ColorWavelength,
-        uv: Vec2,
-        mfd: &MfDistribution,
+        tc: &TexCoord,
+        mfd: &MfPoint,
     ) -> Color {
         let wh = (wo + wi).normalize();
 
@@ -146,8 +148,8 @@ pub mod diffuse {
         let fd = mfd.disney_diffuse(cos_wo, cos_wi, cos_wh);
 
 
-        let ks = mfd.ks(lambda, uv);
-        let kd = mfd.kd(lambda, uv);
+        let ks = mfd.ks(lambda, tc);
+        let kd = mfd.kd(lambda, tc);
 
         fr * ks + kd * (Color::WHITE - f) * fd / crate::PI
     }
@@ -155,7 +157,7 @@ pub mod diffuse {
     #[allow(dead_code)]
     pub fn sample(
         wo: Direction,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
         rand_u: Float,
         rand_sq: Vec2,
     ) -> Option<Direction> {
@@ -178,7 +180,7 @@ pub mod diffuse {
     pub fn pdf(
         wo: Direction,
         wi: Direction,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
     ) -> Float {
         if !spherical_utils::same_hemisphere(wi, wo) {
             return 0.0;
@@ -212,8 +214,8 @@ pub mod dielectric {
         wi: Direction,
         lambda: &ColorWavelength,
         reflection: bool,
-        uv: Vec2,
-        mfd: &MfDistribution,
+        tc: &TexCoord,
+        mfd: &MfPoint,
         mode: Transport,
     ) -> Color {
         let cos_theta_wo = spherical_utils::cos_theta(wo);
@@ -235,9 +237,11 @@ pub mod dielectric {
         } else {
             (wi * eta_ratio + wo).normalize()
         };
+        // orient MS normal to same side as geometric normal for fresnel
+        let wh = if spherical_utils::cos_theta(wh) < 0.0 { -wh } else { wh };
 
         if reflection {
-            let ks = mfd.ks(lambda, uv);
+            let ks = mfd.ks(lambda, tc);
             if eta == 1.0 || mfd.is_delta() {
                 let f = mfd.f(wo, wh, lambda);
                 ks * f / cos_theta_wi.abs()
@@ -246,7 +250,6 @@ pub mod dielectric {
             }
         } else {
             let f = mfd.f(wo, wh, lambda);
-            let wh = if spherical_utils::cos_theta(wh) < 0.0 { -wh } else { wh };
 
             // scale coefficient if transporting radiance
             let scale = match mode {
@@ -254,7 +257,7 @@ pub mod dielectric {
                 Transport::Importance => 1.0,
             };
 
-            let tf = mfd.tf(lambda, uv);
+            let tf = mfd.tf(lambda, tc);
 
             if eta == 1.0 || mfd.is_delta() {
                 tf * (Color::WHITE - f) / (scale * cos_theta_wi.abs())
@@ -267,14 +270,14 @@ pub mod dielectric {
 
                 tf * d * (Color::WHITE - f) * g / scale
                     * (wh_dot_wi * wh_dot_wo / (cos_theta_wi * cos_theta_wo)).abs()
-                    / (eta_ratio * wh_dot_wi + wh_dot_wo).powi(2)
+                    / (wh_dot_wi + wh_dot_wo / eta_ratio).powi(2)
             }
         }
     }
 
     pub fn sample(
         wo: Direction,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
         lambda: &mut ColorWavelength,
         rand_u: Float,
         rand_sq: Vec2,
@@ -307,7 +310,7 @@ pub mod dielectric {
         wi: Direction,
         reflection: bool,
         lambda: &ColorWavelength,
-        mfd: &MfDistribution,
+        mfd: &MfPoint,
     ) -> Float {
         let cos_theta_wo = spherical_utils::cos_theta(wo);
         let cos_theta_wi = spherical_utils::cos_theta(wi);
@@ -372,3 +375,91 @@ pub mod dielectric {
         }
     }
 }
+
+/*
+ * THIN DIELECTRIC
+ * Infinitesimally thin dielectric sheet, e.g. window panes. Light passes
+ * straight through without refraction offset, internal bounces are summed
+ * up in closed form. If the distribution has a thin film, the sheet is the
+ * film and the reflectance includes interference.
+ */
+pub mod thin_dielectric {
+    use super::*;
+
+    /// Reflectance of the sheet at `wl`. Transmittance is `1 - R`
+    pub fn reflectance(wo: Direction, wl: Float, mfd: &MfPoint) -> Float {
+        let cos_theta_wo = spherical_utils::cos_theta(wo).abs();
+        match mfd.film() {
+            Some(film) => film.reflectance(cos_theta_wo, 1.0, 1.0.into(), wl),
+            None => {
+                let r = mfd.f_at(Direction::new(0.0, 0.0, cos_theta_wo), Normal::Z, wl);
+                if r < 1.0 {
+                    // geometric series of bounces inside the sheet
+                    let t = 1.0 - r;
+                    r + t * t * r / (1.0 - r * r)
+                } else {
+                    r
+                }
+            }
+        }
+    }
+
+    pub fn f(
+        wo: Direction,
+        wi: Direction,
+        lambda: &ColorWavelength,
+        reflection: bool,
+        tc: &TexCoord,
+        mfd: &MfPoint,
+    ) -> Color {
+        let samples = lambda.iter()
+            .map(|wl| reflectance(wo, *wl, mfd))
+            .collect::<Vec<Float>>().try_into().unwrap();
+        let r = Color::from_array(samples);
+        let cos_theta_wi = spherical_utils::cos_theta(wi).abs();
+
+        if reflection {
+            mfd.ks(lambda, tc) * r / cos_theta_wi
+        } else {
+            mfd.tf(lambda, tc) * (Color::WHITE - r) / cos_theta_wi
+        }
+    }
+
+    pub fn sample(
+        wo: Direction,
+        mfd: &MfPoint,
+        lambda: &ColorWavelength,
+        rand_u: Float,
+    ) -> Option<Direction> {
+        let pr = reflectance(wo, lambda.leading_sample(), mfd);
+
+        if rand_u < pr {
+            Some( Direction::new(-wo.x, -wo.y, wo.z) )
+        } else {
+            Some( -wo )
+        }
+    }
+
+    pub fn pdf(
+        wo: Direction,
+        wi: Direction,
+        reflection: bool,
+        lambda: &ColorWavelength,
+        mfd: &MfPoint,
+    ) -> Float {
+        let pr = reflectance(wo, lambda.leading_sample(), mfd);
+
+        if reflection {
+            let wh = (wo + wi).normalize();
+            if 1.0 - spherical_utils::cos_theta(wh).abs() < crate::EPSILON {
+                pr
+            } else {
+                0.0
+            }
+        } else if 1.0 + wo.dot(wi) < crate::EPSILON {
+            1.0 - pr
+        } else {
+            0.0
+        }
+    }
+}
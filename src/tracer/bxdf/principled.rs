@@ -0,0 +1,454 @@
+use super::*;
+use crate::tracer::{
+    DenseSpectrum, Texture, microfacet::MicrofacetConfig
+};
+
+/// Smallest perceptual roughness of the specular lobes. Keeps the
+/// distribution away from the delta code paths of the microfacet lobes.
+const MIN_ROUGHNESS: Float = 0.05;
+/// Roughness of the clearcoat shadow-masking term as set by Burley 2012
+const CLEARCOAT_ROUGHNESS: Float = 0.25;
+/// Reflectance at normal incidence of the clearcoat layer (η = 1.5)
+const CLEARCOAT_F0: Float = 0.04;
+
+/// Principled BSDF by Burley 2012 and 2015. Diffuse, sheen, specular and
+/// clearcoat lobes are blended with a rough dielectric lobe for transmission.
+pub struct Principled {
+    /// Base color of the surface, also tints transmission
+    base_color: Texture,
+    /// Metallic [0,1], blends from dielectric to conductor
+    metallic: Float,
+    /// Optional map that scales `metallic` at each point
+    metallic_map: Option<Box<Texture>>,
+    /// Perceptual roughness [0,1], α = roughness^2
+    roughness: Float,
+    /// Optional map that scales `roughness` at each point
+    roughness_map: Option<Box<Texture>>,
+    /// Anisotropy [0,1], stretches the specular lobe along the tangent
+    anisotropic: Float,
+    /// Tints the dielectric specular reflection towards base color [0,1]
+    specular_tint: Float,
+    /// Strength of the sheen lobe at grazing angles [0,1]
+    sheen: Float,
+    /// Tints the sheen towards base color [0,1]
+    sheen_tint: Float,
+    /// Strength of the clearcoat lobe [0,1]
+    clearcoat: Float,
+    /// Glossiness of the clearcoat [0,1]
+    clearcoat_gloss: Float,
+    /// Amount of light transmitted through the surface [0,1]
+    transmission: Float,
+    /// Refraction index of the dielectric lobes
+    eta: Float,
+    /// Distribution of the specular lobe
+    mfd: MfDistribution,
+}
+
+impl Principled {
+    /// Rough dielectric with `base_color`. Other parameters set with
+    /// the builder methods.
+    pub fn new(base_color: Texture) -> Self {
+        let roughness = 0.5;
+        let anisotropic = 0.0;
+        let eta = 1.5;
+        Self {
+            base_color,
+            metallic: 0.0,
+            metallic_map: None,
+            roughness,
+            roughness_map: None,
+            anisotropic,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            eta,
+            mfd: Self::distribution(roughness, anisotropic, eta),
+        }
+    }
+
+    /// Set metallic parameter
+    pub fn metallic(mut self, metallic: Float) -> Self {
+        self.metallic = metallic.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Scale metallic parameter at each point by `metallic_map`
+    pub fn metallic_map(mut self, metallic_map: Texture) -> Self {
+        self.metallic_map = Some(Box::new(metallic_map));
+        self
+    }
+
+    /// Set perceptual roughness
+    pub fn roughness(mut self, roughness: Float) -> Self {
+        self.roughness = roughness.clamp(0.0, 1.0);
+        self.mfd = Self::distribution(self.roughness, self.anisotropic, self.eta);
+        self
+    }
+
+    /// Scale perceptual roughness at each point by `roughness_map`
+    pub fn roughness_map(mut self, roughness_map: Texture) -> Self {
+        self.roughness_map = Some(Box::new(roughness_map));
+        self
+    }
+
+    /// Set anisotropy of the specular lobe
+    pub fn anisotropic(mut self, anisotropic: Float) -> Self {
+        self.anisotropic = anisotropic.clamp(0.0, 1.0);
+        self.mfd = Self::distribution(self.roughness, self.anisotropic, self.eta);
+        self
+    }
+
+    /// Set refraction index. Controls the specular reflectance of dielectrics
+    pub fn eta(mut self, eta: Float) -> Self {
+        assert!(eta >= 1.0);
+        self.eta = eta;
+        self.mfd = Self::distribution(self.roughness, self.anisotropic, self.eta);
+        self
+    }
+
+    /// Set specular tint
+    pub fn specular_tint(mut self, specular_tint: Float) -> Self {
+        self.specular_tint = specular_tint.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set sheen strength and tint
+    pub fn sheen(mut self, sheen: Float, sheen_tint: Float) -> Self {
+        self.sheen = sheen.clamp(0.0, 1.0);
+        self.sheen_tint = sheen_tint.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set clearcoat strength and glossiness
+    pub fn clearcoat(mut self, clearcoat: Float, clearcoat_gloss: Float) -> Self {
+        self.clearcoat = clearcoat.clamp(0.0, 1.0);
+        self.clearcoat_gloss = clearcoat_gloss.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set amount of transmission
+    pub fn transmission(mut self, transmission: Float) -> Self {
+        self.transmission = transmission.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Does light pass through the surface?
+    #[inline]
+    pub fn is_transmission(&self) -> bool {
+        // metallic maps only scale metallic down
+        self.transmission > 0.0 && (self.metallic < 1.0 || self.metallic_map.is_some())
+    }
+
+    /// Evaluate the textured parameters at `tc`
+    fn at(&self, lambda: &ColorWavelength, tc: &TexCoord) -> PrincipledPoint<'_> {
+        let scale = |map: &Option<Box<Texture>>| {
+            map.as_ref().map_or(1.0, |map| map.value_at(lambda, tc))
+        };
+        let roughness = (self.roughness * scale(&self.roughness_map)).clamp(0.0, 1.0);
+        let metallic = (self.metallic * scale(&self.metallic_map)).clamp(0.0, 1.0);
+        let mfd = if self.roughness_map.is_some() {
+            self.mfd.with_roughness(Self::alpha(roughness, self.anisotropic))
+        } else {
+            self.mfd.at(lambda, tc)
+        };
+
+        PrincipledPoint { roughness, metallic, mfd }
+    }
+
+    /// Roughness (α) of the specular lobe along the tangent and the bitangent
+    fn alpha(roughness: Float, anisotropic: Float) -> Vec2 {
+        let alpha = roughness.max(MIN_ROUGHNESS).powi(2);
+        let aspect = (1.0 - 0.9 * anisotropic).sqrt();
+        let min_alpha = MIN_ROUGHNESS * MIN_ROUGHNESS;
+
+        Vec2::new(
+            (alpha / aspect).max(min_alpha),
+            (alpha * aspect).max(min_alpha),
+        )
+    }
+
+    fn distribution(roughness: Float, anisotropic: Float, eta: Float) -> MfDistribution {
+        MfDistribution::Ggx(MicrofacetConfig {
+            roughness: Self::alpha(roughness, anisotropic),
+            roughness_map: None,
+            eta: DenseSpectrum::from_constant(eta),
+            eta_map: None,
+            k: DenseSpectrum::from_constant(0.0),
+            kd: Texture::from(Spectrum::WHITE),
+            ks: Texture::from(Spectrum::WHITE),
+            tf: Texture::from(Spectrum::WHITE),
+            film: None,
+        })
+    }
+
+    #[inline]
+    fn transmission_weight(&self, metallic: Float) -> Float {
+        (1.0 - metallic) * self.transmission
+    }
+
+    /// Probabilities to sample the diffuse, specular and clearcoat lobes
+    /// and the dielectric transmission lobe, in that order.
+    #[inline]
+    fn lobe_probabilities(&self, metallic: Float) -> [Float; 4] {
+        let pt = self.transmission_weight(metallic);
+        let pd = 1.0 - metallic;
+        let ps = 1.0;
+        let pc = 0.25 * self.clearcoat;
+        let scale = (1.0 - pt) / (pd + ps + pc);
+
+        [pd * scale, ps * scale, pc * scale, pt]
+    }
+}
+
+/// Textured parameters of a principled BSDF evaluated at a point
+struct PrincipledPoint<'a> {
+    /// Perceptual roughness at the point
+    roughness: Float,
+    /// Metallic at the point
+    metallic: Float,
+    /// Distribution of the specular lobe at the point
+    mfd: MfPoint<'a>,
+}
+
+mod util {
+    use super::*;
+
+    #[inline]
+    pub fn schlick_weight(cos_theta: Float) -> Float {
+        (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+    }
+
+    #[inline]
+    pub fn lerp(a: Color, b: Color, t: Float) -> Color {
+        a * (1.0 - t) + b * t
+    }
+
+    /// Base color normalized to unit luminance
+    #[inline]
+    pub fn tint(base: Color, lambda: &ColorWavelength) -> Color {
+        let luminance = base.luminance(lambda);
+        if luminance > 0.0 { base / luminance } else { Color::WHITE }
+    }
+
+    /// Generalized Trowbridge-Reitz with γ = 1, Burley 2012
+    #[inline]
+    pub fn gtr1(cos_theta_wh: Float, alpha: Float) -> Float {
+        let alpha2 = alpha * alpha;
+        (alpha2 - 1.0)
+            / (crate::PI * alpha2.ln() * (1.0 + (alpha2 - 1.0) * cos_theta_wh.powi(2)))
+    }
+
+    /// Separable Smith shadow-masking for isotropic GGX
+    #[inline]
+    pub fn smith_g1(w: Direction, alpha: Float) -> Float {
+        let tan2_theta = spherical_utils::tan2_theta(w);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+        let lambda = ((1.0 + alpha * alpha * tan2_theta).sqrt() - 1.0) / 2.0;
+        1.0 / (1.0 + lambda)
+    }
+
+    #[inline]
+    pub fn clearcoat_alpha(gloss: Float) -> Float {
+        0.1 * (1.0 - gloss) + 0.001 * gloss
+    }
+
+    pub fn sample_clearcoat(wo: Direction, gloss: Float, rand_sq: Vec2) -> Option<Direction> {
+        let alpha2 = clearcoat_alpha(gloss).powi(2);
+        let cos_theta = ((1.0 - alpha2.powf(1.0 - rand_sq.x)) / (1.0 - alpha2))
+            .max(0.0).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * crate::PI * rand_sq.y;
+        let wh = Normal::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        );
+        let wi = 2.0 * wo.project_onto(wh) - wo;
+
+        if spherical_utils::same_hemisphere(wi, wo) { Some( wi ) } else { None }
+    }
+}
+
+/// Evaluate the reflective lobes for `wo` and `wi` in the upper hemisphere
+fn f_reflection(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    p: &Principled,
+    pp: &PrincipledPoint,
+) -> Color {
+    let cos_theta_wo = spherical_utils::cos_theta(wo);
+    let cos_theta_wi = spherical_utils::cos_theta(wi);
+    let wh = (wo + wi).normalize();
+    let cos_theta_d = wi.dot(wh);
+
+    let base = p.base_color.albedo_at(lambda, tc);
+    let tint = util::tint(base, lambda);
+
+    let fo = util::schlick_weight(cos_theta_wo);
+    let fi = util::schlick_weight(cos_theta_wi);
+    let fd = util::schlick_weight(cos_theta_d);
+
+    // diffuse with retro-reflection, Burley 2015
+    let rr = 2.0 * pp.roughness * cos_theta_d * cos_theta_d;
+    let diffuse = (1.0 - 0.5 * fo) * (1.0 - 0.5 * fi)
+        + rr * (fo + fi + fo * fi * (rr - 1.0));
+    let diffuse = base * diffuse / crate::PI;
+
+    let sheen = p.sheen * fd * util::lerp(Color::WHITE, tint, p.sheen_tint);
+
+    // specular with Schlick Fresnel, F0 lerps from dielectric to base color
+    let eta = p.eta;
+    let f0 = ((eta - 1.0) / (eta + 1.0)).powi(2);
+    let f0 = util::lerp(
+        f0 * util::lerp(Color::WHITE, tint, p.specular_tint),
+        base,
+        pp.metallic,
+    );
+    let fresnel = util::lerp(f0, Color::WHITE, fd);
+    let d = pp.mfd.d(wh);
+    let g = pp.mfd.g(wo, wi, wh);
+    let specular = fresnel * d * g / (4.0 * cos_theta_wo * cos_theta_wi);
+
+    let clearcoat = if p.clearcoat > 0.0 {
+        let alpha = util::clearcoat_alpha(p.clearcoat_gloss);
+        let d = util::gtr1(spherical_utils::cos_theta(wh), alpha);
+        let f = CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * fd;
+        let g = util::smith_g1(wo, CLEARCOAT_ROUGHNESS)
+            * util::smith_g1(wi, CLEARCOAT_ROUGHNESS);
+        0.25 * p.clearcoat * d * f * g / (4.0 * cos_theta_wo * cos_theta_wi)
+    } else {
+        0.0
+    };
+
+    (1.0 - pp.metallic) * (diffuse + sheen) + specular
+        + Color::WHITE * clearcoat
+}
+
+fn pdf_reflection(
+    wo: Direction,
+    wi: Direction,
+    p: &Principled,
+    pp: &PrincipledPoint,
+) -> [Float; 3] {
+    let wh = (wo + wi).normalize();
+    let wh_dot_wo = wo.dot(wh);
+
+    let pdf_diffuse = scatter::lambertian::pdf(wo, wi);
+    let pdf_specular = pp.mfd.sample_normal_pdf(wh, wo) / (4.0 * wh_dot_wo.abs());
+    let pdf_clearcoat = if p.clearcoat > 0.0 {
+        let alpha = util::clearcoat_alpha(p.clearcoat_gloss);
+        let cos_theta_wh = spherical_utils::cos_theta(wh);
+        util::gtr1(cos_theta_wh, alpha) * cos_theta_wh / (4.0 * wh_dot_wo.abs())
+    } else {
+        0.0
+    };
+
+    [pdf_diffuse, pdf_specular, pdf_clearcoat]
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn f(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    reflection: bool,
+    tc: &TexCoord,
+    p: &Principled,
+    mode: Transport,
+) -> Color {
+    let pp = p.at(lambda, tc);
+    let wo_inside = spherical_utils::cos_theta(wo) <= 0.0;
+    let f_dielectric = |scale: Float| {
+        if scale == 0.0 {
+            return Color::BLACK;
+        }
+        let f = microfacet::dielectric::f(wo, wi, lambda, reflection, tc, &pp.mfd, mode);
+        if reflection {
+            scale * f
+        } else {
+            scale * f * p.base_color.albedo_at(lambda, tc)
+        }
+    };
+
+    if wo_inside {
+        // only the dielectric interface is visible from inside
+        f_dielectric(1.0)
+    } else if !reflection {
+        f_dielectric(p.transmission_weight(pp.metallic))
+    } else if spherical_utils::cos_theta(wi) <= 0.0 {
+        Color::BLACK
+    } else {
+        let pt = p.transmission_weight(pp.metallic);
+        (1.0 - pt) * f_reflection(wo, wi, lambda, tc, p, &pp) + f_dielectric(pt)
+    }
+}
+
+pub fn sample(
+    wo: Direction,
+    p: &Principled,
+    lambda: &mut ColorWavelength,
+    tc: &TexCoord,
+    rand_u: Float,
+    rand_sq: Vec2,
+) -> Option<Direction> {
+    let pp = p.at(lambda, tc);
+    if spherical_utils::cos_theta(wo) <= 0.0 {
+        return microfacet::dielectric::sample(wo, &pp.mfd, lambda, rand_u, rand_sq);
+    }
+
+    let [pd, ps, pc, pt] = p.lobe_probabilities(pp.metallic);
+
+    if rand_u < pd {
+        scatter::lambertian::sample(rand_sq)
+    } else if rand_u < pd + ps {
+        let wh = pp.mfd.sample_normal(wo, rand_sq);
+        let wi = 2.0 * wo.project_onto(wh) - wo;
+        if spherical_utils::same_hemisphere(wi, wo) { Some( wi ) } else { None }
+    } else if rand_u < pd + ps + pc {
+        util::sample_clearcoat(wo, p.clearcoat_gloss, rand_sq)
+    } else {
+        // reuse the random number for the reflect/refract choice
+        let rand_u = (rand_u - (1.0 - pt)) / pt;
+        microfacet::dielectric::sample(wo, &pp.mfd, lambda, rand_u.min(1.0), rand_sq)
+    }
+}
+
+pub fn pdf(
+    wo: Direction,
+    wi: Direction,
+    reflection: bool,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    p: &Principled,
+) -> Float {
+    let pp = p.at(lambda, tc);
+    let pdf_dielectric = |scale: Float| {
+        if scale == 0.0 {
+            0.0
+        } else {
+            scale * microfacet::dielectric::pdf(wo, wi, reflection, lambda, &pp.mfd)
+        }
+    };
+
+    if spherical_utils::cos_theta(wo) <= 0.0 {
+        return pdf_dielectric(1.0);
+    }
+
+    let [pd, ps, pc, pt] = p.lobe_probabilities(pp.metallic);
+
+    if !reflection {
+        pdf_dielectric(pt)
+    } else if !spherical_utils::same_hemisphere(wo, wi) {
+        0.0
+    } else {
+        let [pdf_d, pdf_s, pdf_c] = pdf_reflection(wo, wi, p, &pp);
+        pd * pdf_d + ps * pdf_s + pc * pdf_c + pdf_dielectric(pt)
+    }
+}
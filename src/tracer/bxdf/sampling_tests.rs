@@ -1,6 +1,6 @@
 use super::*;
 use crate::{ math::simpson_integration, rng::Xorshift };
-use crate::tracer::{ DenseSpectrum, Spectrum, Texture };
+use crate::tracer::{ DenseSpectrum, Spectrum, Texture, microfacet::MicrofacetConfig };
 
 // used for numerically integrating PDF over whole space
 const THETA_BINS: usize = 80;
@@ -10,6 +10,8 @@ const NUM_BINS: usize = 8;
 const NUM_SAMPLES: usize = THETA_BINS * PHI_BINS * 1_000;
 const BIN_TOLERANCE: Float = 5e-2 * NUM_BINS as Float;
 const INT_TOLERANCE: Float = 1e-2;
+// texture coordinates of the sampled point, `v` gives the offset on hair
+const TC: TexCoord = TexCoord::from_uv(Vec2::new(0.5, 0.3));
 
 macro_rules! test_bxdf {
     ( $( $name:ident, $bxdf:expr ),* ) => {
@@ -55,7 +57,7 @@ macro_rules! test_bxdf {
 
 fn mfd(roughness: Float, eta: Float) -> MfDistribution {
     MfDistribution::new(
-        roughness,
+        Vec2::splat(roughness),
         DenseSpectrum::from_constant(eta),
         DenseSpectrum::from_constant(0.0),
         Texture::from(Spectrum::WHITE),
@@ -64,6 +66,26 @@ fn mfd(roughness: Float, eta: Float) -> MfDistribution {
     )
 }
 
+fn mfd_aniso(roughness: Vec2, beckmann: bool) -> MfDistribution {
+    let cfg = MicrofacetConfig::new(
+        roughness,
+        DenseSpectrum::from_constant(1.5),
+        DenseSpectrum::from_constant(0.0),
+        Texture::from(Spectrum::WHITE),
+        Texture::from(Spectrum::WHITE),
+        Texture::from(Spectrum::WHITE),
+    );
+    if beckmann { MfDistribution::Beckmann(cfg) } else { MfDistribution::Ggx(cfg) }
+}
+
+fn principled() -> Principled {
+    Principled::new(Texture::from(Spectrum::WHITE))
+}
+
+fn coated_diffuse() -> Layered {
+    Layered::coated_diffuse(Texture::from(Spectrum::WHITE))
+}
+
 // TODO: support dielectrics
 test_bxdf!{
     lambertian, BxDF::Lambertian(Spectrum::WHITE),
@@ -72,7 +94,24 @@ test_bxdf!{
     conductor75, BxDF::MfConductor(mfd(0.75, 1.5)),
     conductor50, BxDF::MfConductor(mfd(0.50, 1.5)),
     conductor25, BxDF::MfConductor(mfd(0.25, 1.5)),
-    conductor10, BxDF::MfConductor(mfd(0.10, 1.5))
+    conductor10, BxDF::MfConductor(mfd(0.10, 1.5)),
+
+    conductor_aniso,          BxDF::MfConductor(mfd_aniso(Vec2::new(0.25, 0.75), false)),
+    conductor_beckmann,       BxDF::MfConductor(mfd_aniso(Vec2::splat(0.75), true)),
+    conductor_beckmann_aniso, BxDF::MfConductor(mfd_aniso(Vec2::new(0.75, 0.5), true)),
+
+    principled_plastic, BxDF::Principled(principled().roughness(0.5)),
+    principled_metal,   BxDF::Principled(principled().metallic(1.0).roughness(0.4)),
+    principled_coated,  BxDF::Principled(principled().sheen(1.0, 0.5).clearcoat(1.0, 0.5)),
+
+    layered_diffuse,   BxDF::Layered(coated_diffuse().coat(0.3, 1.5)),
+    layered_conductor, BxDF::Layered(
+        Layered::coated_conductor(Texture::from(Spectrum::WHITE), 0.5, 1.5, 3.0)
+    ),
+
+    sheen, BxDF::Sheen(
+        Sheen::new(Texture::from(Spectrum::WHITE), Texture::from(Spectrum::WHITE), 0.5)
+    )
 }
 
 /* ripped from pbrt. take v = normal and sample NUM_SAMPLES times.
@@ -94,13 +133,13 @@ fn do_sampling(rng: &mut Xorshift, bxdf: BxDF) -> Vec<Vec<Float>> {
     // let wo face directly the normal
     let wo = Direction::Z;
     for _ in 0..NUM_SAMPLES {
-        let wi = bxdf.sample(wo, false, &mut lambda, rng.gen_float(), rng.gen_vec2());
+        let wi = bxdf.sample(wo, false, &mut lambda, &TC, rng.gen_float(), rng.gen_vec2());
         match wi {
             None => num_failed += 1,
             Some(wi) => {
                 let reflection = spherical_utils::cos_theta(wo)
                     * spherical_utils::cos_theta(wi) >= 0.0;
-                let pdf = bxdf.pdf(wo, wi, reflection, &lambda);
+                let pdf = bxdf.pdf(wo, wi, reflection, &lambda, &TC);
                 if pdf == 0.0 {
                     panic!("Sampled direction with 0 probability");
                 }
@@ -152,7 +191,7 @@ fn integrate_sphere(wo: Direction, lambda: &ColorWavelength, bxdf: &BxDF) -> Flo
                 let reflection = spherical_utils::cos_theta(wo)
                     * spherical_utils::cos_theta(wi) >= 0.0;
                 // pdf in solid angle, change to spherical coordinates
-                bxdf.pdf(wo, wi, reflection, lambda) * theta.sin()
+                bxdf.pdf(wo, wi, reflection, lambda, &TC) * theta.sin()
             };
             integral += simpson_integration::simpson2d(f, theta0, theta1, phi0, phi1);
         }
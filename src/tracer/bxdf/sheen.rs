@@ -0,0 +1,141 @@
+use super::*;
+use crate::tracer::Texture;
+
+/// Number of tabulated directional albedos of the sheen lobe
+const ALBEDO_SAMPLES: usize = 32;
+/// Resolution of the numerical integration used for the albedo table
+const ALBEDO_STEPS: usize = 64;
+/// Smallest supported roughness, the distribution degenerates below this
+const MIN_ROUGHNESS: Float = 0.05;
+
+/// Microfiber sheen on top of a diffuse base for cloth, e.g. velvet and satin.
+/// The sheen uses the "Charlie" distribution of Estevez & Kulla 2017 with the
+/// visibility term of Neubelt & Pettineo 2013. The base is scaled by the
+/// energy the sheen does not reflect, so that the sum stays energy conserving.
+pub struct Sheen {
+    /// Diffuse color of the base
+    kd: Texture,
+    /// Color of the sheen
+    tint: Texture,
+    /// Roughness of the fibers [0,1]
+    roughness: Float,
+    /// Directional albedo of the sheen lobe, tabulated over `cos(θ)`
+    albedo: [Float; ALBEDO_SAMPLES],
+    /// Cosine weighted average of `albedo`
+    albedo_avg: Float,
+}
+
+impl Sheen {
+    /// Sheen of color `tint` on a diffuse base of color `kd`
+    pub fn new(kd: Texture, tint: Texture, roughness: Float) -> Self {
+        assert!((0.0..=1.0).contains(&roughness));
+        let roughness = roughness.max(MIN_ROUGHNESS);
+
+        let mut albedo = [0.0; ALBEDO_SAMPLES];
+        for (i, e) in albedo.iter_mut().enumerate() {
+            let cos_theta = (i as Float + 0.5) / ALBEDO_SAMPLES as Float;
+            *e = Self::directional_albedo(cos_theta, roughness).min(1.0);
+        }
+        let albedo_avg = 2.0 * albedo.iter().enumerate()
+            .map(|(i, e)| e * (i as Float + 0.5) / ALBEDO_SAMPLES as Float)
+            .sum::<Float>() / ALBEDO_SAMPLES as Float;
+
+        Self { kd, tint, roughness, albedo, albedo_avg }
+    }
+
+    /// The "Charlie" sheen distribution
+    fn d(wh: Normal, roughness: Float) -> Float {
+        let inv_alpha = 1.0 / roughness;
+        let sin_theta = spherical_utils::sin_theta(wh);
+
+        (2.0 + inv_alpha) * sin_theta.powf(inv_alpha) / (2.0 * crate::PI)
+    }
+
+    /// Visibility term, replaces the shadow-masking and the denominator
+    fn v(cos_theta_wo: Float, cos_theta_wi: Float) -> Float {
+        1.0 / (4.0 * (cos_theta_wi + cos_theta_wo - cos_theta_wi * cos_theta_wo))
+    }
+
+    /// Sheen lobe without the tint
+    fn sheen(wo: Direction, wi: Direction, roughness: Float) -> Float {
+        let wh = (wo + wi).normalize();
+        let cos_theta_wo = spherical_utils::cos_theta(wo);
+        let cos_theta_wi = spherical_utils::cos_theta(wi);
+
+        Self::d(wh, roughness) * Self::v(cos_theta_wo, cos_theta_wi)
+    }
+
+    /// Numerically integrate the reflected energy from direction at `cos_theta`
+    fn directional_albedo(cos_theta: Float, roughness: Float) -> Float {
+        let wo = Direction::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta);
+        let step = 1.0 / ALBEDO_STEPS as Float;
+
+        let mut e = 0.0;
+        for i in 0..ALBEDO_STEPS {
+            let cos_theta_wi = (i as Float + 0.5) * step;
+            let sin_theta_wi = (1.0 - cos_theta_wi * cos_theta_wi).sqrt();
+            // lobe is symmetric in phi, integrate half of it
+            for j in 0..ALBEDO_STEPS {
+                let phi = (j as Float + 0.5) * step * crate::PI;
+                let wi = Direction::new(
+                    sin_theta_wi * phi.cos(),
+                    sin_theta_wi * phi.sin(),
+                    cos_theta_wi,
+                );
+                e += Self::sheen(wo, wi, roughness) * cos_theta_wi;
+            }
+        }
+
+        2.0 * e * step * step * crate::PI
+    }
+
+    /// Tabulated directional albedo at `cos_theta`
+    fn albedo_at(&self, cos_theta: Float) -> Float {
+        let x = (cos_theta * ALBEDO_SAMPLES as Float - 0.5)
+            .clamp(0.0, (ALBEDO_SAMPLES - 1) as Float);
+        let i = (x as usize).min(ALBEDO_SAMPLES - 2);
+        let t = x - i as Float;
+
+        (1.0 - t) * self.albedo[i] + t * self.albedo[i + 1]
+    }
+}
+
+pub fn f(
+    wo: Direction,
+    wi: Direction,
+    lambda: &ColorWavelength,
+    tc: &TexCoord,
+    s: &Sheen,
+) -> Color {
+    let cos_theta_wo = spherical_utils::cos_theta(wo);
+    let cos_theta_wi = spherical_utils::cos_theta(wi);
+    if cos_theta_wo <= 0.0 || cos_theta_wi <= 0.0 {
+        return Color::BLACK;
+    }
+
+    let sheen = s.tint.albedo_at(lambda, tc) * Sheen::sheen(wo, wi, s.roughness);
+
+    let e_wo = s.albedo_at(cos_theta_wo);
+    let e_wi = s.albedo_at(cos_theta_wi);
+    let scale = (1.0 - e_wo) * (1.0 - e_wi) / (1.0 - s.albedo_avg);
+    let diffuse = s.kd.albedo_at(lambda, tc) * scale / crate::PI;
+
+    sheen + diffuse
+}
+
+pub fn sample(rand_u: Float, rand_sq: Vec2) -> Option<Direction> {
+    // sheen is strongest at grazing angles, where cosine sampling is poor
+    if rand_u < 0.5 {
+        scatter::lambertian::sample(rand_sq)
+    } else {
+        Some( rng::maps::square_to_hemisphere(rand_sq) )
+    }
+}
+
+pub fn pdf(wo: Direction, wi: Direction) -> Float {
+    if spherical_utils::cos_theta(wo) <= 0.0 || spherical_utils::cos_theta(wi) <= 0.0 {
+        return 0.0;
+    }
+
+    0.5 * scatter::lambertian::pdf(wo, wi) + 0.5 / (2.0 * crate::PI)
+}
@@ -5,14 +5,16 @@ use crate::{
 use crate::math::vec2::UVec2;
 use crate::tracer::{
     Color, ColorSpace, color::{DenseSpectrum, illuminants},
-    Film, PixelFilter, ray::Ray
+    Film, PixelFilter, ray::{Ray, RayDifferential}
 };
 
 mod matrices;
 mod builder;
+mod path;
 
 pub use builder::CameraBuilder;
 pub use builder::CameraType;
+pub use path::CameraPath;
 
 
 /// Common configuration for cameras
@@ -31,9 +33,11 @@ pub struct CameraConfig {
     world_to_camera: Transform,
     /// Image plane area in camera space
     pub image_plane_area: Float,
+    /// Times at which the shutter opens and closes
+    pub shutter: (Float, Float),
     pixel_filter: PixelFilter,
     color_space: &'static ColorSpace,
-    illuminant: &'static DenseSpectrum,
+    illuminant: DenseSpectrum,
 }
 
 impl CameraConfig {
@@ -45,12 +49,14 @@ impl CameraConfig {
         resolution: (u64, u64),
         color_space: &'static ColorSpace,
         pixel_filter: PixelFilter,
-        illuminant: &'static DenseSpectrum,
+        illuminant: DenseSpectrum,
         world_to_camera: Transform,
         screen_to_raster: Transform,
         camera_to_screen: Transform,
+        shutter: (Float, Float),
     ) -> Self {
         assert!(lens_radius >= 0.0);
+        assert!(shutter.0 <= shutter.1);
 
         let (width, height) = resolution;
         let resolution = UVec2::new(width, height);
@@ -78,6 +84,7 @@ impl CameraConfig {
             screen_to_raster,
             resolution,
             image_plane_area,
+            shutter,
             pixel_filter,
             color_space,
             illuminant,
@@ -143,7 +150,7 @@ impl Camera {
             .zoom(2.8)
             .focal_length(0.035)
             .resolution((512, 512))
-            .illuminant(illuminants::CORNELL)
+            .illuminant(illuminants::CORNELL.clone())
             .build()
     }
 
@@ -155,7 +162,7 @@ impl Camera {
             samples,
             cfg.color_space,
             cfg.pixel_filter,
-            cfg.illuminant,
+            &cfg.illuminant,
         )
     }
 
@@ -253,8 +260,29 @@ impl Camera {
         }
     }
 
-    /// Generates a ray given a point in raster space `\[0,width\] x \[0,height\]`
+    /// Time within the shutter interval for `rand_u` in `\[0,1\]`
+    pub fn sample_time(&self, rand_u: Float) -> Float {
+        let (open, close) = self.get_cfg().shutter;
+        open + rand_u * (close - open)
+    }
+
+    /// Generates a ray given a point in raster space `\[0,width\] x \[0,height\]`.
+    /// Differentials go through the same lens point one pixel over in `x` and `y`.
     pub fn generate_ray(&self, raster_xy: Vec2, rand_sq: Vec2) -> Ray {
+        let rx = self.ray_through(raster_xy + Vec2::X, rand_sq);
+        let ry = self.ray_through(raster_xy + Vec2::Y, rand_sq);
+
+        self.ray_through(raster_xy, rand_sq)
+            .with_differentials(RayDifferential {
+                rx_origin: rx.origin,
+                rx_dir: rx.dir,
+                ry_origin: ry.origin,
+                ry_dir: ry.dir,
+            })
+    }
+
+    /// Ray through `raster_xy` and the point `rand_sq` on the lens
+    fn ray_through(&self, raster_xy: Vec2, rand_sq: Vec2) -> Ray {
         match self {
             Self::Perspective(cfg) => {
                 let wi_local = cfg.raster_to_camera(raster_xy).normalize();
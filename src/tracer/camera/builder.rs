@@ -1,6 +1,7 @@
 use super::*;
 
 /// Specifies the camera type
+#[derive(Clone, Copy)]
 pub enum CameraType {
     /// "Traditional" pinhole camera
     Perspective,
@@ -9,6 +10,7 @@ pub enum CameraType {
 }
 
 /// Temporary structure to hold data while building cameras
+#[derive(Clone)]
 pub struct CameraBuilder {
     origin: Point,
     towards: Point,
@@ -20,8 +22,9 @@ pub struct CameraBuilder {
     camera_type: CameraType,
     pixel_filter: PixelFilter,
     color_space: &'static ColorSpace,
-    illuminant: &'static DenseSpectrum,
+    illuminant: DenseSpectrum,
     vfov: Float,
+    shutter: (Float, Float),
 }
 
 impl Default for CameraBuilder {
@@ -45,7 +48,8 @@ impl CameraBuilder {
             vfov: 90.0,
             color_space: ColorSpace::default(),
             pixel_filter: PixelFilter::default(),
-            illuminant: illuminants::D65,
+            illuminant: illuminants::D65.clone(),
+            shutter: (0.0, 0.0),
         }
     }
 
@@ -116,11 +120,24 @@ impl CameraBuilder {
     }
 
     /// Set the illuminant of the camera sensor
-    pub fn illuminant(mut self, illuminant: &'static DenseSpectrum) -> Self {
+    pub fn illuminant(mut self, illuminant: DenseSpectrum) -> Self {
         self.illuminant = illuminant;
         self
     }
 
+    /// Set the illuminant of the camera sensor to a blackbody at
+    /// `temperature` Kelvin
+    pub fn color_temperature(self, temperature: Float) -> Self {
+        self.illuminant(DenseSpectrum::blackbody(temperature))
+    }
+
+    /// Set the times at which the shutter opens and closes. Objects animated
+    /// within the interval get motion blurred.
+    pub fn shutter(mut self, open: Float, close: Float) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
     /// Build the camera
     pub fn build(&self) -> Camera {
         let cts = match self.camera_type {
@@ -137,10 +154,11 @@ impl CameraBuilder {
             self.resolution,
             self.color_space,
             self.pixel_filter,
-            self.illuminant,
+            self.illuminant.clone(),
             wtc,
             sctr,
             cts,
+            self.shutter,
         );
 
         match self.camera_type {
@@ -0,0 +1,94 @@
+use super::*;
+use crate::tracer::animation::Keyframes;
+
+/// Camera that moves along keyframes over time. Parameters that are not
+/// keyframed come from the builder.
+#[derive(Clone)]
+pub struct CameraPath {
+    builder: CameraBuilder,
+    origin: Option<Keyframes<Point>>,
+    towards: Option<Keyframes<Point>>,
+    up: Option<Keyframes<Direction>>,
+    vfov: Option<Keyframes<Float>>,
+    focal_length: Option<Keyframes<Float>>,
+    shutter: (Float, Float),
+}
+
+impl CameraPath {
+    /// Path of the camera configured in `builder`
+    pub fn new(builder: CameraBuilder) -> Self {
+        Self {
+            builder,
+            origin: None,
+            towards: None,
+            up: None,
+            vfov: None,
+            focal_length: None,
+            shutter: (0.0, 0.0),
+        }
+    }
+
+    /// Keyframe the `origin` of the camera
+    pub fn origin(mut self, origin: Keyframes<Point>) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Keyframe the point `towards` which the camera is looking
+    pub fn towards(mut self, towards: Keyframes<Point>) -> Self {
+        self.towards = Some(towards);
+        self
+    }
+
+    /// Keyframe the `up` direction of the camera
+    pub fn up(mut self, up: Keyframes<Direction>) -> Self {
+        self.up = Some(up);
+        self
+    }
+
+    /// Keyframe the vertical field of view of a perspective camera
+    pub fn vfov(mut self, vfov: Keyframes<Float>) -> Self {
+        self.vfov = Some(vfov);
+        self
+    }
+
+    /// Keyframe the `focal_length` of the camera
+    pub fn focal_length(mut self, focal_length: Keyframes<Float>) -> Self {
+        self.focal_length = Some(focal_length);
+        self
+    }
+
+    /// Open and close the shutter at these offsets from the time of a frame
+    pub fn shutter(mut self, open: Float, close: Float) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    /// Camera at `time` with the shutter open around it
+    pub fn camera_at(&self, time: Float) -> Camera {
+        let (open, close) = self.shutter;
+        let mut builder = self.builder.clone()
+            .shutter(time + open, time + close);
+
+        if let Some(origin) = &self.origin {
+            let o = origin.at(time);
+            builder = builder.origin(o.x, o.y, o.z);
+        }
+        if let Some(towards) = &self.towards {
+            let t = towards.at(time);
+            builder = builder.towards(t.x, t.y, t.z);
+        }
+        if let Some(up) = &self.up {
+            let u = up.at(time);
+            builder = builder.up(u.x, u.y, u.z);
+        }
+        if let Some(vfov) = &self.vfov {
+            builder = builder.vfov(vfov.at(time));
+        }
+        if let Some(focal_length) = &self.focal_length {
+            builder = builder.focal_length(focal_length.at(time));
+        }
+
+        builder.build()
+    }
+}
@@ -6,7 +6,7 @@ use std::ops::{
 use std::fmt;
 
 pub use wavelength::ColorWavelength;
-pub use spectrum::Spectrum;
+pub use spectrum::{Spectrum, Uplift};
 pub use rgb::RGB;
 pub use space::ColorSpace;
 pub use dense_spectrum::DenseSpectrum;
@@ -37,6 +37,7 @@ pub mod illuminants {
 }
 
 
+/// Measured and analytical refraction indices and absorption coefficients
 pub mod materials {
     #![allow(dead_code)]
     use super::*;
@@ -44,13 +45,58 @@ pub mod materials {
     macro_rules! materials {
         ( $( $name:ident ),* ) => {
             $(
+                #[doc = concat!("Tabulated `", stringify!($name), "`")]
                 pub const $name: &'static DenseSpectrum =
                     &DenseSpectrum::new(samples::materials::$name::SAMPLES);
             )*
         }
     }
 
-    materials! { diamond_eta, glass_eta, mirror_eta, mirror_k }
+    materials! {
+        diamond_eta, glass_eta, mirror_eta, mirror_k,
+        gold_eta, gold_k, copper_eta, copper_k, aluminium_eta, aluminium_k,
+        chrome_eta, chrome_k, titanium_eta, titanium_k
+    }
+
+    /// Refraction index of silver, same as the mirror
+    pub const silver_eta: &DenseSpectrum = mirror_eta;
+    /// Absorption coefficient of silver, same as the mirror
+    pub const silver_k: &DenseSpectrum = mirror_k;
+
+    /// Measured refraction index and absorption coefficient of a named metal
+    pub fn conductor(name: &str) -> Option<(DenseSpectrum, DenseSpectrum)> {
+        let (eta, k) = match name.to_ascii_lowercase().as_str() {
+            "gold" | "au" => (gold_eta, gold_k),
+            "copper" | "cu" => (copper_eta, copper_k),
+            "silver" | "ag" => (silver_eta, silver_k),
+            "aluminium" | "aluminum" | "al" => (aluminium_eta, aluminium_k),
+            "chrome" | "chromium" | "cr" => (chrome_eta, chrome_k),
+            "titanium" | "ti" => (titanium_eta, titanium_k),
+            _ => return None,
+        };
+        Some((eta.clone(), k.clone()))
+    }
+
+    /// Refraction index of a named dielectric
+    pub fn dielectric(name: &str) -> Option<DenseSpectrum> {
+        match name.to_ascii_lowercase().as_str() {
+            "glass" => Some(glass_eta.clone()),
+            "diamond" => Some(diamond_eta.clone()),
+            // Schott N-BK7
+            "bk7" => Some(DenseSpectrum::sellmeier(
+                [1.039_612_12, 0.231_792_344, 1.010_469_45],
+                [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+            )),
+            // Malitson 1965
+            "fused_silica" | "quartz" => Some(DenseSpectrum::sellmeier(
+                [0.696_166_3, 0.407_942_6, 0.897_479_4],
+                [0.004_679_148, 0.013_512_063, 97.934_003],
+            )),
+            // Daimon & Masumura 2007, fitted to the visible range
+            "water" => Some(DenseSpectrum::cauchy(1.3199, 0.003_1)),
+            _ => None,
+        }
+    }
 }
 
 const LAMBDA_MIN: Float = 360.0;
@@ -134,6 +180,12 @@ impl Color {
         self.samples.iter().all(|v| v == &0.0)
     }
 
+    /// Sample at the leading wavelength
+    #[inline]
+    pub fn leading_sample(&self) -> Float {
+        self.samples[0]
+    }
+
     /// Mean of the samples
     #[inline]
     pub fn mean(&self) -> Float {
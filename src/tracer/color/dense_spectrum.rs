@@ -4,6 +4,19 @@ use super::*;
 // every 5nm for [360, 830]
 pub const DENSE_SAMPLES: usize = 95;
 
+/// Speed of light in vacuum (m/s)
+const C: Float = 299_792_458.0;
+/// Planck constant (J s)
+const H: Float = 6.626_070_15e-34;
+/// Boltzmann constant (J/K)
+const KB: Float = 1.380_649e-23;
+/// Wavelength (nm) at which blackbody spectra are normalized to 100,
+/// same as the CIE standard illuminants
+const BLACKBODY_NORM_LAMBDA: Float = 560.0;
+
+#[cfg(test)]
+mod dense_spectrum_tests;
+
 /// Spectrum sampled at 5nm steps from 360nm to 830nm
 #[derive(Clone)]
 pub struct DenseSpectrum {
@@ -23,6 +36,59 @@ impl DenseSpectrum {
         Self::new([constant; DENSE_SAMPLES])
     }
 
+    /// Spectral radiance of a blackbody at `temperature` Kelvin given by
+    /// Planck's law. Normalized to 100 at 560nm, as the CIE standard illuminants.
+    pub fn blackbody(temperature: Float) -> Self {
+        assert!(temperature > 0.0);
+        let norm = 100.0 / Self::planck(BLACKBODY_NORM_LAMBDA, temperature);
+
+        let mut values = [0.0; DENSE_SAMPLES];
+        for i in 0..DENSE_SAMPLES {
+            let lambda = LAMBDA_MIN + i as Float * Self::STEP;
+            values[i] = norm * Self::planck(lambda, temperature);
+        }
+
+        Self { values }
+    }
+
+    /// New dense spectrum by evaluating `f` at the wavelengths in nanometers
+    pub fn from_fn(f: impl Fn(Float) -> Float) -> Self {
+        let mut values = [0.0; DENSE_SAMPLES];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = f(LAMBDA_MIN + i as Float * Self::STEP);
+        }
+
+        Self { values }
+    }
+
+    /// Refraction index from the Sellmeier equation
+    /// `n^2 = 1 + sum_i b_i λ^2 / (λ^2 - c_i)`, with `λ` in micrometers
+    pub fn sellmeier(b: [Float; 3], c: [Float; 3]) -> Self {
+        Self::from_fn(|lambda| {
+            let lambda2 = (lambda * 1e-3).powi(2);
+            let n2 = 1.0 + (0..3)
+                .map(|j| b[j] * lambda2 / (lambda2 - c[j]))
+                .sum::<Float>();
+            n2.max(1.0).sqrt()
+        })
+    }
+
+    /// Refraction index from Cauchy's equation `n = a + b / λ^2`,
+    /// with `λ` in micrometers
+    pub fn cauchy(a: Float, b: Float) -> Self {
+        Self::from_fn(|lambda| {
+            let lambda = lambda * 1e-3;
+            a + b / (lambda * lambda)
+        })
+    }
+
+    /// Planck's law for wavelength `lambda` in nanometers
+    fn planck(lambda: Float, temperature: Float) -> Float {
+        let lambda = lambda * 1e-9;
+        2.0 * H * C * C
+            / (lambda.powi(5) * ((H * C / (lambda * KB * temperature)).exp() - 1.0))
+    }
+
     /// Are we a constant spectrum?
     pub fn is_constant(&self) -> bool {
         // cache?
@@ -0,0 +1,64 @@
+use super::*;
+
+#[test]
+fn blackbody_normalized() {
+    for temperature in [1000.0, 2700.0, 5000.0, 6500.0, 10_000.0] {
+        let bb = DenseSpectrum::blackbody(temperature);
+        assert!((bb.sample_one(BLACKBODY_NORM_LAMBDA) - 100.0).abs() < crate::EPSILON);
+    }
+}
+
+#[test]
+fn blackbody_matches_illuminant_A() {
+    // CIE illuminant A is a blackbody at ~2856K
+    let bb = DenseSpectrum::blackbody(2856.0);
+    let A = illuminants::A;
+
+    for i in 0..DENSE_SAMPLES {
+        let rel_err = (bb.values[i] - A.values[i]).abs() / A.values[i];
+        assert!(rel_err < 1e-2);
+    }
+}
+
+#[test]
+fn blackbody_hotter_is_bluer() {
+    let xy_cold = xyz::to_xyY(DenseSpectrum::blackbody(2000.0).to_xyz());
+    let xy_hot = xyz::to_xyY(DenseSpectrum::blackbody(10_000.0).to_xyz());
+
+    assert!(xy_cold.x > xy_hot.x);
+}
+
+#[test]
+fn sellmeier_bk7() {
+    // refraction index of N-BK7 at the helium d-line
+    let bk7 = materials::dielectric("bk7").unwrap();
+    assert!((bk7.sample_one(587.56) - 1.5168).abs() < 1e-3);
+}
+
+#[test]
+fn sellmeier_matches_glass() {
+    let silica = materials::dielectric("fused_silica").unwrap();
+    for i in 0..DENSE_SAMPLES {
+        assert!((silica.values[i] - materials::glass_eta.values[i]).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn dispersion_is_normal() {
+    for name in ["bk7", "fused_silica", "water"] {
+        let eta = materials::dielectric(name).unwrap();
+        assert!(eta.values.is_sorted_by(|l, r| l >= r));
+    }
+}
+
+#[test]
+fn gold_reflects_red() {
+    let (eta, k) = materials::conductor("Au").unwrap();
+    // normal incidence Fresnel reflectance of a conductor
+    let r = |wl: Float| {
+        let (n, k) = (eta.sample_one(wl), k.sample_one(wl));
+        ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k)
+    };
+    assert!(r(650.0) > r(450.0));
+    assert!(materials::conductor("unobtainium").is_none());
+}
@@ -57,7 +57,12 @@ impl RGB {
     /// Decode gamma from a srgb value
     #[inline]
     pub fn srgb_decode(v: u8) -> Float {
-        let u = v as Float / 255.0;
+        Self::srgb_decode_unorm(v as Float / 255.0)
+    }
+
+    /// Decode gamma from a srgb value in `\[0,1\]`
+    #[inline]
+    fn srgb_decode_unorm(u: Float) -> Float {
         if u <= 0.04045 {
             u / 12.92
         } else {
@@ -75,16 +80,23 @@ impl RGB {
         Self { rgb }
     }
 
+    /// Linear rgb value from 16-bit srgb channels
+    pub fn from_srgb16(r: u16, g: u16, b: u16) -> Self {
+        let decode = |c: u16| Self::srgb_decode_unorm(c as Float / u16::MAX as Float);
+        let rgb = Vec3::new(decode(r), decode(g), decode(b));
+        Self { rgb }
+    }
+
     /// Linear rgb value from rgbe bytes
     pub fn from_rgbe(r: u8, g: u8, b: u8, e: u8) -> Self {
         if e == 0 {
             Self::BLACK
         } else {
             let v = Float::powi(2.0, e as i32 - 128) / 256.0;
-            let rgb = 0.5 + Vec3::new(
-                v * r as Float,
-                v * g as Float,
-                v * b as Float,
+            let rgb = Vec3::new(
+                v * (r as Float + 0.5),
+                v * (g as Float + 0.5),
+                v * (b as Float + 0.5),
             );
 
             Self { rgb }
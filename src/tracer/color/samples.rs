@@ -48,6 +48,169 @@ pub mod materials {
             1.45332, 1.45323, 1.45315, 1.45306, 1.45298, 1.4529,  1.45282,
         ],
 
+        // conductors linearly interpolated from measurements of
+        // Johnson & Christy 1972, 1974 and Rakić 1995
+        gold_eta,
+        [
+            1.7,     1.67125, 1.6425,  1.61375, 1.585,   1.55625, 1.5275,  1.49875,
+            1.47,    1.463,   1.456,   1.449,   1.442,   1.435,   1.428,   1.421,
+            1.414,   1.407,   1.4,     1.357,   1.314,   1.271,   1.228,   1.185,
+            1.142,   1.099,   1.056,   1.013,   0.97,    0.916,   0.862,   0.808,
+            0.754,   0.7,     0.646,   0.592,   0.538,   0.484,   0.43,    0.412,
+            0.394,   0.376,   0.358,   0.34,    0.322,   0.304,   0.286,   0.268,
+            0.25,    0.242,   0.234,   0.226,   0.218,   0.21,    0.202,   0.194,
+            0.186,   0.178,   0.17,    0.169,   0.168,   0.167,   0.166,   0.165,
+            0.164,   0.163,   0.162,   0.161,   0.16,    0.158,   0.156,   0.154,
+            0.152,   0.15,    0.148,   0.146,   0.144,   0.142,   0.14,    0.141,
+            0.142,   0.143,   0.144,   0.145,   0.146,   0.147,   0.148,   0.149,
+            0.15,    0.15167, 0.15333, 0.155,   0.15667, 0.15833, 0.16,
+        ],
+
+        gold_k,
+        [
+            1.89,    1.8975,  1.905,   1.9125,  1.92,    1.9275,  1.935,   1.9425,
+            1.95,    1.943,   1.936,   1.929,   1.922,   1.915,   1.908,   1.901,
+            1.894,   1.887,   1.88,    1.879,   1.878,   1.877,   1.876,   1.875,
+            1.874,   1.873,   1.872,   1.871,   1.87,    1.928,   1.986,   2.044,
+            2.102,   2.16,    2.218,   2.276,   2.334,   2.392,   2.45,    2.503,
+            2.556,   2.609,   2.662,   2.715,   2.768,   2.821,   2.874,   2.927,
+            2.98,    3.032,   3.084,   3.136,   3.188,   3.24,    3.292,   3.344,
+            3.396,   3.448,   3.5,     3.545,   3.59,    3.635,   3.68,    3.725,
+            3.77,    3.815,   3.86,    3.905,   3.95,    3.995,   4.04,    4.085,
+            4.13,    4.175,   4.22,    4.265,   4.31,    4.355,   4.4,     4.451,
+            4.502,   4.553,   4.604,   4.655,   4.706,   4.757,   4.808,   4.859,
+            4.91,    4.95833, 5.00667, 5.055,   5.10333, 5.15167, 5.2,
+        ],
+
+        copper_eta,
+        [
+            1.27,    1.25875, 1.2475,  1.23625, 1.225,   1.21375, 1.2025,  1.19125,
+            1.18,    1.179,   1.178,   1.177,   1.176,   1.175,   1.174,   1.173,
+            1.172,   1.171,   1.17,    1.166,   1.162,   1.158,   1.154,   1.15,
+            1.146,   1.142,   1.138,   1.134,   1.13,    1.115,   1.1,     1.085,
+            1.07,    1.055,   1.04,    1.025,   1.01,    0.995,   0.98,    0.904,
+            0.828,   0.752,   0.676,   0.6,     0.534,   0.468,   0.402,   0.336,
+            0.27,    0.264,   0.258,   0.252,   0.246,   0.24,    0.234,   0.228,
+            0.222,   0.216,   0.21,    0.21,    0.21,    0.21,    0.21,    0.21,
+            0.21,    0.21,    0.21,    0.21,    0.21,    0.213,   0.216,   0.219,
+            0.222,   0.225,   0.228,   0.231,   0.234,   0.237,   0.24,    0.242,
+            0.244,   0.246,   0.248,   0.25,    0.252,   0.254,   0.256,   0.258,
+            0.26,    0.26167, 0.26333, 0.265,   0.26667, 0.26833, 0.27,
+        ],
+
+        copper_k,
+        [
+            1.95,    1.9825,  2.015,   2.0475,  2.08,    2.1125,  2.145,   2.1775,
+            2.21,    2.229,   2.248,   2.267,   2.286,   2.305,   2.324,   2.343,
+            2.362,   2.381,   2.4,     2.416,   2.432,   2.448,   2.464,   2.48,
+            2.496,   2.512,   2.528,   2.544,   2.56,    2.562,   2.564,   2.566,
+            2.568,   2.57,    2.572,   2.574,   2.576,   2.578,   2.58,    2.608,
+            2.636,   2.664,   2.692,   2.72,    2.858,   2.996,   3.134,   3.272,
+            3.41,    3.436,   3.462,   3.488,   3.514,   3.54,    3.566,   3.592,
+            3.618,   3.644,   3.67,    3.724,   3.778,   3.832,   3.886,   3.94,
+            3.994,   4.048,   4.102,   4.156,   4.21,    4.249,   4.288,   4.327,
+            4.366,   4.405,   4.444,   4.483,   4.522,   4.561,   4.6,     4.64,
+            4.68,    4.72,    4.76,    4.8,     4.84,    4.88,    4.92,    4.96,
+            5.0,     5.03667, 5.07333, 5.11,    5.14667, 5.18333, 5.22,
+        ],
+
+        aluminium_eta,
+        [
+            0.4,     0.41125, 0.4225,  0.43375, 0.445,   0.45625, 0.4675,  0.47875,
+            0.49,    0.503,   0.516,   0.529,   0.542,   0.555,   0.568,   0.581,
+            0.594,   0.607,   0.62,    0.635,   0.65,    0.665,   0.68,    0.695,
+            0.71,    0.725,   0.74,    0.755,   0.77,    0.789,   0.808,   0.827,
+            0.846,   0.865,   0.884,   0.903,   0.922,   0.941,   0.96,    0.984,
+            1.008,   1.032,   1.056,   1.08,    1.104,   1.128,   1.152,   1.176,
+            1.2,     1.227,   1.254,   1.281,   1.308,   1.335,   1.362,   1.389,
+            1.416,   1.443,   1.47,    1.506,   1.542,   1.578,   1.614,   1.65,
+            1.686,   1.722,   1.758,   1.794,   1.83,    1.874,   1.918,   1.962,
+            2.006,   2.05,    2.094,   2.138,   2.182,   2.226,   2.27,    2.323,
+            2.376,   2.429,   2.482,   2.535,   2.588,   2.641,   2.694,   2.747,
+            2.8,     2.78333, 2.76667, 2.75,    2.73333, 2.71667, 2.7,
+        ],
+
+        aluminium_k,
+        [
+            4.32,    4.3875,  4.455,   4.5225,  4.59,    4.6575,  4.725,   4.7925,
+            4.86,    4.921,   4.982,   5.043,   5.104,   5.165,   5.226,   5.287,
+            5.348,   5.409,   5.47,    5.531,   5.592,   5.653,   5.714,   5.775,
+            5.836,   5.897,   5.958,   6.019,   6.08,    6.141,   6.202,   6.263,
+            6.324,   6.385,   6.446,   6.507,   6.568,   6.629,   6.69,    6.747,
+            6.804,   6.861,   6.918,   6.975,   7.032,   7.089,   7.146,   7.203,
+            7.26,    7.313,   7.366,   7.419,   7.472,   7.525,   7.578,   7.631,
+            7.684,   7.737,   7.79,    7.842,   7.894,   7.946,   7.998,   8.05,
+            8.102,   8.154,   8.206,   8.258,   8.31,    8.346,   8.382,   8.418,
+            8.454,   8.49,    8.526,   8.562,   8.598,   8.634,   8.67,    8.648,
+            8.626,   8.604,   8.582,   8.56,    8.538,   8.516,   8.494,   8.472,
+            8.45,    8.425,   8.4,     8.375,   8.35,    8.325,   8.3,
+        ],
+
+        chrome_eta,
+        [
+            1.55,    1.575,   1.6,     1.625,   1.65,    1.675,   1.7,     1.725,
+            1.75,    1.805,   1.86,    1.915,   1.97,    2.025,   2.08,    2.135,
+            2.19,    2.245,   2.3,     2.345,   2.39,    2.435,   2.48,    2.525,
+            2.57,    2.615,   2.66,    2.705,   2.75,    2.775,   2.8,     2.825,
+            2.85,    2.875,   2.9,     2.925,   2.95,    2.975,   3.0,     3.017,
+            3.034,   3.051,   3.068,   3.085,   3.102,   3.119,   3.136,   3.153,
+            3.17,    3.183,   3.196,   3.209,   3.222,   3.235,   3.248,   3.261,
+            3.274,   3.287,   3.3,     3.31,    3.32,    3.33,    3.34,    3.35,
+            3.36,    3.37,    3.38,    3.39,    3.4,     3.41,    3.42,    3.43,
+            3.44,    3.45,    3.46,    3.47,    3.48,    3.49,    3.5,     3.51,
+            3.52,    3.53,    3.54,    3.55,    3.56,    3.57,    3.58,    3.59,
+            3.6,     3.60833, 3.61667, 3.625,   3.63333, 3.64167, 3.65,
+        ],
+
+        chrome_k,
+        [
+            2.8,     2.825,   2.85,    2.875,   2.9,     2.925,   2.95,    2.975,
+            3.0,     3.02,    3.04,    3.06,    3.08,    3.1,     3.12,    3.1399,
+            3.16,    3.18,    3.2,     3.209,   3.218,   3.227,   3.236,   3.245,
+            3.254,   3.263,   3.272,   3.281,   3.29,    3.294,   3.298,   3.302,
+            3.306,   3.31,    3.314,   3.318,   3.322,   3.326,   3.33,    3.327,
+            3.324,   3.321,   3.318,   3.315,   3.312,   3.309,   3.306,   3.303,
+            3.3,     3.302,   3.304,   3.306,   3.308,   3.31,    3.312,   3.314,
+            3.316,   3.318,   3.32,    3.324,   3.328,   3.332,   3.336,   3.34,
+            3.344,   3.348,   3.352,   3.356,   3.36,    3.367,   3.374,   3.381,
+            3.388,   3.395,   3.402,   3.409,   3.416,   3.423,   3.43,    3.437,
+            3.444,   3.451,   3.458,   3.465,   3.472,   3.479,   3.486,   3.493,
+            3.5,     3.50833, 3.51667, 3.525,   3.53333, 3.54167, 3.55,
+        ],
+
+        titanium_eta,
+        [
+            1.85,    1.86875, 1.8875,  1.90625, 1.925,   1.94375, 1.9625,  1.98125,
+            2.0,     2.015,   2.03,    2.045,   2.06,    2.075,   2.09,    2.105,
+            2.12,    2.135,   2.15,    2.165,   2.18,    2.195,   2.21,    2.225,
+            2.24,    2.255,   2.27,    2.285,   2.3,     2.315,   2.33,    2.345,
+            2.36,    2.375,   2.39,    2.405,   2.42,    2.435,   2.45,    2.465,
+            2.48,    2.495,   2.51,    2.525,   2.54,    2.555,   2.57,    2.585,
+            2.6,     2.615,   2.63,    2.645,   2.66,    2.675,   2.69,    2.705,
+            2.72,    2.735,   2.75,    2.765,   2.78,    2.795,   2.81,    2.825,
+            2.84,    2.855,   2.87,    2.885,   2.9,     2.91154, 2.92308, 2.93462,
+            2.94615, 2.95769, 2.96923, 2.98077, 2.99231, 3.00385, 3.01538, 3.02692,
+            3.03846, 3.05,    3.06154, 3.07308, 3.08462, 3.09615, 3.10769, 3.11923,
+            3.13077, 3.14231, 3.15385, 3.16538, 3.17692, 3.18846, 3.2,
+        ],
+
+        titanium_k,
+        [
+            2.7,     2.725,   2.75,    2.775,   2.8,     2.825,   2.85,    2.875,
+            2.9,     2.91,    2.92,    2.93,    2.94,    2.95,    2.96,    2.97,
+            2.98,    2.99,    3.0,     3.01,    3.02,    3.03,    3.04,    3.05,
+            3.06,    3.07,    3.08,    3.09,    3.1,     3.115,   3.13,    3.145,
+            3.16,    3.175,   3.19,    3.205,   3.22,    3.235,   3.25,    3.265,
+            3.28,    3.295,   3.31,    3.325,   3.34,    3.355,   3.37,    3.385,
+            3.4,     3.415,   3.43,    3.445,   3.46,    3.475,   3.49,    3.505,
+            3.52,    3.535,   3.55,    3.565,   3.58,    3.595,   3.61,    3.625,
+            3.64,    3.655,   3.67,    3.685,   3.7,     3.71154, 3.72308, 3.73462,
+            3.74615, 3.75769, 3.76923, 3.78077, 3.79231, 3.80385, 3.81538, 3.82692,
+            3.83846, 3.85,    3.86154, 3.87308, 3.88462, 3.89615, 3.90769, 3.91923,
+            3.93077, 3.94231, 3.95385, 3.96538, 3.97692, 3.98846, 4.0,
+        ],
+
+        // silver, from measurements of Johnson & Christy 1972
         mirror_eta,
         [
             0.0873,  0.07635, 0.06691, 0.05956, 0.05221, 0.05,    0.05,    0.05,
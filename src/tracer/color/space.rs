@@ -141,7 +141,7 @@ impl ColorSpace {
     }
 
     /// Von Kries transformation matrix for the color space and `illuminant`
-    pub fn wb_matrix(&self, illuminant: &'static DenseSpectrum) -> Mat3 {
+    pub fn wb_matrix(&self, illuminant: &DenseSpectrum) -> Mat3 {
         let illum_xy = xyz::to_xyY(illuminant.to_xyz());
 
         let diagonal = Self::XYZ_to_LMS.mul_vec3(*self.W)
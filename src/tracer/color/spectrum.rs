@@ -8,6 +8,21 @@ mod spectrum_tests;
 
 type TexFloat = f32;
 
+/// Model used to uplift linear RGB values to spectra. All fit the sigmoid
+/// polynomials of Jakob & Hanika 2019, but handle brightness differently.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Uplift {
+    /// Fit the color as is, scaled only if a channel exceeds one
+    #[default]
+    Sigmoid,
+    /// Channels clamped to `\[0,1\]` so that the spectrum is a valid
+    /// reflectance, e.g. for albedo maps
+    Reflectance,
+    /// Unbounded, brightness is always carried by the scale of a fit at
+    /// half intensity. Gives smooth spectra for emission, e.g. HDR maps.
+    Illuminant,
+}
+
 #[derive(Clone)]
 /// Represents a color spectrum at `BINS` bins of uniform width
 /// between `LAMBDA_MIN` and `LAMBDA_MAX` wavelengths.
@@ -48,18 +63,34 @@ impl Spectrum {
     }
 
     /// Create spectrum value from linear RGB value
-    // Jakob & Hanika 2019
     pub const fn from_rgb(rgb: RGB) -> Self {
+        Self::uplift(rgb, Uplift::Sigmoid)
+    }
+
+    /// Create spectrum value from linear RGB value with the `uplift` model
+    // Jakob & Hanika 2019
+    pub const fn uplift(rgb: RGB, uplift: Uplift) -> Self {
+        const fn saturate(c: Float) -> Float {
+            if c > 1.0 { 1.0 } else { c }
+        }
+
+        let rgb = match uplift {
+            Uplift::Reflectance => RGB::new(
+                saturate(rgb.r()), saturate(rgb.g()), saturate(rgb.b())
+            ),
+            Uplift::Sigmoid | Uplift::Illuminant => rgb,
+        };
+
         let maxc = if rgb.r() > rgb.g() { 0 } else { 1 };
         let maxc = if rgb.c(maxc) > rgb.b() { maxc } else { 2 };
         if rgb.c(maxc) == 0.0 || rgb.is_black() {
             return Spectrum::BLACK;
         }
 
-        let scale = if rgb.c(maxc) > 1.0 {
-            2.0 * rgb.c(maxc) as TexFloat
-        } else {
-            1.0
+        let scale = match uplift {
+            Uplift::Sigmoid if rgb.c(maxc) > 1.0 => 2.0 * rgb.c(maxc) as TexFloat,
+            Uplift::Illuminant => 2.0 * rgb.c(maxc) as TexFloat,
+            _ => 1.0,
         };
 
         let mx = rgb.c(maxc) as TexFloat;
@@ -95,6 +126,13 @@ impl Spectrum {
         Self::from_XYZ(dense_spec.to_xyz())
     }
 
+    /// Spectrum with the chromaticity of a blackbody at `temperature` Kelvin
+    /// and unit luminance
+    pub fn blackbody(temperature: Float) -> Self {
+        let xyz = DenseSpectrum::blackbody(temperature).to_xyz();
+        Self::from_XYZ(xyz / xyz.y)
+    }
+
     /// Samples `self` at `lambda` wavelengths
     pub fn sample(&self, lambda: &ColorWavelength) -> Color {
         let samples: [Float; SPECTRUM_SAMPLES] = lambda.iter()
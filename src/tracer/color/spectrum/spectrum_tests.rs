@@ -42,6 +42,27 @@ fn white_correct() {
     assert!(pass(spec, ans));
 }
 
+#[test]
+fn reflectance_uplift_is_bounded() {
+    let spec = Spectrum::uplift(RGB::new(4.0, 2.0, 0.5), Uplift::Reflectance);
+    for i in 0..=TEST_POINTS {
+        let wl = 360.0 + 470.0 * i as Float / TEST_POINTS as Float;
+        assert!(spec.sample_one(wl) <= 1.0);
+    }
+}
+
+#[test]
+fn illuminant_uplift_scales_linearly() {
+    let rgb = RGB::new(0.3, 0.1, 0.2);
+    let spec = Spectrum::uplift(rgb.clone(), Uplift::Illuminant);
+    let spec4 = Spectrum::uplift(4.0 * rgb, Uplift::Illuminant);
+    for i in 0..=TEST_POINTS {
+        let wl = 360.0 + 470.0 * i as Float / TEST_POINTS as Float;
+        let diff = 4.0 * spec.sample_one(wl) - spec4.sample_one(wl);
+        assert!(diff.abs() < 1e-5);
+    }
+}
+
 
 const TEST_DATA: [[TexFloat; 3]; 2 * TEST_POINTS] = [
     [0.840188, 0.394383, 0.783099],
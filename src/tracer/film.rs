@@ -124,7 +124,7 @@ impl Film {
         samples: u64,
         cs: &'static ColorSpace,
         filter: PixelFilter,
-        illuminant: &'static DenseSpectrum,
+        illuminant: &DenseSpectrum,
     ) -> Self {
         let n = resolution.x * resolution.y;
 
@@ -1,5 +1,8 @@
 use crate::{ Point, Float, Direction, Normal, efloat, Vec2, Vec3 };
-use crate::tracer::{ material::Material, ray::Ray };
+use crate::tracer::{ material::Material, ray::{Ray, RayDifferential}, texture::TexCoord };
+
+/// Texture coordinate differentials get clamped to this
+const MAX_DUV: Float = 1e8;
 
 /// Stores information about a hit between a ray and an object
 #[derive(Clone)]
@@ -10,6 +13,9 @@ pub struct Hit<'a> {
     pub material: &'a Material,
     /// 3D point where object was hit
     pub p: Point,
+    /// Impact point in the space of the object that got hit, i.e. before
+    /// any instance transforms. Same as `p` for objects that are not instanced.
+    pub p_local: Point,
     /// Floating point error bounds of the impact point
     pub fp_error: Vec3,
     /// Normal of the surface used for shading calculations
@@ -18,8 +24,24 @@ pub struct Hit<'a> {
     pub ng: Normal,
     /// Texture coordinates in `\[0,1\]^2`
     pub uv: Vec2,
+    /// Direction of increasing `u` on the surface, if the object defines one.
+    /// Orients anisotropic materials.
+    pub tangent: Option<Direction>,
+    /// Partial derivatives of the position with respect to `u` and `v`,
+    /// if the object defines them
+    pub dpduv: Option<(Vec3, Vec3)>,
+    /// Partial derivatives of the shading normal with respect to `u` and
+    /// `v`, if the object defines them. Curved specular surfaces spread
+    /// ray differentials with these.
+    pub dnduv: Option<(Vec3, Vec3)>,
+    /// Change in the impact point between neighbouring pixels in `x` and `y`
+    pub dpdxy: (Vec3, Vec3),
+    /// Change in texture coordinates between neighbouring pixels in `x` and `y`
+    pub duvdxy: (Vec2, Vec2),
     /// Are we on the backface?
     pub backface: bool,
+    /// Time of the ray that hit, rays leaving the hit travel at the same time
+    pub time: Float,
 }
 
 impl<'a> Hit<'a> {
@@ -44,6 +66,7 @@ impl<'a> Hit<'a> {
         ng: Normal,
         uv: Vec2,
     ) -> Option<Self> {
+        let uv = Self::wrap_uv(uv);
         let backface = wo.dot(ng) > 0.0;
 
         Some(Self {
@@ -51,13 +74,98 @@ impl<'a> Hit<'a> {
             material,
             backface,
             p: xi,
+            p_local: xi,
             fp_error,
             ns,
             ng,
-            uv: Self::wrap_uv(uv),
+            uv,
+            tangent: None,
+            dpduv: None,
+            dnduv: None,
+            dpdxy: (Vec3::ZERO, Vec3::ZERO),
+            duvdxy: (Vec2::ZERO, Vec2::ZERO),
+            time: 0.0,
         })
     }
 
+    /// The hit, unless the opacity mask of the material cuts it out for
+    /// a ray travelling in direction `wo`. Objects call this once the
+    /// texture coordinates of the hit are final.
+    pub fn unless_cut_out(self, wo: Direction) -> Option<Self> {
+        if self.material.is_cut_out(self.uv, self.p, wo) {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Set the partial derivatives of the position with respect to `u` and `v`
+    pub fn with_dpduv(mut self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        self.dpduv = Some((dpdu, dpdv));
+        self
+    }
+
+    /// Set the partial derivatives of the shading normal with respect to `u` and `v`
+    pub fn with_dnduv(mut self, dndu: Vec3, dndv: Vec3) -> Self {
+        self.dnduv = Some((dndu, dndv));
+        self
+    }
+
+    /// Estimate the footprint of `r` at the hit from its differentials by
+    /// intersecting the offset rays with the tangent plane.
+    pub fn with_differentials(mut self, r: &Ray) -> Self {
+        let Some(rd) = &r.differentials else { return self };
+
+        let offset = |xo: Point, wi: Direction| -> Option<Vec3> {
+            let t = self.ng.dot(self.p - xo) / self.ng.dot(wi);
+            let dp = xo + t * wi - self.p;
+            if dp.length_squared().is_finite() { Some(dp) } else { None }
+        };
+        let (Some(dpdx), Some(dpdy)) = (
+            offset(rd.rx_origin, rd.rx_dir),
+            offset(rd.ry_origin, rd.ry_dir),
+        ) else { return self };
+        self.dpdxy = (dpdx, dpdy);
+
+        if let Some((dpdu, dpdv)) = self.dpduv {
+            // least squares solution to dp = du * dpdu + dv * dpdv
+            let ata00 = dpdu.dot(dpdu);
+            let ata01 = dpdu.dot(dpdv);
+            let ata11 = dpdv.dot(dpdv);
+            let inv_det = 1.0 / (ata00 * ata11 - ata01 * ata01);
+            let inv_det = if inv_det.is_finite() { inv_det } else { 0.0 };
+
+            let duv = |dp: Vec3| -> Vec2 {
+                let atb0 = dpdu.dot(dp);
+                let atb1 = dpdv.dot(dp);
+                let duv = inv_det * Vec2::new(
+                    ata11 * atb0 - ata01 * atb1,
+                    ata00 * atb1 - ata01 * atb0,
+                );
+                duv.max(-Vec2::splat(MAX_DUV)).min(Vec2::splat(MAX_DUV))
+            };
+            self.duvdxy = (duv(dpdx), duv(dpdy));
+        }
+
+        self
+    }
+
+    /// Set the tangent of the surface. Degenerate tangents are ignored.
+    pub fn with_tangent(mut self, tangent: Direction) -> Self {
+        let length = tangent.length();
+        if length > crate::EPSILON && length.is_finite() {
+            self.tangent = Some(tangent / length);
+        }
+        self
+    }
+
+    /// Texture coordinates of the hit for evaluating textures
+    pub fn tex_coord(&self) -> TexCoord {
+        TexCoord::new(self.uv, self.p, self.ns)
+            .with_local_point(self.p_local)
+            .with_footprint(self.duvdxy.0, self.duvdxy.1)
+    }
+
     #[inline(always)]
     pub fn wrap_uv(uv: Vec2) -> Vec2 {
         let uv = uv.fract();
@@ -118,7 +226,64 @@ impl<'a> Hit<'a> {
         Ray::new(
             xi,
             wi
-        )
+        ).with_time(self.time)
+    }
+
+    /// Generates a ray at point of impact that carries the differentials of
+    /// `ro` through a specular bounce to `wi`. The change of the shading
+    /// normal across the footprint spreads the differentials on curved
+    /// surfaces, surfaces without `dnduv` are treated as locally flat. The
+    /// relative index of refraction is recovered from `wi`.
+    pub fn generate_ray_differential(&self, ro: &Ray, wi: Direction) -> Ray {
+        let ri = self.generate_ray(wi);
+        let Some(rd) = &ro.differentials else { return ri };
+        if self.dpdxy.0.length_squared() + self.dpdxy.1.length_squared() == 0.0 {
+            return ri;
+        }
+
+        let wo = -ro.dir;
+        let wi = ri.dir;
+        let flip = if wo.dot(self.ns) < 0.0 { -1.0 } else { 1.0 };
+        let ns = flip * self.ns;
+        let cos_o = wo.dot(ns);
+
+        // change of the shading normal between neighbouring pixels
+        let dndxy = |duv: Vec2| -> Vec3 {
+            match self.dnduv {
+                Some((dndu, dndv)) => flip * (duv.x * dndu + duv.y * dndv),
+                None => Vec3::ZERO,
+            }
+        };
+
+        // relative index of refraction, `None` for reflection
+        let eta = if wi.dot(ns) > 0.0 {
+            None
+        } else {
+            let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+            let sin_i = (1.0 - wi.dot(ns).powi(2)).max(0.0).sqrt();
+            Some( if sin_o < crate::EPSILON { 1.0 } else { sin_i / sin_o } )
+        };
+        let cos_i = wi.dot(ns).abs().max(crate::EPSILON);
+
+        let offset = |rx_dir: Direction, dn: Vec3| -> Direction {
+            let dwo = ro.dir - rx_dir;
+            let dcos_o = dwo.dot(ns) + wo.dot(dn);
+            match eta {
+                None => wi - dwo + 2.0 * (cos_o * dn + dcos_o * ns),
+                Some(eta) => {
+                    let mu = eta * cos_o - cos_i;
+                    let dmu = (eta - eta * eta * cos_o / cos_i) * dcos_o;
+                    wi - eta * dwo + mu * dn + dmu * ns
+                }
+            }
+        };
+
+        ri.with_differentials(RayDifferential {
+            rx_origin: self.p + self.dpdxy.0,
+            rx_dir: offset(rd.rx_dir, dndxy(self.duvdxy.0)),
+            ry_origin: self.p + self.dpdxy.1,
+            ry_dir: offset(rd.ry_dir, dndxy(self.duvdxy.1)),
+        })
     }
 
     /// Did we hit a medium?
@@ -53,7 +53,8 @@ impl Integrator {
         #[cfg(debug_assertions)]
         assert!(delta > 0.0);
 
-        let r = c.generate_ray(raster_xy, rng.gen_vec2());
+        let r = c.generate_ray(raster_xy, rng.gen_vec2())
+            .with_time(c.sample_time(rng.gen_float()));
         let lambda = ColorWavelength::sample(rng.gen_float());
         match self {
             Self::PathTrace => {
@@ -99,7 +100,7 @@ fn single_shadow_ray(
 
     // sample light first
     radiance += {
-        let wi = light.sample_towards(xo, rng.gen_vec2());
+        let wi = light.sample_towards(xo, ho.time, rng.gen_vec2());
         let ri = ho.generate_ray(wi);
         match scene.hit_light(&ri, rng, light) {
             None => Color::BLACK,
@@ -29,7 +29,7 @@ pub fn integrate(
     delta: Float,
     raster_xy: Vec2
 ) -> Vec<FilmSample> {
-    let light_path = path_gen::light_path(scene, rng, delta, &mut lambda);
+    let light_path = path_gen::light_path(scene, rng, delta, &mut lambda, r.time);
     let camera_path = path_gen::camera_path(scene, camera, r, rng, delta, &mut lambda);
 
     let mut radiance = Color::BLACK;
@@ -94,7 +94,7 @@ fn connect_light_path(
     // sample direction
     let hi = &light_last.h;
     let xi = hi.p;
-    let ri = camera.sample_towards(xi, rng.gen_vec2())?;
+    let ri = camera.sample_towards(xi, rng.gen_vec2())?.with_time(hi.time);
     let xo = ri.origin;
     let wi = ri.dir;
     // MIS checks this too
@@ -174,7 +174,7 @@ fn connect_camera_path(
     let ho = &camera_last.h;
     let xo = ho.p;
 
-    let wi = light.sample_towards(xo, rng.gen_vec2());
+    let wi = light.sample_towards(xo, ho.time, rng.gen_vec2());
     // alternatively let MIS take care of this
     let p_sct = camera_last.bsdf_pdf(wi, lambda, false);
     if p_sct == 0.0 {
@@ -190,7 +190,7 @@ fn _from_camera<'a>(
 
             let light_idx = sce.sample_light(rng.gen_float());
             let (light, _) = sce.get_light(light_idx);
-            let xi = light.sample_towards(xo, rng.gen_vec2());
+            let xi = light.sample_towards(xo, ho.time, rng.gen_vec2());
             let wi = (xi - xo).normalize();
             let r = ho.generate_ray(wi);
 
@@ -266,7 +266,7 @@ fn _from_light<'a>(
     let r = cam.generate_ray(Vec2::ZERO, rng.gen_vec2());
     let xc = r.origin;
     loop {
-        pth = path_gen::light_path(sce, rng, 0.0, lambda);
+        pth = path_gen::light_path(sce, rng, 0.0, lambda, 0.0);
         // too short, try another path
         if pth.len() <= 2 { continue; }
         let ls = &pth[pth.len() - 1];
@@ -13,21 +13,24 @@ pub fn camera_path<'a>(
     let xo = r.origin;
     let pdf_wi = camera.pdf_wi(&r);
     let pdf_xo = camera.pdf_xo(&r);
-    let root = Vertex::camera(xo, pdf_xo, gathered);
+    let mut root = Vertex::camera(xo, pdf_xo, gathered);
+    root.h.time = r.time;
 
     walk(scene, r, rng, lambda, delta, root, gathered, pdf_wi, Transport::Radiance)
 }
 
-/// Generates a ray path strating from a light
+/// Generates a ray path strating from a light at `time`
 pub fn light_path<'a>(
     scene: &'a Scene,
     rng: &mut Xorshift,
     delta: Float,
-    lambda: &mut ColorWavelength
+    lambda: &mut ColorWavelength,
+    time: Float,
 ) -> Vec<Vertex<'a>> {
     let light_idx = scene.sample_light(rng.gen_float());
     let (light, pdf_light) = scene.get_light(light_idx);
     let (ri, ho) = light.sample_leaving(
+        time,
         rng.gen_vec2(),
         rng.gen_vec2(),
     );
@@ -99,7 +102,13 @@ fn walk<'a>(
                 break;
             }
             Some(wi) => {
-                let ri = ho.generate_ray(wi);
+                // camera paths carry the footprint through specular bounces
+                // like the path tracer, light paths have no differentials
+                let ri = if material.is_delta(lambda) {
+                    ho.generate_ray_differential(&ro, wi)
+                } else {
+                    ho.generate_ray(wi)
+                };
                 let wi = ri.dir;
 
                 pdf_fwd = material.bsdf_pdf(wo, wi, ho, lambda, false);
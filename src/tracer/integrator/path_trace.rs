@@ -39,7 +39,11 @@ pub fn integrate(
                     );
                 }
 
-                let ri = ho.generate_ray(wi);
+                let ri = if material.is_delta(&lambda) {
+                    ho.generate_ray_differential(&ro, wi)
+                } else {
+                    ho.generate_ray(wi)
+                };
                 let wi = ri.dir;
 
                 let p_scatter = material.bsdf_pdf(wo, wi, &ho, &mut lambda, false);
@@ -1,30 +1,42 @@
-use crate::{ Normal, Direction, Transport, Float, Vec2, Image };
+use crate::{ Normal, Direction, Point, Transport, Float, Vec2 };
 use crate::tracer::{
+    alpha::AlphaMask, normal_map::NormalMap,
     Color, ColorWavelength, color::illuminants, Spectrum, hit::Hit,
-    microfacet::MfDistribution, color::materials,
-    color::DenseSpectrum, texture::Texture, bsdf::BSDF, bxdf::BxDF, onb::Onb,
+    microfacet::{MfDistribution, ThinFilm}, color::materials,
+    color::DenseSpectrum, texture::{TexCoord, Texture}, bsdf::BSDF, animation::Keyframes, bxdf::{BxDF, Hair, Layered, Principled, Sheen}, onb::Onb,
 };
 
 #[cfg(test)]
 mod white_furnace_tests;
+#[cfg(test)]
+mod mix_tests;
 
 /// Describes which material an object is made out of
 pub enum Material {
-    /// Materials with standard BSDF
-    Standard(BSDF, Option<Image<Normal>>),
-    /// Emits light
-    Light(Texture, &'static DenseSpectrum, Float, bool),
+    /// Materials with standard BSDF, optional normal map and opacity mask
+    Standard(BSDF, Option<NormalMap>, Option<AlphaMask>),
+    /// Emits light. Emittance, illuminant, scale of the emittance over time
+    /// and whether both sides emit.
+    Light(Texture, DenseSpectrum, Keyframes<Float>, bool),
     /// Volumetric material for mediums. `scatter_param`, `sigma_t`, `sigma_s`
     Volumetric(BSDF),
+    /// Picks the second material with probability given by the texture at
+    /// each hit and the first one otherwise. Resolved when the hit is found,
+    /// so the integrators only ever see one of the materials.
+    Mix(Box<Material>, Box<Material>, Texture),
+    /// Linear blend of the BSDFs of two materials, weight of the second
+    /// material given by the texture
+    Blend(Box<Material>, Box<Material>, Texture),
     /// Not specified. Used with objects that are built on top of other objects.
     Blank,
 }
 
 impl Material {
-    /// General microfacet constructor
+    /// General microfacet constructor. `roughness` is given separately along
+    /// the tangent (`x`) and bitangent (`y`) of the surface.
     #[allow(clippy::too_many_arguments)]
     pub fn microfacet(
-        roughness: Float,
+        roughness: Vec2,
         eta: Float,
         k: Float,
         is_transparent: bool,
@@ -32,7 +44,7 @@ impl Material {
         kd: Texture,
         ks: Texture,
         tf: Texture,
-        bump_map: Option<Image<Normal>>,
+        bump_map: Option<NormalMap>,
     ) -> Self {
         let eta = if !is_transparent {
             DenseSpectrum::from_constant(eta)
@@ -44,6 +56,31 @@ impl Material {
             }
         };
         let k = DenseSpectrum::from_constant(k);
+        Self::microfacet_spectral(
+            roughness,
+            eta,
+            k,
+            is_transparent,
+            fresnel_enabled,
+            kd, ks, tf,
+            bump_map,
+        )
+    }
+
+    /// General microfacet constructor with spectrally varying refraction index
+    /// and absorption coefficient, see [`materials`] for measured data.
+    #[allow(clippy::too_many_arguments)]
+    pub fn microfacet_spectral(
+        roughness: Vec2,
+        eta: DenseSpectrum,
+        k: DenseSpectrum,
+        is_transparent: bool,
+        fresnel_enabled: bool,
+        kd: Texture,
+        ks: Texture,
+        tf: Texture,
+        bump_map: Option<NormalMap>,
+    ) -> Self {
         let mfd = MfDistribution::new(roughness, eta, k, kd, ks, tf);
         Self::from_mfd(is_transparent, fresnel_enabled, bump_map, mfd)
     }
@@ -51,7 +88,7 @@ impl Material {
     fn from_mfd(
         is_transparent: bool,
         fresnel_enabled: bool,
-        bump_map: Option<Image<Normal>>,
+        bump_map: Option<NormalMap>,
         mfd: MfDistribution
     ) -> Self {
         // dirty dirty...
@@ -62,11 +99,17 @@ impl Material {
         } else {
             BSDF::new(BxDF::MfDiffuse(mfd))
         };
-        Self::Standard(bsdf, bump_map)
+        Self::Standard(bsdf, bump_map, None)
     }
 
     /// Microfacet mirror with assignable roughness
     pub fn metal(ks: Texture, roughness: Float, eta: Float, k: Float) -> Self {
+        Self::metal_anisotropic(ks, Vec2::splat(roughness), eta, k)
+    }
+
+    /// Microfacet mirror with roughness `x` along the surface tangent
+    /// and `y` along the bitangent, e.g. brushed metal
+    pub fn metal_anisotropic(ks: Texture, roughness: Vec2, eta: Float, k: Float) -> Self {
         let kd = Texture::from(Spectrum::WHITE);
         let tf = Texture::from(Spectrum::BLACK);
 
@@ -84,12 +127,32 @@ impl Material {
         )
     }
 
+    /// Microfacet mirror with measured refraction index and absorption
+    /// coefficient, e.g. from [`materials::conductor`]
+    pub fn conductor(ks: Texture, roughness: Float, eta: DenseSpectrum, k: DenseSpectrum) -> Self {
+        let kd = Texture::from(Spectrum::WHITE);
+        let tf = Texture::from(Spectrum::BLACK);
+
+        let is_transparent = false;
+        let fresnel_enabled = true;
+
+        Self::microfacet_spectral(
+            Vec2::splat(roughness),
+            eta,
+            k,
+            is_transparent,
+            fresnel_enabled,
+            kd, ks, tf,
+            None,
+        )
+    }
+
     /// Diffuse material with a microfacet based BxDF
     pub fn diffuse(kd: Texture) -> Self {
         let ks = Texture::from(Spectrum::WHITE);
         let tf = Texture::from(Spectrum::BLACK);
 
-        let roughness = 1.0;
+        let roughness = Vec2::ONE;
         let eta = 1.5;
         let k = 0.0;
         let is_transparent = false;
@@ -108,11 +171,37 @@ impl Material {
 
     /// Plain lambertian diffuse material
     pub fn lambertian(spec: Spectrum) -> Self {
-        Self::Standard(BSDF::new(BxDF::Lambertian(spec)), None)
+        Self::Standard(BSDF::new(BxDF::Lambertian(spec)), None, None)
     }
 
     /// Transparent material
     pub fn transparent(tf: Texture, roughness: Float, eta: Float) -> Self {
+        Self::transparent_anisotropic(tf, Vec2::splat(roughness), eta)
+    }
+
+    /// Transparent material with a dispersive refraction index,
+    /// e.g. from [`materials::dielectric`]
+    pub fn dielectric(tf: Texture, roughness: Float, eta: DenseSpectrum) -> Self {
+        let kd = Texture::from(Spectrum::BLACK);
+        let ks = Texture::from(Spectrum::WHITE);
+
+        let is_transparent = true;
+        let fresnel_enabled = true;
+
+        Self::microfacet_spectral(
+            Vec2::splat(roughness),
+            eta,
+            DenseSpectrum::from_constant(0.0),
+            is_transparent,
+            fresnel_enabled,
+            kd, ks, tf,
+            None,
+        )
+    }
+
+    /// Transparent material with roughness `x` along the surface tangent
+    /// and `y` along the bitangent
+    pub fn transparent_anisotropic(tf: Texture, roughness: Vec2, eta: Float) -> Self {
         let kd = Texture::from(Spectrum::BLACK);
         let ks = Texture::from(Spectrum::WHITE);
 
@@ -137,7 +226,7 @@ impl Material {
         let ks = Texture::from(Spectrum::WHITE);
         let tf = Texture::from(Spectrum::BLACK);
 
-        let roughness = 0.0;
+        let roughness = Vec2::ZERO;
         let is_transparent = false;
         let fresnel_enabled = true;
 
@@ -157,7 +246,7 @@ impl Material {
         let ks = Texture::from(Spectrum::WHITE);
         let tf = Texture::from(Spectrum::WHITE);
 
-        let roughness = 0.0;
+        let roughness = Vec2::ZERO;
         let k = 0.0;
         let is_transparent = true;
         let fresnel_enabled = true;
@@ -172,6 +261,128 @@ impl Material {
         Self::from_mfd(is_transparent, fresnel_enabled, None, mfd)
     }
 
+    /// Metal coated with a thin film of `film_thickness` nanometers and
+    /// refraction index `film_eta`, e.g. heat tinted steel
+    pub fn metal_film(
+        ks: Texture,
+        roughness: Float,
+        eta: Float,
+        k: Float,
+        film_thickness: Float,
+        film_eta: Float,
+    ) -> Self {
+        let kd = Texture::from(Spectrum::WHITE);
+        let tf = Texture::from(Spectrum::BLACK);
+
+        let is_transparent = false;
+        let fresnel_enabled = true;
+
+        let mfd = MfDistribution::new(
+            Vec2::splat(roughness),
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(k),
+            kd, ks, tf,
+        ).with_film(ThinFilm::new(film_thickness, film_eta));
+
+        Self::from_mfd(is_transparent, fresnel_enabled, None, mfd)
+    }
+
+    /// Transparent material coated with a thin film of `film_thickness`
+    /// nanometers and refraction index `film_eta`, e.g. oil on water
+    pub fn transparent_film(
+        tf: Texture,
+        roughness: Float,
+        eta: Float,
+        film_thickness: Float,
+        film_eta: Float,
+    ) -> Self {
+        let kd = Texture::from(Spectrum::BLACK);
+        let ks = Texture::from(Spectrum::WHITE);
+
+        let is_transparent = true;
+        let fresnel_enabled = true;
+
+        let mfd = MfDistribution::new(
+            Vec2::splat(roughness),
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(0.0),
+            kd, ks, tf,
+        ).with_film(ThinFilm::new(film_thickness, film_eta));
+
+        Self::from_mfd(is_transparent, fresnel_enabled, None, mfd)
+    }
+
+    /// Infinitesimally thin glass sheet, e.g. a window pane
+    pub fn thin_glass(tf: Texture, eta: Float) -> Self {
+        let kd = Texture::from(Spectrum::BLACK);
+        let ks = Texture::from(Spectrum::WHITE);
+
+        let mfd = MfDistribution::new(
+            Vec2::ZERO,
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(0.0),
+            kd, ks, tf,
+        );
+
+        Self::Standard(BSDF::new(BxDF::ThinDielectric(mfd)), None, None)
+    }
+
+    /// Free standing thin film of `thickness` nanometers and refraction index
+    /// `eta`, e.g. a soap bubble
+    pub fn thin_film(tf: Texture, thickness: Float, eta: Float) -> Self {
+        let kd = Texture::from(Spectrum::BLACK);
+        let ks = Texture::from(Spectrum::WHITE);
+
+        let mfd = MfDistribution::new(
+            Vec2::ZERO,
+            DenseSpectrum::from_constant(eta),
+            DenseSpectrum::from_constant(0.0),
+            kd, ks, tf,
+        ).with_film(ThinFilm::new(thickness, eta));
+
+        Self::Standard(BSDF::new(BxDF::ThinDielectric(mfd)), None, None)
+    }
+
+    /// Principled material, see [`Principled`] for the parameters
+    pub fn principled(principled: Principled, bump_map: Option<NormalMap>) -> Self {
+        Self::Standard(BSDF::new(BxDF::Principled(principled)), bump_map, None)
+    }
+
+    /// Coated material, see [`Layered`] for the parameters
+    pub fn layered(layered: Layered, bump_map: Option<NormalMap>) -> Self {
+        Self::Standard(BSDF::new(BxDF::Layered(layered)), bump_map, None)
+    }
+
+    /// Cloth with a diffuse base of color `kd` and sheen of color `sheen`
+    pub fn cloth(kd: Texture, sheen: Texture, roughness: Float) -> Self {
+        Self::Standard(BSDF::new(BxDF::Sheen(Sheen::new(kd, sheen, roughness))), None, None)
+    }
+
+    /// Hair fibers, see [`Hair`] for the parameters. Use on curves.
+    pub fn hair(hair: Hair) -> Self {
+        Self::Standard(BSDF::new(BxDF::Hair(hair)), None, None)
+    }
+
+    /// Stochastically pick `m2` with probability `weight` and `m1` otherwise.
+    /// Works with any surface materials, including ones that follow delta
+    /// distributions. Weights are meant to be grey, coloured weights get
+    /// evaluated at a random wavelength.
+    pub fn mix(m1: Material, m2: Material, weight: Texture) -> Self {
+        assert!(m1.is_surface() && m2.is_surface());
+        Self::Mix(Box::new(m1), Box::new(m2), weight)
+    }
+
+    /// Blend the BSDFs of `m1` and `m2` linearly, `m2` weighted by `weight`.
+    /// Gives smoother results than [`Material::mix`], but neither material
+    /// can follow a delta distribution or be a mix. Mixes can blend instead.
+    pub fn blend(m1: Material, m2: Material, weight: Texture) -> Self {
+        let lambda = ColorWavelength::sample(0.5);
+        assert!(m1.is_surface() && m2.is_surface());
+        assert!(!m1.has_mix() && !m2.has_mix());
+        assert!(!m1.is_delta(&lambda) && !m2.is_delta(&lambda));
+        Self::Blend(Box::new(m1), Box::new(m2), weight)
+    }
+
     /// Volumetric material for mediums
     pub fn volumetric(
         g: Float,
@@ -190,7 +401,57 @@ impl Material {
 
     /// Create a light with emittance scaled by `scale`
     pub fn light_scale(ke: Texture, scale: Float) -> Self {
-        Material::Light(ke, illuminants::D65, scale, false)
+        Material::Light(ke, illuminants::D65.clone(), Keyframes::from(scale), false)
+    }
+
+    /// Create a light that emits like a blackbody at `temperature` Kelvin,
+    /// emittance scaled by `scale`
+    pub fn light_temperature(ke: Texture, temperature: Float, scale: Float) -> Self {
+        Material::Light(ke, DenseSpectrum::blackbody(temperature), Keyframes::from(scale), false)
+    }
+
+    /// Keyframe the scale of the emittance of a light over time
+    pub fn with_intensity(mut self, intensity: Keyframes<Float>) -> Self {
+        match &mut self {
+            Self::Light(_, _, s, _) => *s = intensity,
+            _ => panic!("Only lights have an intensity"),
+        }
+        self
+    }
+
+    /// Perturb the shading normals of the material with `normal_map`.
+    /// Only materials with a standard BSDF support normal maps.
+    pub fn with_normal_map(mut self, normal_map: NormalMap) -> Self {
+        match &mut self {
+            Self::Standard(_, nm, _) => *nm = Some(normal_map),
+            _ => panic!("Only standard materials support normal maps"),
+        }
+        self
+    }
+
+    /// Cut out the material with opacity mask `alpha`.
+    /// Only materials with a standard BSDF support masks.
+    pub fn with_alpha(mut self, alpha: AlphaMask) -> Self {
+        match &mut self {
+            Self::Standard(_, _, mask) => *mask = Some(alpha),
+            _ => panic!("Only standard materials support opacity masks"),
+        }
+        self
+    }
+
+    /// Does the material let a ray arriving at `xi` from `wo` pass through at `uv`?
+    #[inline]
+    pub fn is_cut_out(&self, uv: Vec2, xi: Point, wo: Direction) -> bool {
+        match self {
+            Self::Standard(_, _, Some(alpha)) => !alpha.is_opaque(uv, xi, wo),
+            _ => false,
+        }
+    }
+
+    /// Does the material have an opacity mask?
+    #[inline]
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, Self::Standard(_, _, Some(_)))
     }
 
     /// Are we a light?
@@ -199,11 +460,44 @@ impl Material {
         matches!(self, Material::Light(..))
     }
 
+    /// Does the material scatter light on a surface?
+    fn is_surface(&self) -> bool {
+        matches!(self, Self::Standard(..) | Self::Mix(..) | Self::Blend(..))
+    }
+
+    /// Does the material pick from mixed materials? Only resolved at the top.
+    fn has_mix(&self) -> bool {
+        match self {
+            Self::Mix(..) => true,
+            Self::Blend(m1, m2, _) => m1.has_mix() || m2.has_mix(),
+            _ => false,
+        }
+    }
+
+    /// Material that scatters at texture coordinate `tc`. Mix materials pick
+    /// one of their materials with `rand_u`, `rand_v` samples the wavelength
+    /// at which the weight gets evaluated.
+    pub fn resolve(&self, tc: &TexCoord, rand_u: Float, rand_v: Float) -> &Material {
+        match self {
+            Self::Mix(m1, m2, weight) => {
+                let lambda = ColorWavelength::sample(rand_v);
+                let w = weight.value_at(&lambda, tc).clamp(0.0, 1.0);
+                if rand_u < w {
+                    m2.resolve(tc, rand_u / w, rand_v)
+                } else {
+                    m1.resolve(tc, (rand_u - w) / (1.0 - w), rand_v)
+                }
+            }
+            _ => self,
+        }
+    }
+
     /// Is the material specular? I.e. reflects light
     pub fn is_specular(&self) -> bool {
         match self {
             Self::Volumetric(..) => true,
-            Self::Standard(bsdf, _) => bsdf.is_specular(),
+            Self::Standard(bsdf, ..) => bsdf.is_specular(),
+            Self::Blend(m1, m2, _) => m1.is_specular() && m2.is_specular(),
             _ => false,
         }
     }
@@ -213,7 +507,7 @@ impl Material {
     #[inline]
     pub fn is_delta(&self, lambda: &ColorWavelength) -> bool {
         match self {
-            Self::Standard(bsdf, _) => bsdf.is_delta(lambda),
+            Self::Standard(bsdf, ..) => bsdf.is_delta(lambda),
             _ => false,
         }
     }
@@ -226,19 +520,20 @@ impl Material {
                 if !ts && h.backface {
                     Color::BLACK
                 } else {
-                    *s * t.albedo_at(lambda, h.uv) * e.sample(lambda)
+                    s.at(h.time) * t.albedo_at(lambda, &h.tex_coord()) * e.sample(lambda)
                 }
             }
             _ => Color::BLACK,
         }
     }
 
-    /// Power of light material
+    /// Power of light material, at its brightest if the intensity is keyframed
     #[inline]
     pub fn power(&self, lambda: &ColorWavelength) -> Color {
         match self {
             Self::Light(t, e, s, ts) => {
-                let phi = *s * t.power(lambda) * e.sample(lambda);
+                let s = s.values().fold(0.0, |acc: Float, v| acc.max(*v));
+                let phi = s * t.power(lambda) * e.sample(lambda);
                 if !ts { phi } else { 2.0 * phi }
             }
             _ => Color::BLACK,
@@ -258,11 +553,17 @@ impl Material {
     ) -> Color {
         match self {
             Self::Volumetric(bsdf) => {
-                bsdf.f(wo, wi, lambda, h.backface, h.t, h.ng, h.ns, h.uv, mode)
+                let uvw = Onb::new(h.ns);
+                bsdf.f(wo, wi, lambda, h.backface, h.t, h.ng, &uvw, &h.tex_coord(), mode)
+            }
+            Self::Standard(bsdf, normal_map, _) => {
+                let uvw = Self::shading_frame(h, normal_map.as_ref());
+                bsdf.f(wo, wi, lambda, h.backface, h.t, h.ng, &uvw, &h.tex_coord(), mode)
             }
-            Self::Standard(bsdf, normal_map) => {
-                let ns = Self::map_normal(h.ns, h.uv, normal_map.as_ref());
-                bsdf.f(wo, wi, lambda, h.backface, h.t, h.ng, ns, h.uv, mode)
+            Self::Blend(m1, m2, weight) => {
+                let w = Self::blend_weight(weight, lambda, h);
+                (1.0 - w) * m1.bsdf_f(wo, wi, lambda, mode, h)
+                    + w * m2.bsdf_f(wo, wi, lambda, mode, h)
             }
             _ => Color::BLACK,
         }
@@ -280,11 +581,20 @@ impl Material {
     ) -> Option<Direction> {
         match self {
             Self::Volumetric(bsdf) => {
-                bsdf.sample(wo, h.ns, h.backface, lambda, rand_u, rand_sq)
+                let uvw = Onb::new(h.ns);
+                bsdf.sample(wo, &uvw, &h.tex_coord(), h.backface, lambda, rand_u, rand_sq)
             }
-            Self::Standard(bsdf, normal_map) => {
-                let ns = Self::map_normal(h.ns, h.uv, normal_map.as_ref());
-                bsdf.sample(wo, ns, h.backface, lambda, rand_u, rand_sq)
+            Self::Standard(bsdf, normal_map, _) => {
+                let uvw = Self::shading_frame(h, normal_map.as_ref());
+                bsdf.sample(wo, &uvw, &h.tex_coord(), h.backface, lambda, rand_u, rand_sq)
+            }
+            Self::Blend(m1, m2, weight) => {
+                let w = Self::blend_weight(weight, lambda, h);
+                if rand_u < w {
+                    m2.bsdf_sample(wo, h, lambda, rand_u / w, rand_sq)
+                } else {
+                    m1.bsdf_sample(wo, h, lambda, (rand_u - w) / (1.0 - w), rand_sq)
+                }
             }
             _ => None,
         }
@@ -300,12 +610,18 @@ impl Material {
         lambda: &ColorWavelength,
         swap_dir: bool
     ) -> Float {
+        if let Self::Blend(m1, m2, weight) = self {
+            let w = Self::blend_weight(weight, lambda, h);
+            return (1.0 - w) * m1.bsdf_pdf(wo, wi, h, lambda, swap_dir)
+                + w * m2.bsdf_pdf(wo, wi, h, lambda, swap_dir);
+        }
+
         let (wo, wi) = if swap_dir { (wi, wo) } else { (wo, wi) };
         match self {
-            Self::Volumetric(bsdf) => bsdf.pdf(wo, wi, h.ng, h.ns, lambda),
-            Self::Standard(bsdf, normal_map) => {
-                let ns = Self::map_normal(h.ns, h.uv, normal_map.as_ref());
-                bsdf.pdf(wo, wi, h.ng, ns, lambda)
+            Self::Volumetric(bsdf) => bsdf.pdf(wo, wi, h.ng, &Onb::new(h.ns), &h.tex_coord(), lambda),
+            Self::Standard(bsdf, normal_map, _) => {
+                let uvw = Self::shading_frame(h, normal_map.as_ref());
+                bsdf.pdf(wo, wi, h.ng, &uvw, &h.tex_coord(), lambda)
             }
             _ => 0.0,
         }
@@ -315,18 +631,26 @@ impl Material {
     #[inline]
     pub fn shading_cosine(&self, wi: Direction, ns: Normal) -> Float {
         match self {
-            Self::Standard(..) => ns.dot(wi).abs(),
+            Self::Standard(..) | Self::Blend(..) => ns.dot(wi).abs(),
             _ => 1.0,
         }
     }
 
+    /// Weight of the second material of a blend at `h`
     #[inline(always)]
-    fn map_normal(ns: Normal, uv: Vec2, normal_map: Option<&Image<Normal>>) -> Normal {
-        if let Some(normal_map) = normal_map {
-            let onb = Onb::new(ns);
-            onb.to_world(normal_map.value_at(uv)).normalize()
-        } else {
-            ns
+    fn blend_weight(weight: &Texture, lambda: &ColorWavelength, h: &Hit) -> Float {
+        weight.value_at(lambda, &h.tex_coord()).clamp(0.0, 1.0)
+    }
+
+    /// Shading frame at `h`. Oriented along the surface tangent, or `dp/du`
+    /// if the surface has no tangent but has texture coordinate derivatives.
+    #[inline(always)]
+    fn shading_frame(h: &Hit, normal_map: Option<&NormalMap>) -> Onb {
+        let tangent = h.tangent.or_else(|| h.dpduv.map(|(dpdu, _)| dpdu));
+        let ns = normal_map.map_or(h.ns, |nm| nm.perturb(h.ns, tangent, h.uv));
+        match tangent {
+            Some(tangent) => Onb::new_with_tangent(ns, tangent),
+            None => Onb::new(ns),
         }
     }
 }
@@ -0,0 +1,110 @@
+use super::*;
+use crate::{ Point, rng::{self, Xorshift} };
+use crate::tracer::ray::Ray;
+use crate::tracer::object::{ Object, Disk };
+
+const NUM_SAMPLES: usize = 1_000;
+
+fn diffuse() -> Material {
+    Material::diffuse(Texture::from(Spectrum::WHITE))
+}
+
+fn glossy() -> Material {
+    Material::metal(Texture::from(Spectrum::WHITE), 0.3, 1.5, 3.0)
+}
+
+fn disk_hit(disk: &Disk) -> Hit<'_> {
+    let r = Ray::new(Point::Z, -Direction::Z);
+    disk.hit(&r, 0.0, crate::INF).unwrap()
+}
+
+#[test]
+fn mix_selects_by_weight() {
+    let mix = Material::mix(diffuse(), Material::mirror(), Texture::from(0.3));
+    let tc = TexCoord::from_uv(Vec2::ZERO);
+
+    let mirrors = (0..NUM_SAMPLES)
+        .map(|i| (i as Float + 0.5) / NUM_SAMPLES as Float)
+        .filter(|u| mix.resolve(&tc, *u, 0.5).is_specular())
+        .count();
+
+    assert!(mirrors == 300);
+}
+
+#[test]
+fn nested_mix_resolves_to_leaf() {
+    let mix = Material::mix(
+        Material::mix(diffuse(), glossy(), Texture::from(0.5)),
+        Material::mirror(),
+        Texture::from(0.5),
+    );
+    let tc = TexCoord::from_uv(Vec2::ZERO);
+    let mut rng = Xorshift::new(123);
+
+    for _ in 0..NUM_SAMPLES {
+        let m = mix.resolve(&tc, rng.gen_float(), rng.gen_float());
+        assert!(matches!(m, Material::Standard(..)));
+    }
+}
+
+#[test]
+fn blend_is_weighted_sum() {
+    let w = 0.3;
+    let blend = Disk::new(
+        Point::ZERO, Normal::Z, 1.0,
+        Material::blend(diffuse(), glossy(), Texture::from(w)),
+    );
+    let d1 = Disk::new(Point::ZERO, Normal::Z, 1.0, diffuse());
+    let d2 = Disk::new(Point::ZERO, Normal::Z, 1.0, glossy());
+    let (hb, h1, h2) = (disk_hit(&blend), disk_hit(&d1), disk_hit(&d2));
+
+    let mut rng = Xorshift::new(321);
+    let lambda = ColorWavelength::sample(rng.gen_float());
+    for _ in 0..NUM_SAMPLES {
+        let wo = rng::maps::square_to_hemisphere(rng.gen_vec2());
+        let wi = rng::maps::square_to_hemisphere(rng.gen_vec2());
+
+        let f = hb.material.bsdf_f(wo, wi, &lambda, Transport::Radiance, &hb);
+        let expected = (1.0 - w) * h1.material.bsdf_f(wo, wi, &lambda, Transport::Radiance, &h1)
+            + w * h2.material.bsdf_f(wo, wi, &lambda, Transport::Radiance, &h2);
+        assert!((f - expected).max().abs() < 1e-10 && (f - expected).min().abs() < 1e-10);
+
+        let pdf = hb.material.bsdf_pdf(wo, wi, &hb, &lambda, false);
+        let expected = (1.0 - w) * h1.material.bsdf_pdf(wo, wi, &h1, &lambda, false)
+            + w * h2.material.bsdf_pdf(wo, wi, &h2, &lambda, false);
+        assert!((pdf - expected).abs() < 1e-10);
+    }
+}
+
+#[test]
+#[should_panic]
+fn blend_rejects_delta() {
+    Material::blend(diffuse(), Material::mirror(), Texture::from(0.5));
+}
+
+#[test]
+#[should_panic]
+fn blend_rejects_mix() {
+    let mix = Material::mix(diffuse(), glossy(), Texture::from(0.5));
+    Material::blend(mix, diffuse(), Texture::from(0.5));
+}
+
+#[test]
+#[should_panic]
+fn blend_rejects_nested_mix() {
+    let mix = Material::mix(diffuse(), glossy(), Texture::from(0.5));
+    let blend = Material::Blend(Box::new(diffuse()), Box::new(mix), Texture::from(0.5));
+    Material::blend(blend, diffuse(), Texture::from(0.5));
+}
+
+#[test]
+fn mix_of_blends_resolves() {
+    let mix = Material::mix(
+        Material::blend(diffuse(), glossy(), Texture::from(0.5)),
+        diffuse(),
+        Texture::from(0.5),
+    );
+    let tc = TexCoord::from_uv(Vec2::ZERO);
+    assert!(matches!(mix.resolve(&tc, 0.25, 0.5), Material::Standard(..)));
+    assert!(matches!(mix.resolve(&tc, 0.75, 0.5), Material::Blend(..)));
+}
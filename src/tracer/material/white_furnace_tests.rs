@@ -49,6 +49,55 @@ macro_rules! test_material {
     }
 }
 
+/// Lower bound for materials that should not absorb any energy
+const MIN_RADIANCE: Float = 0.97;
+/// Seed of the energy conservation tests, the margins leave little room for noise
+const SEED: u64 = 0x0ff1ce;
+
+/// Furnace for materials that absorb no energy, mean radiance should be one.
+/// Radiance transmitted into a medium with refraction index `eta` gets
+/// compressed by `1 / eta^2`, so that is undone before comparing.
+macro_rules! test_energy_conserving {
+    ( $( $name:ident, $mat:expr, $mode:expr, $eta:expr ),* ) => {
+        $(
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn energy_conserving() {
+                    let mut rng = Xorshift::new(SEED);
+
+                    let d = Disk::new(Point::ZERO, Normal::Z, 1.0, $mat);
+                    let r = Ray::new(Point::Z, -Direction::Z);
+                    let h = d.hit(&r, 0.0, crate::INF).unwrap();
+                    for _ in 0..NUM_RUNS {
+                        let wo = rng::maps::square_to_hemisphere(rng.gen_vec2());
+                        let radiance = albedo_sample(wo, &h, &mut rng, $mode, $eta);
+
+                        let pass = radiance.min() > MIN_RADIANCE && radiance.max() < MAX_RADIANCE;
+                        if !pass {
+                            println!("L: {}, wo: {}", radiance, wo);
+                        }
+                        assert!(pass);
+                    }
+                }
+            }
+        )*
+    }
+}
+
+/// Conductor that reflects all light
+fn perfect_conductor(roughness: Float) -> Material {
+    Material::metal(Texture::from(1.0), roughness, 1.0, 1e4)
+}
+
+/// Dielectric that transmits all light it does not reflect
+fn clear_dielectric(roughness: Float, eta: Float) -> Material {
+    let (kd, ks, tf) = (Texture::from(0.0), Texture::from(1.0), Texture::from(1.0));
+    let (eta, k) = (DenseSpectrum::from_constant(eta), DenseSpectrum::from_constant(0.0));
+    Material::microfacet_spectral(Vec2::splat(roughness), eta, k, true, true, kd, ks, tf, None)
+}
+
 fn white_tex() -> Texture {
     Texture::from(Spectrum::WHITE)
 }
@@ -61,10 +110,37 @@ fn conductor(roughness: Float, eta: Float, k: Float) -> Material {
     Material::metal(white_tex(), roughness, eta, k)
 }
 
+fn coated(layered: Layered) -> Material {
+    Material::layered(layered, None)
+}
+
 test_material!{
     lambertian,  Material::lambertian(Spectrum::WHITE), Transport::Radiance,
     diffuse,     Material::diffuse(white_tex()),        Transport::Radiance,
 
+    thin_glass,        Material::thin_glass(white_tex(), 1.5),             Transport::Radiance,
+    thin_film,         Material::thin_film(white_tex(), 400.0, 1.33),      Transport::Radiance,
+    metal_film,        Material::metal_film(white_tex(), 0.3, 1.5, 3.0, 300.0, 1.4), Transport::Radiance,
+    transparent_film,  Material::transparent_film(white_tex(), 0.3, 1.5, 300.0, 1.4),
+    Transport::Radiance,
+
+    blend, Material::blend(
+        Material::diffuse(white_tex()), conductor(0.3, 1.5, 3.0), Texture::from(0.3)
+    ), Transport::Radiance,
+
+    cloth,       Material::cloth(white_tex(), white_tex(), 0.5), Transport::Radiance,
+    cloth_rough, Material::cloth(white_tex(), white_tex(), 1.0), Transport::Radiance,
+    cloth_tight, Material::cloth(white_tex(), white_tex(), 0.1), Transport::Radiance,
+
+    layered_diffuse, coated(Layered::coated_diffuse(white_tex())), Transport::Radiance,
+    layered_rough,   coated(Layered::coated_diffuse(white_tex()).coat(0.5, 1.5)), Transport::Radiance,
+    layered_medium,  coated(
+        Layered::coated_diffuse(white_tex()).medium(Spectrum::WHITE, 0.3).thickness(0.1)
+    ), Transport::Radiance,
+    layered_conductor, coated(
+        Layered::coated_conductor(white_tex(), 0.3, 1.5, 3.0)
+    ), Transport::Radiance,
+
     conductor75_eta15_k3, conductor(0.75, 1.5, 3.0), Transport::Radiance,
     conductor50_eta15_k3, conductor(0.75, 1.5, 3.0), Transport::Radiance,
     conductor25_eta15_k3, conductor(0.75, 1.5, 3.0), Transport::Radiance,
@@ -121,3 +197,52 @@ fn furnace_sample(wo: Direction, h: Hit, rng: &mut Xorshift, mode: Transport) ->
 
     radiance * lambda.pdf() / (lambda.pdf() * (NUM_SAMPLES - misses) as Float)
 }
+
+test_energy_conserving!{
+    perfect_conductor100, perfect_conductor(1.00), Transport::Radiance, 1.0,
+    perfect_conductor75,  perfect_conductor(0.75), Transport::Radiance, 1.0,
+    perfect_conductor50,  perfect_conductor(0.50), Transport::Radiance, 1.0,
+    perfect_conductor25,  perfect_conductor(0.25), Transport::Radiance, 1.0,
+
+    dielectric100_eta15, clear_dielectric(1.00, 1.5), Transport::Importance, 1.5,
+    dielectric75_eta15,  clear_dielectric(0.75, 1.5), Transport::Importance, 1.5,
+    dielectric50_eta15,  clear_dielectric(0.50, 1.5), Transport::Importance, 1.5,
+    dielectric25_eta15,  clear_dielectric(0.25, 1.5), Transport::Importance, 1.5,
+    dielectric100_eta25, clear_dielectric(1.00, 2.5), Transport::Importance, 2.5,
+    dielectric50_eta25,  clear_dielectric(0.50, 2.5), Transport::Importance, 2.5,
+
+    dielectric100_eta15_radiance,clear_dielectric(1.00, 1.5), Transport::Radiance, 1.5,
+    dielectric75_eta15_radiance, clear_dielectric(0.75, 1.5), Transport::Radiance, 1.5,
+    dielectric50_eta15_radiance, clear_dielectric(0.50, 1.5), Transport::Radiance, 1.5,
+    dielectric25_eta15_radiance, clear_dielectric(0.25, 1.5), Transport::Radiance, 1.5,
+    dielectric100_eta25_radiance,clear_dielectric(1.00, 2.5), Transport::Radiance, 2.5,
+    dielectric50_eta25_radiance, clear_dielectric(0.50, 2.5), Transport::Radiance, 2.5
+}
+
+/// Like `furnace_sample`, but failed samples count as absorbed energy
+fn albedo_sample(
+    wo: Direction,
+    h: &Hit,
+    rng: &mut Xorshift,
+    mode: Transport,
+    eta: Float,
+) -> Color {
+    let m = h.material;
+
+    let mut radiance = Color::BLACK;
+    let mut lambda = ColorWavelength::sample(rng.gen_float());
+
+    for _ in 0..NUM_SAMPLES {
+        if let Some(wi) = m.bsdf_sample(wo, h, &mut lambda, rng.gen_float(), rng.gen_vec2()) {
+            let scale = match mode {
+                Transport::Radiance if wi.dot(h.ng) < 0.0 => eta * eta,
+                _ => 1.0,
+            };
+            radiance += scale * m.bsdf_f(wo, wi, &lambda, mode, h)
+                * m.shading_cosine(wi, h.ns)
+                / m.bsdf_pdf(wo, wi, h, &lambda, false);
+        }
+    }
+
+    radiance * lambda.pdf() / (lambda.pdf() * NUM_SAMPLES as Float)
+}
@@ -1,13 +1,32 @@
 use crate::{ Normal, Direction, Float, Vec2 };
 use crate::math::{ complex::Complex, spherical_utils };
-use crate::tracer::{ Color, ColorWavelength, DenseSpectrum, Texture };
+use crate::tracer::{ Color, ColorWavelength, DenseSpectrum, TexCoord, Texture };
+use std::ops::Deref;
+use albedo::AlbedoTables;
+
+mod albedo;
+
+#[cfg(test)]
+mod film_tests;
+
+#[cfg(test)]
+mod map_tests;
+
+/// Smallest roughness from roughness maps. Keeps textured surfaces
+/// away from the delta code paths, as those get decided per material.
+const MIN_TEXTURED_ROUGHNESS: Float = 2e-3;
 
 /// Configurable parameters for a microsurface
 pub struct MicrofacetConfig {
-    /// Roughness of the surface (α) [0,1]
+    /// Roughness of the surface (α) [0,1] along the tangent (`x`)
+    /// and the bitangent (`y`)
     pub roughness: Vec2,
+    /// Optional map that scales `roughness` at each point
+    pub roughness_map: Option<Texture>,
     /// Refraction index of the material >= 1.0
     pub eta: DenseSpectrum,
+    /// Optional map of the refraction index, replaces `eta` at each point
+    pub eta_map: Option<Texture>,
     /// Absoprtion coefficient
     pub k: DenseSpectrum,
     /// Diffuse reflectance
@@ -16,32 +35,94 @@ pub struct MicrofacetConfig {
     pub ks: Texture,
     /// Transmission filter
     pub tf: Texture,
+    /// Optional thin film on top of the surface
+    pub film: Option<ThinFilm>,
+}
+
+/// Thin dielectric film on top of a surface. Light reflecting from the
+/// top and the bottom of the film interferes, which gives soap bubbles
+/// and oil slicks their iridescent colors.
+#[derive(Clone, Copy)]
+pub struct ThinFilm {
+    /// Thickness of the film in nanometers
+    pub thickness: Float,
+    /// Refraction index of the film
+    pub eta: Float,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: Float, eta: Float) -> Self {
+        assert!(thickness >= 0.0);
+        assert!(eta > 0.0);
+
+        Self { thickness, eta }
+    }
+
+    /// Airy reflectance of the film, averaged over both polarizations.
+    /// Accounts for all of the internal bounces inside the film.
+    ///
+    /// # Arguments
+    /// * `cos_1` - Cosine of the incident angle, positive
+    /// * `n1`    - Refraction index of the incident medium
+    /// * `n3`    - Complex refraction index of the medium below the film
+    /// * `wl`    - Wavelength in nanometers
+    pub fn reflectance(&self, cos_1: Float, n1: Float, n3: Complex, wl: Float) -> Float {
+        let n1 = Complex::from(n1);
+        let n2 = Complex::from(self.eta);
+        let cos_1 = Complex::from(cos_1.clamp(0.0, 1.0));
+        let sin2_1 = 1.0 - cos_1 * cos_1;
+
+        // complex cosines handle total internal reflection and absorption
+        let cos_2 = (1.0 - sin2_1 * (n1 * n1) / (n2 * n2)).sqrt();
+        let cos_3 = (1.0 - sin2_1 * (n1 * n1) / (n3 * n3)).sqrt();
+
+        // phase difference between consecutive bounces
+        let delta = 4.0 * crate::PI * self.thickness / wl * n2 * cos_2;
+        let phase = (Complex::new(0.0, 1.0) * delta).exp();
+
+        let airy = |r12: Complex, r23: Complex| -> Float {
+            let r = (r12 + r23 * phase) / (1.0 + r12 * r23 * phase);
+            r.norm_sqr()
+        };
+
+        let r12_per = (n1 * cos_1 - n2 * cos_2) / (n1 * cos_1 + n2 * cos_2);
+        let r23_per = (n2 * cos_2 - n3 * cos_3) / (n2 * cos_2 + n3 * cos_3);
+        let r12_par = (n2 * cos_1 - n1 * cos_2) / (n2 * cos_1 + n1 * cos_2);
+        let r23_par = (n3 * cos_2 - n2 * cos_3) / (n3 * cos_2 + n2 * cos_3);
+
+        let r = (airy(r12_per, r23_per) + airy(r12_par, r23_par)) / 2.0;
+        r.clamp(0.0, 1.0)
+    }
 }
 
 impl MicrofacetConfig {
     pub fn new(
-        roughness: Float,
+        roughness: Vec2,
         eta: DenseSpectrum,
         k: DenseSpectrum,
         kd: Texture,
         ks: Texture,
         tf: Texture,
     ) -> Self {
-        assert!((0.0..=1.0).contains(&roughness));
+        assert!((0.0..=1.0).contains(&roughness.x));
+        assert!((0.0..=1.0).contains(&roughness.y));
         // assert!(eta > 0.0);
         // assert!(k >= 0.0);
 
         Self {
-            roughness: Vec2::splat(roughness.max(1e-5)),
+            roughness: roughness.max(Vec2::splat(1e-5)),
+            roughness_map: None,
             eta,
+            eta_map: None,
             k,
             kd, ks, tf,
+            film: None,
         }
     }
 }
 
-/// Defines a distribution of normals for a microfacet. `Float` parameter is the
-/// roughness (α) of the surface.
+/// Defines a distribution of normals for a microfacet. Both distributions
+/// support anisotropic roughness, see [`MicrofacetConfig`].
 pub enum MfDistribution {
     /// Walter et al. 2007
     Ggx(MicrofacetConfig),
@@ -51,7 +132,7 @@ pub enum MfDistribution {
 
 impl MfDistribution {
     pub fn new(
-        roughness: Float,
+        roughness: Vec2,
         eta: DenseSpectrum,
         k: DenseSpectrum,
         kd: Texture,
@@ -68,34 +149,79 @@ impl MfDistribution {
         )
     }
 
+    /// Coat the surface with a thin film
+    pub fn with_film(mut self, film: ThinFilm) -> Self {
+        match &mut self {
+            Self::Ggx(cfg) | Self::Beckmann(cfg) => cfg.film = Some(film),
+        }
+        self
+    }
+
+    /// Scale roughness at each point by `roughness_map`
+    pub fn with_roughness_map(mut self, roughness_map: Texture) -> Self {
+        match &mut self {
+            Self::Ggx(cfg) | Self::Beckmann(cfg) => cfg.roughness_map = Some(roughness_map),
+        }
+        self
+    }
+
+    /// Read refraction index at each point from `eta_map`
+    pub fn with_eta_map(mut self, eta_map: Texture) -> Self {
+        match &mut self {
+            Self::Ggx(cfg) | Self::Beckmann(cfg) => cfg.eta_map = Some(eta_map),
+        }
+        self
+    }
+
+    /// Evaluate the textured parameters at `tc`
+    pub fn at(&self, lambda: &ColorWavelength, tc: &TexCoord) -> MfPoint<'_> {
+        let cfg = self.get_config();
+        let roughness = match &cfg.roughness_map {
+            None => cfg.roughness,
+            Some(map) => (cfg.roughness * map.value_at(lambda, tc))
+                .max(Vec2::splat(MIN_TEXTURED_ROUGHNESS))
+                .min(Vec2::ONE),
+        };
+        let eta = cfg.eta_map.as_ref().map(|map| map.value_at(lambda, tc));
+
+        MfPoint { mfd: self, roughness, eta }
+    }
+
+    /// Distribution with `roughness` at a point, ignores the roughness map
+    pub fn with_roughness(&self, roughness: Vec2) -> MfPoint<'_> {
+        MfPoint { mfd: self, roughness, eta: None }
+    }
+
     /// might need tuning, send ratio that emittance is multiplied with?
     #[inline]
     pub fn is_specular(&self) -> bool {
-        let roughness = self.roughness();
-        (roughness.x + roughness.y) / 2.0 < 0.01
+        let cfg = self.get_config();
+        let roughness = cfg.roughness;
+        cfg.roughness_map.is_none() && (roughness.x + roughness.y) / 2.0 < 0.01
     }
 
     /// Does the material have delta scattering distribution?
+    /// Materials with roughness maps never do.
     #[inline]
     pub fn is_delta(&self) -> bool {
-        let roughness = self.roughness();
-        (roughness.x + roughness.y) / 2.0 < 1e-3
+        let cfg = self.get_config();
+        let roughness = cfg.roughness;
+        cfg.roughness_map.is_none() && (roughness.x + roughness.y) / 2.0 < 1e-3
     }
 
+    /// Is the refraction index the same over all wavelengths?
     #[inline]
     pub fn constant_eta(&self) -> bool {
-        self.get_config().eta.is_constant()
-    }
-
-    /// Get refraction index from config
-    #[inline]
-    pub fn eta(&self, lambda: &ColorWavelength) -> Color {
-        self.get_config().eta.sample(lambda)
+        let cfg = self.get_config();
+        cfg.eta_map.is_some() || cfg.eta.is_constant()
     }
 
+    /// Does light at `wl` pass through without refracting, i.e. is the
+    /// refraction index one? Never for materials with refraction index maps.
     #[inline]
-    pub fn eta_at(&self, wl: Float) -> Float {
-        self.get_config().eta.sample_one(wl)
+    pub fn is_index_matched(&self, wl: Float) -> bool {
+        let cfg = self.get_config();
+        cfg.eta_map.is_none() && cfg.eta.sample_one(wl) == 1.0
     }
 
     /// Get absorption coefficient from config
@@ -109,28 +235,28 @@ impl MfDistribution {
         self.get_config().k.sample_one(wl)
     }
 
-    /// Get roughness from config
+    /// Get the thin film on top of the surface, if any
     #[inline]
-    pub fn roughness(&self) -> Vec2 {
-        self.get_config().roughness
+    pub fn film(&self) -> Option<&ThinFilm> {
+        self.get_config().film.as_ref()
     }
 
     /// Get Kd value at `h`
     #[inline]
-    pub fn kd(&self, lambda: &ColorWavelength, uv: Vec2) -> Color {
-        self.get_config().kd.albedo_at(lambda, uv)
+    pub fn kd(&self, lambda: &ColorWavelength, tc: &TexCoord) -> Color {
+        self.get_config().kd.albedo_at(lambda, tc)
     }
 
     /// Get Ks value at `h`
     #[inline]
-    pub fn ks(&self, lambda: &ColorWavelength, uv: Vec2) -> Color {
-        self.get_config().ks.albedo_at(lambda, uv)
+    pub fn ks(&self, lambda: &ColorWavelength, tc: &TexCoord) -> Color {
+        self.get_config().ks.albedo_at(lambda, tc)
     }
 
     /// Get Tf value at `h`
     #[inline]
-    pub fn tf(&self, lambda: &ColorWavelength, uv: Vec2) -> Color {
-        self.get_config().tf.albedo_at(lambda, uv)
+    pub fn tf(&self, lambda: &ColorWavelength, tc: &TexCoord) -> Color {
+        self.get_config().tf.albedo_at(lambda, tc)
     }
 
     /// Getter, better way to do this?
@@ -141,6 +267,139 @@ impl MfDistribution {
         }
     }
 
+    /// Schlicks approximation for Fresnel term
+    #[inline]
+    pub fn f_schlick(&self, f0: Float, f90: Float, cos_theta: Float) -> Float {
+        f0 + (f90 - f0) * (1.0 - cos_theta).powi(5)
+    }
+
+    #[inline]
+    fn fr_complex(&self, wo: Direction, wh: Normal, eta: Float, k: Float) -> Float {
+        // this is a complex number: n + ik
+        let eta = Complex::new(eta, k);
+        let cos_o = wo.dot(wh).clamp(0.0, 1.0);
+        let sin2_o = 1.0 - cos_o * cos_o;
+
+        let sin2_i: Complex = sin2_o / (eta * eta);
+        let cos_i: Complex = (1.0 - sin2_i).sqrt();
+
+        let r_par: Complex = (eta * cos_o - cos_i) / (eta * cos_o + cos_i);
+        let r_per: Complex = (cos_o - eta * cos_i) / (cos_o + eta * cos_i);
+
+        (r_par.norm_sqr() + r_per.norm_sqr()) / 2.0
+    }
+
+    #[inline]
+    fn fr_film(
+        &self,
+        wo: Direction,
+        wh: Normal,
+        eta: Float,
+        k: Float,
+        film: &ThinFilm,
+        wl: Float
+    ) -> Float {
+        let cos_o = wo.dot(wh);
+        // for dielectrics from inside the film sits between us and the outside
+        let (n1, n3) = if cos_o < 0.0 && k == 0.0 {
+            (eta, Complex::from(1.0))
+        } else {
+            (1.0, Complex::new(eta, k))
+        };
+
+        film.reflectance(cos_o.abs(), n1, n3, wl)
+    }
+
+    #[inline]
+    fn fr_real(&self, wo: Direction, wh: Normal, eta: Float) -> Float {
+        let cos_o = wo.dot(wh);
+        let inside = cos_o < 0.0;
+        let eta = if inside { 1.0 / eta } else { eta };
+
+        let cos_o = cos_o.abs();
+        let sin2_o = 1.0 - cos_o * cos_o;
+        let sin2_i = sin2_o / (eta * eta);
+
+        // total internal reflection
+        if sin2_i >= 1.0 {
+            return 1.0;
+        }
+
+        let cos_i = (1.0 - sin2_i).max(0.0).sqrt();
+
+        let r_par = (eta * cos_o - cos_i) / (eta * cos_o + cos_i);
+        let r_per = (cos_o - eta * cos_i) / (cos_o + eta * cos_i);
+
+        (r_par * r_par + r_per * r_per) / 2.0
+    }
+
+    #[inline]
+    fn chi_pass(&self, wo: Direction, wh: Normal) -> bool {
+        // signum to fix refraction
+        let cos_theta_wh = spherical_utils::cos_theta(wh);
+        let cos_theta_wo = spherical_utils::cos_theta(wo);
+        let chi = cos_theta_wh.signum() * wo.dot(wh) * cos_theta_wo;
+        chi > crate::EPSILON
+    }
+}
+
+/// Microfacet distribution with its textured parameters evaluated at a
+/// point. Dereferences to the distribution for the untextured parameters.
+pub struct MfPoint<'a> {
+    mfd: &'a MfDistribution,
+    /// Roughness at the point
+    roughness: Vec2,
+    /// Refraction index at the point, if given by a map
+    eta: Option<Float>,
+}
+
+impl Deref for MfPoint<'_> {
+    type Target = MfDistribution;
+
+    fn deref(&self) -> &MfDistribution {
+        self.mfd
+    }
+}
+
+impl MfPoint<'_> {
+    /// Get roughness at the point
+    #[inline]
+    pub fn roughness(&self) -> Vec2 {
+        self.roughness
+    }
+
+    /// Get refraction index at `wl`
+    #[inline]
+    pub fn eta_at(&self, wl: Float) -> Float {
+        self.eta.unwrap_or_else(|| self.get_config().eta.sample_one(wl))
+    }
+
+    /// Isotropic roughness that loses about as much energy as ours
+    #[inline]
+    fn isotropic_roughness(&self) -> Float {
+        (self.roughness.x * self.roughness.y).sqrt()
+    }
+
+    /// Directional albedo of single scattering from a perfect conductor
+    /// with our roughness, viewed from `cos_theta`
+    pub fn albedo(&self, cos_theta: Float) -> Float {
+        AlbedoTables::get(self.mfd).conductor(self.isotropic_roughness(), cos_theta)
+    }
+
+    /// Average albedo of single scattering from a perfect conductor
+    /// over the cosine weighted hemisphere
+    pub fn albedo_avg(&self) -> Float {
+        AlbedoTables::get(self.mfd).conductor_avg(self.isotropic_roughness())
+    }
+
+    /// Directional albedo of single scattering, reflection and transmission
+    /// together, from a non-absorbing dielectric at `wl` viewed from
+    /// `cos_theta`. Negative `cos_theta` views from inside.
+    pub fn dielectric_albedo(&self, cos_theta: Float, wl: Float) -> Float {
+        AlbedoTables::get(self.mfd)
+            .dielectric(self.isotropic_roughness(), cos_theta, self.eta_at(wl))
+    }
+
     /// Disney diffuse (Burley 2012) with renormalization to conserve energy
     /// as done in Frostbite (Lagarde et al. 2014)
     #[inline]
@@ -150,7 +409,8 @@ impl MfDistribution {
         cos_theta_wi: Float,
         cos_theta_wh: Float
     ) -> Float {
-        let roughness2 = self.roughness().x.powi(2);
+        let roughness = self.roughness();
+        let roughness2 = roughness.x * roughness.y;
         let energy_bias = 0.5 * roughness2;
         let fd90 = energy_bias + 2.0 * cos_theta_wh.powi(2) * roughness2;
 
@@ -168,11 +428,14 @@ impl MfDistribution {
     /// * Beckmann - exp(-tan^2(θ) / α^2) / (π * α^2 * cos^4(θ))
     /// * GGX - α^2 / (π * (cos^4(θ) * (α^2 - 1.0) + 1.0)^2)
     ///
+    /// For anisotropic roughness `1 / α^2` is replaced by
+    /// `cos^2(φ) / α_x^2 + sin^2(φ) / α_y^2`
+    ///
     /// # Arguments
     /// * `wh` - Microsurface normal in shading space
     pub fn d(&self, wh: Normal) -> Float {
-        match self {
-            Self::Ggx(cfg) => {
+        match self.mfd {
+            MfDistribution::Ggx(_) => {
                 let tan2_theta = spherical_utils::tan2_theta(wh);
 
                 if tan2_theta.is_infinite() {
@@ -185,38 +448,41 @@ impl MfDistribution {
                     let cos_phi = spherical_utils::cos_phi(wh);
                     let sin_phi = spherical_utils::sin_phi(wh);
 
-                    let alpha2 = cfg.roughness.x * cfg.roughness.y;
+                    let alpha2 = self.roughness.x * self.roughness.y;
                     let e = tan2_theta * (
-                        (cos_phi / cfg.roughness.x).powi(2)
-                            + (sin_phi / cfg.roughness.y).powi(2)
+                        (cos_phi / self.roughness.x).powi(2)
+                            + (sin_phi / self.roughness.y).powi(2)
                     );
 
                     1.0 / (crate::PI * alpha2 * cos4_theta * (1.0 + e).powi(2))
 
                 }
             }
-            Self::Beckmann(cfg) => {
+            MfDistribution::Beckmann(_) => {
                 let tan2_theta = spherical_utils::tan2_theta(wh);
 
                 if tan2_theta.is_infinite() {
                     0.0
                 } else {
-                    let roughness2 = cfg.roughness.x * cfg.roughness.x;
                     let cos4_theta = spherical_utils::cos2_theta(wh).powi(2);
+                    if cos4_theta < crate::EPSILON.powi(2) {
+                        return 0.0;
+                    }
+                    let cos_phi = spherical_utils::cos_phi(wh);
+                    let sin_phi = spherical_utils::sin_phi(wh);
 
-                    (-tan2_theta / roughness2).exp()
-                        / (crate::PI * roughness2 * cos4_theta)
+                    let alpha2 = self.roughness.x * self.roughness.y;
+                    let e = tan2_theta * (
+                        (cos_phi / self.roughness.x).powi(2)
+                            + (sin_phi / self.roughness.y).powi(2)
+                    );
+
+                    (-e).exp() / (crate::PI * alpha2 * cos4_theta)
                 }
             }
         }
     }
 
-    /// Schlicks approximation for Fresnel term
-    #[inline]
-    pub fn f_schlick(&self, f0: Float, f90: Float, cos_theta: Float) -> Float {
-        f0 + (f90 - f0) * (1.0 - cos_theta).powi(5)
-    }
-
     /// Fresnel term with the full equations
     /// # Arguments
     /// * `wo`      - Direction to viewer in shading space
@@ -231,7 +497,9 @@ impl MfDistribution {
     pub fn f_at(&self, wo: Direction, wh: Normal, wl: Float) -> Float {
         let eta = self.eta_at(wl);
         let k = self.k_at(wl);
-        if k == 0.0 {
+        if let Some(film) = self.film() {
+            self.fr_film(wo, wh, eta, k, film, wl)
+        } else if k == 0.0 {
             if eta == 0.0 {
                 0.0
             } else {
@@ -242,54 +510,6 @@ impl MfDistribution {
         }
     }
 
-    #[inline]
-    fn fr_complex(&self, wo: Direction, wh: Normal, eta: Float, k: Float) -> Float {
-        // this is a complex number: n + ik
-        let eta = Complex::new(eta, k);
-        let cos_o = wo.dot(wh).clamp(0.0, 1.0);
-        let sin2_o = 1.0 - cos_o * cos_o;
-
-        let sin2_i: Complex = sin2_o / (eta * eta);
-        let cos_i: Complex = (1.0 - sin2_i).sqrt();
-
-        let r_par: Complex = (eta * cos_o - cos_i) / (eta * cos_o + cos_i);
-        let r_per: Complex = (cos_o - eta * cos_i) / (cos_o + eta * cos_i);
-
-        (r_par.norm_sqr() + r_per.norm_sqr()) / 2.0
-    }
-
-    #[inline]
-    fn fr_real(&self, wo: Direction, wh: Normal, eta: Float) -> Float {
-        let cos_o = wo.dot(wh);
-        let inside = cos_o < 0.0;
-        let eta = if inside { 1.0 / eta } else { eta };
-
-        let cos_o = cos_o.abs();
-        let sin2_o = 1.0 - cos_o * cos_o;
-        let sin2_i = sin2_o / (eta * eta);
-
-        // total internal reflection
-        if sin2_i >= 1.0 {
-            return 1.0;
-        }
-
-        let cos_i = (1.0 - sin2_i).max(0.0).sqrt();
-
-        let r_par = (eta * cos_o - cos_i) / (eta * cos_o + cos_i);
-        let r_per = (cos_o - eta * cos_i) / (cos_o + eta * cos_i);
-
-        (r_par * r_par + r_per * r_per) / 2.0
-    }
-
-    #[inline]
-    fn chi_pass(&self, wo: Direction, wh: Normal) -> bool {
-        // signum to fix refraction
-        let cos_theta_wh = spherical_utils::cos_theta(wh);
-        let cos_theta_wo = spherical_utils::cos_theta(wo);
-        let chi = cos_theta_wh.signum() * wo.dot(wh) * cos_theta_wo;
-        chi > crate::EPSILON
-    }
-
     /// Shadow-masking term. Used to make sure that only microfacets that are
     /// visible from `v` direction are considered. Uses the method described
     /// in Chapter 8.4.3 of PBR due to Heitz et al. 2013.
@@ -322,8 +542,8 @@ impl MfDistribution {
     /// # Arguments
     /// * `w` - Direction to consider in shading space
     fn lambda(&self, w: Direction) -> Float {
-        match self {
-            Self::Ggx(cfg) => {
+        match self.mfd {
+            MfDistribution::Ggx(_) => {
                 let tan2_theta = spherical_utils::tan2_theta(w);
 
                 if tan2_theta.is_infinite() {
@@ -332,18 +552,23 @@ impl MfDistribution {
                     let cos_phi = spherical_utils::cos_phi(w);
                     let sin_phi = spherical_utils::sin_phi(w);
 
-                    let alpha2 = (cfg.roughness.x * cos_phi).powi(2)
-                        + (cfg.roughness.y * sin_phi).powi(2);
+                    let alpha2 = (self.roughness.x * cos_phi).powi(2)
+                        + (self.roughness.y * sin_phi).powi(2);
 
                     ((1.0 + alpha2 * tan2_theta).max(0.0).sqrt() - 1.0) / 2.0
                 }
             }
-            Self::Beckmann(cfg) => {
+            MfDistribution::Beckmann(_) => {
                 let tan2_theta = spherical_utils::tan2_theta(w);
                 if tan2_theta.is_infinite() {
                     0.0
                 } else {
-                    let a = 1.0 / (cfg.roughness.x * tan2_theta);
+                    let cos_phi = spherical_utils::cos_phi(w);
+                    let sin_phi = spherical_utils::sin_phi(w);
+
+                    let alpha = ((self.roughness.x * cos_phi).powi(2)
+                                 + (self.roughness.y * sin_phi).powi(2)).sqrt();
+                    let a = 1.0 / (alpha * tan2_theta.sqrt());
 
                     if a >= 1.6 {
                         0.0
@@ -363,12 +588,12 @@ impl MfDistribution {
         wh: Normal,
         wo: Direction,
     ) -> Float {
-        let pdf = match self {
-            Self::Beckmann(..) => {
+        let pdf = match self.mfd {
+            MfDistribution::Beckmann(..) => {
                 let cos_theta_wh = spherical_utils::cos_theta(wh);
                 self.d(wh) * cos_theta_wh
             }
-            Self::Ggx(..) => {
+            MfDistribution::Ggx(..) => {
                 let wh_dot_wo = wh.dot(wo);
                 let cos_theta_wo = spherical_utils::cos_theta(wo);
 
@@ -382,12 +607,12 @@ impl MfDistribution {
     /// Sampling microfacet normals per distribution for importance sampling.
     /// `wo` in shading space.
     pub fn sample_normal(&self, wo: Direction, rand_sq: Vec2) -> Normal {
-        match self {
-            Self::Ggx(cfg) => {
+        match self.mfd {
+            MfDistribution::Ggx(_) => {
                 // Heitz 2018 or
                 // https://schuttejoe.github.io/post/ggximportancesamplingpart2/
 
-                let roughness = cfg.roughness;
+                let roughness = self.roughness;
                 // Map the GGX ellipsoid to a hemisphere
                 let wo_stretch = Normal::new(
                     wo.x * roughness.x,
@@ -431,10 +656,24 @@ impl MfDistribution {
                     wm.z.max(crate::EPSILON)
                 ).normalize()
             }
-            Self::Beckmann(cfg) => {
-                let roughness2 = cfg.roughness.x * cfg.roughness.x;
-                let theta = (-roughness2 * (1.0 - rand_sq.y).ln()).sqrt().atan();
-                let phi = 2.0 * crate::PI * rand_sq.x;
+            MfDistribution::Beckmann(_) => {
+                let roughness = self.roughness;
+                let log_sample = (1.0 - rand_sq.y).ln();
+                let (tan2_theta, phi) = if roughness.x == roughness.y {
+                    let phi = 2.0 * crate::PI * rand_sq.x;
+                    (-roughness.x * roughness.x * log_sample, phi)
+                } else {
+                    // PBR 3rd ed. Chapter 8.4.2, sample phi proportional
+                    // to the elliptical distribution
+                    let phi = (roughness.y / roughness.x
+                               * (2.0 * crate::PI * rand_sq.x + 0.5 * crate::PI).tan())
+                        .atan();
+                    let phi = if rand_sq.x > 0.5 { phi + crate::PI } else { phi };
+                    let e = (phi.cos() / roughness.x).powi(2)
+                        + (phi.sin() / roughness.y).powi(2);
+                    (-log_sample / e, phi)
+                };
+                let theta = tan2_theta.sqrt().atan();
 
                 // already normalized?
                 Normal::new(
@@ -0,0 +1,216 @@
+use super::*;
+use std::sync::OnceLock;
+
+/// Resolution of the tables along the cosine and the roughness. The cosine
+/// nodes are spaced uniformly in its square root, as the albedos change
+/// quickly at grazing angles.
+const RES: usize = 32;
+/// Resolution of the dielectric tables along the refraction index
+const ETA_RES: usize = 16;
+/// Refraction indices of the dielectric tables span `1..ETA_MAX`
+const ETA_MAX: Float = 3.0;
+/// Square root of the number of stratified samples per conductor table entry.
+/// The compensation lobe of conductors adds energy if the albedo falls short.
+const CONDUCTOR_STRATA: usize = 32;
+/// Square root of the number of stratified samples per dielectric table entry
+const DIELECTRIC_STRATA: usize = 16;
+/// Smallest roughness in the tables, keeps the sampling stable
+const TABLE_MIN: Float = 1e-3;
+/// Smallest cosine in the tables
+const COS_MIN: Float = 1e-6;
+
+static GGX_TABLES: OnceLock<AlbedoTables> = OnceLock::new();
+static BECKMANN_TABLES: OnceLock<AlbedoTables> = OnceLock::new();
+
+/// Precomputed albedos of single scattering from the microsurface of one
+/// distribution. Used to compensate for the energy lost to the multiple
+/// bounces between the microfacets that the models ignore.
+pub struct AlbedoTables {
+    /// Directional albedo of a perfect conductor by roughness and cosine
+    conductor: Vec<Float>,
+    /// Cosine weighted average of `conductor` over the hemisphere by roughness
+    conductor_avg: Vec<Float>,
+    /// Directional albedo of a non-absorbing dielectric by the side of the
+    /// surface, refraction index, roughness and cosine
+    dielectric: Vec<Float>,
+}
+
+impl AlbedoTables {
+    /// Tables of the distribution of `mfd`, computed on first use
+    pub fn get(mfd: &MfDistribution) -> &'static Self {
+        match mfd {
+            MfDistribution::Ggx(_) => GGX_TABLES.get_or_init(|| Self::new(false)),
+            MfDistribution::Beckmann(_) => BECKMANN_TABLES.get_or_init(|| Self::new(true)),
+        }
+    }
+
+    fn new(beckmann: bool) -> Self {
+        let cfg = MicrofacetConfig::new(
+            Vec2::ONE,
+            DenseSpectrum::from_constant(1.5),
+            DenseSpectrum::from_constant(0.0),
+            Texture::default(),
+            Texture::default(),
+            Texture::default(),
+        );
+        let mfd = if beckmann { MfDistribution::Beckmann(cfg) } else { MfDistribution::Ggx(cfg) };
+
+        let node = |i: usize, res: usize| (i as Float / (res - 1) as Float).max(TABLE_MIN);
+        let sqrt_mu = |i: usize| i as Float / (RES - 1) as Float;
+        let wo_at = |i: usize| {
+            let cos_theta = (sqrt_mu(i) * sqrt_mu(i)).max(COS_MIN);
+            Direction::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta)
+        };
+
+        let mut conductor = Vec::with_capacity(RES * RES);
+        let mut conductor_avg = Vec::with_capacity(RES);
+        for j in 0..RES {
+            let mfp = mfd.with_roughness(Vec2::splat(node(j, RES)));
+            let row: Vec<Float> = (0..RES)
+                .map(|i| single_scattering(&mfp, wo_at(i), None, CONDUCTOR_STRATA))
+                .collect();
+            // 2 * int_0^1 E(μ) μ dμ = 4 * int_0^1 E(s) s^3 ds with s = sqrt(μ),
+            // exact for E interpolated linearly in s. Then the compensation
+            // lobe integrates to exactly 1 - E(wo).
+            let avg = (1..RES)
+                .map(|i| {
+                    let (s0, s1) = (sqrt_mu(i - 1), sqrt_mu(i));
+                    let a = (s1.powi(4) - s0.powi(4)) / 4.0;
+                    let b = (s1.powi(5) - s0.powi(5)) / 5.0 - s0 * a;
+                    4.0 * (row[i - 1] * a + (row[i] - row[i - 1]) / (s1 - s0) * b)
+                })
+                .sum::<Float>();
+            conductor_avg.push(avg.min(1.0));
+            conductor.extend(row);
+        }
+
+        let mut dielectric = Vec::with_capacity(2 * ETA_RES * RES * RES);
+        for inside in [false, true] {
+            for k in 0..ETA_RES {
+                let eta = 1.0 + node(k, ETA_RES) * (ETA_MAX - 1.0);
+                for j in 0..RES {
+                    let mfp = mfd.with_roughness(Vec2::splat(node(j, RES)));
+                    for i in 0..RES {
+                        let wo = wo_at(i);
+                        let wo = if inside { Direction::new(wo.x, wo.y, -wo.z) } else { wo };
+                        dielectric.push(
+                            single_scattering(&mfp, wo, Some(eta), DIELECTRIC_STRATA)
+                        );
+                    }
+                }
+            }
+        }
+
+        Self { conductor, conductor_avg, dielectric }
+    }
+
+    /// Directional albedo of a perfect conductor
+    pub fn conductor(&self, roughness: Float, cos_theta: Float) -> Float {
+        lerp2(&self.conductor, roughness, cos_theta.abs())
+    }
+
+    /// Average albedo of a perfect conductor
+    pub fn conductor_avg(&self, roughness: Float) -> Float {
+        let (j, t) = lerp_index(roughness, RES);
+        (1.0 - t) * self.conductor_avg[j] + t * self.conductor_avg[j + 1]
+    }
+
+    /// Directional albedo of a non-absorbing dielectric with refraction
+    /// index `eta`. Negative `cos_theta` means that we are inside.
+    pub fn dielectric(&self, roughness: Float, cos_theta: Float, eta: Float) -> Float {
+        let side = if cos_theta < 0.0 { ETA_RES } else { 0 };
+        let (k, t) = lerp_index((eta - 1.0) / (ETA_MAX - 1.0), ETA_RES);
+        let table = |k: usize| {
+            let start = (side + k) * RES * RES;
+            lerp2(&self.dielectric[start..start + RES * RES], roughness, cos_theta.abs())
+        };
+        (1.0 - t) * table(k) + t * table(k + 1)
+    }
+}
+
+/// Index of the node below `x` in `\[0,1\]` and the distance from it
+fn lerp_index(x: Float, res: usize) -> (usize, Float) {
+    let x = x.clamp(0.0, 1.0) * (res - 1) as Float;
+    let i = (x as usize).min(res - 2);
+    (i, x - i as Float)
+}
+
+/// Bilinear interpolation of a `RES x RES` table by roughness and the square
+/// root of cosine
+fn lerp2(table: &[Float], roughness: Float, cos_theta: Float) -> Float {
+    let (j, tj) = lerp_index(roughness, RES);
+    let (i, ti) = lerp_index(cos_theta.sqrt(), RES);
+    let at = |j: usize, i: usize| table[j * RES + i];
+
+    (1.0 - tj) * ((1.0 - ti) * at(j, i) + ti * at(j, i + 1))
+        + tj * ((1.0 - ti) * at(j + 1, i) + ti * at(j + 1, i + 1))
+}
+
+/// Estimate the directional albedo at `wo` by sampling microfacet normals.
+/// Perfect conductor if `eta` is `None`, otherwise both reflection and
+/// transmission of a dielectric with refraction index `eta`. Uses
+/// `strata x strata` stratified samples.
+fn single_scattering(
+    mfp: &MfPoint,
+    wo: Direction,
+    eta: Option<Float>,
+    strata: usize,
+) -> Float {
+    let cos_theta_wo = spherical_utils::cos_theta(wo).abs();
+    let mut sum = 0.0;
+
+    for i in 0..strata {
+        for j in 0..strata {
+            let rand_sq = Vec2::new(
+                (i as Float + 0.5) / strata as Float,
+                (j as Float + 0.5) / strata as Float,
+            );
+            let wh = mfp.sample_normal(wo, rand_sq);
+            let wh = if spherical_utils::cos_theta(wh) < 0.0 { -wh } else { wh };
+            let pdf = mfp.sample_normal_pdf(wh, wo);
+            if pdf <= 0.0 {
+                continue;
+            }
+
+            // f * cos / pdf for the direction scattered from `wh`
+            let weight = |wi: Direction| {
+                mfp.d(wh) * mfp.g(wo, wi, wh) * wo.dot(wh).abs() / (cos_theta_wo * pdf)
+            };
+
+            let wi = 2.0 * wo.project_onto(wh) - wo;
+            let reflects = spherical_utils::same_hemisphere(wi, wo);
+            match eta {
+                None => if reflects { sum += weight(wi) },
+                Some(eta) => {
+                    let fr = mfp.fr_real(wo, wh, eta);
+                    if reflects {
+                        sum += fr * weight(wi);
+                    }
+                    if let Some(wi) = refract(wo, wh, eta) {
+                        sum += (1.0 - fr) * weight(wi);
+                    }
+                }
+            }
+        }
+    }
+
+    (sum / (strata * strata) as Float).clamp(0.0, 1.0)
+}
+
+/// Direction refracted from `wo` through microfacet `wh`, `None` on total
+/// internal reflection or if it stays on the same side
+fn refract(wo: Direction, wh: Normal, eta: Float) -> Option<Direction> {
+    let (cos_o, eta, n) = if wh.dot(wo) < 0.0 {
+        (-wh.dot(wo), 1.0 / eta, -wh)
+    } else {
+        (wh.dot(wo), eta, wh)
+    };
+    let sin2_i = (1.0 - cos_o * cos_o) / (eta * eta);
+    if sin2_i >= 1.0 {
+        return None;
+    }
+
+    let cos_i = (1.0 - sin2_i).sqrt();
+    let wi = -wo / eta + (cos_o / eta - cos_i) * n;
+    if spherical_utils::same_hemisphere(wi, wo) { None } else { Some(wi) }
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+const TOLERANCE: Float = 1e-4;
+
+fn dielectric(eta: Float) -> MfDistribution {
+    MfDistribution::new(
+        Vec2::ZERO,
+        DenseSpectrum::from_constant(eta),
+        DenseSpectrum::from_constant(0.0),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+    )
+}
+
+fn directions() -> Vec<Direction> {
+    (1..10).map(|i| {
+        let cos_theta = i as Float / 10.0;
+        Direction::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta)
+    }).collect()
+}
+
+#[test]
+fn zero_thickness_is_fresnel() {
+    let plain = dielectric(1.5);
+    let film = dielectric(1.5).with_film(ThinFilm::new(0.0, 1.33));
+
+    let (plain, film) = (plain.with_roughness(Vec2::ZERO), film.with_roughness(Vec2::ZERO));
+    for wo in directions() {
+        let diff = plain.f_at(wo, Normal::Z, 550.0) - film.f_at(wo, Normal::Z, 550.0);
+        assert!(diff.abs() < TOLERANCE);
+    }
+}
+
+#[test]
+fn index_matched_film_is_fresnel() {
+    let plain = dielectric(1.5);
+    let film = dielectric(1.5).with_film(ThinFilm::new(300.0, 1.5));
+
+    let (plain, film) = (plain.with_roughness(Vec2::ZERO), film.with_roughness(Vec2::ZERO));
+    for wo in directions() {
+        for wl in [400.0, 550.0, 700.0] {
+            let diff = plain.f_at(wo, Normal::Z, wl) - film.f_at(wo, Normal::Z, wl);
+            assert!(diff.abs() < TOLERANCE);
+        }
+    }
+}
+
+#[test]
+fn film_is_iridescent() {
+    let film = ThinFilm::new(400.0, 1.33);
+    let r = [400.0, 475.0, 550.0, 625.0, 700.0].map(|wl| {
+        let r = film.reflectance(1.0, 1.0, Complex::from(1.0), wl);
+        assert!((0.0..=1.0).contains(&r));
+        r
+    });
+
+    let min = r.iter().fold(crate::INF, |acc, r| acc.min(*r));
+    let max = r.iter().fold(0.0, |acc: Float, r| acc.max(*r));
+    assert!(max - min > 0.05);
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+fn mfd(roughness: Vec2) -> MfDistribution {
+    MfDistribution::new(
+        roughness,
+        DenseSpectrum::from_constant(1.5),
+        DenseSpectrum::from_constant(0.0),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+        Texture::from(crate::tracer::Spectrum::WHITE),
+    )
+}
+
+#[test]
+fn roughness_map_scales() {
+    let lambda = ColorWavelength::sample(0.5);
+    let tc = TexCoord::from_uv(Vec2::new(0.25, 0.0));
+    let mfd = mfd(Vec2::new(0.4, 0.8)).with_roughness_map(Texture::Gradient(Vec2::X));
+
+    let roughness = mfd.at(&lambda, &tc).roughness();
+    assert!(roughness.x == 0.1 && roughness.y == 0.2);
+}
+
+#[test]
+fn roughness_map_is_never_delta() {
+    let lambda = ColorWavelength::sample(0.5);
+    let tc = TexCoord::from_uv(Vec2::ZERO);
+    let mfd = mfd(Vec2::splat(0.5)).with_roughness_map(Texture::Gradient(Vec2::X));
+
+    assert!(!mfd.is_delta());
+    let roughness = mfd.at(&lambda, &tc).roughness();
+    assert!((roughness.x + roughness.y) / 2.0 >= 1e-3);
+}
+
+#[test]
+fn eta_map_replaces() {
+    let lambda = ColorWavelength::sample(0.5);
+    let tc = TexCoord::from_uv(Vec2::new(0.5, 0.0));
+    let eta_map = Texture::Gradient(Vec2::X).remap(Vec2::Y, Vec2::new(1.0, 2.0));
+    let mfd = mfd(Vec2::splat(0.5)).with_eta_map(eta_map);
+
+    assert!(mfd.constant_eta());
+    assert!(!mfd.is_index_matched(lambda.leading_sample()));
+    let mfd = mfd.at(&lambda, &tc);
+    for wl in [400.0, 550.0, 700.0] {
+        assert!((mfd.eta_at(wl) - 1.5).abs() < 1e-10);
+    }
+}
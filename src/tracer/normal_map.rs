@@ -0,0 +1,119 @@
+use crate::{ Direction, Float, Image, Normal, Vec2 };
+use crate::tracer::onb::Onb;
+use std::io::{self, Read};
+
+/// Perturbs the shading normal of a surface
+pub enum NormalMap {
+    /// Normals in tangent space. `x` goes along the surface tangent,
+    /// `y` along the bitangent and `z` along the shading normal.
+    Tangent(Image<Normal>),
+    /// Height field. Slopes of the heights tilt the normal, scaled by the float.
+    Bump(Image<Float>, Float),
+}
+
+impl NormalMap {
+    /// Parse a normal map from file. Grayscale images are treated as height
+    /// fields with slopes scaled by `scale` and colored images as tangent
+    /// space normals.
+    pub fn from_file<R: Read>(read: R, scale: Float) -> io::Result<Self> {
+        let pixels = Image::<Normal>::decode(read)?;
+        let is_grayscale = pixels.rgba.iter().all(|px| px[0] == px[1] && px[1] == px[2]);
+
+        if is_grayscale {
+            Ok(Self::Bump(Image::heights_from_pixels(&pixels), scale))
+        } else {
+            Ok(Self::Tangent(Image::normals_from_pixels(&pixels)))
+        }
+    }
+
+    /// Perturbed shading normal at `uv` of a surface with shading normal `ns`.
+    /// `tangent` orients the tangent space along increasing `u`, without it
+    /// the space is arbitrary.
+    pub fn perturb(&self, ns: Normal, tangent: Option<Direction>, uv: Vec2) -> Normal {
+        let uvw = match tangent {
+            Some(tangent) => Onb::new_with_tangent(ns, tangent),
+            None => Onb::new(ns),
+        };
+
+        let ns_local = match self {
+            Self::Tangent(img) => img.value_at(uv),
+            Self::Bump(img, scale) => {
+                let du = 1.0 / img.width as Float;
+                let dv = 1.0 / img.height as Float;
+
+                // central differences over one texel, slopes per unit of
+                // uv so that the strength does not depend on the resolution
+                let dhdu = (img.value_at(uv + Vec2::new(du, 0.0))
+                            - img.value_at(uv - Vec2::new(du, 0.0))) / (2.0 * du);
+                let dhdv = (img.value_at(uv + Vec2::new(0.0, dv))
+                            - img.value_at(uv - Vec2::new(0.0, dv))) / (2.0 * dv);
+
+                Normal::new(-scale * dhdu, -scale * dhdv, 1.0)
+            }
+        };
+
+        let ns_mapped = uvw.to_world(ns_local);
+        if ns_mapped.length_squared() == 0.0 {
+            ns
+        } else {
+            ns_mapped.normalize()
+        }
+    }
+}
+
+#[cfg(test)]
+mod normal_map_tests {
+    use super::*;
+
+    fn image<T: Clone>(buffer: Vec<T>, width: u32, mean: T) -> Image<T> {
+        let height = buffer.len() as u32 / width;
+        Image::new(buffer, width, height, mean)
+    }
+
+    #[test]
+    fn flat_tangent_map_keeps_normal() {
+        let map = NormalMap::Tangent(image(vec![Normal::Z; 4], 2, Normal::Z));
+        let ns = Normal::new(1.0, 2.0, 3.0).normalize();
+        let tangent = Some(Direction::new(3.0, 0.0, -1.0));
+
+        let ns_mapped = map.perturb(ns, tangent, Vec2::new(0.3, 0.6));
+        assert!(ns_mapped.distance(ns) < 1e-10);
+    }
+
+    #[test]
+    fn tangent_map_follows_tangent() {
+        let n = Normal::new(1.0, 0.0, 1.0).normalize();
+        let map = NormalMap::Tangent(image(vec![n; 4], 2, n));
+
+        let ns_mapped = map.perturb(Normal::Y, Some(Direction::Z), Vec2::new(0.5, 0.5));
+        assert!(ns_mapped.distance(Normal::new(0.0, 1.0, 1.0).normalize()) < 1e-10);
+    }
+
+    #[test]
+    fn bump_independent_of_resolution() {
+        // same height ramp along u sampled at two resolutions
+        let ramp = |width: usize| -> NormalMap {
+            let heights = (0..width * width)
+                .map(|i| (i % width) as Float / width as Float)
+                .collect();
+            NormalMap::Bump(image(heights, width as u32, 0.5), 0.1)
+        };
+        let uv = Vec2::new(0.5, 0.5);
+
+        let coarse = ramp(8).perturb(Normal::Z, Some(Direction::X), uv);
+        let fine = ramp(64).perturb(Normal::Z, Some(Direction::X), uv);
+        assert!(coarse.distance(fine) < 1e-10);
+        assert!(coarse.distance(Normal::new(-0.1, 0.0, 1.0).normalize()) < 1e-10);
+    }
+
+    #[test]
+    fn bump_tilts_against_slope() {
+        // heights grow along u
+        let heights = (0..16).map(|i| (i % 4) as Float / 4.0).collect();
+        let map = NormalMap::Bump(image(heights, 4, 0.5), 1.0);
+
+        let ns_mapped = map.perturb(Normal::Z, Some(Direction::X), Vec2::new(0.5, 0.5));
+        assert!(ns_mapped.x < 0.0);
+        assert!(ns_mapped.y.abs() < 1e-10);
+    }
+}
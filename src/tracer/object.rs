@@ -13,17 +13,23 @@ use std::sync::Arc;
 use crate::rng::Xorshift;
 
 pub use aabb::AaBoundingBox;
-pub use bvh::BVH;
+pub use bvh::{BVH, BVHBuild, BVHPrimitive, CurveBVH, MeshBVH};
 pub use cone::Cone;
 pub use cube::Cube;
+pub use curve::{Curve, CurveBasis, CurveType};
 pub use cylinder::Cylinder;
 pub use disk::Disk;
 pub use instance::{Instance, Instanceable};
 pub use kdtree::{KdTree, Mesh};
+pub(crate) use kdtree::CachedKdTree;
 pub use rectangle::Rectangle;
 pub use sphere::Sphere;
+pub use stats::AccelStats;
 pub use triangle::Triangle;
-pub use triangle_mesh::{TriangleMesh, Face};
+pub(crate) use triangle::TriangleIndices;
+pub use triangle_mesh::{
+    Displacement, Subdivision, SubdivisionScheme, TriangleMesh, Face, MeshAccel
+};
 
 /// Axis aligned bounding boxes
 mod aabb;
@@ -33,6 +39,8 @@ mod bvh;
 mod cone;
 /// Defines a unit cube. Transform to desired shape with instances.
 mod cube;
+/// Cubic Bézier curves for hair and fur
+mod curve;
 /// Defines y axis aligned cylinders
 mod cylinder;
 /// Defines disks
@@ -46,6 +54,8 @@ mod kdtree;
 mod rectangle;
 /// Defines spheres.
 mod sphere;
+/// Build time and quality of acceleration structures
+mod stats;
 /// Defines triangles.
 mod triangle;
 /// Triangle meshes, stores vertices, normals and texture coordinates to save space
@@ -102,10 +112,12 @@ pub trait Sampleable: Object {
     /// Material of the sampleable object
     fn material(&self) -> &Material;
 
-    /// Samples a ray leaving at random point on the surface of the object.
-    /// Direction cos weighed on the hemisphere. Returns also normal at ray origin
-    fn sample_leaving(&self, rand_sq0: Vec2, rand_sq1: Vec2) -> (Ray, Hit) {
-        let ho = self.sample_on(rand_sq0);
+    /// Samples a ray leaving at random point on the surface of the object
+    /// at `time`. Direction cos weighed on the hemisphere. Returns also
+    /// normal at ray origin
+    fn sample_leaving(&self, time: Float, rand_sq0: Vec2, rand_sq1: Vec2) -> (Ray, Hit) {
+        let mut ho = self.sample_on(time, rand_sq0);
+        ho.time = time;
         let ns = ho.ns;
         let uvw = Onb::new(ns);
         let wi_local = rng::maps::square_to_cos_hemisphere(rand_sq1);
@@ -126,17 +138,18 @@ pub trait Sampleable: Object {
         (pdf_origin, pdf_dir)
     }
 
-    /// Returns randomly sampled point on the surface of the object
-    fn sample_on(&self, rand_sq: Vec2) -> Hit;
+    /// Returns randomly sampled point on the surface of the object at `time`
+    fn sample_on(&self, time: Float, rand_sq: Vec2) -> Hit;
 
     /// Sample random direction from `xo` towards area of object
     /// that is visible form `xo`
     ///
     /// # Arguments
     /// * `xo` - Point on the "from" object
+    /// * `time` - Time of the ray from `xo`
     /// * `rand_sq` - Uniformly random point on unit square
-    fn sample_towards(&self, xo: Point, rand_sq: Vec2) -> Direction {
-        let xi = self.sample_on(rand_sq).p;
+    fn sample_towards(&self, xo: Point, time: Float, rand_sq: Vec2) -> Direction {
+        let xi = self.sample_on(time, rand_sq).p;
         (xi - xo).normalize()
     }
 
@@ -144,7 +157,7 @@ pub trait Sampleable: Object {
     /// with respect to SA, not guaranteed to check that `ri` hits `self`
     ///
     /// # Arguments
-    /// * `ri` - Sampled ray from `xo` to `xi`
+    /// * `ri` - Sampled ray from `xo` to `xi` at the time of sampling
     /// * `xi` - Point on `self`
     fn sample_towards_pdf(&self, ri: &Ray, xi: Point, ng: Normal) -> Float {
         let p_area = 1.0 / self.area();
@@ -1,11 +1,15 @@
 use super::*;
-use std::time::Instant;
+use std::time::{ Duration, Instant };
 use std::collections::VecDeque;
 use node::BVHNode;
+use wide::{ WideLeaf, WideNode, WideRay, LEAF_BIT };
 use crate::formatting;
 use crate::tracer::ColorWavelength;
 
 mod node;
+mod wide;
+#[cfg(test)]
+mod bvh_tests;
 
 const MAX_LEAF_SIZE: usize = 4;
 const IDX_NAN: usize = usize::MAX;
@@ -13,26 +17,111 @@ const MORTON_ORDER: usize = 10;
 const MORTON_MAX: u64 = 1 << MORTON_ORDER;
 const MORTON_BITS: usize = MORTON_ORDER * 3;
 const SAH_MAX_DEPTH: usize = MORTON_BITS / 2;
+const STACK_SIZE: usize = 192;
+// wide nodes push at most three extra per two binary levels,
+// deeper builds could overflow the traversal stack
+const MAX_DEPTH: usize = 2 * ((STACK_SIZE - 1) / 3);
 
 const SAMPLE_POWER: bool = true;
 
-/// Bounding volume hierarchy to accelerate scenes with multiple objects
-pub struct BVH<T> {
+/// Triangle mesh constructed as a BVH
+pub type MeshBVH = BVH<Triangle>;
+
+/// Curves, e.g. strands of hair, constructed as a BVH
+pub type CurveBVH = BVH<Curve>;
+
+/// Algorithm used to split the nodes of a BVH
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BVHBuild {
+    /// SAH swept over sorted objects near the root and splits along
+    /// Morton codes of the object centers below, Garanzha et al. 2011
+    #[default]
+    Hlbvh,
+    /// SAH evaluated for object centers gathered to bins, Wald 2007.
+    /// Faster to build for large meshes.
+    BinnedSah,
+}
+
+/// Primitive stored in a BVH. Lets scenes store boxed objects and
+/// meshes store their triangles without an extra indirection.
+pub trait BVHPrimitive: Sync + Send + Sized {
+    /// Type of the stored object
+    type Object: Object + ?Sized;
+    /// Data precomputed for the objects of a leaf
+    type Leaf: Sync + Send;
+
+    /// The stored object
+    fn as_object(&self) -> &Self::Object;
+
+    /// Precompute data to intersect the `objects` of a leaf
+    fn leaf(objects: &[Self]) -> Self::Leaf;
+
+    /// Closest hit, or any hit if not `GEO`, of `r` with the `objects` of
+    /// a leaf in `(t_min, t_max)`. Returns index to `objects` and distance
+    fn hit_leaf<const GEO: bool>(
+        _leaf: &Self::Leaf,
+        objects: &[Self],
+        r: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Float)> {
+        hit_objects::<GEO, Self>(objects, r, t_min, t_max)
+    }
+}
+
+impl<T: ?Sized + Object> BVHPrimitive for Box<T> {
+    type Object = T;
+    type Leaf = ();
+
+    fn as_object(&self) -> &T { self.as_ref() }
+
+    fn leaf(_objects: &[Self]) -> Self::Leaf {}
+}
+
+/// Test `objects` one by one
+pub fn hit_objects<const GEO: bool, T: BVHPrimitive>(
+    objects: &[T],
+    r: &Ray,
+    t_min: Float,
+    t_max: Float,
+) -> Option<(usize, Float)> {
+    let mut closest = None;
+    let mut tt = t_max;
+    for (i, object) in objects.iter().enumerate() {
+        let t = object.as_object().hit_t(r, t_min, tt);
+        if t < tt {
+            if !GEO { return Some((i, t)); }
+            tt = t;
+            closest = Some((i, t));
+        }
+    }
+    closest
+}
+
+/// Bounding volume hierarchy to accelerate scenes with multiple objects.
+/// Built as a binary tree and collapsed to nodes with four children for traversal.
+pub struct BVH<T: BVHPrimitive> {
     objects: Vec<T>,
-    nodes: Vec<BVHNode>,
+    nodes: Vec<WideNode>,
+    leaves: Vec<WideLeaf<T::Leaf>>,
     boundary: AaBoundingBox,
     num_primitives: usize,
+    build: BVHBuild,
+    stats: AccelStats,
     alias_table: Vec<(Float, usize)>,
     alias_pdf: Vec<Float>,
 }
 
-impl<T> Default for BVH<T> {
+impl<T: BVHPrimitive> Default for BVH<T> {
     fn default() -> Self {
         BVH {
             objects: vec!(),
             nodes: vec!(),
+            leaves: vec!(),
             num_primitives: 0,
             boundary: AaBoundingBox::default(),
+            build: BVHBuild::default(),
+            stats: AccelStats::default(),
             alias_table: vec!(),
             alias_pdf: vec!(),
         }
@@ -42,7 +131,9 @@ impl<T> Default for BVH<T> {
 impl BVH<Box<dyn Object>> {
     /// Build the BVH
     pub fn build(&mut self) {
+        println!("Building BVH with {} objects", self.objects.len());
         self._build();
+        println!("Built BVH in {}", formatting::fmt_elapsed(self.stats.build_time));
     }
 }
 
@@ -96,14 +187,16 @@ impl BVH<Box<dyn Sampleable>> {
     /// Get option wrapped index to light at hit `h`
     pub fn get_light_at(&self, h: &Hit) -> Option<usize> {
         let xo = h.ray_origin(true);
-        let ri = Ray::new(xo, -h.ng);
+        let ri = Ray::new(xo, -h.ng).with_time(h.time);
         // check distance?
-        self._hit::<true>(&ri, 0.0, crate::INF)
+        self._hit::<true>(&ri, 0.0, crate::INF).map(|(idx, _)| idx)
     }
 
     /// Build the BVH and light power sampling data structures
     pub fn build(&mut self) {
+        println!("Building BVH with {} objects", self.objects.len());
         self._build();
+        println!("Built BVH in {}", formatting::fmt_elapsed(self.stats.build_time));
 
         if !SAMPLE_POWER { return; }
 
@@ -191,11 +284,31 @@ impl BVH<Box<dyn Sampleable>> {
     }
 }
 
-impl<T: ?Sized + Object> BVH<Box<T>> {
+impl<T: BVHPrimitive> BVH<T> {
+    /// Constructs a BVH of `objects` split with `build`
+    pub fn new(objects: Vec<T>, build: BVHBuild) -> Self {
+        let mut bvh = Self::default().with_build(build);
+        if objects.len() > 10_000 {
+            println!("Creating {:?} BVH of {} objects", build, objects.len());
+        }
+        objects.into_iter().for_each(|object| bvh.add(object));
+        bvh._build();
+        if bvh.objects.len() > 10_000 {
+            println!("Created BVH in {}", formatting::fmt_elapsed(bvh.stats.build_time));
+        }
+        bvh
+    }
+
+    /// Split the nodes with `build` once the BVH gets built
+    pub fn with_build(mut self, build: BVHBuild) -> Self {
+        self.build = build;
+        self
+    }
+
     /// Add a object to the BVH
-    pub fn add(&mut self, object: Box<T>) {
-        self.boundary = self.boundary.merge(&object.bounding_box());
-        self.num_primitives += object.num_primitives();
+    pub fn add(&mut self, object: T) {
+        self.boundary = self.boundary.merge(&object.as_object().bounding_box());
+        self.num_primitives += object.as_object().num_primitives();
         self.objects.push(object)
     }
 
@@ -205,6 +318,19 @@ impl<T: ?Sized + Object> BVH<Box<T>> {
     /// Return the number of objects in the BVH tree
     pub fn num_objects(&self) -> usize { self.objects.len() }
 
+    /// Statistics of the latest build
+    pub fn stats(&self) -> &AccelStats { &self.stats }
+
+    /// Returns self uniformly scaled as an instance with largest dimension
+    /// of bounding box scaled to 1.0
+    pub fn to_unit_size(self) -> Box<Instance<Self>> {
+        let AaBoundingBox { ax_min, ax_max } = self.bounding_box();
+
+        let bb_dim = ax_max - ax_min;
+        let s = 1.0 / bb_dim.max_element();
+        self.scale_uniform(s)
+    }
+
     fn morton_code(&self, center: Point) -> u64 {
         let diff = center - self.boundary.ax_min;
         let dim = self.boundary.ax_max - self.boundary.ax_min;
@@ -228,17 +354,19 @@ impl<T: ?Sized + Object> BVH<Box<T>> {
     }
 
     /// Build the BVH
-    // Garanzha et al. 2011
     fn _build(&mut self) {
         assert!(!self.objects.is_empty());
-        println!("Building BVH with {} objects", self.objects.len());
         let start = Instant::now();
+        let mut nodes: Vec<BVHNode> = vec!();
 
+        let bounds: Vec<AaBoundingBox> = self.objects.iter()
+            .map(|obj| obj.as_object().bounding_box())
+            .collect();
+
+        // sorted by the codes for both builds to keep close objects close
         let mut codes = Vec::with_capacity(self.objects.len());
-        for i in 0..self.objects.len() {
-            let obj = &self.objects[i];
-            let center = obj.bounding_box().center();
-            let code = self.morton_code(center);
+        for (i, bb) in bounds.iter().enumerate() {
+            let code = self.morton_code(bb.center());
             codes.push((code, i));
         }
 
@@ -255,18 +383,18 @@ impl<T: ?Sized + Object> BVH<Box<T>> {
 
         while let Some((node, idx, is_left, depth)) = que.pop_front() {
             // insert left nodes right after parent and right nodes to the end for cache
-            self.nodes.push(node);
-            let pos = self.nodes.len() - 1;
+            nodes.push(node);
+            let pos = nodes.len() - 1;
 
             // update parent pointers
             if idx != IDX_NAN && !is_left {
-                self.nodes[idx].right = pos;
+                nodes[idx].right = pos;
             }
 
             // create splits
-            let Some((left, right)) = self.nodes[pos].split(&self.objects, depth) else {
+            let Some((left, right)) = nodes[pos].split(&bounds, depth, self.build) else {
                 // "mark" it as a leaf
-                self.nodes[pos].codes.clear();
+                nodes[pos].codes.clear();
                 continue
             };
 
@@ -279,37 +407,72 @@ impl<T: ?Sized + Object> BVH<Box<T>> {
         }
 
         // update bounding boxes and remove objects from non-leaf nodes
-        for i in (0..self.nodes.len()).rev() {
+        for i in (0..nodes.len()).rev() {
             let is_leaf = {
-                let node = &self.nodes[i];
+                let node = &nodes[i];
                 node.codes.len() != node.objects.len()
             };
             if is_leaf {
-                self.nodes[i].bounds = {
-                    let node = &self.nodes[i];
+                nodes[i].bounds = {
+                    let node = &nodes[i];
                     node.objects.iter()
-                        .fold(AaBoundingBox::default(), |b1, i| {
-                            b1.merge(&self.objects[*i].bounding_box())
-                        })
+                        .fold(AaBoundingBox::default(), |b1, i| b1.merge(&bounds[*i]))
                 };
             } else {
-                self.nodes[i].objects.clear();
-                self.nodes[i].codes.clear();
-                self.nodes[i].bounds = {
-                    let node = &self.nodes[i];
+                nodes[i].objects.clear();
+                nodes[i].codes.clear();
+                nodes[i].bounds = {
+                    let node = &nodes[i];
                     let right = node.right;
                     if right == IDX_NAN {
-                        self.nodes[i + 1].bounds
+                        nodes[i + 1].bounds
                     } else {
-                        self.nodes[i + 1].bounds
-                            .merge(&self.nodes[node.right].bounds)
+                        nodes[i + 1].bounds
+                            .merge(&nodes[node.right].bounds)
                     }
                 };
             }
         }
 
-        let elapsed = start.elapsed();
-        println!("Built BVH in {}", formatting::fmt_elapsed(elapsed));
+        // leaves of the wide nodes are contiguous ranges of objects
+        let (wide, leaves, order) = wide::collapse(&nodes);
+        let mut objects: Vec<Option<T>> = self.objects.drain(..).map(Some).collect();
+        self.objects = order.iter()
+            .map(|i| objects[*i].take().unwrap())
+            .collect();
+        self.leaves = leaves.into_iter()
+            .map(|(start, len)| WideLeaf {
+                start,
+                len,
+                data: T::leaf(&self.objects[start..start + len]),
+            })
+            .collect();
+        self.nodes = wide;
+        assert!(wide::stack_bound(&self.nodes) <= STACK_SIZE);
+
+        self.stats = self.compute_stats(&nodes, start.elapsed());
+    }
+
+    fn compute_stats(&self, nodes: &[BVHNode], build_time: Duration) -> AccelStats {
+        let mut stats = AccelStats::new(build_time);
+        let root_area = self.boundary.area();
+
+        let mut stack = vec![(0, 1)];
+        while let Some((idx, depth)) = stack.pop() {
+            let node = &nodes[idx];
+            let area = node.bounds.area() / root_area;
+            if node.objects.is_empty() {
+                stats.add_inner(area, depth);
+                stack.push((idx + 1, depth + 1));
+                if node.right != IDX_NAN {
+                    stack.push((node.right, depth + 1));
+                }
+            } else {
+                stats.add_leaf(area, depth, node.objects.len());
+            }
+        }
+
+        stats
     }
 
     fn _hit<const GEO: bool>(
@@ -317,63 +480,71 @@ impl<T: ?Sized + Object> BVH<Box<T>> {
         r: &Ray,
         t_min: Float,
         t_max: Float,
-    ) -> Option<usize> {
-        let origin = r.origin;
-        let inv_dir = 1.0 / r.dir;
+    ) -> Option<(usize, Float)> {
+        if self.nodes.is_empty() { return None; }
+        let wr = WideRay::new(r);
 
-        let mut stack = [0; 64];
-        let mut stack_ptr = 0;
-        let mut curr = 0;
+        // children with distances to their boxes
+        let mut stack = [(0, t_min); STACK_SIZE];
+        let mut stack_ptr = 1;
 
-        let mut idx = None;
+        let mut closest = None;
         let mut tt = t_max;
-        loop {
+        while stack_ptr > 0 {
+            stack_ptr -= 1;
+            let (curr, t_start) = stack[stack_ptr];
+            // a closer hit got found after the push
+            if t_start > tt { continue; }
+
+            if curr & LEAF_BIT != 0 {
+                let leaf = &self.leaves[curr & !LEAF_BIT];
+                let objects = &self.objects[leaf.start..leaf.start + leaf.len];
+                if let Some((i, t)) = T::hit_leaf::<GEO>(&leaf.data, objects, r, t_min, tt) {
+                    if !GEO { return Some((leaf.start + i, t)); }
+                    tt = t;
+                    closest = Some((leaf.start + i, t));
+                }
+                continue;
+            }
+
             let node = &self.nodes[curr];
-            let (t_start, t_end) = node.bounds.intersect(origin, inv_dir);
-            let (t_start, t_end) = (t_start.max(t_min), t_end.min(tt));
-
-            if t_start <= t_end {
-                if node.objects.is_empty() {
-                    // move to left child
-                    curr += 1;
-                    if node.right != IDX_NAN {
-                        stack[stack_ptr] = node.right;
-                        stack_ptr += 1;
-                    }
-                    continue;
-                } else {
-                    for i in &node.objects {
-                        let t = self.objects[*i].hit_t(r, t_min, tt);
-                        if GEO {
-                            if t < tt { tt = t; idx = Some(*i); }
-                        } else {
-                            if t < tt { return Some(*i); }
-                        }
+            let (mut mask, t_starts) = node.intersect(&wr, t_min, tt);
+            let first = stack_ptr;
+            while mask != 0 {
+                let lane = mask.trailing_zeros() as usize;
+                mask &= mask - 1;
+                let child = (node.children[lane], t_starts[lane]);
+
+                // keep the children sorted far to near, the closest gets popped first
+                let mut pos = stack_ptr;
+                if GEO {
+                    while pos > first && stack[pos - 1].1 < child.1 {
+                        stack[pos] = stack[pos - 1];
+                        pos -= 1;
                     }
                 }
+                stack[pos] = child;
+                stack_ptr += 1;
             }
-
-            if stack_ptr == 0 { break; }
-            stack_ptr -= 1;
-            curr = stack[stack_ptr];
         }
 
-        idx
+        closest
     }
 }
 
-impl<T: ?Sized + Object> Object for BVH<Box<T>> {
+impl<T: BVHPrimitive> Object for BVH<T> {
     fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<Hit> {
         self._hit::<true>(r, t_min, t_max)
-            .and_then(|idx| self.objects[idx].hit(r, t_min, t_max))
+            .and_then(|(idx, _)| self.objects[idx].as_object().hit(r, t_min, t_max))
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
-        self._hit::<false>(r, t_min, t_max)
-            .map_or(crate::INF, |idx| self.objects[idx].hit_t(r, t_min, t_max))
+        self._hit::<false>(r, t_min, t_max).map_or(crate::INF, |(_, t)| t)
     }
 
     fn bounding_box(&self) -> AaBoundingBox {
         self.boundary
     }
+
+    fn num_primitives(&self) -> usize { self.num_primitives }
 }
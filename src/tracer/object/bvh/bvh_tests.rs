@@ -0,0 +1,158 @@
+use super::*;
+use crate::rng;
+
+const NUM_RAYS: usize = 10_000;
+const NUM_PACKET_RAYS: usize = 100;
+const BUILDS: [BVHBuild; 2] = [BVHBuild::Hlbvh, BVHBuild::BinnedSah];
+
+/// Small random triangles in the unit cube and copies of one triangle
+fn soup(rng: &mut Xorshift) -> (Vec<Point>, Vec<Face>) {
+    let mut vertices = vec!();
+    let mut faces = vec!();
+    for i in 0..2_000 {
+        let v0 = if i < 100 { Point::splat(0.5) } else { rng.gen_vec3() };
+        let v1 = v0 + 0.05 * (rng.gen_vec3() - 0.5);
+        let v2 = v0 + 0.05 * (rng.gen_vec3() - 0.5);
+        vertices.extend([v0, v1, v2]);
+        faces.push(Face::new(vec!(3 * i, 3 * i + 1, 3 * i + 2), vec!(), vec!()));
+    }
+    (vertices, faces)
+}
+
+fn soup_bvh(build: BVHBuild) -> MeshBVH {
+    let (vertices, faces) = soup(&mut Xorshift::new(0));
+    TriangleMesh::new_bvh(vertices, faces, vec!(), vec!(), Material::Blank, build)
+}
+
+/// Triangles at exponentially growing distances, splits peel off few at a time
+fn chain() -> (Vec<Point>, Vec<Face>) {
+    let mut vertices = vec!();
+    let mut faces = vec!();
+    for i in 0..1_000 {
+        let v0 = Point::new(1.05_f64.powi(i), 0.0, 0.0);
+        vertices.extend([v0, v0 + Point::Y, v0 + Point::Z]);
+        let i = i as usize;
+        faces.push(Face::new(vec!(3 * i, 3 * i + 1, 3 * i + 2), vec!(), vec!()));
+    }
+    (vertices, faces)
+}
+
+#[test]
+fn hits_match_kdtree() {
+    let (vertices, faces) = soup(&mut Xorshift::new(0));
+    let kdtree = TriangleMesh::new(vertices, faces, vec!(), vec!(), Material::Blank);
+
+    for build in BUILDS {
+        let bvh = soup_bvh(build);
+        let mut rng = Xorshift::default();
+        for _ in 0..NUM_RAYS {
+            let xo = 2.0 * rng::maps::square_to_sphere(rng.gen_vec2()) + Point::splat(0.5);
+            let xi = rng.gen_vec3();
+            let r = Ray::new(xo, xi - xo);
+
+            // hit_t can stop at any hit, only the closest hits have to agree
+            let t_kd = kdtree.hit(&r, 0.0, crate::INF).map_or(crate::INF, |h| h.t);
+            let t_bvh = bvh.hit(&r, 0.0, crate::INF).map_or(crate::INF, |h| h.t);
+            assert!(t_kd == t_bvh || (t_kd - t_bvh).abs() < crate::EPSILON);
+            assert!((t_kd == crate::INF) == (bvh.hit_t(&r, 0.0, crate::INF) == crate::INF));
+        }
+    }
+}
+
+#[test]
+fn nodes_bound_children() {
+    let contains = |outer: &AaBoundingBox, inner: &AaBoundingBox| {
+        outer.ax_min.min(inner.ax_min) == outer.ax_min
+            && outer.ax_max.max(inner.ax_max) == outer.ax_max
+    };
+
+    for build in BUILDS {
+        let bvh = soup_bvh(build);
+        let mut found = vec![false; bvh.num_objects()];
+
+        for node in &bvh.nodes {
+            for lane in 0..wide::WIDTH {
+                let Some(bounds) = node.bounds(lane) else { continue };
+                let child = node.children[lane];
+                if child & LEAF_BIT == 0 {
+                    assert!((0..wide::WIDTH).filter_map(|l| bvh.nodes[child].bounds(l))
+                            .all(|bb| contains(&bounds, &bb)));
+                } else {
+                    let leaf = &bvh.leaves[child & !LEAF_BIT];
+                    for i in leaf.start..leaf.start + leaf.len {
+                        assert!(contains(&bounds, &bvh.objects[i].bounding_box()));
+                        assert!(!found[i]);
+                        found[i] = true;
+                    }
+                }
+            }
+        }
+
+        assert!(found.iter().all(|f| *f));
+    }
+}
+
+#[test]
+fn packets_match_scalar() {
+    let bvh = soup_bvh(BVHBuild::BinnedSah);
+    let mut rng = Xorshift::default();
+
+    for _ in 0..NUM_PACKET_RAYS {
+        let xo = 2.0 * rng::maps::square_to_sphere(rng.gen_vec2()) + Point::splat(0.5);
+        let r = Ray::new(xo, rng.gen_vec3() - xo);
+        let t_max = 4.0 * rng.gen_float();
+
+        for leaf in &bvh.leaves {
+            assert!(leaf.data.is_some());
+            let objects = &bvh.objects[leaf.start..leaf.start + leaf.len];
+            let packet = Triangle::hit_leaf::<true>(&leaf.data, objects, &r, 0.0, t_max);
+            let scalar = hit_objects::<true, Triangle>(objects, &r, 0.0, t_max);
+            assert!(packet == scalar);
+        }
+    }
+}
+
+#[test]
+fn stats_cover_all_objects() {
+    for build in BUILDS {
+        let bvh = soup_bvh(build);
+        let stats = bvh.stats();
+
+        assert!(stats.num_references == bvh.num_objects());
+        assert!(stats.num_leaves == bvh.leaves.len());
+        assert!(stats.max_leaf_size <= MAX_LEAF_SIZE);
+        assert!(stats.max_depth < 64);
+        assert!(stats.expected_nodes >= 1.0);
+        assert!(stats.expected_intersections < bvh.num_objects() as Float / 10.0);
+    }
+}
+
+#[test]
+fn deep_builds_fit_stack() {
+    fn leaf_depth(node: BVHNode, bounds: &[AaBoundingBox], depth: usize, build: BVHBuild) -> usize {
+        let Some((left, right)) = node.split(bounds, depth, build) else {
+            assert!(node.objects.len() <= MAX_LEAF_SIZE);
+            return depth;
+        };
+        let left = leaf_depth(left, bounds, depth + 1, build);
+        if right.objects.is_empty() {
+            left
+        } else {
+            left.max(leaf_depth(right, bounds, depth + 1, build))
+        }
+    }
+
+    for build in BUILDS {
+        let (vertices, faces) = chain();
+        let bvh = TriangleMesh::new_bvh(vertices, faces, vec!(), vec!(), Material::Blank, build);
+        assert!(bvh.stats().max_depth <= MAX_DEPTH);
+        assert!(wide::stack_bound(&bvh.nodes) <= STACK_SIZE);
+
+        // splits started close to the limit end up in leaves above it
+        let bounds: Vec<AaBoundingBox> = bvh.objects.iter()
+            .map(|t| t.bounding_box())
+            .collect();
+        let root = BVHNode::new((0..bounds.len()).collect(), vec![0; bounds.len()]);
+        assert!(leaf_depth(root, &bounds, MAX_DEPTH - 12, build) <= MAX_DEPTH);
+    }
+}
@@ -4,6 +4,9 @@ use crate::Axis;
 const COST_INTERSECT: Float = 15.0;
 const COST_TRAVERSE: Float = 20.0;
 const EMPTY_BONUS: Float = 0.2;
+const NUM_BINS: usize = 16;
+// binned splits can peel off few objects at a time, bound the depth
+const BINNED_MAX_DEPTH: usize = 48;
 
 pub struct BVHNode {
     // left = self + 1
@@ -29,36 +32,39 @@ impl BVHNode {
         }
     }
 
-    pub fn split<T: ?Sized + Object>(
+    pub fn split(
         &self,
-        objects: &[Box<T>],
-        depth: usize
+        bounds: &[AaBoundingBox],
+        depth: usize,
+        build: BVHBuild,
     ) -> Option<(Self, Self)> {
         if self.objects.len() <= 1 {
-            None
-        } else if depth > SAH_MAX_DEPTH {
-            self.morton_split(depth)
-        } else {
-            self.sah_split(objects)
+            return None;
         }
-    }
 
-    fn morton_split(&self, depth: usize) -> Option<(Self, Self)> {
-        let rss = MORTON_BITS - depth;
-        let first = (self.codes[0] >> rss) & 1;
-        let last = (self.codes.last().unwrap() >> rss) & 1;
+        // keep enough levels left to halve the objects down to leaves
+        let levels = self.objects.len().div_ceil(MAX_LEAF_SIZE).next_power_of_two().ilog2();
+        if depth + 1 + levels as usize > MAX_DEPTH {
+            return self.median_split();
+        }
 
-        let split = if first == last {
-            // leaf
-            if self.codes.len() > MAX_LEAF_SIZE {
-                self.codes.len() / 2
-            } else {
-                return None;
-            }
+        match build {
+            BVHBuild::Hlbvh if depth > SAH_MAX_DEPTH => self.morton_split(depth),
+            BVHBuild::Hlbvh => self.sah_split(bounds),
+            BVHBuild::BinnedSah if depth > BINNED_MAX_DEPTH => self.median_split(),
+            BVHBuild::BinnedSah => self.binned_split(bounds),
+        }
+    }
+
+    fn median_split(&self) -> Option<(Self, Self)> {
+        if self.codes.len() > MAX_LEAF_SIZE {
+            Some(self.partition_at(self.codes.len() / 2))
         } else {
-            self.codes.partition_point(|c| ((c >> rss) & 1) == first)
-        };
+            None
+        }
+    }
 
+    fn partition_at(&self, split: usize) -> (Self, Self) {
         let left = Self::new(
             self.objects[..split].to_vec(),
             self.codes[..split].to_vec(),
@@ -68,10 +74,114 @@ impl BVHNode {
             self.codes[split..].to_vec(),
         );
 
+        (left, right)
+    }
+
+    fn morton_split(&self, depth: usize) -> Option<(Self, Self)> {
+        // all bits of the codes used up
+        let Some(rss) = MORTON_BITS.checked_sub(depth) else {
+            return self.median_split();
+        };
+        let first = (self.codes[0] >> rss) & 1;
+        let last = (self.codes.last().unwrap() >> rss) & 1;
+
+        if first == last {
+            return self.median_split();
+        }
+
+        let split = self.codes.partition_point(|c| ((c >> rss) & 1) == first);
+        Some(self.partition_at(split))
+    }
+
+    /// Sweep SAH over the centers of the objects gathered to bins, Wald 2007
+    fn binned_split(&self, bounds: &[AaBoundingBox]) -> Option<(Self, Self)> {
+        let (node_bounds, center_bounds) = self.objects.iter()
+            .fold((AaBoundingBox::default(), AaBoundingBox::default()), |(nb, cb), i| {
+                let c = bounds[*i].center();
+                (nb.merge(&bounds[*i]), cb.merge(&AaBoundingBox::new(c, c)))
+            });
+        let total_area = node_bounds.area();
+
+        let bin_of = |axis: Axis, i: usize| -> usize {
+            let mi = center_bounds.min(axis);
+            let extent = center_bounds.max(axis) - mi;
+            let c = bounds[i].center().axis(axis);
+            let bin = (NUM_BINS as Float * (c - mi) / extent) as usize;
+            bin.min(NUM_BINS - 1)
+        };
+
+        let mut best_cost = crate::INF;
+        let mut best_axis = Axis::X;
+        let mut best_bin = 0;
+
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            if center_bounds.max(axis) <= center_bounds.min(axis) {
+                continue;
+            }
+
+            let mut counts = [0; NUM_BINS];
+            let mut bins = [AaBoundingBox::default(); NUM_BINS];
+            for i in &self.objects {
+                let bin = bin_of(axis, *i);
+                counts[bin] += 1;
+                bins[bin] = bins[bin].merge(&bounds[*i]);
+            }
+
+            // area and count of objects right of each split plane
+            let mut area_right = [0.0; NUM_BINS];
+            let mut num_right = [0; NUM_BINS];
+            let mut bb = AaBoundingBox::default();
+            let mut num = 0;
+            for bin in (1..NUM_BINS).rev() {
+                bb = bb.merge(&bins[bin]);
+                num += counts[bin];
+                area_right[bin] = bb.area();
+                num_right[bin] = num;
+            }
+
+            let mut bb = AaBoundingBox::default();
+            let mut num_left = 0;
+            for bin in 1..NUM_BINS {
+                bb = bb.merge(&bins[bin - 1]);
+                num_left += counts[bin - 1];
+                if num_left == 0 || num_right[bin] == 0 { continue; }
+
+                let cost = COST_TRAVERSE + COST_INTERSECT
+                    * (num_left as Float * bb.area()
+                       + num_right[bin] as Float * area_right[bin])
+                    / total_area;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_axis = axis;
+                    best_bin = bin;
+                }
+            }
+        }
+
+        let leaf_cost = COST_INTERSECT * self.objects.len() as Float;
+        if best_cost == crate::INF {
+            // centers coincide
+            return self.median_split();
+        } else if best_cost >= leaf_cost && self.objects.len() <= MAX_LEAF_SIZE {
+            return None;
+        }
+
+        let mut left = Self::new(vec!(), vec!());
+        let mut right = Self::new(vec!(), vec!());
+        for i in 0..self.objects.len() {
+            let node = if bin_of(best_axis, self.objects[i]) < best_bin {
+                &mut left
+            } else {
+                &mut right
+            };
+            node.objects.push(self.objects[i]);
+            node.codes.push(self.codes[i]);
+        }
+
         Some((left, right))
     }
 
-    fn sah_split<T: ?Sized + Object>(&self, objects: &[Box<T>]) -> Option<(Self, Self)> {
+    fn sah_split(&self, bounds: &[AaBoundingBox]) -> Option<(Self, Self)> {
         let mut best_cost = crate::INF;
         let mut best_axis = Axis::X;
         let mut best_center = crate::INF;
@@ -80,36 +190,34 @@ impl BVHNode {
         for axis in [Axis::X, Axis::Y, Axis::Z] {
             let mut indices = self.objects.clone();
             indices.sort_by(|i, j| {
-                let pi = objects[*i].bounding_box().center().axis(axis);
-                let pj = objects[*j].bounding_box().center().axis(axis);
+                let pi = bounds[*i].center().axis(axis);
+                let pj = bounds[*j].center().axis(axis);
                 pi.total_cmp(&pj)
             });
 
             let mut area_left = Vec::with_capacity(indices.len() + 1);
             area_left.push(crate::INF);
-            let mut bounds = AaBoundingBox::default();
+            let mut bb = AaBoundingBox::default();
             for i in &indices {
-                bounds = bounds.merge(&objects[*i].bounding_box());
-                area_left.push(bounds.area());
+                bb = bb.merge(&bounds[*i]);
+                area_left.push(bb.area());
             }
             let mut area_right = Vec::with_capacity(indices.len() + 1);
             area_right.push(crate::INF);
-            let mut bounds = AaBoundingBox::default();
+            let mut bb = AaBoundingBox::default();
             for i in indices.iter().rev() {
-                bounds = bounds.merge(&objects[*i].bounding_box());
-                area_right.push(bounds.area());
+                bb = bb.merge(&bounds[*i]);
+                area_right.push(bb.area());
             }
             let total_area = area_right[indices.len()];
 
             let mut i = 0;
             while i < indices.len() {
-                let get_center = |i| {
+                let get_center = |i: usize| {
                     if i == indices.len() {
                         crate::INF
                     } else {
-                        #[allow(clippy::borrowed_box)]
-                        let obj: &Box<T> = &objects[indices[i]];
-                        obj.bounding_box().center().axis(axis)
+                        bounds[indices[i]].center().axis(axis)
                     }
                 };
                 let center = get_center(i);
@@ -139,15 +247,15 @@ impl BVHNode {
             }
         }
 
-        self.sah_partition(best_axis, best_center, best_side, objects)
+        self.sah_partition(best_axis, best_center, best_side, bounds)
     }
 
-    fn sah_partition<T: ?Sized + Object>(
+    fn sah_partition(
         &self,
         axis: Axis,
         center: Float,
         side: BVHSplitSide,
-        objects: &[Box<T>]
+        bounds: &[AaBoundingBox]
     ) -> Option<(Self, Self)> {
         let mut left_codes = Vec::with_capacity(self.codes.len() / 2);
         let mut left_objects = Vec::with_capacity(self.codes.len() / 2);
@@ -155,8 +263,7 @@ impl BVHNode {
         let mut right_objects = Vec::with_capacity(self.codes.len() / 2);
 
         for i in 0..self.codes.len() {
-            let object = &objects[self.objects[i]];
-            let c = object.bounding_box().center().axis(axis);
+            let c = bounds[self.objects[i]].center().axis(axis);
             if c < center || (c == center && matches!(side, BVHSplitSide::Left)) {
                 left_codes.push(self.codes[i]);
                 left_objects.push(self.objects[i]);
@@ -0,0 +1,175 @@
+use super::*;
+use crate::math::simd::F64x4;
+
+pub const WIDTH: usize = 4;
+/// Children of a wide node are marked as leaves with the highest bit
+pub const LEAF_BIT: usize = 1 << (usize::BITS - 1);
+
+/// Node with up to `WIDTH` children. Bounds of the children are stored per
+/// axis to intersect a ray with all of them at once.
+pub struct WideNode {
+    min: [F64x4; 3],
+    max: [F64x4; 3],
+    /// Index to a node, or to a leaf with `LEAF_BIT` set
+    pub children: [usize; WIDTH],
+    /// Bit mask of the children in use
+    valid: u32,
+}
+
+/// Objects from `start` to `start + len` with data precomputed for them
+pub struct WideLeaf<L> {
+    pub start: usize,
+    pub len: usize,
+    pub data: L,
+}
+
+/// Ray prepared for the box tests
+pub struct WideRay {
+    origin: [F64x4; 3],
+    inv_dir: [F64x4; 3],
+}
+
+impl WideRay {
+    pub fn new(r: &Ray) -> Self {
+        let inv_dir = 1.0 / r.dir;
+        Self {
+            origin: [r.origin.x, r.origin.y, r.origin.z].map(F64x4::splat),
+            inv_dir: [inv_dir.x, inv_dir.y, inv_dir.z].map(F64x4::splat),
+        }
+    }
+}
+
+impl WideNode {
+    fn new(bounds: &[AaBoundingBox], children: [usize; WIDTH]) -> Self {
+        let mut min = [[0.0; WIDTH]; 3];
+        let mut max = [[0.0; WIDTH]; 3];
+        for (i, bb) in bounds.iter().enumerate() {
+            (min[0][i], min[1][i], min[2][i]) = (bb.ax_min.x, bb.ax_min.y, bb.ax_min.z);
+            (max[0][i], max[1][i], max[2][i]) = (bb.ax_max.x, bb.ax_max.y, bb.ax_max.z);
+        }
+
+        Self {
+            min: min.map(F64x4::new),
+            max: max.map(F64x4::new),
+            children,
+            valid: (1 << bounds.len()) - 1,
+        }
+    }
+
+    /// Bounds of the child in `lane`
+    #[cfg(test)]
+    pub fn bounds(&self, lane: usize) -> Option<AaBoundingBox> {
+        if self.valid & (1 << lane) == 0 {
+            return None;
+        }
+        let [min, max] = [self.min, self.max]
+            .map(|v| Point::new(v[0].to_array()[lane], v[1].to_array()[lane], v[2].to_array()[lane]));
+        Some(AaBoundingBox::new(min, max))
+    }
+
+    /// Mask of the children whose boxes `r` hits in `(t_min, t_max)` and
+    /// distances to the boxes. Same arithmetic as `AaBoundingBox::intersect`.
+    #[inline(always)]
+    pub fn intersect(&self, r: &WideRay, t_min: Float, t_max: Float) -> (u32, [Float; WIDTH]) {
+        let slab = |axis: usize| {
+            let t0 = (self.min[axis] - r.origin[axis]) * r.inv_dir[axis];
+            let t1 = (self.max[axis] - r.origin[axis]) * r.inv_dir[axis];
+            (t0.min(t1), t1.max(t0))
+        };
+        let (x0, x1) = slab(0);
+        let (y0, y1) = slab(1);
+        let (z0, z1) = slab(2);
+
+        let t_start = x0.max(y0.max(z0)).max(F64x4::splat(t_min));
+        let t_end = x1.min(y1.min(z1)) * F64x4::splat(1.0 + 2.0 * efloat::gamma(3));
+        let t_end = t_end.min(F64x4::splat(t_max));
+
+        (self.valid & t_start.le_mask(t_end), t_start.to_array())
+    }
+}
+
+/// Collapse the binary `nodes` to wide nodes. Returns the wide nodes, the
+/// leaves as ranges and the order of the objects to make the ranges
+/// contiguous. Each wide node skips at least one level of the binary tree.
+pub fn collapse(nodes: &[BVHNode]) -> (Vec<WideNode>, Vec<(usize, usize)>, Vec<usize>) {
+    let mut wide = vec!();
+    let mut leaves = vec!();
+    let mut order = vec!();
+
+    if nodes[0].objects.is_empty() {
+        collapse_node(nodes, 0, &mut wide, &mut leaves, &mut order);
+    } else {
+        // root is a leaf, put it under a wide node of its own
+        wide.push(WideNode::new(&[nodes[0].bounds], [LEAF_BIT; WIDTH]));
+        wide[0].children[0] = collapse_node(nodes, 0, &mut wide, &mut leaves, &mut order);
+    }
+
+    (wide, leaves, order)
+}
+
+/// Most entries the traversal stack holds at once for the wide `nodes`
+pub fn stack_bound(nodes: &[WideNode]) -> usize {
+    // children come after their parents
+    let mut bound = vec![1; nodes.len()];
+    for idx in (0..nodes.len()).rev() {
+        let node = &nodes[idx];
+        let num_children = node.valid.count_ones() as usize;
+        bound[idx] = (0..num_children)
+            .map(|lane| node.children[lane])
+            .map(|c| if c & LEAF_BIT != 0 { 1 } else { bound[c] })
+            .max()
+            .map_or(1, |b| num_children - 1 + b);
+    }
+    bound.first().copied().unwrap_or(0)
+}
+
+fn binary_children(nodes: &[BVHNode], idx: usize) -> impl Iterator<Item = usize> {
+    let right = nodes[idx].right;
+    std::iter::once(idx + 1).chain((right != IDX_NAN).then_some(right))
+}
+
+fn collapse_node(
+    nodes: &[BVHNode],
+    idx: usize,
+    wide: &mut Vec<WideNode>,
+    leaves: &mut Vec<(usize, usize)>,
+    order: &mut Vec<usize>,
+) -> usize {
+    let node = &nodes[idx];
+    if !node.objects.is_empty() {
+        leaves.push((order.len(), node.objects.len()));
+        order.extend(&node.objects);
+        return LEAF_BIT | (leaves.len() - 1);
+    }
+
+    // open the inner child with the largest area while there is room
+    let mut children: Vec<usize> = binary_children(nodes, idx).collect();
+    loop {
+        let largest = children.iter()
+            .enumerate()
+            .filter(|(_, c)| nodes[**c].objects.is_empty())
+            .max_by(|(_, c1), (_, c2)| {
+                nodes[**c1].bounds.area().total_cmp(&nodes[**c2].bounds.area())
+            })
+            .map(|(i, _)| i);
+
+        match largest {
+            Some(i) if children.len() + binary_children(nodes, children[i]).count()
+                <= WIDTH + 1 => {
+                let c = children.swap_remove(i);
+                children.extend(binary_children(nodes, c));
+            }
+            _ => break,
+        }
+    }
+
+    let bounds: Vec<AaBoundingBox> = children.iter().map(|c| nodes[*c].bounds).collect();
+    let pos = wide.len();
+    wide.push(WideNode::new(&bounds, [LEAF_BIT; WIDTH]));
+
+    for (lane, c) in children.into_iter().enumerate() {
+        wide[pos].children[lane] = collapse_node(nodes, c, wide, leaves, order);
+    }
+
+    pos
+}
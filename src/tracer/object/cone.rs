@@ -83,9 +83,16 @@ impl Object for Cone {
         let uv = Vec2::new(u, v);
 
         Hit::new(t.value, &self.material, wi, xi, err, ni, ni, uv)
+            .and_then(|h| h.unless_cut_out(wi))
+            // cut out by the opacity mask, try the far side
+            .or_else(|| self.hit(r, t.high, t_max))
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if self.material.has_alpha() {
+            return self.hit(r, t_min, t_max).map_or(crate::INF, |h| h.t);
+        }
+
         let xo = r.origin;
         let wi = r.dir;
 
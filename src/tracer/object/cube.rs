@@ -64,7 +64,7 @@ impl Sampleable for Cube {
 
     fn material(&self) -> &Material { self.mesh.material() }
 
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
+    fn sample_on(&self, _time: Float, rand_sq: Vec2) -> Hit {
         let rand_sphere = 0.5 * rng::maps::square_to_sphere(rand_sq);
         // reproject to reduce floating point accuracies and move inside unit cube
         let rand_sphere = 0.5 * rand_sphere / rand_sphere.length() + 0.5;
@@ -0,0 +1,575 @@
+use super::*;
+use super::bvh::BVHPrimitive;
+
+/// Maximum depth of the recursive subdivision in intersection tests
+const MAX_DEPTH: i32 = 10;
+
+/// How the cross section of a curve looks like
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum CurveType {
+    /// Flat ribbon that always faces the ray
+    #[default]
+    Flat,
+    /// Ribbon facing the ray but shaded like a cylinder, normals turn
+    /// towards the edges
+    Cylinder,
+    /// Ribbon oriented by the normals at the start and the end of the curve,
+    /// normals in between are interpolated spherically. Narrows down when
+    /// seen from the side, like a blade of grass.
+    Ribbon(Normal, Normal),
+}
+
+/// Basis of the control points of a curve
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CurveBasis {
+    /// Cubic Bézier, consecutive segments share their end points
+    #[default]
+    Bezier,
+    /// Uniform cubic B-spline, consecutive segments share three control points
+    BSpline,
+}
+
+/// Cubic Bézier curve rendered as a ribbon, meant for thin geometry e.g.
+/// hair, fur and grass. Width is interpolated linearly along the curve.
+/// Texture coordinate `u` goes along the curve and `v` across it.
+/// Intersection follows PBR 3rd ed. Chapter 3.7.
+pub struct Curve {
+    /// Control points of the curve
+    cps: [Point; 4],
+    /// Width at the start of the curve
+    width0: Float,
+    /// Width at the end of the curve
+    width1: Float,
+    /// Shape of the cross section
+    curve_type: CurveType,
+    /// Material of the curve, can be shared between many curves
+    material: Arc<Material>,
+}
+
+/// Closest intersection found in the recursive intersection test
+struct CurveHit {
+    /// Distance along the normalized ray
+    z: Float,
+    /// Parameter along the curve
+    u: Float,
+    /// Offset across the curve, `0.5` at the center
+    v: Float,
+    /// Width of the curve at the hit
+    width: Float,
+}
+
+impl Curve {
+    /// Curve from Bézier control points `cps` with width going from `width0`
+    /// to `width1`. Either end can taper to zero width, e.g. tips of hair.
+    /// `material` can be an `Arc` to share it between curves.
+    pub fn new(
+        cps: [Point; 4],
+        width0: Float,
+        width1: Float,
+        material: impl Into<Arc<Material>>,
+    ) -> Box<Self> {
+        assert!(width0 >= 0.0 && width1 >= 0.0 && width0.max(width1) > 0.0);
+
+        Box::new(Self {
+            cps,
+            width0,
+            width1,
+            curve_type: CurveType::default(),
+            material: material.into(),
+        })
+    }
+
+    /// Curve from the control points `cps` of a uniform cubic B-spline segment
+    pub fn bspline(
+        cps: [Point; 4],
+        width0: Float,
+        width1: Float,
+        material: impl Into<Arc<Material>>,
+    ) -> Box<Self> {
+        Self::new(bezier::from_bspline(&cps), width0, width1, material)
+    }
+
+    /// Splits a strand with control points `cps` in `basis` to its segments.
+    /// Width and ribbon normals are interpolated from the start of the strand
+    /// to its end.
+    pub fn strand(
+        cps: &[Point],
+        basis: CurveBasis,
+        width0: Float,
+        width1: Float,
+        curve_type: CurveType,
+        material: Arc<Material>,
+    ) -> Vec<Self> {
+        let (num_segments, stride) = match basis {
+            CurveBasis::Bezier => {
+                assert!(cps.len() >= 4 && (cps.len() - 1) % 3 == 0);
+                ((cps.len() - 1) / 3, 3)
+            }
+            CurveBasis::BSpline => {
+                assert!(cps.len() >= 4);
+                (cps.len() - 3, 1)
+            }
+        };
+
+        let lerp = |a: Float, b: Float, t: Float| (1.0 - t) * a + t * b;
+        (0..num_segments).map(|i| {
+            let (t0, t1) = (i as Float / num_segments as Float, (i + 1) as Float / num_segments as Float);
+            let seg = [0, 1, 2, 3].map(|j| cps[i * stride + j]);
+            let (w0, w1) = (lerp(width0, width1, t0), lerp(width0, width1, t1));
+
+            let curve = match basis {
+                CurveBasis::Bezier => Self::new(seg, w0, w1, Arc::clone(&material)),
+                CurveBasis::BSpline => Self::bspline(seg, w0, w1, Arc::clone(&material)),
+            };
+            let curve_type = match curve_type {
+                CurveType::Ribbon(n0, n1) => {
+                    let (n0, n1) = (n0.normalize(), n1.normalize());
+                    CurveType::Ribbon(
+                        Self::ribbon_normal(n0, n1, t0),
+                        Self::ribbon_normal(n0, n1, t1),
+                    )
+                }
+                _ => curve_type,
+            };
+
+            *curve.with_type(curve_type)
+        }).collect()
+    }
+
+    /// Use `curve_type` for the cross section
+    pub fn with_type(mut self, curve_type: CurveType) -> Box<Self> {
+        self.curve_type = match curve_type {
+            CurveType::Ribbon(n0, n1) => CurveType::Ribbon(n0.normalize(), n1.normalize()),
+            _ => curve_type,
+        };
+        Box::new(self)
+    }
+
+    #[inline]
+    fn width_at(&self, u: Float) -> Float {
+        (1.0 - u) * self.width0 + u * self.width1
+    }
+
+    /// Spherically interpolated normal of a ribbon at `u`
+    fn ribbon_normal(n0: Normal, n1: Normal, u: Float) -> Normal {
+        let theta = n0.dot(n1).clamp(-1.0, 1.0).acos();
+        if theta < crate::EPSILON {
+            return n0;
+        }
+        let sin_theta = theta.sin();
+
+        ((((1.0 - u) * theta).sin() / sin_theta) * n0
+            + ((u * theta).sin() / sin_theta) * n1).normalize()
+    }
+
+    /// Recursively split the curve until we reach depth zero
+    /// and then intersect the segment as a line.
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_hit(
+        &self,
+        cps: &[Point; 4],
+        u0: Float,
+        u1: Float,
+        depth: i32,
+        z_min: Float,
+        z_max: Float,
+        dir: Direction,
+    ) -> Option<CurveHit> {
+        if depth > 0 {
+            let split = bezier::subdivide(cps);
+            let us = [u0, (u0 + u1) / 2.0, u1];
+            let mut z_max = z_max;
+            let mut closest = None;
+
+            for seg in 0..2 {
+                let cps = [split[3 * seg], split[3 * seg + 1], split[3 * seg + 2], split[3 * seg + 3]];
+                let half_width = self.width_at(us[seg]).max(self.width_at(us[seg + 1])) / 2.0;
+
+                let ax_min = cps.iter().fold(Point::splat(crate::INF), |acc, p| acc.min(*p));
+                let ax_max = cps.iter().fold(Point::splat(-crate::INF), |acc, p| acc.max(*p));
+
+                // the ray goes along the positive z axis in ray space
+                if ax_max.x + half_width < 0.0 || ax_min.x - half_width > 0.0
+                    || ax_max.y + half_width < 0.0 || ax_min.y - half_width > 0.0
+                    || ax_max.z + half_width < z_min || ax_min.z - half_width > z_max {
+                    continue;
+                }
+
+                if let Some(h) = self.recursive_hit(&cps, us[seg], us[seg + 1], depth - 1, z_min, z_max, dir) {
+                    z_max = h.z;
+                    closest = Some(h);
+                }
+            }
+
+            return closest;
+        }
+
+        // check that the ray is not beyond the end points of the segment
+        let edge = (cps[1].y - cps[0].y) * -cps[0].y + cps[0].x * (cps[0].x - cps[1].x);
+        if edge < 0.0 {
+            return None;
+        }
+        let edge = (cps[2].y - cps[3].y) * -cps[3].y + cps[3].x * (cps[3].x - cps[2].x);
+        if edge < 0.0 {
+            return None;
+        }
+
+        // closest point on the segment to the ray
+        let segment = Vec2::new(cps[3].x - cps[0].x, cps[3].y - cps[0].y);
+        let denom = segment.dot(segment);
+        if denom == 0.0 {
+            return None;
+        }
+        let w = Vec2::new(-cps[0].x, -cps[0].y).dot(segment) / denom;
+        let w = w.clamp(0.0, 1.0);
+        let u = (1.0 - w) * u0 + w * u1;
+        let width = match self.curve_type {
+            // narrower when seen from the side
+            CurveType::Ribbon(n0, n1) => {
+                self.width_at(u) * Self::ribbon_normal(n0, n1, u).dot(dir).abs()
+            }
+            _ => self.width_at(u),
+        };
+
+        let (pc, dpcdw) = bezier::eval(cps, w);
+        let dist2 = pc.x * pc.x + pc.y * pc.y;
+        if dist2 > width * width / 4.0 || pc.z <= z_min || pc.z >= z_max {
+            return None;
+        }
+
+        // signed distance across the curve, on the left of the tangent is positive
+        let edge_func = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let dist = dist2.sqrt() / width;
+        let v = if edge_func > 0.0 { 0.5 + dist } else { 0.5 - dist };
+
+        Some(CurveHit { z: pc.z, u, v, width })
+    }
+}
+
+impl Curve {
+    /// Normal of a cylinder around the curve at `xi`, `ng` faces the ray and
+    /// `v` tells how far across the curve `xi` is
+    fn cylinder_normal(&self, ng: Normal, dpdu: Direction, xi: Point, u: Float, v: Float) -> Normal {
+        let (center, _) = bezier::eval(&self.cps, u);
+        let side = xi - center;
+        let side = side - dpdu * side.dot(dpdu) / dpdu.length_squared();
+        let side = side - ng * side.dot(ng);
+        let length = side.length();
+        if length < crate::EPSILON {
+            return ng;
+        }
+
+        // sine of the angle between the normal and `ng`
+        let sin_theta = (2.0 * v - 1.0).abs().min(1.0);
+        let cos_theta = (1.0 - sin_theta * sin_theta).sqrt();
+        (cos_theta * ng + sin_theta * side / length).normalize()
+    }
+}
+
+impl BVHPrimitive for Curve {
+    type Object = Self;
+    type Leaf = ();
+
+    fn as_object(&self) -> &Self { self }
+
+    fn leaf(_objects: &[Self]) -> Self::Leaf {}
+}
+
+impl Object for Curve {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<Hit<'_>> {
+        let dir_length = r.dir.length();
+        let uvw = Onb::new(r.dir / dir_length);
+
+        // transform to ray space, where the ray starts at origin towards +z
+        let cps = self.cps.map(|p| uvw.to_local(p - r.origin));
+
+        // the flatter the curve, the less we need to subdivide
+        let l0 = (0..2).map(|i| {
+            (cps[i] - 2.0 * cps[i + 1] + cps[i + 2]).abs().max_element()
+        }).fold(0.0, Float::max);
+        let eps = self.width0.max(self.width1) * 0.05;
+        let depth = if l0 > 0.0 {
+            let r0 = (std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() / 2.0;
+            (r0.round() as i32).clamp(0, MAX_DEPTH)
+        } else {
+            0
+        };
+
+        let ch = self.recursive_hit(
+            &cps,
+            0.0,
+            1.0,
+            depth,
+            t_min * dir_length,
+            t_max.min(crate::INF) * dir_length,
+            r.dir / dir_length,
+        )?;
+
+        let t = ch.z / dir_length;
+        let xi = r.at(t);
+
+        let (_, dpdu) = bezier::eval(&self.cps, ch.u);
+        let dpdu = if dpdu.length_squared() == 0.0 {
+            self.cps[3] - self.cps[0]
+        } else {
+            dpdu
+        };
+        let ni = match self.curve_type {
+            CurveType::Ribbon(n0, n1) => {
+                let n = Self::ribbon_normal(n0, n1, ch.u);
+                (n - dpdu * n.dot(dpdu) / dpdu.length_squared()).normalize()
+            }
+            // dp/dv is perpendicular to both the curve and the ray
+            _ => dpdu.cross(dpdu.cross(r.dir)).normalize(),
+        };
+
+        // orient the normal towards the ray, v follows dp/dv
+        let (ni, v) = if ni.dot(r.dir) > 0.0 { (-ni, 1.0 - ch.v) } else { (ni, ch.v) };
+        let v = v.clamp(0.0, 1.0 - crate::EPSILON);
+
+        let ns = match self.curve_type {
+            CurveType::Cylinder => self.cylinder_normal(ni, dpdu, xi, ch.u, ch.v),
+            _ => ni,
+        };
+
+        let err = Vec3::splat(2.0 * ch.width);
+
+        Hit::new(t, &self.material, r.dir, xi, err, ns, ni, Vec2::new(ch.u, v))
+            .and_then(|h| h.unless_cut_out(r.dir))
+            .map(|h| h.with_tangent(dpdu))
+            // cut out by the opacity mask, try farther along the curve
+            .or_else(|| self.hit(r, (ch.z + ch.width) / dir_length, t_max))
+    }
+
+    fn bounding_box(&self) -> AaBoundingBox {
+        let half_width = self.width0.max(self.width1) / 2.0;
+        let ax_min = self.cps.iter().fold(Point::splat(crate::INF), |acc, p| acc.min(*p));
+        let ax_max = self.cps.iter().fold(Point::splat(-crate::INF), |acc, p| acc.max(*p));
+
+        AaBoundingBox::new(
+            ax_min - Vec3::splat(half_width),
+            ax_max + Vec3::splat(half_width),
+        )
+    }
+}
+
+mod bezier {
+    use super::*;
+
+    /// Point and derivative of the curve at `u`
+    pub fn eval(cps: &[Point; 4], u: Float) -> (Point, Direction) {
+        let lerp = |a: Point, b: Point| (1.0 - u) * a + u * b;
+        let cp1 = [lerp(cps[0], cps[1]), lerp(cps[1], cps[2]), lerp(cps[2], cps[3])];
+        let cp2 = [lerp(cp1[0], cp1[1]), lerp(cp1[1], cp1[2])];
+
+        (lerp(cp2[0], cp2[1]), 3.0 * (cp2[1] - cp2[0]))
+    }
+
+    /// Bézier control points of a uniform cubic B-spline segment
+    pub fn from_bspline(cps: &[Point; 4]) -> [Point; 4] {
+        [
+            (cps[0] + 4.0 * cps[1] + cps[2]) / 6.0,
+            (2.0 * cps[1] + cps[2]) / 3.0,
+            (cps[1] + 2.0 * cps[2]) / 3.0,
+            (cps[1] + 4.0 * cps[2] + cps[3]) / 6.0,
+        ]
+    }
+
+    /// Split the curve in half. Returns the control points of both halves,
+    /// the middle control point is shared.
+    pub fn subdivide(cps: &[Point; 4]) -> [Point; 7] {
+        [
+            cps[0],
+            (cps[0] + cps[1]) / 2.0,
+            (cps[0] + 2.0 * cps[1] + cps[2]) / 4.0,
+            (cps[0] + 3.0 * cps[1] + 3.0 * cps[2] + cps[3]) / 8.0,
+            (cps[1] + 2.0 * cps[2] + cps[3]) / 4.0,
+            (cps[2] + cps[3]) / 2.0,
+            cps[3],
+        ]
+    }
+}
+
+#[cfg(test)]
+mod curve_tests {
+    use super::*;
+    use crate::Image;
+    use crate::tracer::{AlphaMask, Texture};
+
+    fn curve() -> Box<Curve> {
+        Curve::new(
+            [
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(-0.3, 0.2, 0.0),
+                Point::new(0.3, -0.2, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ],
+            0.5,
+            0.3,
+            Material::Blank,
+        )
+    }
+
+    test_util::test_object!(curve());
+
+    #[test]
+    fn hits_center() {
+        let c = curve();
+        let r = Ray::new(Point::new(0.0, 0.0, 2.0), -Direction::Z);
+        let h = c.hit(&r, 0.0, crate::INF).unwrap();
+
+        assert!((h.t - 2.0).abs() < 1e-2);
+        assert!((h.uv.x - 0.5).abs() < 1e-2);
+        assert!((h.uv.y - 0.5).abs() < 1e-2);
+        assert!(h.ng.dot(r.dir) < 0.0);
+    }
+
+    #[test]
+    fn tapers_to_tip() {
+        let c = Curve::new(
+            [0.0, 1.0, 2.0, 3.0].map(|x| Point::new(x, 0.0, 0.0)),
+            0.4,
+            0.0,
+            Material::Blank,
+        );
+        let hit = |x: Float, y: Float| c.hit(&Ray::new(Point::new(x, y, 2.0), -Direction::Z), 0.0, crate::INF);
+
+        assert!(hit(0.5, 0.15).is_some());
+        assert!(hit(2.5, 0.15).is_none());
+        assert!(hit(2.9, 0.0).is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_width_is_rejected() {
+        Curve::new([Point::ZERO, Point::X, Point::Y, Point::Z], 0.0, 0.0, Material::Blank);
+    }
+
+    #[test]
+    fn cut_out_hits_retry_farther() {
+        // opaque for u below half, cut out above
+        let mask = AlphaMask::from_image(Image::new(vec![1.0, 0.0], 2, 1, 0.5))
+            .with_threshold(0.5);
+        let material = Material::diffuse(Texture::default()).with_alpha(mask);
+        // U shape opening towards +z, the ray crosses both arms
+        let cps = [
+            Point::new(-1.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, 1.0),
+        ];
+        let c = Curve::new(cps, 0.1, 0.1, material);
+        let r = Ray::new(Point::new(2.0, 0.0, 0.5), -Direction::X);
+        let h = c.hit(&r, 0.0, crate::INF).unwrap();
+
+        assert!(h.uv.x < 0.5);
+        assert!(h.p.x < 0.0);
+    }
+
+    #[test]
+    fn misses_beyond_width() {
+        let c = curve();
+        let r = Ray::new(Point::new(0.0, 0.3, 2.0), -Direction::Z);
+        assert!(c.hit(&r, 0.0, crate::INF).is_none());
+    }
+
+    #[test]
+    fn v_goes_across() {
+        let c = curve();
+        let above = Ray::new(Point::new(0.0, 0.1, 2.0), -Direction::Z);
+        let below = Ray::new(Point::new(0.0, -0.1, 2.0), -Direction::Z);
+        let va = c.hit(&above, 0.0, crate::INF).unwrap().uv.y;
+        let vb = c.hit(&below, 0.0, crate::INF).unwrap().uv.y;
+
+        assert!((va + vb - 1.0).abs() < 1e-2);
+        assert!((va - vb).abs() > 0.4);
+    }
+
+    #[test]
+    fn bspline_of_line_spans_middle_points() {
+        let cps = [0.0, 1.0, 2.0, 3.0].map(|x| Point::new(x, 0.0, 0.0));
+        let c = Curve::bspline(cps, 0.2, 0.2, Material::Blank);
+        let hit = |x: Float| c.hit(&Ray::new(Point::new(x, 0.0, 2.0), -Direction::Z), 0.0, crate::INF);
+
+        assert!(hit(1.5).is_some_and(|h| (h.uv.x - 0.5).abs() < 1e-2));
+        assert!(hit(0.5).is_none());
+        assert!(hit(2.5).is_none());
+    }
+
+    #[test]
+    fn cylinder_normals_turn_to_edges() {
+        let c = curve().with_type(CurveType::Cylinder);
+        let center = c.hit(&Ray::new(Point::new(0.0, 0.0, 2.0), -Direction::Z), 0.0, crate::INF).unwrap();
+        let edge = c.hit(&Ray::new(Point::new(0.0, 0.18, 2.0), -Direction::Z), 0.0, crate::INF).unwrap();
+
+        assert!(center.ns.dot(center.ng) > 0.99);
+        assert!(edge.ns.dot(edge.ng) < 0.8);
+        assert!(edge.ns.y > 0.5);
+        assert!(edge.ng.dot(Direction::Z) > 0.99);
+    }
+
+    #[test]
+    fn ribbon_narrows_from_side() {
+        let c = curve().with_type(CurveType::Ribbon(Normal::Z, Normal::Z));
+        let facing = Ray::new(Point::new(0.0, 0.1, 2.0), -Direction::Z);
+        // at 45 degrees, crosses the plane of the ribbon at `y`
+        let tilted = |y: Float| Ray::new(Point::new(0.0, 2.0 + y, 2.0), -Direction::new(0.0, 1.0, 1.0));
+        let side = Ray::new(Point::new(0.0, 2.0, 0.05), -Direction::Y);
+
+        assert!(c.hit(&facing, 0.0, crate::INF).is_some_and(|h| h.ng.dot(Normal::Z) > 0.99));
+        assert!(c.hit(&tilted(0.15), 0.0, crate::INF).is_some());
+        // a ray facing ribbon would get hit
+        assert!(curve().hit(&tilted(0.25), 0.0, crate::INF).is_some());
+        assert!(c.hit(&tilted(0.25), 0.0, crate::INF).is_none());
+        assert!(c.hit(&side, 0.0, crate::INF).is_none());
+    }
+
+    #[test]
+    fn strand_splits_to_segments() {
+        let material = Arc::new(Material::Blank);
+        let cps: Vec<Point> = (0..7).map(|i| Point::new(i as Float, (i % 2) as Float, 0.0)).collect();
+
+        let bezier = Curve::strand(&cps, CurveBasis::Bezier, 0.3, 0.1, CurveType::Flat, Arc::clone(&material));
+        assert!(bezier.len() == 2);
+        assert!(bezier[0].cps[3] == bezier[1].cps[0]);
+        assert!(bezier[0].width1 == bezier[1].width0);
+        assert!(bezier[0].width0 == 0.3 && bezier[1].width1 == 0.1);
+
+        let bspline = Curve::strand(&cps, CurveBasis::BSpline, 0.3, 0.1, CurveType::Flat, material);
+        assert!(bspline.len() == 4);
+        assert!(bspline[1].cps[3].distance(bspline[2].cps[0]) < crate::EPSILON);
+    }
+
+    #[test]
+    fn bvh_matches_curves() {
+        let mut rng = Xorshift::new(0xc0ffee);
+        let material = Arc::new(Material::Blank);
+        let cps: Vec<Point> = (0..40).map(|_| 2.0 * rng.gen_vec3() - 1.0).collect();
+        let curves = Curve::strand(&cps, CurveBasis::BSpline, 0.1, 0.02, CurveType::Cylinder, material);
+        let ts = |r: &Ray| curves.iter()
+            .filter_map(|c| c.hit(r, 0.0, crate::INF))
+            .map(|h| h.t)
+            .fold(crate::INF, Float::min);
+        let bvh = CurveBVH::new(Curve::strand(
+            &cps, CurveBasis::BSpline, 0.1, 0.02, CurveType::Cylinder, Arc::new(Material::Blank),
+        ), BVHBuild::BinnedSah);
+
+        for _ in 0..1_000 {
+            let xo = 3.0 * rng::maps::square_to_sphere(rng.gen_vec2());
+            let r = Ray::new(xo, 0.5 * rng.gen_vec3() - 0.25 - xo);
+            let t = bvh.hit(&r, 0.0, crate::INF).map_or(crate::INF, |h| h.t);
+            assert!(t == ts(&r));
+        }
+    }
+
+    #[test]
+    fn shares_material() {
+        let material = Arc::new(Material::Blank);
+        let cps = [0.0, 1.0, 2.0, 3.0].map(|x| Point::new(x, 0.0, 0.0));
+        let curves: Vec<_> = (0..3)
+            .map(|_| Curve::new(cps, 0.1, 0.1, Arc::clone(&material)))
+            .collect();
+
+        assert!(Arc::strong_count(&material) == 1 + curves.len());
+    }
+}
@@ -85,11 +85,21 @@ impl Object for Cylinder {
         let uv = Vec2::new(u, v);
 
         let err = efloat::gamma(3) * Vec3::new(xi.x, 0.0, xi.z).abs();
+        // dp/du
+        let tangent = Direction::new(xi.z, 0.0, -xi.x);
 
         Hit::new(t.value, &self.material, r.dir, xi, err, ni, ni, uv)
+            .and_then(|h| h.unless_cut_out(r.dir))
+            .map(|h| h.with_tangent(tangent))
+            // cut out by the opacity mask, try the far side
+            .or_else(|| self.hit(r, t.high, t_max))
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if self.material.has_alpha() {
+            return self.hit(r, t_min, t_max).map_or(crate::INF, |h| h.t);
+        }
+
         let xo = r.origin;
         let wi = r.dir;
 
@@ -94,12 +94,16 @@ impl Object for Disk {
                 self.normal,
                 self.normal,
                 uv
-            )
+            ).and_then(|h| h.unless_cut_out(r.dir))
         }
     }
 
     #[allow(clippy::if_same_then_else)]
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if self.material.has_alpha() {
+            return self.hit(r, t_min, t_max).map_or(crate::INF, |h| h.t);
+        }
+
         let xo = r.origin;
         let wi = r.dir;
 
@@ -134,7 +138,7 @@ impl Sampleable for Disk {
 
     fn material(&self) -> &Material { &self.material }
 
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
+    fn sample_on(&self, _time: Float, rand_sq: Vec2) -> Hit {
         let rand_disk = rng::maps::square_to_disk(rand_sq);
 
         let xo = self.origin + self.uvw.to_world(Point::new(
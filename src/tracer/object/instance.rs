@@ -1,8 +1,14 @@
 use super::*;
+use std::borrow::Cow;
+use crate::math::transform::AnimatedTransform;
+
+/// Number of steps over the motion at which the bounding box is evaluated
+const MOTION_BOUND_STEPS: usize = 32;
 
 /// Instance of an object i.e. an object to which affine transformations have
-/// been applied
-pub struct Instance<T> {
+/// been applied. Clones share the object and the material, so the object
+/// can be placed many times in the scene while stored only once.
+pub struct Instance<T: ?Sized> {
     /// Object to be instanced
     object: Arc<T>,
     /// Transformation from local to world
@@ -11,45 +17,81 @@ pub struct Instance<T> {
     /// Transpose of `inv_transform` without translation.
     normal_transform: Mat3,
     /// Optional material to use for the instance
-    material: Option<Material>,
+    material: Option<Arc<Material>>,
+    /// Keyframed motion of the instance in consecutive time intervals,
+    /// `transform` is the first keyframe
+    motion: Vec<AnimatedTransform>,
 }
 
 impl<T> Instance<T> {
     /// Constructs an instance of `object` that is transformed with
     /// `transform`.
     pub fn new(object: T) -> Self {
-        let object = Arc::new(object);
+        Self::from_arc(Arc::new(object))
+    }
+}
+
+impl<T: ?Sized> Instance<T> {
+    /// Constructs an instance of `object` that is shared with other instances
+    pub fn from_arc(object: Arc<T>) -> Self {
         let transform = Transform::default();
         let normal_transform = transform.to_normal();
         let material = None;
+        let motion = Vec::new();
 
-        Self { object, transform, normal_transform, material }
+        Self { object, transform, normal_transform, material, motion }
     }
 
-    /// Clone the instance without cloning the underlying object
+    /// Clone the instance without cloning the underlying object. `material`
+    /// replaces the material of `self`, which is shared otherwise.
     pub fn clone(&self, material: Option<Material>) -> Self {
         Self {
-            material,
+            material: material.map(Arc::new).or_else(|| self.material.clone()),
             object: Arc::clone(&self.object),
             transform: self.transform.clone(),
             normal_transform: self.normal_transform.clone(),
+            motion: self.motion.clone(),
+        }
+    }
+
+    /// Use `material` instead of the materials of the object
+    pub fn with_material(mut self, material: Material) -> Box<Self> {
+        self.material = Some(Arc::new(material));
+        Box::new(self)
+    }
+
+    /// The shared object being instanced
+    pub fn object(&self) -> &Arc<T> {
+        &self.object
+    }
+
+    /// Transformation and normal transformation at `time`
+    fn transforms_at(&self, time: Float) -> (Cow<'_, Transform>, Cow<'_, Mat3>) {
+        if self.motion.is_empty() {
+            return (Cow::Borrowed(&self.transform), Cow::Borrowed(&self.normal_transform));
         }
+
+        // last interval that starts before `time`, or the first one
+        let idx = self.motion.partition_point(|m| m.time_range().0 <= time).max(1) - 1;
+        let transform = self.motion[idx].at(time);
+        let normal_transform = transform.to_normal();
+        (Cow::Owned(transform), Cow::Owned(normal_transform))
     }
 
-    fn propagate_fp_err(&self, xo: Point, fp_error: Vec3) -> Vec3 {
+    fn propagate_fp_err(transform: &Transform, xo: Point, fp_error: Vec3) -> Vec3 {
         let e3 = fp_error.abs();
         let p3 = xo.abs();
 
         if e3.x == 0.0 && e3.y == 0.0 && e3.z == 0.0 {
-            efloat::gamma(3) * self.transform.abs().transform_pt(p3)
+            efloat::gamma(3) * transform.abs().transform_pt(p3)
         } else {
-            efloat::gamma(3) * self.transform.abs().transform_pt(p3)
-                + (efloat::gamma(3) + 1.0) * self.transform.abs().transform_dir(e3)
+            efloat::gamma(3) * transform.abs().transform_pt(p3)
+                + (efloat::gamma(3) + 1.0) * transform.abs().transform_dir(e3)
         }
     }
 }
 
-impl<T: Object> Instance<T> {
+impl<T: Object + ?Sized> Instance<T> {
     /// Translate `self`, such that bounding box center is at the origin
     pub fn to_origin(self) -> Box<Self> {
         let AaBoundingBox { ax_min, ax_max } = self.bounding_box();
@@ -77,41 +119,95 @@ impl<T: Object> Instance<T> {
     }
 }
 
-impl<T: Object> Object for Instance<T> {
+impl<T: Object + ?Sized> Object for Instance<T> {
     fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<Hit> {
         // inner object is in world coordinates. hence apply inverse
         // transformation to ray instead of transformation to object.
-        let ray_local = r.transform::<false>(&self.transform);
+        let (transform, normal_transform) = self.transforms_at(r.time);
+        let ray_local = r.transform::<false>(&transform);
 
         self.object.hit(&ray_local, t_min, t_max)
             .map(|mut h| {
-                h.ns = (self.normal_transform.mul_vec3(h.ns)).normalize();
-                h.ng = (self.normal_transform.mul_vec3(h.ng)).normalize();
-
-                h.fp_error = self.propagate_fp_err(h.p, h.fp_error);
+                h.ns = (normal_transform.mul_vec3(h.ns)).normalize();
+                h.ng = (normal_transform.mul_vec3(h.ng)).normalize();
+                h.tangent = h.tangent.map(|t| transform.transform_dir(t).normalize());
+                h.dpduv = h.dpduv.map(|(dpdu, dpdv)| (
+                    transform.transform_dir(dpdu),
+                    transform.transform_dir(dpdv),
+                ));
+                h.dnduv = h.dnduv.map(|(dndu, dndv)| (
+                    normal_transform.mul_vec3(dndu),
+                    normal_transform.mul_vec3(dndv),
+                ));
+
+                h.fp_error = Self::propagate_fp_err(&transform, h.p, h.fp_error);
 
                 if let Some(material) = self.material.as_ref() {
                     h.material = material;
                 }
 
-                h.p =  self.transform.transform_pt(h.p);
+                h.p =  transform.transform_pt(h.p);
                 h
             })
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
-        let r_local = r.transform::<false>(&self.transform);
+        let (transform, _) = self.transforms_at(r.time);
+        let r_local = r.transform::<false>(&transform);
         self.object.hit_t(&r_local, t_min, t_max)
     }
 
     fn bounding_box(&self) -> AaBoundingBox {
+        if self.motion.is_empty() {
+            return self.bounding_box_with(&self.transform);
+        }
+
+        self.motion.iter().fold(AaBoundingBox::default(), |acc, motion| {
+            acc.merge(&self.motion_bounding_box(motion))
+        })
+    }
+
+    fn num_primitives(&self) -> usize { self.object.num_primitives() }
+}
+
+impl<T: Object + ?Sized> Instance<T> {
+    /// Bounding box of the object over the interval of `motion`
+    fn motion_bounding_box(&self, motion: &AnimatedTransform) -> AaBoundingBox {
+        // boxes at evenly spaced times contain the corners of the object
+        // box at those times. in between the corners move along curves
+        // that stray at most `deviation` from the boxes.
+        let (t0, t1) = motion.time_range();
+        let aabb = (0..=MOTION_BOUND_STEPS)
+            .map(|i| t0 + (t1 - t0) * i as Float / MOTION_BOUND_STEPS as Float)
+            .fold(AaBoundingBox::default(), |acc, time| {
+                acc.merge(&self.bounding_box_with(&motion.at(time)))
+            });
+
+        let AaBoundingBox { ax_min, ax_max } = self.object.bounding_box();
+        let deviation = (0..8)
+            .map(|i| Point::new(
+                if i & 1 == 0 { ax_min.x } else { ax_max.x },
+                if i & 2 == 0 { ax_min.y } else { ax_max.y },
+                if i & 4 == 0 { ax_min.z } else { ax_max.z },
+            ))
+            .map(|p| motion.path_deviation(p, MOTION_BOUND_STEPS))
+            .fold(0.0, Float::max);
+
+        AaBoundingBox::new(
+            aabb.ax_min - Vec3::splat(deviation),
+            aabb.ax_max + Vec3::splat(deviation),
+        )
+    }
+
+    /// Bounding box of the object transformed with `transform`
+    fn bounding_box_with(&self, transform: &Transform) -> AaBoundingBox {
         /* Graphics Gems I, TRANSFORMING AXIS-ALIGNED BOUNDING BOXES */
-        let mut ax_min = self.transform.to_translation();
-        let mut ax_max = self.transform.to_translation();
+        let mut ax_min = transform.to_translation();
+        let mut ax_max = transform.to_translation();
         let aabb = self.object.bounding_box();
 
         for axis in [Axis::X, Axis::Y, Axis::Z] {
-            let ri = self.transform.row(axis as usize).truncate();
+            let ri = transform.row(axis as usize).truncate();
             let a0 = ri * aabb.ax_min;
             let a1 = ri * aabb.ax_max;
             let mi = a0.min(a1).dot(Vec3::ONE);
@@ -125,11 +221,9 @@ impl<T: Object> Object for Instance<T> {
 
         AaBoundingBox::new(ax_min, ax_max)
     }
-
-    fn num_primitives(&self) -> usize { self.object.num_primitives() }
 }
 
-impl<T: Sampleable> Sampleable for Instance<T> {
+impl<T: Sampleable + ?Sized> Sampleable for Instance<T> {
     fn area(&self) -> Float {
         let scale = self.transform.to_scale();
 
@@ -139,18 +233,25 @@ impl<T: Sampleable> Sampleable for Instance<T> {
             // TODO: add warning for non-uniform scaling of lights
         }
 
+        // the area of animated lights has to stay the same over the motion
+        if self.motion.iter().any(|m| m.end().to_scale().distance(scale) > crate::EPSILON) {
+            panic!("Animated lights can not change in scale");
+        }
+
         scale.x * scale.y * self.object.area()
     }
 
     fn material(&self) -> &Material { self.object.material() }
 
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
-        let mut ho = self.object.sample_on(rand_sq);
+    fn sample_on(&self, time: Float, rand_sq: Vec2) -> Hit {
+        let (transform, normal_transform) = self.transforms_at(time);
+        let mut ho = self.object.sample_on(time, rand_sq);
 
-        ho.ng = self.normal_transform.mul_vec3(ho.ng).normalize();
-        ho.ns = self.normal_transform.mul_vec3(ho.ns).normalize();
-        ho.p = self.transform.transform_pt(ho.p);
-        ho.fp_error = self.propagate_fp_err(ho.p, ho.fp_error);
+        ho.ng = normal_transform.mul_vec3(ho.ng).normalize();
+        ho.ns = normal_transform.mul_vec3(ho.ns).normalize();
+        ho.tangent = ho.tangent.map(|t| transform.transform_dir(t).normalize());
+        ho.p = transform.transform_pt(ho.p);
+        ho.fp_error = Self::propagate_fp_err(&transform, ho.p, ho.fp_error);
 
         if let Some(material) = self.material.as_ref() {
             ho.material = material;
@@ -159,18 +260,20 @@ impl<T: Sampleable> Sampleable for Instance<T> {
         ho
     }
 
-    fn sample_towards(&self, xo: Point, rand_sq: Vec2) -> Direction {
-        let xo_local = self.transform.transform_pt_inv(xo);
-        let dir_local = self.object.sample_towards(xo_local, rand_sq);
+    fn sample_towards(&self, xo: Point, time: Float, rand_sq: Vec2) -> Direction {
+        let (transform, _) = self.transforms_at(time);
+        let xo_local = transform.transform_pt_inv(xo);
+        let dir_local = self.object.sample_towards(xo_local, time, rand_sq);
 
-        self.transform.transform_dir(dir_local).normalize()
+        transform.transform_dir(dir_local).normalize()
     }
 
     fn sample_towards_pdf(&self, ri: &Ray, xi: Point, ng: Normal) -> Float {
-        let normal_transform_inverse = self.normal_transform.inv().transpose();
-        let ng_local = (normal_transform_inverse.mul_vec3(ng)).normalize();
-        let xi_local = self.transform.transform_pt_inv(xi);
-        let ri_local = ri.transform::<true>(&self.transform);
+        let (transform, normal_transform) = self.transforms_at(ri.time);
+        // normals map back with the inverse of the normal transformation
+        let ng_local = normal_transform.inv().mul_vec3(ng).normalize();
+        let xi_local = transform.transform_pt_inv(xi);
+        let ri_local = ri.transform::<true>(&transform);
 
         let wi = ri.dir;
         let wi_local = ri_local.dir;
@@ -186,8 +289,8 @@ impl<T: Sampleable> Sampleable for Instance<T> {
         // the base of the parallellepiped gives us the area scale at the
         // point of impact. think this is not exact with ansiotropic
         // scaling of solids.
-        let height = ng.dot(self.transform.transform_dir(ng_local)).abs();
-        let volume = self.transform.to_mat3().det().abs();
+        let height = ng.dot(transform.transform_dir(ng_local)).abs();
+        let volume = transform.to_mat3().det().abs();
         let jacobian = volume / height;
 
         // jacoabian takes care of area, just undo and redo SA conversion
@@ -218,6 +321,9 @@ pub trait Instanceable<T> {
 
     /// Rotate around z-axis by `r` radians
     fn rotate_z(self, r: Float) -> Box<Instance<T>>;
+
+    /// Keyframe the motion of the object from `time_start` to `time_end`
+    fn animate(self, time_start: Float, time_end: Float) -> Box<Instance<T>>;
 }
 
 /// To make applying transformations to objects easy
@@ -251,23 +357,60 @@ impl<T: Object> Instanceable<T> for T {
         Instance::new(self)
             .rotate_z(r)
     }
+
+    fn animate(self, time_start: Float, time_end: Float) -> Box<Instance<T>> {
+        Instance::new(self)
+            .animate(time_start, time_end)
+    }
 }
 
 /// Prevent nested Instance structs
-impl<T: Object> Instance<T> {
-    /// Apply translation AFTER curret transformations
-    pub fn translate(mut self, x: Float, y: Float, z: Float) -> Box<Instance<T>> {
-        self.transform = Transform::translation(x, y, z) * self.transform;
-        self.normal_transform = self.transform.to_normal();
+impl<T: Object + ?Sized> Instance<T> {
+    /// Start keyframing the motion of `self` from `time_start` to `time_end`.
+    /// The current transformation becomes the keyframe at `time_start` and
+    /// transformations applied after this only change the keyframe at
+    /// `time_end`. Call again to continue the motion in a later interval.
+    /// Rays see the object interpolated between the keyframes at their time.
+    pub fn animate(mut self, time_start: Float, time_end: Float) -> Box<Instance<T>> {
+        let current = match self.motion.last() {
+            None => self.transform.clone(),
+            Some(motion) => {
+                assert!(motion.time_range().1 <= time_start);
+                motion.end().clone()
+            }
+        };
+
+        self.motion.push(AnimatedTransform::new(current.clone(), current, time_start, time_end));
+        Box::new(self)
+    }
+
+    /// Apply `transform` AFTER current transformations, to the last keyframe
+    /// if `self` is animated
+    pub(crate) fn apply(mut self, transform: Transform) -> Box<Instance<T>> {
+        match self.motion.pop() {
+            None => {
+                self.transform = transform * self.transform;
+                self.normal_transform = self.transform.to_normal();
+            }
+            Some(motion) => {
+                let (t0, t1) = motion.time_range();
+                let start = motion.start().clone();
+                let end = transform * motion.end().clone();
+                self.motion.push(AnimatedTransform::new(start, end, t0, t1));
+            }
+        }
         Box::new(self)
     }
 
+    /// Apply translation AFTER curret transformations
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Box<Instance<T>> {
+        self.apply(Transform::translation(x, y, z))
+    }
+
     /// Apply scale AFTER current transformations
-    pub fn scale(mut self, x: Float, y: Float, z: Float) -> Box<Instance<T>> {
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Box<Instance<T>> {
         assert!(x * y * z != 0.0);
-        self.transform = Transform::scale(x, y, z) * self.transform;
-        self.normal_transform = self.transform.to_normal();
-        Box::new(self)
+        self.apply(Transform::scale(x, y, z))
     }
 
     /// Apply uniform scale AFTER current transformations
@@ -277,24 +420,18 @@ impl<T: Object> Instance<T> {
 
     /// Apply x-rotation AFTER current transformations.
     /// Looking at positive x, rotation in clockwise direction.
-    pub fn rotate_x(mut self, r: Float) -> Box<Instance<T>> {
-        self.transform = Transform::rotate_x(r) * self.transform;
-        self.normal_transform = self.transform.to_normal();
-        Box::new(self)
+    pub fn rotate_x(self, r: Float) -> Box<Instance<T>> {
+        self.apply(Transform::rotate_x(r))
     }
 
     /// Apply y-rotation AFTER current transformations
-    pub fn rotate_y(mut self, r: Float) -> Box<Instance<T>> {
-        self.transform = Transform::rotate_y(r) * self.transform;
-        self.normal_transform = self.transform.to_normal();
-        Box::new(self)
+    pub fn rotate_y(self, r: Float) -> Box<Instance<T>> {
+        self.apply(Transform::rotate_y(r))
     }
 
     /// Apply z-rotation AFTER current transformations
-    pub fn rotate_z(mut self, r: Float) -> Box<Instance<T>> {
-        self.transform = Transform::rotate_z(r) * self.transform;
-        self.normal_transform = self.transform.to_normal();
-        Box::new(self)
+    pub fn rotate_z(self, r: Float) -> Box<Instance<T>> {
+        self.apply(Transform::rotate_z(r))
     }
 }
 
@@ -325,7 +462,7 @@ mod instance_tests {
 
         for _ in 0..NUM_SAMPLES {
             let xo = 5.0 * rng::maps::square_to_sphere(rng.gen_vec2());
-            let wi = s.sample_towards(xo, rng.gen_vec2());
+            let wi = s.sample_towards(xo, 0.0, rng.gen_vec2());
             let r = Ray::new(xo, wi);
             let Some(h) = s.hit(&r, 0.0, crate::INF) else { panic!() };
             let Some(h_ref) = s_ref.hit(&r, 0.0, crate::INF) else { panic!() };
@@ -341,4 +478,133 @@ mod instance_tests {
         let (s, s_ref) = spheres();
         assert!((s.area() - s_ref.area()).abs() < crate::EPSILON);
     }
+
+    fn moving_sphere() -> Box<Instance<Sphere>> {
+        Sphere::new(0.5, Material::Blank)
+            .animate(0.0, 1.0)
+            .translate(4.0, 0.0, 0.0)
+            .rotate_z(crate::PI / 2.0)
+    }
+
+    #[test]
+    fn animated_hit_follows_ray_time() {
+        let s = moving_sphere();
+        let r = |x: Float, y: Float, time: Float| {
+            Ray::new(Point::new(x, y, 2.0), -Direction::Z).with_time(time)
+        };
+
+        assert!(s.hit(&r(0.0, 0.0, 0.0), 0.0, crate::INF).is_some());
+        assert!(s.hit(&r(0.0, 0.0, 1.0), 0.0, crate::INF).is_none());
+        assert!(s.hit(&r(0.0, 4.0, 1.0), 0.0, crate::INF).is_some());
+        // translation of the keyframes gets interpolated linearly
+        assert!(s.hit_t(&r(0.0, 2.0, 0.5), 0.0, crate::INF) < crate::INF);
+    }
+
+    #[test]
+    fn animated_sampling_follows_time() {
+        let s = moving_sphere();
+        let s_ref = Sphere::new(0.5, Material::Blank);
+        let mut rng = Xorshift::default();
+        let xo = Point::new(0.0, 0.0, 3.0);
+
+        for i in 0..=NUM_SAMPLES {
+            let time = i as Float / NUM_SAMPLES as Float;
+            let center = s.motion[0].at(time).transform_pt(Point::ZERO);
+            let ho = s.sample_on(time, rng.gen_vec2());
+            assert!((ho.p.distance(center) - 0.5).abs() < crate::EPSILON);
+
+            let wi = s.sample_towards(xo, time, rng.gen_vec2());
+            let r = Ray::new(xo, wi).with_time(time);
+            let Some(h) = s.hit(&r, 0.0, crate::INF) else { panic!() };
+            let p = s.sample_towards_pdf(&r, h.p, h.ng);
+            let r_ref = Ray::new(xo - center, wi);
+            let p_ref = s_ref.sample_towards_pdf(&r_ref, h.p - center, h.ng);
+
+            assert!((p - p_ref).abs() < crate::EPSILON.sqrt());
+        }
+    }
+
+    #[test]
+    fn animated_bounds_contain_motion() {
+        let s = moving_sphere();
+        let aabb = s.bounding_box();
+        let motion = &s.motion[0];
+
+        for i in 0..=NUM_SAMPLES {
+            let time = i as Float / NUM_SAMPLES as Float;
+            let at = s.bounding_box_with(&motion.at(time));
+            assert!(aabb.contains(at.ax_min) && aabb.contains(at.ax_max));
+        }
+    }
+
+    #[test]
+    fn bvh_hits_animated_instances() {
+        let mut bvh: BVH<Box<dyn Object>> = BVH::default();
+        for y in 0..4 {
+            bvh.add(Sphere::new(0.5, Material::Blank)
+                .translate(0.0, 3.0 * y as Float, 0.0)
+                .animate(0.0, 1.0)
+                .translate(4.0, 0.0, 0.0));
+        }
+        bvh.build();
+
+        for y in 0..4 {
+            let y = 3.0 * y as Float;
+            let r = Ray::new(Point::new(4.0, y, 2.0), -Direction::Z);
+            assert!(bvh.hit(&r, 0.0, crate::INF).is_none());
+            assert!(bvh.hit(&r.with_time(1.0), 0.0, crate::INF).is_some());
+        }
+    }
+
+    #[test]
+    fn animation_continues_in_later_intervals() {
+        let s = Sphere::new(0.5, Material::Blank)
+            .animate(0.0, 1.0)
+            .translate(4.0, 0.0, 0.0)
+            .animate(2.0, 3.0)
+            .translate(0.0, 4.0, 0.0);
+        let hits = |x: Float, y: Float, time: Float| {
+            let r = Ray::new(Point::new(x, y, 2.0), -Direction::Z).with_time(time);
+            s.hit(&r, 0.0, crate::INF).is_some()
+        };
+
+        assert!(hits(0.0, 0.0, -1.0));
+        assert!(hits(4.0, 0.0, 1.5));
+        assert!(hits(4.0, 2.0, 2.5));
+        assert!(hits(4.0, 4.0, 4.0));
+        assert!(!hits(4.0, 0.0, 4.0));
+
+        let aabb = s.bounding_box();
+        assert!(aabb.contains(Point::ZERO) && aabb.contains(Point::new(4.0, 4.0, 0.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Animated lights can not change in scale")]
+    fn growing_lights_are_rejected() {
+        let light = Sphere::new(0.5, Material::Blank)
+            .animate(0.0, 1.0)
+            .scale_uniform(2.0);
+        crate::tracer::Scene::default().add_light(light);
+    }
+
+    #[test]
+    fn clones_share_object_and_material() {
+        let num_instances = 1_000;
+        let sphere: Arc<Sphere> = Arc::from(Sphere::new(1.0, Material::Blank));
+        let tree = Instance::from_arc(sphere).with_material(Material::mirror());
+
+        let mut bvh: BVH<Box<dyn Object>> = BVH::default();
+        for i in 0..num_instances {
+            bvh.add(tree.clone(None).translate(3.0 * i as Float, 0.0, 0.0));
+        }
+        bvh.build();
+        assert!(Arc::strong_count(tree.object()) == num_instances + 1);
+
+        for i in (0..num_instances).step_by(97) {
+            let r = Ray::new(Point::new(3.0 * i as Float, 0.0, 5.0), -Direction::Z);
+            let Some(h) = bvh.hit(&r, 0.0, crate::INF) else { panic!() };
+            assert!(h.material.is_specular());
+            assert!((h.t - 4.0).abs() < crate::EPSILON.sqrt());
+        }
+    }
 }
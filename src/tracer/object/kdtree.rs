@@ -1,10 +1,12 @@
 use super::*;
 use crate::formatting;
-use std::{ cmp::Ordering, sync::mpsc, thread, time::Instant };
+use std::{ cmp::Ordering, sync::mpsc, thread, time::{ Duration, Instant } };
 use rustc_hash::{FxHashMap, FxHashSet};
 use node::{KdNode, KdNodeBuilder};
 use event::{ KdEvent, KdEventType };
 
+pub use cache::CachedKdTree;
+
 /// Triangle mesh constructed as a kD-tree
 pub type Mesh = KdTree<Triangle>;
 
@@ -12,6 +14,8 @@ const IDX_NAN: usize = usize::MAX;
 
 mod node;
 mod event;
+/// Binary encoding of kD-trees of triangles
+mod cache;
 #[cfg(test)]
 mod kdtree_tests;
 
@@ -22,6 +26,7 @@ pub struct KdTree<T> {
     objects: Vec<T>,
     nodes: Vec<KdNode>,
     boundary: AaBoundingBox,
+    stats: AccelStats,
 }
 
 impl KdTree<Triangle> {
@@ -81,11 +86,39 @@ impl<T: Object> KdTree<T> {
             println!("Created kd-tree in {}", formatting::fmt_elapsed(start.elapsed()));
         };
 
-        Self {
+        let mut tree = Self {
             nodes,
             objects,
             boundary,
+            stats: AccelStats::default(),
+        };
+        tree.stats = tree.compute_stats(start.elapsed());
+        tree
+    }
+
+    /// Statistics of the build. Build time is the load time for trees read
+    /// from a cache.
+    pub fn stats(&self) -> &AccelStats { &self.stats }
+
+    fn compute_stats(&self, build_time: Duration) -> AccelStats {
+        let mut stats = AccelStats::new(build_time);
+        let root_area = self.boundary.area();
+
+        let mut stack = vec![(0, self.boundary, 1)];
+        while let Some((idx, bounds, depth)) = stack.pop() {
+            let node = &self.nodes[idx];
+            let area = bounds.area() / root_area;
+            if node.leaf {
+                stats.add_leaf(area, depth, node.indices.len());
+            } else {
+                stats.add_inner(area, depth);
+                let (left, right) = bounds.split(node.axis, node.point);
+                stack.push((idx + 1, left, depth + 1));
+                stack.push((node.right, right, depth + 1));
+            }
         }
+
+        stats
     }
 
     /// Returns self uniformly scaled as an instance with largest dimension
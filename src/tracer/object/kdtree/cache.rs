@@ -0,0 +1,116 @@
+use super::*;
+use crate::binary::Binary;
+use crate::tracer::TriangleIndices;
+use std::io::{ self, Read, Result, Write };
+
+impl Binary for KdNode {
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.leaf.write(w)?;
+        if self.leaf {
+            self.indices.write(w)
+        } else {
+            self.axis.write(w)?;
+            self.point.write(w)?;
+            self.right.write(w)
+        }
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        if bool::read(r)? {
+            Ok(KdNode::leaf(Vec::read(r)?))
+        } else {
+            let mut node = KdNode::split(Axis::read(r)?, Float::read(r)?);
+            node.right = usize::read(r)?;
+            Ok(node)
+        }
+    }
+}
+
+/// kD-tree of triangles read from a cache. Turned to a `KdTree` once the
+/// mesh of the triangles has its materials.
+pub struct CachedKdTree {
+    triangles: Vec<TriangleIndices>,
+    nodes: Vec<KdNode>,
+    boundary: AaBoundingBox,
+}
+
+impl KdTree<Triangle> {
+    /// Writes the triangles and nodes of the tree to `w`. The mesh of the
+    /// triangles gets written separately.
+    pub(crate) fn write_cache(&self, w: &mut impl Write) -> Result<()> {
+        self.boundary.ax_min.write(w)?;
+        self.boundary.ax_max.write(w)?;
+        self.objects.len().write(w)?;
+        for triangle in &self.objects {
+            triangle.write_cache(w)?;
+        }
+        self.nodes.write(w)
+    }
+
+    /// Mesh of the triangles, `None` if the tree is empty
+    pub(crate) fn mesh(&self) -> Option<&TriangleMesh> {
+        self.objects.first().map(Triangle::mesh)
+    }
+}
+
+impl CachedKdTree {
+    /// Reads a tree written by `KdTree::write_cache` with triangles of `mesh`
+    /// that has `num_materials` materials. Checks that the tree is well formed.
+    pub fn read(r: &mut impl Read, mesh: &TriangleMesh, num_materials: usize) -> Result<Self> {
+        let boundary = AaBoundingBox::new(Point::read(r)?, Point::read(r)?);
+        let num_triangles = usize::read(r)?;
+        let triangles = (0..num_triangles)
+            .map(|_| TriangleIndices::read(r, mesh, num_materials))
+            .collect::<Result<Vec<_>>>()?;
+        let nodes: Vec<KdNode> = Vec::read(r)?;
+
+        if !Self::well_formed(&nodes, num_triangles) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed kD-tree"));
+        }
+
+        Ok(Self { triangles, nodes, boundary })
+    }
+
+    /// Each node is reached exactly once from the root and the leaves index
+    /// existing triangles
+    fn well_formed(nodes: &[KdNode], num_triangles: usize) -> bool {
+        let mut seen = vec![false; nodes.len()];
+        let mut stack = vec![0];
+
+        while let Some(idx) = stack.pop() {
+            if idx >= nodes.len() || seen[idx] {
+                return false;
+            }
+            seen[idx] = true;
+
+            let node = &nodes[idx];
+            if node.leaf {
+                if node.indices.iter().any(|i| *i >= num_triangles) {
+                    return false;
+                }
+            } else {
+                stack.push(idx + 1);
+                stack.push(node.right);
+            }
+        }
+
+        seen.iter().all(|s| *s)
+    }
+
+    /// The tree over triangles of `mesh`
+    pub fn build(self, mesh: &Arc<TriangleMesh>) -> KdTree<Triangle> {
+        let start = Instant::now();
+        let objects = self.triangles.iter()
+            .map(|indices| Triangle::from_cache(Arc::clone(mesh), indices))
+            .collect();
+
+        let mut tree = KdTree {
+            objects,
+            nodes: self.nodes,
+            boundary: self.boundary,
+            stats: AccelStats::default(),
+        };
+        tree.stats = tree.compute_stats(start.elapsed());
+        tree
+    }
+}
@@ -193,3 +193,77 @@ mod mesh {
         TriangleMesh::new(vertices, f, vec!(), vec!(), Material::Blank)
     }
 }
+
+mod cache {
+    use super::*;
+    use crate::binary::Binary;
+    use std::io::Cursor;
+
+    fn soup() -> Mesh {
+        let mut rng = Xorshift::default();
+        let mut vertices = vec!();
+        let mut faces = vec!();
+        for i in 0..300 {
+            let center = 10.0 * rng.gen_vec3();
+            for _ in 0..3 {
+                vertices.push(center + rng.gen_vec3());
+            }
+            let idx = vec!(3 * i, 3 * i + 1, 3 * i + 2);
+            faces.push(Face::new(idx.clone(), vec!(i % 3; 3), idx));
+        }
+        let normals = vec!(Normal::X, Normal::Y, Normal::Z);
+        let uvs = vec!(Vec2::ZERO; vertices.len());
+
+        TriangleMesh::new(vertices, faces, normals, uvs, Material::Blank)
+    }
+
+    fn read(bytes: &[u8]) -> std::io::Result<Mesh> {
+        let mut r = Cursor::new(bytes);
+        let mut mesh = TriangleMesh::read_cache(&mut r)?;
+        let tree = CachedKdTree::read(&mut r, &mesh, 1)?;
+        mesh.materials = vec!(Material::Blank);
+        Ok(tree.build(&Arc::new(mesh)))
+    }
+
+    #[test]
+    fn round_trip() {
+        let mesh = soup();
+        let mut bytes = vec!();
+        mesh.mesh().unwrap().write_cache(&mut bytes).unwrap();
+        mesh.write_cache(&mut bytes).unwrap();
+        let cached = read(&bytes).unwrap();
+
+        assert!(cached.nodes.len() == mesh.nodes.len());
+        assert!(cached.stats().num_leaves == mesh.stats().num_leaves);
+
+        let mut rng = Xorshift::default();
+        for _ in 0..NUM_RAYS {
+            let r = Ray::new(20.0 * rng.gen_vec3() - 5.0, rng::maps::square_to_sphere(rng.gen_vec2()));
+            let t = mesh.hit(&r, 0.0, crate::INF).map(|h| h.t);
+            let t_cached = cached.hit(&r, 0.0, crate::INF).map(|h| h.t);
+            assert!(t == t_cached);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let mesh = soup();
+        let mut bytes = vec!();
+        mesh.mesh().unwrap().write_cache(&mut bytes).unwrap();
+        let mesh_bytes = bytes.len();
+        mesh.write_cache(&mut bytes).unwrap();
+
+        // truncated
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+
+        // split node pointing past the nodes
+        bytes.truncate(mesh_bytes);
+        mesh.boundary.ax_min.write(&mut bytes).unwrap();
+        mesh.boundary.ax_max.write(&mut bytes).unwrap();
+        0usize.write(&mut bytes).unwrap();
+        let mut split = KdNode::split(Axis::X, 0.0);
+        split.right = 2;
+        vec!(split, KdNode::leaf(vec!())).write(&mut bytes).unwrap();
+        assert!(read(&bytes).is_err());
+    }
+}
@@ -92,11 +92,15 @@ impl Object for Plane {
                 self.normal,
                 self.normal,
                 uv
-            )
+            ).and_then(|h| h.unless_cut_out(wi))
         }
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if self.material.has_alpha() {
+            return self.hit(r, t_min, t_max).map_or(crate::INF, |h| h.t);
+        }
+
         let xo = r.origin;
         let wi = r.dir;
 
@@ -31,12 +31,17 @@ impl Rectangle {
         // mirror `b` around `ac`
         let d = origin + b0 + b1;
         let vertices = vec![a, b, c, d];
+        // texture coordinates are linear in the position, so the triangles
+        // interpolate them exactly before the opacity mask gets evaluated
+        let uvs = vertices.iter()
+            .map(|v| Vec2::new(b0.dot(*v), b1.dot(*v)))
+            .collect();
 
-        let faces = vec![Face::new(vec![0,1,2,3], vec![], vec![])];
+        let faces = vec![Face::new(vec![0,1,2,3], vec![], vec![0,1,2,3])];
 
         Box::new(Self {
             origin, b0, b1,
-            mesh: TriangleMesh::new(vertices, faces, vec![], vec![], material),
+            mesh: TriangleMesh::new(vertices, faces, vec![], uvs, material),
         })
     }
 
@@ -73,15 +78,6 @@ impl Rectangle {
 impl Object for Rectangle {
     fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<Hit> {
         self.mesh.hit(r, t_min, t_max)
-            .map(|mut h| {
-                let uv = Vec2::new(
-                    self.b0.dot(h.p),
-                    self.b1.dot(h.p),
-                );
-                h.uv = Hit::wrap_uv(uv);
-
-                h
-            })
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
@@ -110,7 +106,7 @@ impl Sampleable for Rectangle {
 
     fn material(&self) -> &Material { self.mesh.material() }
 
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
+    fn sample_on(&self, _time: Float, rand_sq: Vec2) -> Hit {
         let xo = self.origin + rand_sq.x * self.b0 + rand_sq.y * self.b1;
         let ng = self.b0.cross(self.b1).normalize();
 
@@ -135,13 +131,68 @@ impl Sampleable for Rectangle {
 #[cfg(test)]
 mod rectangle_tests {
     use super::*;
+    use crate::tracer::ray::RayDifferential;
+
+    fn rectangle() -> Box<Rectangle> {
+        Rectangle::new(
+            Mat3::new(
+                -Point::Y + Point::X,
+                Point::Y + Point::X,
+                Point::Y + -Point::X,
+            ),
+            Material::Blank,
+        )
+    }
 
-    test_util::test_sampleable!(Rectangle::new(
-        Mat3::new(
-            -Point::Y + Point::X,
-            Point::Y + Point::X,
-            Point::Y + -Point::X,
-        ),
-        Material::Blank,
-    ));
+    test_util::test_sampleable!(rectangle());
+
+    #[test]
+    fn differentials_footprint() {
+        let rect = rectangle();
+        let xo = Point::new(0.5, 0.5, 1.0);
+        let rd = RayDifferential {
+            rx_origin: xo + 0.1 * Point::X,
+            rx_dir: -Direction::Z,
+            ry_origin: xo + 0.2 * Point::Y,
+            ry_dir: -Direction::Z,
+        };
+        let r = Ray::new(xo, -Direction::Z).with_differentials(rd);
+        let h = rect.hit(&r, 0.0, crate::INF).unwrap().with_differentials(&r);
+
+        // texture coordinates are `b0 . p` and `b1 . p` with `|b0| = |b1| = 2`
+        let (duvdx, duvdy) = h.duvdxy;
+        assert!((duvdx.dot(duvdx).sqrt() - 0.2).abs() < 1e-10);
+        assert!((duvdy.dot(duvdy).sqrt() - 0.4).abs() < 1e-10);
+
+        // mirror reflection keeps the offset rays parallel
+        let ri = h.generate_ray_differential(&r, Direction::Z);
+        let rd = ri.differentials.unwrap();
+        assert!(rd.rx_dir.distance(Direction::Z) < 1e-10);
+        assert!(rd.ry_dir.distance(Direction::Z) < 1e-10);
+        assert!(rd.rx_origin.distance(Point::new(0.6, 0.5, 0.0)) < 1e-10);
+    }
+
+    #[test]
+    fn alpha_uses_rectangle_uv() {
+        use crate::Image;
+        use crate::tracer::{AlphaMask, Texture};
+        // opaque for u < 0.5, where u = -2x wraps to [0,1)
+        let alpha = (0..8).map(|x| if x < 4 { 1.0 } else { 0.0 }).collect();
+        let mask = AlphaMask::from_image(Image::new(alpha, 8, 1, 0.5)).with_threshold(0.5);
+        let rect = Rectangle::new(
+            Mat3::new(
+                -Point::Y + Point::X,
+                Point::Y + Point::X,
+                Point::Y + -Point::X,
+            ),
+            Material::diffuse(Texture::default()).with_alpha(mask),
+        );
+
+        let hit_at = |x: Float| {
+            let r = Ray::new(Point::new(x, 0.3, 1.0), -Direction::Z);
+            rect.hit(&r, 0.0, crate::INF).is_some()
+        };
+        assert!(hit_at(-0.1));
+        assert!(!hit_at(-0.4));
+    }
 }
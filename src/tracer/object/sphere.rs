@@ -69,11 +69,29 @@ impl Object for Sphere {
         let u = ((-ni.z).atan2(ni.x) + crate::PI) / (2.0 * crate::PI);
         let v = (-ni.y).acos() / crate::PI;
         let uv = Vec2::new(u, v);
+        // dp/du, undefined at the poles
+        let tangent = Direction::new(xi.z, 0.0, -xi.x);
+        let rho = (xi.x * xi.x + xi.z * xi.z).sqrt();
 
         Hit::new(t.value, &self.material, r.dir, xi, err, ni, ni, uv)
+            .and_then(|h| h.unless_cut_out(r.dir))
+            .map(|h| h.with_tangent(tangent))
+            .map(|h| if rho < crate::EPSILON { h } else {
+                let dpdu = 2.0 * crate::PI * tangent;
+                let dpdv = crate::PI * Direction::new(-xi.y * xi.x / rho, rho, -xi.y * xi.z / rho);
+                // the normal is the point scaled by the radius
+                h.with_dpduv(dpdu, dpdv)
+                    .with_dnduv(dpdu / self.radius, dpdv / self.radius)
+            })
+            // cut out by the opacity mask, try the far side
+            .or_else(|| self.hit(r, t.high, t_max))
     }
 
     fn hit_t(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if self.material.has_alpha() {
+            return self.hit(r, t_min, t_max).map_or(crate::INF, |h| h.t);
+        }
+
         let xo = r.origin;
         let wi = r.dir;
 
@@ -108,7 +126,7 @@ impl Sampleable for Sphere {
     fn material(&self) -> &Material { &self.material }
 
     /// Sample on unit sphere and scale
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
+    fn sample_on(&self, _time: Float, rand_sq: Vec2) -> Hit {
         let rand_sph = rng::maps::square_to_sphere(rand_sq);
 
         let xo = self.radius * rand_sph;
@@ -132,13 +150,13 @@ impl Sampleable for Sphere {
     /// Visible area from `xo` forms a cone. Sample a random point on the
     /// spherical cap that the visible area forms. Return a ray with direction
     /// towards the sampled point.
-    fn sample_towards(&self, xo: Point, rand_sq: Vec2) -> Direction {
+    fn sample_towards(&self, xo: Point, time: Float, rand_sq: Vec2) -> Direction {
         let dist_origin2 = xo.length_squared();
         let radius2 = self.radius * self.radius;
 
         let xi = if dist_origin2 < radius2 {
             // if inside sphere, just sample on the surface
-            let xi = self.sample_on(rand_sq).p;
+            let xi = self.sample_on(time, rand_sq).p;
             xi
         } else {
             /* sample a direction from the cone of visible areas */
@@ -210,5 +228,42 @@ impl Sampleable for Sphere {
 #[cfg(test)]
 mod sphere_tests {
     use super::*;
+    use crate::tracer::{AlphaMask, Texture};
     test_util::test_sampleable!(Sphere::new(1.0, Material::Blank));
+
+    #[test]
+    fn mirror_differentials_spread() {
+        use crate::tracer::ray::RayDifferential;
+        let s = Sphere::new(1.0, Material::Blank);
+        let xo = Point::new(0.0, 0.0, 2.0);
+        let rd = RayDifferential {
+            rx_origin: xo + 1e-3 * Point::X,
+            rx_dir: -Direction::Z,
+            ry_origin: xo + 1e-3 * Point::Y,
+            ry_dir: -Direction::Z,
+        };
+        let r = Ray::new(xo, -Direction::Z).with_differentials(rd);
+        let h = s.hit(&r, 0.0, crate::INF).unwrap().with_differentials(&r);
+        let ri = h.generate_ray_differential(&r, Direction::Z);
+        let rd = ri.differentials.unwrap();
+
+        // mirror reflection of the offset ray traced for real
+        let reflect = |xo: Point| {
+            let r = Ray::new(xo, -Direction::Z);
+            let n = s.hit(&r, 0.0, crate::INF).unwrap().ns;
+            r.dir - 2.0 * r.dir.dot(n) * n
+        };
+        assert!(rd.rx_dir.distance(reflect(xo + 1e-3 * Point::X)) < 1e-5);
+        assert!(rd.ry_dir.distance(reflect(xo + 1e-3 * Point::Y)) < 1e-5);
+    }
+
+    #[test]
+    fn alpha_cuts_out_both_sides() {
+        let cut_out = AlphaMask::constant(0.0);
+        let s = Sphere::new(1.0, Material::diffuse(Texture::default()).with_alpha(cut_out));
+        let r = Ray::new(Point::new(0.0, 0.0, 2.0), -Direction::Z);
+
+        assert!(s.hit(&r, 0.0, crate::INF).is_none());
+        assert!(s.hit_t(&r, 0.0, crate::INF) == crate::INF);
+    }
 }
@@ -0,0 +1,71 @@
+use crate::{ Float, formatting };
+use std::{ fmt, time::Duration };
+
+/// Build time and quality of an acceleration structure. The expected
+/// counts assume rays that hit the root bounds are spread uniformly,
+/// which makes them comparable between structures built from the same objects.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AccelStats {
+    /// Time it took to build the structure
+    pub build_time: Duration,
+    /// Number of nodes, inner and leaves
+    pub num_nodes: usize,
+    /// Number of leaf nodes
+    pub num_leaves: usize,
+    /// Length of the longest path from the root to a leaf
+    pub max_depth: usize,
+    /// Most objects in one leaf
+    pub max_leaf_size: usize,
+    /// Object references over all leaves. Above the number of objects
+    /// if objects get split to multiple leaves
+    pub num_references: usize,
+    /// Expected number of nodes visited by a ray
+    pub expected_nodes: Float,
+    /// Expected number of objects tested by a ray
+    pub expected_intersections: Float,
+}
+
+impl AccelStats {
+    pub(crate) fn new(build_time: Duration) -> Self {
+        Self { build_time, ..Default::default() }
+    }
+
+    /// Add an inner node with `area` relative to the root at `depth`
+    pub(crate) fn add_inner(&mut self, area: Float, depth: usize) {
+        self.num_nodes += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.expected_nodes += area;
+    }
+
+    /// Add a leaf with `size` objects and `area` relative to the root at `depth`
+    pub(crate) fn add_leaf(&mut self, area: Float, depth: usize, size: usize) {
+        self.add_inner(area, depth);
+        self.num_leaves += 1;
+        self.max_leaf_size = self.max_leaf_size.max(size);
+        self.num_references += size;
+        self.expected_intersections += area * size as Float;
+    }
+
+    /// Average number of objects in a leaf
+    pub fn avg_leaf_size(&self) -> Float {
+        self.num_references as Float / self.num_leaves.max(1) as Float
+    }
+}
+
+impl fmt::Display for AccelStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "built in {}, {} nodes, {} leaves, depth {}, {:.2} ({}) objects per leaf, \
+             {:.1} nodes and {:.1} objects tested per ray",
+            formatting::fmt_elapsed(self.build_time),
+            self.num_nodes,
+            self.num_leaves,
+            self.max_depth,
+            self.avg_leaf_size(),
+            self.max_leaf_size,
+            self.expected_nodes,
+            self.expected_intersections,
+        )
+    }
+}
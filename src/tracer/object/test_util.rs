@@ -59,7 +59,7 @@ macro_rules! test_sampleable {
 
             let xo = 5.0 * rng::maps::square_to_sphere(rng.gen_vec2());
             for _ in 0..NUM_SAMPLES {
-                let wi = s.sample_towards(xo, rng.gen_vec2());
+                let wi = s.sample_towards(xo, 0.0, rng.gen_vec2());
                 let ri = Ray::new(xo, wi);
                 let Some(hi) = s.hit(&ri, 0.0, crate::INF) else { panic!() };
 
@@ -74,7 +74,7 @@ macro_rules! test_sampleable {
             let mut rng = Xorshift::default();
 
             for _ in 0..NUM_SAMPLES {
-                let h = s.sample_on(rng.gen_vec2());
+                let h = s.sample_on(0.0, rng.gen_vec2());
                 let xo = 0.5 * Point::ONE;
                 let xi = h.p;
                 let ri = Ray::new(xo, xi - xo);
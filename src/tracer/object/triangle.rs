@@ -1,4 +1,11 @@
 use super::*;
+use super::bvh::{ self, BVHPrimitive };
+use packet::Triangle4;
+use crate::binary::{ self, Binary };
+use std::io::{ Read, Result, Write };
+
+/// Four triangles intersected at once
+mod packet;
 
 /// Triangle specified by three points
 pub struct Triangle {
@@ -14,6 +21,40 @@ pub struct Triangle {
     tidx: Option<(usize, usize, usize)>,
 }
 
+/// Indices of a triangle read from a cache before its mesh is ready
+#[derive(Clone, Copy)]
+pub(crate) struct TriangleIndices {
+    vidx: (usize, usize, usize),
+    midx: usize,
+    nidx: Option<(usize, usize, usize)>,
+    tidx: Option<(usize, usize, usize)>,
+}
+
+impl TriangleIndices {
+    /// Reads indices written by `Triangle::write_cache` and checks that they
+    /// are in bounds of `mesh` with `num_materials` materials
+    pub fn read(r: &mut impl Read, mesh: &TriangleMesh, num_materials: usize) -> Result<Self> {
+        let check = |(a, b, c): (usize, usize, usize), len: usize| -> Result<_> {
+            Ok((
+                binary::check_index(a, len)?,
+                binary::check_index(b, len)?,
+                binary::check_index(c, len)?,
+            ))
+        };
+
+        let vidx = check(Binary::read(r)?, mesh.vertices.len())?;
+        let midx = binary::check_index(usize::read(r)?, num_materials)?;
+        let nidx = Option::read(r)?
+            .map(|nidx| check(nidx, mesh.normals.len()))
+            .transpose()?;
+        let tidx = Option::read(r)?
+            .map(|tidx| check(tidx, mesh.uvs.len()))
+            .transpose()?;
+
+        Ok(Self { vidx, midx, nidx, tidx })
+    }
+}
+
 impl Triangle {
     /// Constructs on triangle of the mesh.
     ///
@@ -38,6 +79,23 @@ impl Triangle {
         }
     }
 
+    /// Writes the indices of the triangle to `w`
+    pub(crate) fn write_cache(&self, w: &mut impl Write) -> Result<()> {
+        self.vidx.write(w)?;
+        self.midx.write(w)?;
+        self.nidx.write(w)?;
+        self.tidx.write(w)
+    }
+
+    /// The mesh the triangle is in
+    pub(crate) fn mesh(&self) -> &TriangleMesh { &self.mesh }
+
+    /// Triangle of `mesh` with indices read from a cache
+    pub(crate) fn from_cache(mesh: Arc<TriangleMesh>, indices: &TriangleIndices) -> Self {
+        let TriangleIndices { vidx, midx, nidx, tidx } = *indices;
+        Self::new(mesh, vidx, midx, nidx, tidx)
+    }
+
     #[inline(always)]
     fn a(&self) -> Point { self.mesh.vertices[self.vidx.0] }
     #[inline(always)]
@@ -126,7 +184,8 @@ impl Triangle {
 
         let t = t_scaled / det;
 
-        if !GEO {
+        // opacity masks need the full hit
+        if !GEO && !self.material().has_alpha() {
             return Hit::from_t(t);
         }
 
@@ -184,6 +243,69 @@ impl Triangle {
 
         // material will be set by parent object
         Hit::new(t, self.material(), r.dir, xi, err, ns, ng, uv)
+            .and_then(|h| h.unless_cut_out(r.dir))
+            .map(|h| match self.tangent(barycentrics, ta, tb, tc) {
+                Some(tangent) => h.with_tangent(tangent),
+                None => h,
+            })
+            .map(|h| match Self::dpduv(self.a(), self.b(), self.c(), ta, tb, tc) {
+                Some((dpdu, dpdv)) => h.with_dpduv(dpdu, dpdv),
+                None => h,
+            })
+            .map(|h| match self.dnduv(ta, tb, tc) {
+                Some((dndu, dndv)) => h.with_dnduv(dndu, dndv),
+                None => h,
+            })
+    }
+
+    /// Tangent interpolated from the vertex tangents of the mesh.
+    /// Falls back to `dp/du` of the triangle if the mesh has no tangents.
+    fn tangent(&self, barycentrics: Vec3, ta: Vec2, tb: Vec2, tc: Vec2) -> Option<Direction> {
+        if let Some(nidx) = self.nidx.filter(|_| !self.mesh.tangents.is_empty()) {
+            let tangent = barycentrics.x * self.mesh.tangents[nidx.0]
+                + barycentrics.y * self.mesh.tangents[nidx.1]
+                + barycentrics.z * self.mesh.tangents[nidx.2];
+            if tangent.length_squared() > crate::EPSILON {
+                return Some(tangent);
+            }
+        }
+
+        Self::dpdu(self.a(), self.b(), self.c(), ta, tb, tc)
+    }
+
+    /// Partial derivative of the position with respect to `u`.
+    /// `None` if the texture coordinates are degenerate.
+    pub(super) fn dpdu(
+        a: Point, b: Point, c: Point,
+        ta: Vec2, tb: Vec2, tc: Vec2
+    ) -> Option<Direction> {
+        Self::dpduv(a, b, c, ta, tb, tc).map(|(dpdu, _)| dpdu)
+    }
+
+    /// Partial derivatives of the interpolated shading normal with respect
+    /// to `u` and `v`. `None` without shading normals.
+    fn dnduv(&self, ta: Vec2, tb: Vec2, tc: Vec2) -> Option<(Direction, Direction)> {
+        let nidx = self.nidx?;
+        let normals = &self.mesh.normals;
+        Self::dpduv(normals[nidx.0], normals[nidx.1], normals[nidx.2], ta, tb, tc)
+    }
+
+    /// Partial derivatives of the position with respect to `u` and `v`.
+    /// `None` if the texture coordinates are degenerate.
+    pub(super) fn dpduv(
+        a: Point, b: Point, c: Point,
+        ta: Vec2, tb: Vec2, tc: Vec2
+    ) -> Option<(Direction, Direction)> {
+        let duv_ac = ta - tc;
+        let duv_bc = tb - tc;
+        let det = duv_ac.x * duv_bc.y - duv_ac.y * duv_bc.x;
+        if det.abs() < crate::EPSILON.powi(2) {
+            None
+        } else {
+            let dpdu = (duv_bc.y * (a - c) - duv_ac.y * (b - c)) / det;
+            let dpdv = (duv_ac.x * (b - c) - duv_bc.x * (a - c)) / det;
+            Some( (dpdu, dpdv) )
+        }
     }
 }
 
@@ -204,6 +326,28 @@ impl Object for Triangle {
     }
 }
 
+impl BVHPrimitive for Triangle {
+    type Object = Self;
+    type Leaf = Option<Triangle4>;
+
+    fn as_object(&self) -> &Self { self }
+
+    fn leaf(objects: &[Self]) -> Self::Leaf { Triangle4::new(objects) }
+
+    fn hit_leaf<const GEO: bool>(
+        leaf: &Self::Leaf,
+        objects: &[Self],
+        r: &Ray,
+        t_min: Float,
+        t_max: Float,
+    ) -> Option<(usize, Float)> {
+        match leaf {
+            Some(triangles) => triangles.hit(r, t_min, t_max),
+            None => bvh::hit_objects::<GEO, Self>(objects, r, t_min, t_max),
+        }
+    }
+}
+
 impl Sampleable for Triangle {
     fn area(&self) -> Float {
         (self.b() - self.a()).cross(self.c() - self.a()).length() / 2.0
@@ -212,7 +356,7 @@ impl Sampleable for Triangle {
     fn material(&self) -> &Material { &self.mesh.materials[self.midx] }
 
     /// Random point with barycentrics.
-    fn sample_on(&self, rand_sq: Vec2) -> Hit {
+    fn sample_on(&self, _time: Float, rand_sq: Vec2) -> Hit {
         let gamma = 1.0 - (1.0 - rand_sq.x).sqrt();
         let beta = rand_sq.y * (1.0 - gamma);
         let alpha = 1.0 - gamma - beta;
@@ -244,6 +388,7 @@ impl Sampleable for Triangle {
 #[cfg(test)]
 mod triangle_tests {
     use super::*;
+    use crate::tracer::{AlphaMask, Texture};
     fn mesh() -> TriangleMesh {
         TriangleMesh {
             vertices: vec![
@@ -253,9 +398,64 @@ mod triangle_tests {
             ],
             normals: vec![],
             uvs: vec![],
+            tangents: vec![],
             materials: vec![Material::Blank],
         }
     }
 
     test_util::test_sampleable!(Triangle::new(Arc::new(mesh()), (0, 1, 2), 0, None, None));
+
+    #[test]
+    fn tangent_follows_uvs() {
+        let mut mesh = mesh();
+        // u grows along x and v along y
+        mesh.uvs = vec![Vec2::new(1.0, 0.0), Vec2::ONE, Vec2::new(0.0, 1.0)];
+        let t = Triangle::new(Arc::new(mesh), (0, 1, 2), 0, None, Some((0, 1, 2)));
+
+        let r = Ray::new(Point::new(0.5, 0.5, 1.0), -Direction::Z);
+        let Some(h) = t.hit(&r, 0.0, crate::INF) else { panic!() };
+        let Some(tangent) = h.tangent else { panic!() };
+
+        assert!(tangent.distance(Direction::X) < crate::EPSILON);
+    }
+
+    #[test]
+    fn alpha_cuts_out() {
+        let mut mesh = mesh();
+        let cut_out = AlphaMask::constant(0.3).with_threshold(0.5);
+        mesh.materials = vec![Material::diffuse(Texture::default()).with_alpha(cut_out)];
+        let t = Triangle::new(Arc::new(mesh), (0, 1, 2), 0, None, None);
+
+        let r = Ray::new(Point::new(0.5, 0.5, 1.0), -Direction::Z);
+        assert!(t.hit(&r, 0.0, crate::INF).is_none());
+        assert!(t.hit_t(&r, 0.0, crate::INF) == crate::INF);
+    }
+
+    #[test]
+    fn alpha_does_not_affect_sampling() {
+        let mut mesh = mesh();
+        let cut_out = AlphaMask::constant(0.0);
+        mesh.materials = vec![Material::diffuse(Texture::default()).with_alpha(cut_out)];
+        let t = Triangle::new(Arc::new(mesh), (0, 1, 2), 0, None, None);
+
+        let h = t.sample_on(0.0, Vec2::new(0.3, 0.3));
+        assert!(h.p.z.abs() < crate::EPSILON);
+    }
+
+    #[test]
+    fn alpha_stochastic_agrees_with_shadows() {
+        let mut mesh = mesh();
+        mesh.materials = vec![
+            Material::diffuse(Texture::default()).with_alpha(AlphaMask::constant(0.5))
+        ];
+        let t = Triangle::new(Arc::new(mesh), (0, 1, 2), 0, None, None);
+        let mut rng = Xorshift::new(123);
+
+        for _ in 0..100 {
+            let xo = Point::new(0.1 + 0.5 * rng.gen_float(), 0.1 + 0.5 * rng.gen_float(), 1.0);
+            let r = Ray::new(xo, -Direction::Z);
+            let t_hit = t.hit(&r, 0.0, crate::INF).map_or(crate::INF, |h| h.t);
+            assert!(t_hit == t.hit_t(&r, 0.0, crate::INF));
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use super::*;
+use crate::math::simd::F64x4;
+
+const WIDTH: usize = 4;
+
+/// Vertices of up to four triangles of a BVH leaf, stored per coordinate
+/// to intersect all of them at once
+pub struct Triangle4 {
+    a: [F64x4; 3],
+    b: [F64x4; 3],
+    c: [F64x4; 3],
+    /// Bit mask of lanes with a triangle
+    valid: u32,
+}
+
+impl Triangle4 {
+    /// Packs `triangles`. `None` if there are too many of them or if some
+    /// have opacity masks, those need the full hit.
+    pub fn new(triangles: &[Triangle]) -> Option<Self> {
+        if triangles.len() > WIDTH
+            || triangles.iter().any(|t| t.material().has_alpha()) {
+            return None;
+        }
+
+        let lanes = |vertex: fn(&Triangle) -> Point| -> [F64x4; 3] {
+            let mut xyz = [[0.0; WIDTH]; 3];
+            for (i, triangle) in triangles.iter().enumerate() {
+                let v = vertex(triangle);
+                (xyz[0][i], xyz[1][i], xyz[2][i]) = (v.x, v.y, v.z);
+            }
+            xyz.map(F64x4::new)
+        };
+
+        Some(Self {
+            a: lanes(Triangle::a),
+            b: lanes(Triangle::b),
+            c: lanes(Triangle::c),
+            valid: (1 << triangles.len()) - 1,
+        })
+    }
+
+    /// Closest hit in `(t_min, t_max)`. Returns the lane and distance to the hit.
+    /// Same arithmetic as `Triangle::hit_t` lane by lane, so the two agree exactly.
+    pub fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<(usize, Float)> {
+        let wi_abs = r.dir.abs();
+        // the permutation of `Triangle::_hit`, max component of direction to z
+        let (kx, ky, kz) = if wi_abs.x > wi_abs.y && wi_abs.x > wi_abs.z {
+            (1, 2, 0)
+        } else if wi_abs.y > wi_abs.z {
+            (2, 0, 1)
+        } else {
+            (0, 1, 2)
+        };
+
+        let dir = [r.dir.x, r.dir.y, r.dir.z];
+        let origin = [r.origin.x, r.origin.y, r.origin.z];
+        let wz = dir[kz];
+        let sx = F64x4::splat(-dir[kx] / wz);
+        let sy = F64x4::splat(-dir[ky] / wz);
+        let (ox, oy, oz) = (
+            F64x4::splat(origin[kx]),
+            F64x4::splat(origin[ky]),
+            F64x4::splat(origin[kz]),
+        );
+
+        let shear = |v: &[F64x4; 3]| {
+            let z = v[kz] - oz;
+            (v[kx] - ox + sx * z, v[ky] - oy + sy * z, z)
+        };
+        let (ax, ay, az) = shear(&self.a);
+        let (bx, by, bz) = shear(&self.b);
+        let (cx, cy, cz) = shear(&self.c);
+
+        let ex = bx * cy - by * cx;
+        let ey = cx * ay - cy * ax;
+        let ez = ax * by - ay * bx;
+
+        let zero = F64x4::splat(0.0);
+        let outside = (ex.lt_mask(zero) | ey.lt_mask(zero) | ez.lt_mask(zero))
+            & (ex.gt_mask(zero) | ey.gt_mask(zero) | ez.gt_mask(zero));
+
+        let det = ex + ey + ez;
+        let t_scaled = (ex * az + ey * bz + ez * cz) / F64x4::splat(wz);
+
+        let (t_min_det, t_max_det) = (F64x4::splat(t_min) * det, F64x4::splat(t_max) * det);
+        let b1 = det.lt_mask(zero)
+            & (t_scaled.gt_mask(t_min_det) | t_scaled.lt_mask(t_max_det));
+        let b2 = det.gt_mask(zero)
+            & (t_scaled.lt_mask(t_min_det) | t_scaled.gt_mask(t_max_det));
+
+        let mut hits = self.valid & !(outside | det.eq_mask(zero) | b1 | b2);
+        let t = (t_scaled / det).to_array();
+
+        let mut closest = None;
+        let mut tt = t_max;
+        while hits != 0 {
+            let i = hits.trailing_zeros() as usize;
+            hits &= hits - 1;
+            if t[i] < tt {
+                tt = t[i];
+                closest = Some((i, tt));
+            }
+        }
+
+        closest
+    }
+}
@@ -1,4 +1,14 @@
 use super::*;
+use crate::binary::Binary;
+use std::io::{ self, Read, Write };
+
+pub use displacement::Displacement;
+pub use subdivision::{Subdivision, SubdivisionScheme};
+
+/// Tessellation and displacement of meshes with height fields
+mod displacement;
+/// Catmull-Clark and Loop subdivision of polygon meshes
+mod subdivision;
 
 /// A single face of a polygon mesh
 pub struct Face {
@@ -21,7 +31,27 @@ impl Face {
     }
 }
 
-/// Mesh of triangles accelerated with a kdtree
+/// Acceleration structure built for the triangles of a mesh
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MeshAccel {
+    /// SAH kD-tree, slow to build for large meshes but fast to trace
+    #[default]
+    KdTree,
+    /// BVH with nodes split by the given algorithm
+    BVH(BVHBuild),
+}
+
+impl MeshAccel {
+    /// Build the acceleration structure over `triangles`
+    pub fn build(&self, triangles: Vec<Triangle>) -> Box<dyn Object> {
+        match self {
+            Self::KdTree => Box::new(KdTree::new(triangles)),
+            Self::BVH(build) => Box::new(BVH::new(triangles, *build)),
+        }
+    }
+}
+
+/// Mesh of triangles accelerated with a kD-tree or a BVH
 pub struct TriangleMesh {
     /// All vertices of the mesh
     pub vertices: Vec<Point>,
@@ -29,6 +59,9 @@ pub struct TriangleMesh {
     pub normals: Vec<Normal>,
     /// All texture coordinates of the mesh
     pub uvs: Vec<Vec2>,
+    /// Tangents of the shading normals, same indexing as `normals`.
+    /// Computed from the texture coordinates, can be empty.
+    pub tangents: Vec<Direction>,
     /// All materials used in the mesh
     pub materials: Vec<Material>,
 }
@@ -44,14 +77,85 @@ impl TriangleMesh {
         uvs: Vec<Vec2>,
         material: Material,
     ) -> Mesh {
-        let mesh = Arc::new(Self {
+        KdTree::new(Self::triangles(vertices, faces, normals, uvs, material, None))
+    }
+
+    /// Constructs a mesh from the given data subdivided with `subdivision`.
+    /// Shading normals come from the subdivided surface, so `normals` get
+    /// ignored. `uvs` may be empty.
+    pub fn new_subdivided(
+        vertices: Vec<Point>,
+        faces: Vec<Face>,
+        normals: Vec<Normal>,
+        uvs: Vec<Vec2>,
+        material: Material,
+        subdivision: &Subdivision,
+    ) -> Mesh {
+        KdTree::new(Self::triangles(vertices, faces, normals, uvs, material, Some(subdivision)))
+    }
+
+    /// Constructs a mesh as a BVH split with `build` instead of a kD-tree.
+    /// `normals` and/or `uvs` may be empty.
+    pub fn new_bvh(
+        vertices: Vec<Point>,
+        faces: Vec<Face>,
+        normals: Vec<Normal>,
+        uvs: Vec<Vec2>,
+        material: Material,
+        build: BVHBuild,
+    ) -> MeshBVH {
+        BVH::new(Self::triangles(vertices, faces, normals, uvs, material, None), build)
+    }
+
+    fn triangles(
+        vertices: Vec<Point>,
+        faces: Vec<Face>,
+        normals: Vec<Normal>,
+        uvs: Vec<Vec2>,
+        material: Material,
+        subdivision: Option<&Subdivision>,
+    ) -> Vec<Triangle> {
+        let mut mesh = Self {
             vertices,
             normals,
             uvs,
+            tangents: vec![],
             materials: vec!(material),
-        });
+        };
+
+        let faces = match subdivision {
+            Some(subdivision) => mesh.subdivide(faces, subdivision),
+            None => faces,
+        };
+        mesh.tangents = Self::vertex_tangents(&mesh.vertices, &mesh.normals, &mesh.uvs, &faces);
+
+        Self::triangles_from_faces(Arc::new(mesh), faces, 0)
+    }
+
+    /// Writes the vertices, shading normals, texture coordinates and
+    /// tangents of the mesh to `w`. Materials are not written.
+    pub(crate) fn write_cache(&self, w: &mut impl Write) -> io::Result<()> {
+        self.vertices.write(w)?;
+        self.normals.write(w)?;
+        self.uvs.write(w)?;
+        self.tangents.write(w)
+    }
+
+    /// Reads a mesh written by `write_cache` without materials
+    pub(crate) fn read_cache(r: &mut impl Read) -> io::Result<Self> {
+        let mesh = Self {
+            vertices: Vec::read(r)?,
+            normals: Vec::read(r)?,
+            uvs: Vec::read(r)?,
+            tangents: Vec::read(r)?,
+            materials: vec![],
+        };
+
+        if !mesh.tangents.is_empty() && mesh.tangents.len() != mesh.normals.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad number of tangents"));
+        }
 
-        KdTree::new(Self::triangles_from_faces(mesh, faces, 0))
+        Ok(mesh)
     }
 
     /// Constructs the triangles defined by `faces` using material with `midx`
@@ -90,6 +194,53 @@ impl TriangleMesh {
         triangles
     }
 
+    /// Tangents for each shading normal. Averages the `dp/du` of the faces
+    /// using the normal weighted by face area and orthogonalizes against the
+    /// normal. Empty if the mesh has no normals or texture coordinates.
+    pub fn vertex_tangents<'a>(
+        vertices: &[Point],
+        normals: &[Normal],
+        uvs: &[Vec2],
+        faces: impl IntoIterator<Item = &'a Face>,
+    ) -> Vec<Direction> {
+        if normals.is_empty() || uvs.is_empty() {
+            return vec![];
+        }
+
+        let mut tangents = vec![Direction::ZERO; normals.len()];
+
+        for face in faces {
+            if face.nidx.is_empty() || face.tidx.is_empty() {
+                continue;
+            }
+
+            for i in 1..face.vidx.len() - 1 {
+                let corners = [0, i, i + 1];
+                let [a, b, c] = corners.map(|k| vertices[face.vidx[k]]);
+                let [ta, tb, tc] = corners.map(|k| uvs[face.tidx[k]]);
+
+                let Some(dpdu) = Triangle::dpdu(a, b, c, ta, tb, tc) else { continue; };
+                let length = dpdu.length();
+                if !length.is_finite() || length == 0.0 {
+                    continue;
+                }
+                let area = (b - a).cross(c - a).length();
+
+                for k in corners {
+                    tangents[face.nidx[k]] += area * dpdu / length;
+                }
+            }
+        }
+
+        tangents.iter().zip(normals)
+            .map(|(t, n)| {
+                let t = *t - *n * n.dot(*t);
+                let length = t.length();
+                if length < crate::EPSILON { Direction::ZERO } else { t / length }
+            })
+            .collect()
+    }
+
     fn degenerate_triangle(a: Point, b: Point, c: Point) -> bool {
         let ng = (b - a).cross(c - a);
         ng.length() == 0.0
@@ -0,0 +1,277 @@
+use super::*;
+use crate::Image;
+use rustc_hash::FxHashMap;
+
+/// Default maximum number of segments per triangle edge
+const DEFAULT_MAX_RATE: usize = 16;
+
+/// Height field that displaces the surface of a mesh along its shading
+/// normals. Meshes get tessellated at load time to roughly one triangle per
+/// texel of the height field.
+pub struct Displacement {
+    /// Heights in `\[0,1\]`
+    map: Image<Float>,
+    /// Displacement of the full height range
+    scale: Float,
+    /// Maximum number of segments each triangle edge gets split to
+    max_rate: usize,
+}
+
+impl Displacement {
+    /// Displace by `scale` times the height in `map`
+    pub fn new(map: Image<Float>, scale: Float) -> Self {
+        Self { map, scale, max_rate: DEFAULT_MAX_RATE }
+    }
+
+    /// Split each triangle edge to at most `max_rate` segments.
+    /// Each triangle becomes up to `max_rate^2` triangles.
+    pub fn max_rate(mut self, max_rate: usize) -> Self {
+        assert!(max_rate > 0);
+        self.max_rate = max_rate;
+        self
+    }
+
+    /// Displacement at `uv`
+    fn height_at(&self, uv: Vec2) -> Float {
+        self.scale * self.map.value_at(Hit::wrap_uv(uv))
+    }
+
+    /// Number of segments per edge, such that the longest edge
+    /// spans roughly one texel per segment.
+    fn rate(&self, uvs: &[Vec2], faces: &[Face]) -> usize {
+        let texels = Vec2::new(self.map.width as Float, self.map.height as Float);
+        let max_texels = faces.iter()
+            .filter(|face| !face.tidx.is_empty())
+            .flat_map(|face| {
+                let n = face.tidx.len();
+                (0..n).map(move |i| (face.tidx[i], face.tidx[(i + 1) % n]))
+            })
+            .map(|(t0, t1)| {
+                let duv = (uvs[t1] - uvs[t0]) * texels;
+                duv.dot(duv).sqrt()
+            })
+            .fold(1.0, Float::max);
+
+        (max_texels.ceil() as usize).clamp(1, self.max_rate)
+    }
+}
+
+/// Identifies a vertex by its position, normal and texture coordinate indices
+type CornerKey = (usize, usize, usize);
+
+/// Identifies a vertex of the tessellation shared between triangles
+#[derive(PartialEq, Eq, Hash)]
+enum VertexKey {
+    /// Vertex of the original mesh
+    Corner(CornerKey),
+    /// Vertex on an edge of the original mesh, `step` segments from `from`
+    Edge { from: CornerKey, to: CornerKey, step: usize },
+    /// Vertex inside the triangle with index `triangle`
+    Interior { triangle: usize, i: usize, j: usize },
+}
+
+impl TriangleMesh {
+    /// Tessellate `faces` and move the created vertices along their shading
+    /// normals by `displacement`. The new vertices, normals and texture
+    /// coordinates are appended to the mesh and the faces using them returned.
+    /// Faces without normals or texture coordinates are returned unchanged.
+    pub fn displace(&mut self, faces: Vec<Face>, displacement: &Displacement) -> Vec<Face> {
+        let rate = displacement.rate(&self.uvs, &faces);
+        println!("Displacing {} faces with {} segments per edge", faces.len(), rate);
+
+        let base = self.vertices.len();
+        let normal_base = self.normals.len();
+        let uv_base = self.uvs.len();
+
+        // interpolated normal of each new vertex, before displacement
+        let mut smooth_normals: Vec<Normal> = Vec::new();
+        let mut indices = FxHashMap::<VertexKey, usize>::default();
+        let mut triangles: Vec<[usize; 3]> = Vec::new();
+        let mut displaced = Vec::new();
+        let mut triangle = 0;
+
+        for face in faces {
+            if face.nidx.is_empty() || face.tidx.is_empty() {
+                displaced.push(face);
+                continue;
+            }
+
+            for f in 1..face.vidx.len() - 1 {
+                triangle += 1;
+                let corners = [0, f, f + 1]
+                    .map(|k| (face.vidx[k], face.nidx[k], face.tidx[k]));
+
+                let mut vertex = |i: usize, j: usize| -> usize {
+                    let key = Self::vertex_key(corners, triangle, rate, i, j);
+                    *indices.entry(key).or_insert_with(|| {
+                        let b = i as Float / rate as Float;
+                        let c = j as Float / rate as Float;
+                        let a = 1.0 - b - c;
+
+                        let [pa, pb, pc] = corners.map(|k| self.vertices[k.0]);
+                        let [na, nb, nc] = corners.map(|k| self.normals[k.1]);
+                        let [ta, tb, tc] = corners.map(|k| self.uvs[k.2]);
+
+                        let ns = (a * na + b * nb + c * nc).normalize();
+                        let uv = a * ta + b * tb + c * tc;
+                        let xi = a * pa + b * pb + c * pc
+                            + ns * displacement.height_at(uv);
+
+                        self.vertices.push(xi);
+                        self.uvs.push(uv);
+                        smooth_normals.push(ns);
+                        smooth_normals.len() - 1
+                    })
+                };
+
+                for i in 0..rate {
+                    for j in 0..rate - i {
+                        let v00 = vertex(i, j);
+                        let v10 = vertex(i + 1, j);
+                        let v01 = vertex(i, j + 1);
+                        triangles.push([v00, v10, v01]);
+
+                        if i + j + 1 < rate {
+                            let v11 = vertex(i + 1, j + 1);
+                            triangles.push([v10, v11, v01]);
+                        }
+                    }
+                }
+            }
+        }
+
+        // shading normals from the displaced surface
+        let mut normals = vec![Normal::ZERO; smooth_normals.len()];
+        for tri in &triangles {
+            let [a, b, c] = tri.map(|k| self.vertices[base + k]);
+            // length is twice the area, i.e. weighs by area
+            let ng = (b - a).cross(c - a);
+            for k in tri {
+                normals[*k] += ng;
+            }
+        }
+
+        self.normals.extend(normals.iter().zip(&smooth_normals)
+            .map(|(n, ns)| {
+                if n.length_squared() == 0.0 {
+                    *ns
+                } else {
+                    // keep the orientation of the original shading normals
+                    let n = n.normalize();
+                    if n.dot(*ns) < 0.0 { -n } else { n }
+                }
+            }));
+
+        displaced.extend(triangles.iter().map(|tri| {
+            Face::new(
+                tri.map(|k| base + k).to_vec(),
+                tri.map(|k| normal_base + k).to_vec(),
+                tri.map(|k| uv_base + k).to_vec(),
+            )
+        }));
+
+        displaced
+    }
+
+    /// Key of the vertex `i` steps from the first corner towards the second
+    /// and `j` towards the third in triangle with index `triangle`. Vertices
+    /// on the edges get the same key from both triangles sharing the edge.
+    fn vertex_key(
+        corners: [CornerKey; 3],
+        triangle: usize,
+        rate: usize,
+        i: usize,
+        j: usize,
+    ) -> VertexKey {
+        let edge = |from: CornerKey, to: CornerKey, step: usize| {
+            if step == 0 {
+                VertexKey::Corner(from)
+            } else if step == rate {
+                VertexKey::Corner(to)
+            } else if from < to {
+                VertexKey::Edge { from, to, step }
+            } else {
+                VertexKey::Edge { from: to, to: from, step: rate - step }
+            }
+        };
+
+        if j == 0 {
+            edge(corners[0], corners[1], i)
+        } else if i == 0 {
+            edge(corners[0], corners[2], j)
+        } else if i + j == rate {
+            edge(corners[1], corners[2], j)
+        } else {
+            VertexKey::Interior { triangle, i, j }
+        }
+    }
+}
+
+#[cfg(test)]
+mod displacement_tests {
+    use super::*;
+
+    /// Unit square on the xy-plane split to two triangles
+    fn square() -> (TriangleMesh, Vec<Face>) {
+        let mesh = TriangleMesh {
+            vertices: vec![Point::ZERO, Point::X, Point::new(1.0, 1.0, 0.0), Point::Y],
+            normals: vec![Normal::Z],
+            uvs: vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y],
+            tangents: vec![],
+            materials: vec![Material::Blank],
+        };
+        let faces = vec![
+            Face::new(vec![0, 1, 2], vec![0, 0, 0], vec![0, 1, 2]),
+            Face::new(vec![0, 2, 3], vec![0, 0, 0], vec![0, 2, 3]),
+        ];
+
+        (mesh, faces)
+    }
+
+    #[test]
+    fn displaces_along_normal() {
+        let (mut mesh, faces) = square();
+        let map = Image::new(vec![1.0; 64], 8, 8, 1.0);
+        let disp = Displacement::new(map, 0.5).max_rate(4);
+
+        let faces = mesh.displace(faces, &disp);
+        assert!(faces.len() == 2 * 4 * 4);
+
+        for face in &faces {
+            for vidx in &face.vidx {
+                assert!((mesh.vertices[*vidx].z - 0.5).abs() < crate::EPSILON);
+            }
+            for nidx in &face.nidx {
+                assert!(mesh.normals[*nidx].distance(Normal::Z) < crate::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn shares_edge_vertices() {
+        let (mut mesh, faces) = square();
+        let map = Image::new(vec![0.0; 64], 8, 8, 0.0);
+        let rate = 4;
+        let vertices_before = mesh.vertices.len();
+
+        mesh.displace(faces, &Displacement::new(map, 1.0).max_rate(rate));
+
+        // two triangles sharing the diagonal
+        let per_triangle = (rate + 1) * (rate + 2) / 2;
+        assert!(mesh.vertices.len() - vertices_before == 2 * per_triangle - (rate + 1));
+    }
+
+    #[test]
+    fn tangents_follow_u() {
+        let (mesh, faces) = square();
+        let tangents = TriangleMesh::vertex_tangents(
+            &mesh.vertices,
+            &mesh.normals,
+            &mesh.uvs,
+            &faces,
+        );
+
+        assert!(tangents.len() == 1);
+        assert!(tangents[0].distance(Direction::X) < crate::EPSILON);
+    }
+}
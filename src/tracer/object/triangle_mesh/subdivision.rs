@@ -0,0 +1,748 @@
+use super::*;
+use rustc_hash::FxHashMap;
+
+/// Scheme used to subdivide a mesh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubdivisionScheme {
+    /// Catmull-Clark, turns each polygon to quads. For quad dominant meshes.
+    CatmullClark,
+    /// Loop, for triangle meshes. Polygons get fanned to triangles first.
+    Loop,
+}
+
+/// Subdivision of a low polygon cage to a smooth surface at load time.
+/// Creased edges stay sharp for as many levels as their sharpness,
+/// boundaries are always sharp and corners of single faces stay in place.
+/// Semi-sharp creases follow
+/// [DeRose et al. 1998](https://graphics.pixar.com/library/Geri/paper.pdf).
+/// Texture coordinates get interpolated linearly and shading normals are
+/// smoothed everywhere but across the remaining creases.
+#[derive(Clone, Debug)]
+pub struct Subdivision {
+    scheme: SubdivisionScheme,
+    levels: usize,
+    /// Sharpness of edges between two vertices of the cage
+    creases: Vec<(usize, usize, Float)>,
+    /// Edges where faces meet at a larger angle, in radians, are infinitely sharp
+    crease_angle: Option<Float>,
+}
+
+impl Subdivision {
+    /// Subdivide `levels` times with `scheme`
+    pub fn new(scheme: SubdivisionScheme, levels: usize) -> Self {
+        Self { scheme, levels, creases: vec![], crease_angle: None }
+    }
+
+    /// Crease the edge between vertices `v0` and `v1` of the mesh.
+    /// Stays sharp for `sharpness` levels, `INFINITY` keeps it sharp.
+    pub fn crease(mut self, v0: usize, v1: usize, sharpness: Float) -> Self {
+        assert!(sharpness >= 0.0);
+        self.creases.push((v0, v1, sharpness));
+        self
+    }
+
+    /// Keep edges sharp where the faces meet at an angle larger than
+    /// `degrees` in the cage
+    pub fn crease_angle(mut self, degrees: Float) -> Self {
+        assert!(degrees >= 0.0);
+        self.crease_angle = Some(degrees.to_radians());
+        self
+    }
+
+    /// Writes the parameters to `w`, meshes subdivided with the same
+    /// parameters write the same bytes
+    pub(crate) fn write_cache(&self, w: &mut impl Write) -> io::Result<()> {
+        let scheme: u64 = match self.scheme {
+            SubdivisionScheme::CatmullClark => 0,
+            SubdivisionScheme::Loop => 1,
+        };
+        scheme.write(w)?;
+        self.levels.write(w)?;
+        self.creases.len().write(w)?;
+        for (v0, v1, sharpness) in &self.creases {
+            v0.write(w)?;
+            v1.write(w)?;
+            sharpness.write(w)?;
+        }
+        self.crease_angle.is_some().write(w)?;
+        self.crease_angle.unwrap_or_default().write(w)
+    }
+}
+
+#[inline]
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+#[inline]
+fn lerp(a: Point, b: Point, t: Float) -> Point {
+    (1.0 - t) * a + t * b
+}
+
+/// Polygon mesh being subdivided. Indices are local to the subdivided faces.
+struct Cage {
+    points: Vec<Point>,
+    uvs: Vec<Vec2>,
+    faces: Vec<Vec<usize>>,
+    /// Texture coordinate indices of each face, empty if the face has none
+    uv_faces: Vec<Vec<usize>>,
+    /// Sharpness of creased edges
+    creases: FxHashMap<(usize, usize), Float>,
+}
+
+/// Adjacency of the cage
+struct Topology {
+    /// End points of the edges, smaller index first
+    edges: Vec<(usize, usize)>,
+    edge_index: FxHashMap<(usize, usize), usize>,
+    edge_faces: Vec<Vec<usize>>,
+    vertex_edges: Vec<Vec<usize>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    fn new(cage: &Cage) -> Self {
+        let mut topology = Self {
+            edges: vec![],
+            edge_index: FxHashMap::default(),
+            edge_faces: vec![],
+            vertex_edges: vec![vec![]; cage.points.len()],
+            vertex_faces: vec![vec![]; cage.points.len()],
+        };
+
+        for (f, face) in cage.faces.iter().enumerate() {
+            for (i, v) in face.iter().enumerate() {
+                topology.vertex_faces[*v].push(f);
+                let key = edge_key(*v, face[(i + 1) % face.len()]);
+                let e = *topology.edge_index.entry(key).or_insert_with(|| {
+                    topology.edges.push(key);
+                    topology.edge_faces.push(vec![]);
+                    topology.vertex_edges[key.0].push(topology.edges.len() - 1);
+                    topology.vertex_edges[key.1].push(topology.edges.len() - 1);
+                    topology.edges.len() - 1
+                });
+                topology.edge_faces[e].push(f);
+            }
+        }
+
+        topology
+    }
+
+    fn edge(&self, a: usize, b: usize) -> usize {
+        self.edge_index[&edge_key(a, b)]
+    }
+}
+
+impl Cage {
+    /// Sharpness of edge `e`, boundaries and non-manifold edges are
+    /// infinitely sharp
+    fn sharpness(&self, topology: &Topology, e: usize) -> Float {
+        if topology.edge_faces[e].len() != 2 {
+            crate::INF
+        } else {
+            self.creases.get(&topology.edges[e]).copied().unwrap_or(0.0)
+        }
+    }
+
+    /// Twice the area times the normal of face `f`, Newell's method
+    fn face_normal(&self, f: usize) -> Normal {
+        let face = &self.faces[f];
+        (0..face.len()).fold(Normal::ZERO, |n, i| {
+            n + self.points[face[i]].cross(self.points[face[(i + 1) % face.len()]])
+        })
+    }
+
+    /// Creases edges with a dihedral angle larger than `angle`
+    fn crease_by_angle(&mut self, angle: Float) {
+        let topology = Topology::new(self);
+        let cos_angle = angle.cos();
+
+        for (e, faces) in topology.edge_faces.iter().enumerate() {
+            if faces.len() != 2 {
+                continue;
+            }
+            let n0 = self.face_normal(faces[0]).normalize();
+            let n1 = self.face_normal(faces[1]).normalize();
+            if n0.dot(n1) < cos_angle {
+                self.creases.insert(topology.edges[e], crate::INF);
+            }
+        }
+    }
+
+    /// Fan the polygons to triangles for Loop subdivision
+    fn triangulate(&mut self) {
+        let mut faces = Vec::with_capacity(self.faces.len());
+        let mut uv_faces = Vec::with_capacity(self.faces.len());
+
+        for (face, uv_face) in self.faces.iter().zip(&self.uv_faces) {
+            for i in 1..face.len() - 1 {
+                faces.push(vec![face[0], face[i], face[i + 1]]);
+                uv_faces.push(if uv_face.is_empty() {
+                    vec![]
+                } else {
+                    vec![uv_face[0], uv_face[i], uv_face[i + 1]]
+                });
+            }
+        }
+
+        self.faces = faces;
+        self.uv_faces = uv_faces;
+    }
+
+    /// Position of vertex `v` after a step. `smooth` is the position from
+    /// the rule of the scheme, `crease` from the end points of two sharp edges.
+    fn vertex_point(
+        &self,
+        topology: &Topology,
+        v: usize,
+        smooth: impl FnOnce() -> Point,
+        crease: impl FnOnce(Point, Point) -> Point,
+    ) -> Point {
+        let p = self.points[v];
+        if topology.vertex_faces[v].is_empty() {
+            return p;
+        }
+
+        let sharp: Vec<(usize, Float)> = topology.vertex_edges[v].iter()
+            .map(|e| (*e, self.sharpness(topology, *e)))
+            .filter(|(_, s)| *s > 0.0)
+            .collect();
+
+        let other_end = |e: usize| {
+            let (a, b) = topology.edges[e];
+            self.points[if a == v { b } else { a }]
+        };
+
+        // corners of single faces stay put like other corners
+        let corner = topology.vertex_faces[v].len() == 1;
+        let sharp_point = match sharp.len() {
+            0 | 1 => return smooth(),
+            2 if !corner => crease(other_end(sharp[0].0), other_end(sharp[1].0)),
+            _ => p,
+        };
+
+        let sharpness = sharp.iter().map(|(_, s)| s).sum::<Float>() / sharp.len() as Float;
+        if sharpness >= 1.0 {
+            sharp_point
+        } else {
+            lerp(smooth(), sharp_point, sharpness)
+        }
+    }
+
+    /// Creases of the edges split from `topology.edges`, which got split by
+    /// the points at `edge_base + e`. Sharpness decreases by one each level.
+    fn child_creases(&self, topology: &Topology, edge_base: usize) -> FxHashMap<(usize, usize), Float> {
+        let mut creases = FxHashMap::default();
+        for (e, (a, b)) in topology.edges.iter().enumerate() {
+            let s = self.sharpness(topology, e) - 1.0;
+            if s > 0.0 && topology.edge_faces[e].len() == 2 {
+                creases.insert(edge_key(*a, edge_base + e), s);
+                creases.insert(edge_key(edge_base + e, *b), s);
+            }
+        }
+        creases
+    }
+
+    /// One step of Catmull-Clark subdivision
+    fn catmull_clark(&self) -> Self {
+        let topology = Topology::new(self);
+        let (nv, ne) = (self.points.len(), topology.edges.len());
+
+        let face_points: Vec<Point> = self.faces.iter()
+            .map(|face| face.iter().fold(Point::ZERO, |acc, v| acc + self.points[*v])
+                 / face.len() as Float)
+            .collect();
+
+        let edge_points = topology.edges.iter().enumerate().map(|(e, (a, b))| {
+            let mid = (self.points[*a] + self.points[*b]) / 2.0;
+            let sharpness = self.sharpness(&topology, e);
+            if sharpness >= 1.0 {
+                return mid;
+            }
+            let faces = &topology.edge_faces[e];
+            let smooth = (self.points[*a] + self.points[*b]
+                          + face_points[faces[0]] + face_points[faces[1]]) / 4.0;
+            lerp(smooth, mid, sharpness)
+        });
+
+        let vertex_points = (0..nv).map(|v| {
+            let p = self.points[v];
+            let smooth = || {
+                let faces = &topology.vertex_faces[v];
+                let edges = &topology.vertex_edges[v];
+                let n = edges.len() as Float;
+                let q = faces.iter().fold(Point::ZERO, |acc, f| acc + face_points[*f])
+                    / faces.len() as Float;
+                let r = edges.iter().fold(Point::ZERO, |acc, e| {
+                    let (a, b) = topology.edges[*e];
+                    acc + (self.points[a] + self.points[b]) / 2.0
+                }) / n;
+                (q + 2.0 * r + (n - 3.0) * p) / n
+            };
+            self.vertex_point(&topology, v, smooth, |a, b| (a + 6.0 * p + b) / 8.0)
+        });
+
+        let mut points: Vec<Point> = vertex_points.collect();
+        points.extend(edge_points);
+        points.extend(face_points);
+
+        let mut uvs = self.uvs.clone();
+        let mut uv_mids = FxHashMap::<(usize, usize), usize>::default();
+        let mut faces = Vec::new();
+        let mut uv_faces = Vec::new();
+
+        for (f, (face, uv_face)) in self.faces.iter().zip(&self.uv_faces).enumerate() {
+            let n = face.len();
+            let uv_center = (!uv_face.is_empty()).then(|| {
+                let center = uv_face.iter().fold(Vec2::ZERO, |acc, t| acc + self.uvs[*t]);
+                uvs.push(center / n as Float);
+                uvs.len() - 1
+            });
+
+            for i in 0..n {
+                let (prev, next) = ((i + n - 1) % n, (i + 1) % n);
+                faces.push(vec![
+                    face[i],
+                    nv + topology.edge(face[i], face[next]),
+                    nv + ne + f,
+                    nv + topology.edge(face[prev], face[i]),
+                ]);
+
+                uv_faces.push(match uv_center {
+                    None => vec![],
+                    Some(center) => {
+                        let mut mid = |a: usize, b: usize| *uv_mids.entry(edge_key(a, b))
+                            .or_insert_with(|| {
+                                uvs.push((self.uvs[a] + self.uvs[b]) / 2.0);
+                                uvs.len() - 1
+                            });
+                        vec![
+                            uv_face[i],
+                            mid(uv_face[i], uv_face[next]),
+                            center,
+                            mid(uv_face[prev], uv_face[i]),
+                        ]
+                    }
+                });
+            }
+        }
+
+        Self {
+            points,
+            uvs,
+            faces,
+            uv_faces,
+            creases: self.child_creases(&topology, nv),
+        }
+    }
+
+    /// One step of Loop subdivision, all faces are triangles
+    fn loop_subdivision(&self) -> Self {
+        let topology = Topology::new(self);
+        let nv = self.points.len();
+
+        let edge_points = topology.edges.iter().enumerate().map(|(e, (a, b))| {
+            let mid = (self.points[*a] + self.points[*b]) / 2.0;
+            let sharpness = self.sharpness(&topology, e);
+            if sharpness >= 1.0 {
+                return mid;
+            }
+            // vertices opposite of the edge
+            let opposite = topology.edge_faces[e].iter().fold(Point::ZERO, |acc, f| {
+                let c = self.faces[*f].iter().find(|v| **v != *a && **v != *b).unwrap();
+                acc + self.points[*c]
+            });
+            let smooth = 3.0 * (self.points[*a] + self.points[*b]) / 8.0 + opposite / 8.0;
+            lerp(smooth, mid, sharpness)
+        });
+
+        let vertex_points = (0..nv).map(|v| {
+            let p = self.points[v];
+            let smooth = || {
+                let edges = &topology.vertex_edges[v];
+                let n = edges.len() as Float;
+                let beta = if edges.len() == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n) };
+                let neighbors = edges.iter().fold(Point::ZERO, |acc, e| {
+                    let (a, b) = topology.edges[*e];
+                    acc + self.points[if a == v { b } else { a }]
+                });
+                (1.0 - n * beta) * p + beta * neighbors
+            };
+            self.vertex_point(&topology, v, smooth, |a, b| 0.75 * p + (a + b) / 8.0)
+        });
+
+        let mut points: Vec<Point> = vertex_points.collect();
+        points.extend(edge_points);
+
+        let mut uvs = self.uvs.clone();
+        let mut uv_mids = FxHashMap::<(usize, usize), usize>::default();
+        let mut faces = Vec::new();
+        let mut uv_faces = Vec::new();
+
+        for (face, uv_face) in self.faces.iter().zip(&self.uv_faces) {
+            let [a, b, c] = [face[0], face[1], face[2]];
+            let [ab, bc, ca] = [(a, b), (b, c), (c, a)].map(|(x, y)| nv + topology.edge(x, y));
+            faces.extend([vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]);
+
+            if uv_face.is_empty() {
+                uv_faces.extend([vec![], vec![], vec![], vec![]]);
+                continue;
+            }
+            let mut mid = |x: usize, y: usize| *uv_mids.entry(edge_key(x, y))
+                .or_insert_with(|| {
+                    uvs.push((self.uvs[x] + self.uvs[y]) / 2.0);
+                    uvs.len() - 1
+                });
+            let [ta, tb, tc] = [uv_face[0], uv_face[1], uv_face[2]];
+            let [tab, tbc, tca] = [mid(ta, tb), mid(tb, tc), mid(tc, ta)];
+            uv_faces.extend([
+                vec![ta, tab, tca], vec![tab, tb, tbc], vec![tca, tbc, tc], vec![tab, tbc, tca],
+            ]);
+        }
+
+        Self {
+            points,
+            uvs,
+            faces,
+            uv_faces,
+            creases: self.child_creases(&topology, nv),
+        }
+    }
+
+    /// Area weighted normals of the face corners. Corners around a vertex
+    /// share a normal unless a sharp edge separates their faces.
+    fn corner_normals(&self) -> (Vec<Normal>, Vec<Vec<usize>>) {
+        let topology = Topology::new(self);
+        let mut first_corner = Vec::with_capacity(self.faces.len());
+        let mut num_corners = 0;
+        for face in &self.faces {
+            first_corner.push(num_corners);
+            num_corners += face.len();
+        }
+        let corner_of = |f: usize, v: usize| {
+            first_corner[f] + self.faces[f].iter().position(|w| *w == v).unwrap()
+        };
+
+        // union-find over the corners
+        let mut parent: Vec<usize> = (0..num_corners).collect();
+        fn root(parent: &mut [usize], mut c: usize) -> usize {
+            while parent[c] != c {
+                parent[c] = parent[parent[c]];
+                c = parent[c];
+            }
+            c
+        }
+
+        for (e, (a, b)) in topology.edges.iter().enumerate() {
+            if self.sharpness(&topology, e) > 0.0 {
+                continue;
+            }
+            let faces = &topology.edge_faces[e];
+            for v in [*a, *b] {
+                let r0 = root(&mut parent, corner_of(faces[0], v));
+                let r1 = root(&mut parent, corner_of(faces[1], v));
+                parent[r0] = r1;
+            }
+        }
+
+        let mut normal_index = FxHashMap::<usize, usize>::default();
+        let mut normals = Vec::new();
+        let mut normal_faces = Vec::with_capacity(self.faces.len());
+        for (f, face) in self.faces.iter().enumerate() {
+            let ng = self.face_normal(f);
+            let nidx: Vec<usize> = (0..face.len()).map(|i| {
+                let r = root(&mut parent, first_corner[f] + i);
+                let n = *normal_index.entry(r).or_insert_with(|| {
+                    normals.push(Normal::ZERO);
+                    normals.len() - 1
+                });
+                normals[n] += ng;
+                n
+            }).collect();
+            normal_faces.push(nidx);
+        }
+
+        let normals = normals.iter()
+            .map(|n| if n.length_squared() == 0.0 { Normal::Z } else { n.normalize() })
+            .collect();
+
+        (normals, normal_faces)
+    }
+}
+
+impl TriangleMesh {
+    /// Subdivide `faces` with `subdivision`. The new vertices, shading
+    /// normals and texture coordinates are appended to the mesh and the
+    /// faces using them returned. Shading normals of `faces` are replaced.
+    pub fn subdivide(&mut self, faces: Vec<Face>, subdivision: &Subdivision) -> Vec<Face> {
+        println!(
+            "Subdividing {} faces {} times with {:?}",
+            faces.len(), subdivision.levels, subdivision.scheme,
+        );
+
+        // only the vertices used by `faces`, in local indices
+        let mut vertex_index = FxHashMap::<usize, usize>::default();
+        let mut uv_index = FxHashMap::<usize, usize>::default();
+        let mut cage = Cage {
+            points: vec![],
+            uvs: vec![],
+            faces: vec![],
+            uv_faces: vec![],
+            creases: FxHashMap::default(),
+        };
+
+        // faces repeating a vertex have edges that look manifold but are not
+        let is_polygon = |face: &&Face| face.vidx.len() >= 3
+            && face.vidx.iter().enumerate().all(|(i, v)| !face.vidx[..i].contains(v));
+        for face in faces.iter().filter(is_polygon) {
+            cage.faces.push(face.vidx.iter().map(|v| *vertex_index.entry(*v).or_insert_with(|| {
+                cage.points.push(self.vertices[*v]);
+                cage.points.len() - 1
+            })).collect());
+            cage.uv_faces.push(face.tidx.iter().map(|t| *uv_index.entry(*t).or_insert_with(|| {
+                cage.uvs.push(self.uvs[*t]);
+                cage.uvs.len() - 1
+            })).collect());
+        }
+
+        for (v0, v1, sharpness) in &subdivision.creases {
+            if let (Some(a), Some(b)) = (vertex_index.get(v0), vertex_index.get(v1)) {
+                let s = cage.creases.entry(edge_key(*a, *b)).or_insert(0.0);
+                *s = s.max(*sharpness);
+            }
+        }
+        if let Some(angle) = subdivision.crease_angle {
+            cage.crease_by_angle(angle);
+        }
+        if subdivision.scheme == SubdivisionScheme::Loop {
+            cage.triangulate();
+        }
+
+        for _ in 0..subdivision.levels {
+            cage = match subdivision.scheme {
+                SubdivisionScheme::CatmullClark => cage.catmull_clark(),
+                SubdivisionScheme::Loop => cage.loop_subdivision(),
+            };
+        }
+
+        let (normals, normal_faces) = cage.corner_normals();
+        let (base, normal_base, uv_base) = (self.vertices.len(), self.normals.len(), self.uvs.len());
+        self.vertices.extend(&cage.points);
+        self.normals.extend(normals);
+        self.uvs.extend(&cage.uvs);
+
+        cage.faces.into_iter().zip(normal_faces).zip(cage.uv_faces)
+            .map(|((vidx, nidx), tidx)| Face::new(
+                vidx.iter().map(|v| base + v).collect(),
+                nidx.iter().map(|n| normal_base + n).collect(),
+                tidx.iter().map(|t| uv_base + t).collect(),
+            ))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod subdivision_tests {
+    use super::*;
+
+    /// Cube `\[-1,1\]^3` of quads facing out
+    fn cube() -> (TriangleMesh, Vec<Face>) {
+        let vertices = (0..8)
+            .map(|i| Point::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            ))
+            .collect();
+        let mesh = TriangleMesh {
+            vertices,
+            normals: vec![],
+            uvs: vec![],
+            tangents: vec![],
+            materials: vec![Material::Blank],
+        };
+        let faces = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]]
+            .iter()
+            .map(|vidx| Face::new(vidx.to_vec(), vec![], vec![]))
+            .collect();
+
+        (mesh, faces)
+    }
+
+    /// Unit square on the xy-plane as a single quad
+    fn square() -> (TriangleMesh, Vec<Face>) {
+        let mesh = TriangleMesh {
+            vertices: vec![Point::ZERO, Point::X, Point::new(1.0, 1.0, 0.0), Point::Y],
+            normals: vec![],
+            uvs: vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y],
+            tangents: vec![],
+            materials: vec![Material::Blank],
+        };
+        let faces = vec![Face::new(vec![0, 1, 2, 3], vec![], vec![0, 1, 2, 3])];
+
+        (mesh, faces)
+    }
+
+    fn assert_normals_unit(mesh: &TriangleMesh, faces: &[Face]) {
+        for face in faces {
+            assert!(face.nidx.len() == face.vidx.len());
+            for nidx in &face.nidx {
+                assert!((mesh.normals[*nidx].length() - 1.0).abs() < crate::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn catmull_clark_cube() {
+        let (mut mesh, faces) = cube();
+        let faces = mesh.subdivide(faces, &Subdivision::new(SubdivisionScheme::CatmullClark, 1));
+
+        assert!(faces.len() == 24);
+        assert!(faces.iter().all(|face| face.vidx.len() == 4));
+        // 8 vertices, 12 edges and 6 faces of the cage
+        assert!(mesh.vertices.len() == 8 + 26);
+        assert_normals_unit(&mesh, &faces);
+
+        let corner = Point::splat(5.0 / 9.0);
+        assert!(mesh.vertices[8..].iter().any(|v| v.distance(corner) < crate::EPSILON));
+    }
+
+    #[test]
+    fn smooth_cube_normals_point_out() {
+        let (mut mesh, faces) = cube();
+        let faces = mesh.subdivide(faces, &Subdivision::new(SubdivisionScheme::CatmullClark, 3));
+
+        for face in &faces {
+            for (vidx, nidx) in face.vidx.iter().zip(&face.nidx) {
+                let v = mesh.vertices[*vidx];
+                assert!(mesh.normals[*nidx].dot(v.normalize()) > 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn sharp_cube_stays_cube() {
+        let (mut mesh, faces) = cube();
+        let subdivision = Subdivision::new(SubdivisionScheme::CatmullClark, 2)
+            .crease_angle(60.0);
+        let faces = mesh.subdivide(faces, &subdivision);
+
+        assert!(faces.len() == 6 * 16);
+        for face in &faces {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[face.vidx[i]]);
+            let ng = (b - a).cross(c - a).normalize();
+            for (vidx, nidx) in face.vidx.iter().zip(&face.nidx) {
+                assert!(mesh.vertices[*vidx].abs().max_element() == 1.0);
+                assert!(mesh.normals[*nidx].distance(ng) < crate::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn boundary_corners_stay() {
+        let (mut mesh, faces) = square();
+        let faces = mesh.subdivide(faces, &Subdivision::new(SubdivisionScheme::CatmullClark, 2));
+
+        assert!(faces.len() == 16);
+        for corner in [Point::ZERO, Point::X, Point::new(1.0, 1.0, 0.0), Point::Y] {
+            assert!(mesh.vertices[4..].iter().any(|v| v.distance(corner) < crate::EPSILON));
+        }
+        for face in &faces {
+            for nidx in &face.nidx {
+                assert!(mesh.normals[*nidx].distance(Normal::Z) < crate::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn uvs_are_linear() {
+        let (mut mesh, faces) = square();
+        let faces = mesh.subdivide(faces, &Subdivision::new(SubdivisionScheme::CatmullClark, 2));
+
+        // the flat square stays the unit square
+        for face in &faces {
+            for (vidx, tidx) in face.vidx.iter().zip(&face.tidx) {
+                let v = mesh.vertices[*vidx];
+                let uv = mesh.uvs[*tidx];
+                assert!(v.z.abs() < crate::EPSILON);
+                assert!(v.x >= 0.0 && v.x <= 1.0 && v.y >= 0.0 && v.y <= 1.0);
+                // boundary vertices keep their parametrization
+                if v.x == 0.0 || v.x == 1.0 || v.y == 0.0 || v.y == 1.0 {
+                    assert!(uv.extend(0.0).distance(v) < crate::EPSILON);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn loop_quadruples_triangles() {
+        let (mut mesh, faces) = cube();
+        let faces = mesh.subdivide(faces, &Subdivision::new(SubdivisionScheme::Loop, 2));
+
+        assert!(faces.len() == 12 * 4 * 4);
+        assert!(faces.iter().all(|face| face.vidx.len() == 3));
+        assert_normals_unit(&mesh, &faces);
+    }
+
+    #[test]
+    fn repeated_vertices_get_dropped() {
+        for scheme in [SubdivisionScheme::CatmullClark, SubdivisionScheme::Loop] {
+            let (mut mesh, mut faces) = cube();
+            faces.push(Face::new(vec![1, 4, 4], vec![], vec![]));
+            faces.push(Face::new(vec![0, 2, 0, 3], vec![], vec![]));
+            let subdivided = mesh.subdivide(faces, &Subdivision::new(scheme, 2));
+
+            let (mut cube_mesh, cube_faces) = cube();
+            assert!(subdivided.len() == cube_mesh.subdivide(cube_faces, &Subdivision::new(scheme, 2)).len());
+            assert_normals_unit(&mesh, &subdivided);
+        }
+    }
+
+    #[test]
+    fn semi_sharp_is_between() {
+        let subdivided = |sharpness: Float| {
+            let (mut mesh, faces) = cube();
+            let subdivision = (0..8).fold(
+                Subdivision::new(SubdivisionScheme::CatmullClark, 1),
+                |s, i| s.crease(i, i ^ 1, sharpness)
+                    .crease(i, i ^ 2, sharpness)
+                    .crease(i, i ^ 4, sharpness),
+            );
+            mesh.subdivide(faces, &subdivision);
+            mesh.vertices
+        };
+
+        let smooth = subdivided(0.0);
+        let sharp = subdivided(1.0);
+        let half = subdivided(0.5);
+        for i in 0..half.len() {
+            assert!(half[i].distance((smooth[i] + sharp[i]) / 2.0) < crate::EPSILON);
+        }
+    }
+
+    #[test]
+    fn cache_bytes_follow_parameters() {
+        let bytes = |subdivision: Subdivision| {
+            let mut w = vec![];
+            subdivision.write_cache(&mut w).unwrap();
+            w
+        };
+        let base = || Subdivision::new(SubdivisionScheme::CatmullClark, 2)
+            .crease(0, 1, 1.5)
+            .crease_angle(30.0);
+
+        assert!(bytes(base()) == bytes(base()));
+        let others = [
+            Subdivision::new(SubdivisionScheme::Loop, 2).crease(0, 1, 1.5).crease_angle(30.0),
+            Subdivision::new(SubdivisionScheme::CatmullClark, 3).crease(0, 1, 1.5).crease_angle(30.0),
+            Subdivision::new(SubdivisionScheme::CatmullClark, 2).crease(0, 2, 1.5).crease_angle(30.0),
+            Subdivision::new(SubdivisionScheme::CatmullClark, 2).crease(0, 1, 2.0).crease_angle(30.0),
+            Subdivision::new(SubdivisionScheme::CatmullClark, 2).crease(0, 1, 1.5),
+            Subdivision::new(SubdivisionScheme::CatmullClark, 2).crease(0, 1, 1.5).crease_angle(0.0),
+            base().crease(1, 2, 1.0),
+        ];
+        for other in others {
+            assert!(bytes(base()) != bytes(other));
+        }
+    }
+}
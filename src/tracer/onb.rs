@@ -39,6 +39,29 @@ impl Onb {
         Self { u, v, w }
     }
 
+    /// Creates a new ONB with `u` pointing towards `tangent`. Falls back to
+    /// [`Onb::new`] if `tangent` is parallel to `w`.
+    ///
+    /// # Arguments
+    /// * `w` - Direction of `z` axis, normalized.
+    /// * `tangent` - Direction of `x` axis, orthogonalized against `w`.
+    #[inline]
+    pub fn new_with_tangent(w: Normal, tangent: Direction) -> Self {
+        #[cfg(debug_assertions)]
+        assert!(w.is_normalized());
+
+        // Gram-Schmidt
+        let u = tangent - w * w.dot(tangent);
+        let length = u.length();
+        if length < crate::EPSILON {
+            return Self::new(w);
+        }
+        let u = u / length;
+        let v = w.cross(u);
+
+        Self { u, v, w }
+    }
+
     #[inline]
     #[allow(dead_code)]
     pub fn new_from_basis(u: Normal, v: Normal, w: Normal) -> Self {
@@ -90,6 +113,20 @@ mod tests {
         assert!(v.distance(vp) < crate::EPSILON);
     }
 
+    #[test]
+    fn with_tangent() {
+        let w = Direction::new(1.23, 4.56, 7.89).normalize();
+        let t = Direction::new(9.87, 6.54, 3.21);
+        let uvw = Onb::new_with_tangent(w, t);
+
+        assert!(uvw.u.dot(uvw.v).abs() < crate::EPSILON);
+        assert!(uvw.u.dot(uvw.w).abs() < crate::EPSILON);
+        assert!(uvw.u.cross(uvw.v).distance(w) < crate::EPSILON);
+        // tangent stays on the positive side of u
+        assert!(uvw.to_local(t).x > 0.0);
+        assert!(uvw.to_local(t).y.abs() < crate::EPSILON);
+    }
+
     #[test]
     fn from_basis() {
         let u = Direction::new(1.23, 4.56, 7.89).normalize();
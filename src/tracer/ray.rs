@@ -1,11 +1,30 @@
 use crate::{ Axis, Direction, Point, Float, Transform };
 
+/// Offset rays through the neighbouring pixels of a camera ray. Used to
+/// estimate the footprint of the ray on surfaces for texture filtering.
+#[derive(Clone, Copy)]
+pub struct RayDifferential {
+    /// Origin of the ray offset by one pixel in raster `x`
+    pub rx_origin: Point,
+    /// Direction of the ray offset by one pixel in raster `x`
+    pub rx_dir: Direction,
+    /// Origin of the ray offset by one pixel in raster `y`
+    pub ry_origin: Point,
+    /// Direction of the ray offset by one pixel in raster `y`
+    pub ry_dir: Direction,
+}
+
 /// Ray abstraction
 pub struct Ray {
     /// Point of origin of the ray
     pub origin: Point,
     /// Direction of the ray. Normalized.
     pub dir: Direction,
+    /// Differentials of the ray, if it can be traced back to the camera
+    /// through specular bounces only
+    pub differentials: Option<RayDifferential>,
+    /// Time at which the ray travels, within the shutter interval of the camera
+    pub time: Float,
 }
 
 impl Ray {
@@ -15,9 +34,23 @@ impl Ray {
         Self {
             origin,
             dir: dir.normalize(),
+            differentials: None,
+            time: 0.0,
         }
     }
 
+    /// Set the time of the ray
+    pub fn with_time(mut self, time: Float) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Set the differentials of the ray
+    pub fn with_differentials(mut self, differentials: RayDifferential) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
     /// Applies `transformation` to `self`. Direction unnormalized to guarantee
     /// correct ray distances in Instance.
     #[inline]
@@ -25,8 +58,14 @@ impl Ray {
         let origin = transformation.transform_pt_inv(self.origin);
         let dir = transformation.transform_dir_inv(self.dir);
         let dir = if NORMALIZE { dir.normalize() } else { dir };
+        let differentials = self.differentials.map(|rd| RayDifferential {
+            rx_origin: transformation.transform_pt_inv(rd.rx_origin),
+            rx_dir: transformation.transform_dir_inv(rd.rx_dir),
+            ry_origin: transformation.transform_pt_inv(rd.ry_origin),
+            ry_dir: transformation.transform_dir_inv(rd.ry_dir),
+        });
 
-        Self { origin, dir }
+        Self { origin, dir, differentials, time: self.time }
     }
 
     /// Position of the ray at time `t`
@@ -2,7 +2,7 @@ use crate::{ Float, rng::Xorshift };
 use crate::tracer::{
     object::AaBoundingBox, hit::Hit, ray::Ray, Material, Texture, Color,
     BVH, Sphere, ColorWavelength, Medium, Object, Rectangle,
-    Sampleable, Instanceable, color::illuminants
+    Sampleable, Instanceable, color::illuminants, animation::Keyframes
 };
 
 #[cfg(test)]
@@ -61,6 +61,8 @@ impl Scene {
     pub fn add_light(&mut self, light: Box<dyn Sampleable>) {
         // how to check material is light?
         // trait upcasting experimental, store lights and objects in separate BVHs
+        // lights that can not be sampled panic here rather than mid-render
+        light.area();
         self.lights.add(light);
     }
 
@@ -72,7 +74,7 @@ impl Scene {
     /// Set the texture to use for environment light
     pub fn set_environment_map(&mut self, env_map: Texture, scale: Float) {
         self.environment_map = Some(
-            Material::Light(env_map, illuminants::D65, scale, true)
+            Material::Light(env_map, illuminants::D65.clone(), Keyframes::from(scale), true)
         );
     }
 
@@ -133,6 +135,12 @@ impl Scene {
         t_max = h.as_ref().map_or(t_max, |hit| hit.t);
 
         h = self.lights.hit(r, 0.0, t_max).or(h);
+        h = h.map(|mut h| {
+            h = h.with_differentials(r);
+            h.time = r.time;
+            h.material = h.material.resolve(&h.tex_coord(), rng.gen_float(), rng.gen_float());
+            h
+        });
 
         #[cfg(debug_assertions)]
         {
@@ -168,7 +176,8 @@ impl Scene {
         rng: &mut Xorshift,
         light: &'a dyn Sampleable,
     ) -> Option<Hit<'a>> {
-        let light_hit = light.hit(r, 0.0, crate::INF)?;
+        let mut light_hit = light.hit(r, 0.0, crate::INF)?;
+        light_hit.time = r.time;
         let t_max = light_hit.t - crate::EPSILON;
 
         if let Some(medium) = &self.medium {
@@ -28,8 +28,8 @@ impl Scene {
 
         let light = Material::Light(
             Texture::from(light_spec),
-            illuminants::CORNELL,
-            1.0,
+            illuminants::CORNELL.clone(),
+            Keyframes::from(1.0),
             false,
         );
 
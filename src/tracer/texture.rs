@@ -1,7 +1,11 @@
-use crate::{ Float, Image, Point, perlin::Perlin, Vec2 };
+use crate::{ Direction, Float, Image, Mat3, Normal, Point, perlin::Perlin, Vec2, Vec3 };
 use crate::math::complex::Complex;
 use crate::tracer::{Color, ColorWavelength, Spectrum };
 
+pub use noise::{NoisePattern, SolidNoise, TextureSpace};
+
+mod noise;
+
 /// Scale of points in perlin. bigger = more noticeable effect
 const MARBLE_SCALE: Float = 4.0;
 /// Frequency of noise in perlin noise. bigger = more frequent
@@ -20,22 +24,109 @@ const MANDELBROT_R: Float = 64.0;
 /// Escape radius squared
 const MANDELBROT_R2: Float = MANDELBROT_R * MANDELBROT_R;
 
+/// Scale of each octave in fractal Brownian motion
+const FBM_GAIN: Float = 0.5;
+
+/// Point on a surface where a texture gets evaluated
+#[derive(Clone, Copy)]
+pub struct TexCoord {
+    /// Texture coordinates in `\[0,1\]^2`
+    pub uv: Vec2,
+    /// Point in world space
+    pub p: Point,
+    /// Point in the space of the object, before instance transforms
+    pub p_local: Point,
+    /// Shading normal of the surface
+    pub n: Normal,
+    /// Change in `uv` between neighbouring pixels in `x`
+    pub duvdx: Vec2,
+    /// Change in `uv` between neighbouring pixels in `y`
+    pub duvdy: Vec2,
+}
+
+impl TexCoord {
+    /// Texture coordinates `uv` at point `p` with shading normal `n`
+    pub const fn new(uv: Vec2, p: Point, n: Normal) -> Self {
+        Self { uv, p, p_local: p, n, duvdx: Vec2::ZERO, duvdy: Vec2::ZERO }
+    }
+
+    /// Set the point in object space, if it differs from the world space point
+    pub const fn with_local_point(mut self, p_local: Point) -> Self {
+        self.p_local = p_local;
+        self
+    }
+
+    /// Set the footprint of the lookup in texture space
+    pub const fn with_footprint(mut self, duvdx: Vec2, duvdy: Vec2) -> Self {
+        self.duvdx = duvdx;
+        self.duvdy = duvdy;
+        self
+    }
+
+    /// Only texture coordinates `uv`, at origin facing `z`
+    pub const fn from_uv(uv: Vec2) -> Self {
+        Self::new(uv, Point::ZERO, Normal::Z)
+    }
+
+    /// Same point with texture coordinates `uv`. The footprint is unknown
+    /// in the new coordinates, so lookups fall back to the finest detail.
+    fn with_uv(&self, uv: Vec2) -> Self {
+        Self { uv, duvdx: Vec2::ZERO, duvdy: Vec2::ZERO, ..*self }
+    }
+}
+
 /// Defines a texture to choose a colour of material at each point.
+/// Textures can be composed to graphs, e.g. mixed, multiplied
+/// and projected, to drive any parameter given as a texture.
 pub enum Texture {
     /// Solid colour.
     Solid(Spectrum),
+    /// Constant grey value, e.g. a fixed weight of a mix
+    Constant(Float),
     /* box avoids having to define lifetime all the way to objects.
      * should texture be a struct instead? */
     /// Checkerboard of textures. Float defines scale,
     /// bigger scale = smaller boxes.
     Checkerboard(Box<Texture>, Box<Texture>, Float),
-    /// Marble like texture generated from Perlin noise.
-    /// Underlying color as argument.
+    /// Marble like texture generated from Perlin noise in texture coordinates.
+    /// Underlying color as argument. See [`SolidNoise::marble`] for a pattern
+    /// that does not depend on the texture coordinates.
     Marble(Perlin, Spectrum),
-    /// Image texture loaded from a .png
+    /// Image texture loaded from a .png, .jpg, .tga or .hdr
     Image(Image<Spectrum>),
+    /// Single channel image, e.g. a roughness or metalness map
+    Grayscale(Image<Float>),
     /// Cheap render of the Mandelbrot set
     Mandelbrot,
+    /// Interpolates from the first texture to the second by the third
+    Mix(Box<Texture>, Box<Texture>, Box<Texture>),
+    /// Product of two textures
+    Multiply(Box<Texture>, Box<Texture>),
+    /// Sum of two textures
+    Add(Box<Texture>, Box<Texture>),
+    /// Linearly maps values of the texture from the range `x..y` of the first
+    /// vector to the range of the second vector. Clamps to the new range.
+    Remap(Box<Texture>, Vec2, Vec2),
+    /// Texture with texture coordinates transformed by an affine matrix.
+    /// Texture coordinates are homogeneous, i.e. `(u, v, 1)`.
+    Transform(Box<Texture>, Mat3),
+    /// Projects the texture along the world axes and blends the projections
+    /// by the shading normal. Floats are the scale of the projection and the
+    /// sharpness of the blend.
+    Triplanar(Box<Texture>, Float, Float),
+    /// Projects the texture on a plane in world space,
+    /// `u` and `v` grow along the directions.
+    Planar(Box<Texture>, Direction, Direction),
+    /// Fractal Brownian motion of Perlin noise in world space.
+    /// Scale of the noise and the number of octaves as arguments.
+    Fbm(Perlin, Float, usize),
+    /// Worley noise in world space, i.e. distance to the closest
+    /// randomly placed feature point. Scale of the noise as argument.
+    Worley(Float),
+    /// Linear gradient from zero to one along the direction in texture coordinates
+    Gradient(Vec2),
+    /// Solid noise evaluated at the hit point in object or world space
+    Noise(SolidNoise),
 }
 
 impl Default for Texture {
@@ -48,11 +139,87 @@ impl From<Spectrum> for Texture {
     }
 }
 
+impl From<Float> for Texture {
+    fn from(value: Float) -> Self {
+        Self::Constant(value)
+    }
+}
+
+impl From<SolidNoise> for Texture {
+    fn from(noise: SolidNoise) -> Self {
+        Self::Noise(noise)
+    }
+}
+
 impl Texture {
-    /// Colour at hit `h`
-    pub fn albedo_at(&self, lambda: &ColorWavelength, uv: Vec2) -> Color {
+    /// Interpolate from `self` to `other` by `factor`
+    pub fn mix(self, other: Texture, factor: Texture) -> Self {
+        Self::Mix(Box::new(self), Box::new(other), Box::new(factor))
+    }
+
+    /// Multiply `self` by `other`
+    pub fn multiply(self, other: Texture) -> Self {
+        Self::Multiply(Box::new(self), Box::new(other))
+    }
+
+    /// Sum of `self` and `other`
+    pub fn sum(self, other: Texture) -> Self {
+        Self::Add(Box::new(self), Box::new(other))
+    }
+
+    /// Map values in `from.x..from.y` to `to.x..to.y`
+    pub fn remap(self, from: Vec2, to: Vec2) -> Self {
+        Self::Remap(Box::new(self), from, to)
+    }
+
+    /// Scale texture coordinates by `scale`, i.e. repeat the texture
+    pub fn scale_uv(self, scale: Vec2) -> Self {
+        self.transform_uv(Mat3::diag(Vec3::new(scale.x, scale.y, 1.0)))
+    }
+
+    /// Rotate texture coordinates by `degrees` around the origin
+    pub fn rotate_uv(self, degrees: Float) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.transform_uv(Mat3::new(
+            Vec3::new(cos, -sin, 0.0),
+            Vec3::new(sin, cos, 0.0),
+            Vec3::Z,
+        ))
+    }
+
+    /// Offset texture coordinates by `offset`
+    pub fn offset_uv(self, offset: Vec2) -> Self {
+        self.transform_uv(Mat3::new(
+            Vec3::new(1.0, 0.0, offset.x),
+            Vec3::new(0.0, 1.0, offset.y),
+            Vec3::Z,
+        ))
+    }
+
+    /// Apply `mat` to the texture coordinates, after any existing transform
+    fn transform_uv(self, mat: Mat3) -> Self {
+        match self {
+            Self::Transform(tex, m) => Self::Transform(tex, mat * m),
+            tex => Self::Transform(Box::new(tex), mat),
+        }
+    }
+
+    /// Project along the world axes, blended by the shading normal
+    pub fn triplanar(self, scale: Float, sharpness: Float) -> Self {
+        Self::Triplanar(Box::new(self), scale, sharpness)
+    }
+
+    /// Project on a plane spanned by `u` and `v` in world space
+    pub fn planar(self, u: Direction, v: Direction) -> Self {
+        Self::Planar(Box::new(self), u, v)
+    }
+
+    /// Colour at texture coordinate `tc`
+    pub fn albedo_at(&self, lambda: &ColorWavelength, tc: &TexCoord) -> Color {
+        let uv = tc.uv;
         match self {
             Texture::Solid(spec) => spec.sample(lambda),
+            Texture::Constant(v) => Color::WHITE * *v,
             Texture::Marble(pn, spec) => {
                 let uvw = uv.extend(0.0);
                 let turb = Self::turbulence(pn, 0.0, MARBLE_SCALE * uvw.abs(), 0);
@@ -65,12 +232,13 @@ impl Texture {
             Texture::Checkerboard(t1, t2, s) => {
                 let uvs = uv * (*s);
                 if (uvs.x.floor() + uvs.y.floor()) as u64 % 2 == 0 {
-                    t1.albedo_at(lambda, uv)
+                    t1.albedo_at(lambda, tc)
                 } else {
-                    t2.albedo_at(lambda, uv)
+                    t2.albedo_at(lambda, tc)
                 }
             }
-            Texture::Image(img) => img.value_at(uv, lambda),
+            Texture::Image(img) => img.filtered_value_at(uv, tc.duvdx, tc.duvdy, lambda),
+            Texture::Grayscale(img) => Color::WHITE * img.value_at(uv),
             Texture::Mandelbrot => {
                 let mut depth = 0;
                 // [-1.5,0.5] x [-1.0,1.0]
@@ -88,14 +256,96 @@ impl Texture {
                     Color::BLACK
                 }
             }
+            Texture::Mix(t1, t2, factor) => {
+                let t = factor.albedo_at(lambda, tc);
+                (Color::WHITE - t) * t1.albedo_at(lambda, tc) + t * t2.albedo_at(lambda, tc)
+            }
+            Texture::Multiply(t1, t2) => {
+                t1.albedo_at(lambda, tc) * t2.albedo_at(lambda, tc)
+            }
+            Texture::Add(t1, t2) => {
+                t1.albedo_at(lambda, tc) + t2.albedo_at(lambda, tc)
+            }
+            Texture::Remap(tex, from, to) => {
+                let t = (tex.albedo_at(lambda, tc) - from.x) / (from.y - from.x);
+                (to.x + t * (to.y - to.x)).clamp(to.x.min(to.y), to.x.max(to.y))
+            }
+            Texture::Transform(tex, mat) => {
+                let uvw = mat.mul_vec3(uv.extend(1.0));
+                let duvdx = mat.mul_vec3(tc.duvdx.extend(0.0));
+                let duvdy = mat.mul_vec3(tc.duvdy.extend(0.0));
+                let tc = tc.with_uv(Vec2::new(uvw.x, uvw.y))
+                    .with_footprint(duvdx.truncate(), duvdy.truncate());
+                tex.albedo_at(lambda, &tc)
+            }
+            Texture::Triplanar(tex, scale, sharpness) => {
+                let p = tc.p * *scale;
+                let n = tc.n.abs();
+                let w = Vec3::new(n.x.powf(*sharpness), n.y.powf(*sharpness), n.z.powf(*sharpness));
+                let w = w / w.dot(Vec3::ONE);
+
+                let project = |uv: Vec2| tex.albedo_at(lambda, &tc.with_uv(uv));
+
+                w.x * project(Vec2::new(p.z, p.y))
+                    + w.y * project(Vec2::new(p.x, p.z))
+                    + w.z * project(Vec2::new(p.x, p.y))
+            }
+            Texture::Planar(tex, u, v) => {
+                let uv = Vec2::new(tc.p.dot(*u), tc.p.dot(*v));
+                tex.albedo_at(lambda, &tc.with_uv(uv))
+            }
+            Texture::Fbm(pn, scale, octaves) => {
+                let p = tc.p * *scale;
+                let (noise, _) = (0..*octaves).fold((0.0, 1.0), |(acc, w), octave| {
+                    let freq = (1 << octave) as Float;
+                    (acc + w * pn.noise_at(freq * p), w * FBM_GAIN)
+                });
+
+                Color::WHITE * (0.5 + 0.5 * noise).clamp(0.0, 1.0)
+            }
+            Texture::Worley(scale) => {
+                Color::WHITE * Self::worley(tc.p * *scale).min(1.0)
+            }
+            Texture::Gradient(dir) => {
+                Color::WHITE * uv.dot(*dir).clamp(0.0, 1.0)
+            }
+            Texture::Noise(noise) => Color::WHITE * noise.value_at(tc.p, tc.p_local),
         }
     }
 
-    /// "Power" of the texture
+    /// Scalar value at texture coordinate `tc` for textures that drive
+    /// material parameters, e.g. roughness. Uses the leading wavelength,
+    /// so that the value stays the same after the others get terminated.
+    pub fn value_at(&self, lambda: &ColorWavelength, tc: &TexCoord) -> Float {
+        self.albedo_at(lambda, tc).leading_sample()
+    }
+
+    /// "Power" of the texture, approximate for composed textures
     pub fn power(&self, lambda: &ColorWavelength) -> Color {
         match self {
             Texture::Solid(spec) => spec.sample(lambda),
+            Texture::Constant(v) => Color::WHITE * *v,
             Texture::Image(img) => img.power(lambda),
+            Texture::Grayscale(img) => Color::WHITE * img.mean(),
+            Texture::Mix(t1, t2, factor) => {
+                let t = factor.power(lambda);
+                (Color::WHITE - t) * t1.power(lambda) + t * t2.power(lambda)
+            }
+            Texture::Multiply(t1, t2) => t1.power(lambda) * t2.power(lambda),
+            Texture::Add(t1, t2) => t1.power(lambda) + t2.power(lambda),
+            Texture::Remap(tex, from, to) => {
+                let t = (tex.power(lambda) - from.x) / (from.y - from.x);
+                (to.x + t * (to.y - to.x)).clamp(to.x.min(to.y), to.x.max(to.y))
+            }
+            Texture::Transform(tex, _)
+                | Texture::Triplanar(tex, ..)
+                | Texture::Planar(tex, ..) => tex.power(lambda),
+            Texture::Fbm(..)
+                | Texture::Worley(..)
+                | Texture::Gradient(..)
+                | Texture::Noise(..) => {
+                Color::WHITE * 0.5
+            }
             _ => unimplemented!(),
         }
     }
@@ -110,4 +360,212 @@ impl Texture {
 
         Self::turbulence(pn, acc + w * pn.noise_at(p).abs(), 2.0 * p, depth + 1)
     }
+
+    /// Distance from `p` to the closest feature point. Each unit cell has one
+    /// feature point at a position hashed from the cell coordinates.
+    fn worley(p: Point) -> Float {
+        let cell = p.floor();
+        let mut dist2 = crate::INF;
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = cell + Vec3::new(dx as Float, dy as Float, dz as Float);
+                    let feature = neighbor + Self::hash_cell(neighbor);
+                    dist2 = dist2.min(feature.distance_squared(p));
+                }
+            }
+        }
+
+        dist2.sqrt()
+    }
+
+    /// Random point in `\[0,1\]^3` for cell at `cell`
+    fn hash_cell(cell: Point) -> Vec3 {
+        let mut h = (cell.x as i64 as u64).wrapping_mul(0x8da6_b343)
+            ^ (cell.y as i64 as u64).wrapping_mul(0xd816_3841)
+            ^ (cell.z as i64 as u64).wrapping_mul(0xcb1a_b31f);
+
+        let mut next = || {
+            // splitmix64
+            h = h.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = h;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 11) as Float / (1_u64 << 53) as Float
+        };
+
+        Vec3::new(next(), next(), next())
+    }
+}
+
+#[cfg(test)]
+mod texture_tests {
+    use super::*;
+    use crate::tracer::{RGB, Uplift};
+
+    fn lambda() -> ColorWavelength {
+        ColorWavelength::sample(0.5)
+    }
+
+    #[test]
+    fn mipmap_filters_footprint() {
+        let lambda = lambda();
+        // 4x4 checkerboard of black and white pixels
+        let rgb = (0..16)
+            .map(|i| if (i % 4 + i / 4) % 2 == 0 { RGB::WHITE } else { RGB::BLACK })
+            .collect();
+        let img = Image::from_rgb(rgb, 4, 4, Uplift::default());
+        assert!(img.mip_levels() == 2);
+
+        let tex = Texture::Image(img);
+        // center of the top left texel
+        let tc = TexCoord::from_uv(Vec2::new(0.125, 0.875));
+        let sharp = tex.albedo_at(&lambda, &tc);
+        let white = Spectrum::WHITE.sample(&lambda);
+        assert!((sharp - white).max().abs() < 1e-10);
+
+        // footprint covers the whole image
+        let tc = tc.with_footprint(Vec2::X, Vec2::Y);
+        let blurred = tex.albedo_at(&lambda, &tc) - tex.power(&lambda);
+        assert!(blurred.max().abs() < 1e-10 && blurred.min().abs() < 1e-10);
+    }
+
+    #[test]
+    fn mix_interpolates() {
+        let lambda = lambda();
+        // factor is u
+        let tc = TexCoord::from_uv(Vec2::new(0.25, 0.7));
+        let mix = Texture::default()
+            .mix(Texture::from(Spectrum::BLACK), Texture::Gradient(Vec2::X));
+
+        let expected = 0.75 * Texture::default().albedo_at(&lambda, &tc);
+        let c = mix.albedo_at(&lambda, &tc) - expected;
+        assert!(c.max().abs() < 1e-10 && c.min().abs() < 1e-10);
+    }
+
+    #[test]
+    fn uv_transforms_compose() {
+        let lambda = lambda();
+        let tex = Texture::Gradient(Vec2::X)
+            .scale_uv(Vec2::splat(0.5))
+            .offset_uv(Vec2::new(0.25, 0.0));
+
+        // 0.5 * 0.5 + 0.25
+        let c = tex.albedo_at(&lambda, &TexCoord::from_uv(Vec2::new(0.5, 0.0)));
+        assert!((c.mean() - 0.5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn rotate_quarter_turn() {
+        let lambda = lambda();
+        let tex = Texture::Gradient(Vec2::X).rotate_uv(90.0);
+
+        // (0, 0.5) rotates to (-0.5, 0) and (0.5, 0) to (0, 0.5)
+        let tc = TexCoord::from_uv(Vec2::new(0.0, 0.5));
+        assert!(tex.albedo_at(&lambda, &tc).mean() < 1e-10);
+        let tc = TexCoord::from_uv(Vec2::new(0.3, -0.5));
+        assert!((tex.albedo_at(&lambda, &tc).mean() - 0.5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn triplanar_picks_facing_axis() {
+        let lambda = lambda();
+        let tex = Texture::Gradient(Vec2::X).triplanar(1.0, 4.0);
+
+        // facing y projects (x, z)
+        let tc = TexCoord::new(Vec2::ZERO, Point::new(0.25, 0.9, 0.6), Normal::Y);
+        assert!((tex.albedo_at(&lambda, &tc).mean() - 0.25).abs() < 1e-10);
+    }
+
+    #[test]
+    fn remap_clamps() {
+        let lambda = lambda();
+        let tex = Texture::Gradient(Vec2::X).remap(Vec2::new(0.0, 0.5), Vec2::new(0.2, 0.4));
+
+        let tc = TexCoord::from_uv(Vec2::new(0.25, 0.0));
+        assert!((tex.albedo_at(&lambda, &tc).mean() - 0.3).abs() < 1e-10);
+        let tc = TexCoord::from_uv(Vec2::new(0.9, 0.0));
+        assert!((tex.albedo_at(&lambda, &tc).mean() - 0.4).abs() < 1e-10);
+    }
+
+    #[test]
+    fn noise_in_unit_range() {
+        let lambda = lambda();
+        let fbm = Texture::Fbm(Perlin::new(123), 3.0, 5);
+        let worley = Texture::Worley(4.0);
+
+        for i in 0..100 {
+            let x = i as Float * 0.137;
+            let tc = TexCoord::new(Vec2::ZERO, Point::new(x, -2.0 * x, 0.5 * x), Normal::Z);
+            for tex in [&fbm, &worley] {
+                let v = tex.albedo_at(&lambda, &tc).mean();
+                assert!((0.0..=1.0).contains(&v));
+            }
+        }
+    }
+
+    #[test]
+    fn perlin_continuous_across_origin() {
+        let pn = Perlin::new(42);
+        for p in [Vec3::X, Vec3::Y, Vec3::Z, Vec3::new(0.3, -0.7, 0.1)] {
+            let eps = 1e-6 * p;
+            assert!((pn.noise_at(eps) - pn.noise_at(-eps)).abs() < 1e-4);
+            let q = p - 2.0 * Vec3::ONE;
+            assert!((pn.noise_at(q + eps) - pn.noise_at(q - eps)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn object_space_noise_follows_object() {
+        let lambda = lambda();
+        let p_local = Point::new(0.3, -0.4, 0.8);
+        let moved = TexCoord::new(Vec2::ZERO, p_local + Vec3::new(5.3, 1.7, -2.9), Normal::Z)
+            .with_local_point(p_local);
+        let fixed = TexCoord::new(Vec2::ZERO, p_local, Normal::Z);
+
+        let tex = Texture::from(SolidNoise::fbm(Perlin::new(7), 4).scale(3.0));
+        let v = tex.albedo_at(&lambda, &fixed).mean();
+        assert!((tex.albedo_at(&lambda, &moved).mean() - v).abs() < 1e-10);
+
+        let tex = Texture::from(SolidNoise::fbm(Perlin::new(7), 4)
+                                .scale(3.0)
+                                .in_space(TextureSpace::World));
+        assert!((tex.albedo_at(&lambda, &moved).mean() - v).abs() > 1e-6);
+    }
+
+    #[test]
+    fn instanced_hit_keeps_local_point() {
+        use crate::tracer::{Instanceable, Material, Object, Sphere, ray::Ray};
+
+        let sphere = Sphere::new(1.0, Material::mirror()).translate(5.0, 0.0, 0.0);
+        let r = Ray::new(Point::new(5.0, 0.0, 5.0), -Direction::Z);
+        let tc = sphere.hit(&r, 0.0, crate::INF).unwrap().tex_coord();
+
+        assert!(tc.p.distance(Point::new(5.0, 0.0, 1.0)) < 1e-6);
+        assert!(tc.p_local.distance(Point::Z) < 1e-6);
+    }
+
+    #[test]
+    fn solid_presets_in_unit_range() {
+        let lambda = lambda();
+        let presets = [
+            SolidNoise::marble(Perlin::new(1)).scale(2.0),
+            SolidNoise::wood(Perlin::new(2)).scale(0.5),
+            SolidNoise::granite(Perlin::new(3)),
+            SolidNoise::fbm(Perlin::new(4), 6).scale(4.0),
+        ];
+
+        for noise in presets {
+            let tex = noise.colorize(Spectrum::BLACK, Spectrum::WHITE);
+            let white = Spectrum::WHITE.sample(&lambda).mean();
+            for i in 0..100 {
+                let x = i as Float * 0.173 - 8.0;
+                let tc = TexCoord::new(Vec2::ZERO, Point::new(x, 0.6 * x, -1.3 * x), Normal::Z);
+                let v = tex.albedo_at(&lambda, &tc).mean();
+                assert!((0.0..=white + 1e-10).contains(&v));
+            }
+        }
+    }
 }
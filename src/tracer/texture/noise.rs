@@ -0,0 +1,143 @@
+use crate::{ Float, Point, perlin::Perlin };
+use crate::tracer::{ Spectrum, Texture };
+
+/// Scale of each octave in fractal Brownian motion and turbulence
+const NOISE_GAIN: Float = 0.5;
+/// Octaves of turbulence that perturb marble veins and wood rings
+const DISTORTION_OCTAVES: usize = 4;
+/// How far turbulence pushes the marble veins
+const MARBLE_AMP: Float = 4.0;
+/// Bigger = thinner marble veins
+const MARBLE_SHARPNESS: i32 = 6;
+/// Number of wood rings per unit distance from the axis
+const WOOD_RINGS: Float = 4.0;
+/// How much noise warps the wood rings
+const WOOD_AMP: Float = 0.3;
+/// Frequency of the granite grains relative to the scale
+const GRANITE_FREQ: Float = 8.0;
+/// Octaves of turbulence in granite
+const GRANITE_OCTAVES: usize = 5;
+/// Contrast of the granite grains
+const GRANITE_CONTRAST: Float = 2.0;
+
+/// Space in which a solid texture gets evaluated
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum TextureSpace {
+    /// Pattern moves along with the object, e.g. when it is instanced
+    #[default]
+    Object,
+    /// Pattern is fixed in the scene and objects are carved out of it
+    World,
+}
+
+/// Pattern of a solid noise texture
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoisePattern {
+    /// Fractal Brownian motion with the given number of octaves
+    Fbm(usize),
+    /// Thin veins of a sine wave along `x` perturbed by turbulence
+    Marble,
+    /// Rings around the `y` axis perturbed by noise
+    Wood,
+    /// Grains of high frequency turbulence
+    Granite,
+}
+
+/// Noise evaluated at the 3D hit point instead of the texture coordinates,
+/// so the pattern does not stretch on surfaces with poor texture coordinates.
+/// Values are in `\[0,1\]`.
+pub struct SolidNoise {
+    perlin: Perlin,
+    pattern: NoisePattern,
+    scale: Float,
+    space: TextureSpace,
+}
+
+impl SolidNoise {
+    /// Noise with `pattern` from `perlin` in object space with unit scale
+    pub fn new(perlin: Perlin, pattern: NoisePattern) -> Self {
+        Self { perlin, pattern, scale: 1.0, space: TextureSpace::default() }
+    }
+
+    /// Fractal Brownian motion with `octaves` octaves
+    pub fn fbm(perlin: Perlin, octaves: usize) -> Self {
+        Self::new(perlin, NoisePattern::Fbm(octaves))
+    }
+
+    /// Marble with veins along the `yz` plane
+    pub fn marble(perlin: Perlin) -> Self {
+        Self::new(perlin, NoisePattern::Marble)
+    }
+
+    /// Wood with the trunk along the `y` axis
+    pub fn wood(perlin: Perlin) -> Self {
+        Self::new(perlin, NoisePattern::Wood)
+    }
+
+    /// Speckled granite
+    pub fn granite(perlin: Perlin) -> Self {
+        Self::new(perlin, NoisePattern::Granite)
+    }
+
+    /// Scale the points before evaluating the noise, bigger = finer pattern
+    pub fn scale(mut self, scale: Float) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Evaluate the noise in `space`
+    pub fn in_space(mut self, space: TextureSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Colour the noise from `low` at zero to `high` at one
+    pub fn colorize(self, low: Spectrum, high: Spectrum) -> Texture {
+        Texture::from(low).mix(Texture::from(high), Texture::Noise(self))
+    }
+
+    /// Value of the noise at world space point `p` that is at `p_local`
+    /// in object space
+    pub fn value_at(&self, p: Point, p_local: Point) -> Float {
+        let p = self.scale * match self.space {
+            TextureSpace::Object => p_local,
+            TextureSpace::World => p,
+        };
+
+        let v = match self.pattern {
+            NoisePattern::Fbm(octaves) => 0.5 + 0.5 * self.fbm_at(p, octaves),
+            NoisePattern::Marble => {
+                let turb = self.turbulence_at(p, DISTORTION_OCTAVES);
+                let wave = 0.5 + 0.5 * (crate::PI * (p.x + MARBLE_AMP * turb)).sin();
+                wave.powi(MARBLE_SHARPNESS)
+            }
+            NoisePattern::Wood => {
+                let r = (p.x * p.x + p.z * p.z).sqrt()
+                    + WOOD_AMP * self.fbm_at(p, DISTORTION_OCTAVES);
+                (WOOD_RINGS * r).rem_euclid(1.0).powi(3)
+            }
+            NoisePattern::Granite => {
+                GRANITE_CONTRAST * self.turbulence_at(GRANITE_FREQ * p, GRANITE_OCTAVES)
+            }
+        };
+
+        v.clamp(0.0, 1.0)
+    }
+
+    /// Sum of noise at `octaves` doubling frequencies
+    fn fbm_at(&self, p: Point, octaves: usize) -> Float {
+        self.octaves_at(p, octaves, |n| n)
+    }
+
+    /// Sum of absolute noise at `octaves` doubling frequencies
+    fn turbulence_at(&self, p: Point, octaves: usize) -> Float {
+        self.octaves_at(p, octaves, Float::abs)
+    }
+
+    fn octaves_at(&self, p: Point, octaves: usize, f: fn(Float) -> Float) -> Float {
+        let (acc, ..) = (0..octaves).fold((0.0, 1.0, p), |(acc, w, p), _| {
+            (acc + w * f(self.perlin.noise_at(p)), w * NOISE_GAIN, 2.0 * p)
+        });
+        acc
+    }
+}